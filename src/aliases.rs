@@ -0,0 +1,87 @@
+use crate::models::{normalize_package_name, DependencySource, Package};
+
+/// PyPI names that only stand in for another project, with that project.
+/// Most are import names registered to catch `pip install <module>`, which
+/// are deprecated or install nothing useful.
+const ALIASES: &[(&str, &str)] = &[
+    ("bs4", "beautifulsoup4"),
+    ("msgpack-python", "msgpack"),
+    ("pil", "pillow"),
+    ("sklearn", "scikit-learn"),
+    ("tensorflow-gpu", "tensorflow"),
+];
+
+/// The project `name` is an alias of, if it is one.
+pub fn canonical_name(name: &str) -> Option<&'static str> {
+    let name = normalize_package_name(name);
+    ALIASES
+        .iter()
+        .find(|(alias, _)| normalize_package_name(alias) == name)
+        .map(|(_, canonical)| *canonical)
+}
+
+/// The note on a requirement that names an alias of `canonical`.
+pub fn alias_note(canonical: &str) -> String {
+    format!("alias of {}; switch to {} instead", canonical, canonical)
+}
+
+/// Sets `alias_of` on each PyPI requirement on an alias, and notes the
+/// project to use instead.
+pub fn apply_alias_notes(packages: &mut [Package]) {
+    for pkg in packages
+        .iter_mut()
+        .filter(|p| matches!(p.source, DependencySource::PyPI) && p.alias_of.is_none())
+    {
+        if let Some(canonical) = canonical_name(&pkg.name) {
+            pkg.alias_of = Some(canonical.to_string());
+            pkg.notes.push(alias_note(canonical));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::parser::parse_requirement_line;
+
+    fn switched(content: &str) -> (App, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(&path, content).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        apply_alias_notes(&mut packages);
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        app.set_packages(packages);
+        app.selected_index = app.packages.iter().position(|p| p.alias_of.is_some()).unwrap();
+        app.switch_to_canonical().unwrap();
+        app.apply_upgrade().unwrap();
+        (app, std::fs::read_to_string(&path).unwrap())
+    }
+
+    #[test]
+    fn test_sklearn_is_flagged_with_scikit_learn_suggestion() {
+        let mut packages = vec![
+            parse_requirement_line("sklearn[extra]==0.0.post12").unwrap(),
+            parse_requirement_line("scikit-learn==1.5.0").unwrap(),
+        ];
+        apply_alias_notes(&mut packages);
+        apply_alias_notes(&mut packages);
+
+        assert_eq!(canonical_name("SKLearn"), Some("scikit-learn"));
+        assert_eq!(packages[0].alias_of.as_deref(), Some("scikit-learn"));
+        assert_eq!(packages[0].notes, vec!["alias of scikit-learn; switch to scikit-learn instead"]);
+        assert!(packages[1].alias_of.is_none() && packages[1].notes.is_empty());
+
+        // Renamed where it stands, constraint, extras and comment included.
+        let (app, written) = switched("numpy==1.26.0\nsklearn[extra]>=0.0  # ml\n");
+        assert_eq!(written, "numpy==1.26.0\nscikit-learn[extra]>=0.0  # ml\n");
+        let renamed = app.packages.iter().find(|p| p.name == "scikit-learn").unwrap();
+        assert!(renamed.alias_of.is_none() && renamed.notes.is_empty());
+
+        // Folded into the canonical entry when the file already lists it.
+        let (_, written) = switched("beautifulsoup4==4.12.0\nbs4[lxml]==0.0.2\n");
+        assert_eq!(written, "beautifulsoup4[lxml]==4.12.0\n");
+    }
+}
@@ -1,10 +1,54 @@
-use crate::models::{Package, UpgradeStats, VersionStatus};
-use crate::pypi::PyPIClient;
+use crate::models::{
+    normalize_package_name, ConstraintUpdate, DependencySource, Package, Severity, UpgradeSimulation, UpgradeStats,
+    VersionStatus,
+};
+use crate::pypi::{PyPIClient, SearchResult};
 use crate::security::SecurityChecker;
 use crate::changelog::ChangelogFetcher;
+use crate::glyphs::GlyphSet;
+use crate::keymap::{Action, Keymap, Lookup};
 use crate::popularity::PopularityChecker;
+use crate::resolver::{Conflict, DependencyResolver};
+use crate::simulator::UpgradeSimulator;
+use crate::upgrade::{PlannedChange, UpgradeManager};
+use crate::wheels::{self, PlatformTags};
+use crossterm::event::{KeyCode, KeyEvent};
 use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use fuzzy_matcher::skim::SkimMatcherV2;
+use tokio::sync::mpsc;
+
+pub const NOTHING_TO_DO: &str = "Nothing to do: no selected package has an upgrade";
+
+/// Results streamed back by the background task from [`App::start_enrichment`].
+pub enum Enrichment {
+    Packages(Vec<Package>),
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyTarget {
+    UpgradeLine,
+    InstallCommand,
+    AdvisoryUrl,
+}
+
+/// Where selected packages are upgraded to, toggled for the whole batch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetMode {
+    AbsoluteLatest,
+    WithinConstraint,
+}
+
+impl TargetMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetMode::AbsoluteLatest => "absolute latest",
+            TargetMode::WithinConstraint => "latest within constraint",
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AppMode {
@@ -12,10 +56,23 @@ pub enum AppMode {
     Display,
     Search,
     Confirm,
+    ConfirmRemove,
     Upgrading,
     Done,
     GraphView,
     ChangelogView,
+    FreshnessView,
+    PackageSearch,
+    EditConstraint,
+    SortMenu,
+}
+
+#[derive(Debug, Default)]
+pub struct PackageSearchState {
+    pub query: String,
+    pub searched_query: String,
+    pub results: Vec<SearchResult>,
+    pub selected: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -27,6 +84,30 @@ pub enum SortBy {
     Popularity,
 }
 
+impl SortBy {
+    /// In the order the sort menu lists them.
+    pub const ALL: [SortBy; 5] = [
+        SortBy::Name,
+        SortBy::Status,
+        SortBy::Current,
+        SortBy::Latest,
+        SortBy::Popularity,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortBy::Name => "Name",
+            SortBy::Status => "Status",
+            SortBy::Current => "Current version",
+            SortBy::Latest => "Latest version",
+            SortBy::Popularity => "Popularity",
+        }
+    }
+}
+
+/// How long a status-bar message stays up before the help text returns.
+const MESSAGE_TTL: Duration = Duration::from_secs(4);
+
 pub struct App {
     pub mode: AppMode,
     pub requirements_path: String,
@@ -35,17 +116,83 @@ pub struct App {
     pub selected_index: usize,
     pub search_query: String,
     pub stats: UpgradeStats,
+    pub conflicts: Vec<Conflict>,
+    /// Dependents per package, by normalized name, kept with the stats so
+    /// drawing doesn't rebuild the dependency graph.
+    pub impacts: HashMap<String, usize>,
+    pub target_mode: TargetMode,
     pub sort_by: SortBy,
+    /// Reverse the order `sort_by` gives.
+    pub sort_reversed: bool,
+    /// The highlighted row of the sort menu.
+    pub sort_menu_index: usize,
+    pub status_order: Vec<VersionStatus>,
+    pub watchlist: Vec<String>,
+    pub group_by_file: bool,
+    /// Draw each package on two lines, with more detail inline.
+    pub two_line_rows: bool,
+    pub glyph_set: GlyphSet,
+    /// Hide transitive pins from the list.
+    pub direct_only: bool,
+    /// Files carrying `# via` annotations, which already tell direct pins
+    /// from transitive ones.
+    pub compiled_files: Vec<String>,
+    /// Warnings for the upgrade confirmation, worked out when it opens.
+    pub confirm_warnings: Vec<String>,
+    /// The simulation the confirmation reports, and why it's critical risk
+    /// if it is; both worked out when it opens.
+    pub confirm_simulation: Option<UpgradeSimulation>,
+    pub confirm_reasons: Vec<String>,
     pub dry_run: bool,
     pub loading_message: String,
     pub error_message: Option<String>,
     pub success_message: Option<String>,
+    pub message_shown_at: Option<Instant>,
     pub pypi_client: PyPIClient,
     pub security_checker: SecurityChecker,
     pub changelog_fetcher: ChangelogFetcher,
     pub popularity_checker: PopularityChecker,
     pub backup_path: Option<String>,
+    /// What the last upgrade rewrote, for the post-upgrade review.
+    pub upgraded_changes: Vec<PlannedChange>,
+    /// The post-upgrade screen lists only `upgraded_changes`.
+    pub show_only_changed: bool,
+    /// Set once the user leaves the post-upgrade screen.
+    pub should_quit: bool,
     pub lock_file_path: Option<String>,
+    pub package_search: PackageSearchState,
+    pub added_packages: Vec<String>,
+    /// Name and origin file of each removed requirement.
+    pub removed_packages: Vec<(String, Option<String>)>,
+    /// Name and origin file of each package whose target was set by the
+    /// within-constraint [`TargetMode`], so it can be undone.
+    pub mode_targets: Vec<(String, Option<String>)>,
+    /// Normalized names of packages whose constraint was edited this
+    /// session, rewritten on write.
+    pub edited_constraints: Vec<String>,
+    /// Aliases switched to their canonical project this session, as
+    /// normalized `(alias, canonical)` names; see [`App::switch_to_canonical`].
+    pub renamed_packages: Vec<(String, String)>,
+    /// The spec being typed in `EditConstraint` mode.
+    pub constraint_input: String,
+    /// What's been typed in `Confirm` mode; a critical-risk upgrade only
+    /// goes ahead once it reads `yes`.
+    pub confirm_input: String,
+    pub refresh_interval: Option<Duration>,
+    pub last_refresh: Instant,
+    pub clipboard: Option<arboard::Clipboard>,
+    pub enriching: bool,
+    /// Updates from [`App::start_refetch`], for the event loop to pick up.
+    pub refetch_updates: Option<mpsc::UnboundedReceiver<Enrichment>>,
+    /// Off for a version-only run (`--no-enrich`): advisories, changelogs
+    /// and download stats aren't fetched.
+    pub enrich: bool,
+    /// Whether the last enrichment ran with `enrich` off, so turning it
+    /// back on has something to catch up on.
+    pub enrichment_skipped: bool,
+    pub keymap: Keymap,
+    /// Keys typed so far of a longer binding, such as the first `g` of `gg`.
+    pub pending_keys: Vec<String>,
 }
 
 impl App {
@@ -63,24 +210,93 @@ impl App {
                 minor_available: 0,
                 major_available: 0,
                 up_to_date: 0,
+                prerelease: 0,
+                unknown: 0,
                 errors: 0,
                 vulnerable: 0,
                 conflicts: 0,
+                fixable_cves: 0,
             },
+            conflicts: Vec::new(),
+            impacts: HashMap::new(),
+            target_mode: TargetMode::AbsoluteLatest,
             sort_by: SortBy::Status,
+            sort_reversed: false,
+            sort_menu_index: 0,
+            status_order: VersionStatus::default_order(),
+            watchlist: Vec::new(),
+            group_by_file: false,
+            two_line_rows: false,
+            glyph_set: GlyphSet::default(),
+            direct_only: false,
+            compiled_files: Vec::new(),
+            confirm_warnings: Vec::new(),
+            confirm_simulation: None,
+            confirm_reasons: Vec::new(),
             dry_run: false,
             loading_message: "Parsing requirements.txt...".to_string(),
             error_message: None,
             success_message: None,
+            message_shown_at: None,
             pypi_client: PyPIClient::new(),
             security_checker: SecurityChecker::new(),
             changelog_fetcher: ChangelogFetcher::new(),
             popularity_checker: PopularityChecker::new(),
             backup_path: None,
+            upgraded_changes: Vec::new(),
+            show_only_changed: false,
+            should_quit: false,
             lock_file_path: None,
+            package_search: PackageSearchState::default(),
+            added_packages: Vec::new(),
+            removed_packages: Vec::new(),
+            mode_targets: Vec::new(),
+            edited_constraints: Vec::new(),
+            renamed_packages: Vec::new(),
+            constraint_input: String::new(),
+            confirm_input: String::new(),
+            refresh_interval: None,
+            last_refresh: Instant::now(),
+            clipboard: None,
+            enriching: false,
+            refetch_updates: None,
+            enrich: true,
+            enrichment_skipped: false,
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
         }
     }
 
+    /// Caps the response bodies every lookup client reads.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.pypi_client = self.pypi_client.with_max_body_bytes(limit);
+        self.security_checker = self.security_checker.with_max_body_bytes(limit);
+        self.changelog_fetcher = self.changelog_fetcher.with_max_body_bytes(limit);
+        self.popularity_checker = self.popularity_checker.with_max_body_bytes(limit);
+        self
+    }
+
+    /// Bounds each lookup cache to `capacity` entries; see `[cache] capacity`.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.pypi_client = self.pypi_client.with_cache_capacity(capacity);
+        self.security_checker = self.security_checker.with_cache_capacity(capacity);
+        self.changelog_fetcher = self.changelog_fetcher.with_cache_capacity(capacity);
+        self.popularity_checker = self.popularity_checker.with_cache_capacity(capacity);
+        self
+    }
+
+    /// Advisory ids the security checker reports as ignored rather than vulnerable.
+    pub fn with_ignored_advisories(mut self, ids: &[String]) -> Self {
+        self.security_checker = self.security_checker.with_ignored(ids);
+        self
+    }
+
+    /// Advisories below `severity` are only noted; see [`SecurityChecker::with_min_severity`].
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.security_checker = self.security_checker.with_min_severity(severity);
+        self
+    }
+
     pub fn set_packages(&mut self, packages: Vec<Package>) {
         self.packages = packages;
         self.refresh_filtered_packages();
@@ -88,6 +304,13 @@ impl App {
     }
 
     pub fn refresh_filtered_packages(&mut self) {
+        let cursor = self.get_selected_package_ref().map(|p| p.name.clone());
+        self.rebuild_filter(cursor);
+    }
+
+    /// Recomputes the visible rows and puts the cursor back on the package
+    /// named `cursor`, falling back to the top when it is no longer visible.
+    fn rebuild_filter(&mut self, cursor: Option<String>) {
         if self.search_query.is_empty() {
             self.filtered_packages = (0..self.packages.len()).collect();
         } else {
@@ -105,25 +328,177 @@ impl App {
                 })
                 .collect();
         }
+        if self.direct_only {
+            let packages = &self.packages;
+            self.filtered_packages.retain(|&idx| packages[idx].is_direct);
+        }
 
-        self.selected_index = 0;
+        self.selected_index = cursor
+            .and_then(|name| {
+                self.filtered_packages
+                    .iter()
+                    .position(|&idx| self.packages[idx].name == name)
+            })
+            .unwrap_or(0);
+    }
+
+    /// Switches to the package list straight away and fetches versions,
+    /// security and changelogs in the background. Updates arrive on the
+    /// returned channel and are merged with [`App::apply_enrichment`]. The
+    /// task works on clones of the clients, which share their caches with ours.
+    pub fn start_enrichment(&mut self, tags: PlatformTags) -> mpsc::UnboundedReceiver<Enrichment> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut packages = self.packages.clone();
+        let pypi_client = self.pypi_client.clone();
+        let mut security_checker = self.security_checker.clone();
+        let mut changelog_fetcher = self.changelog_fetcher.clone();
+        let compiled_files = self.compiled_files.clone();
+        let enrich = self.enrich;
+
+        tokio::spawn(async move {
+            pypi_client.update_packages(&mut packages).await;
+            pypi_client.mark_transitive(&mut packages, &compiled_files);
+            wheels::apply_wheel_compatibility(&mut packages, &pypi_client, &tags, false);
+            wheels::apply_python_support(&mut packages, tags.python);
+            wheels::apply_backport_notes(&mut packages, tags.python);
+            crate::aliases::apply_alias_notes(&mut packages);
+            let _ = tx.send(Enrichment::Packages(packages.clone()));
+
+            if enrich {
+                for pkg in &mut packages {
+                    let _ = security_checker.check_package(pkg).await;
+                    let _ = tx.send(Enrichment::Packages(vec![pkg.clone()]));
+                }
+
+                let mut changelogs = changelog_fetcher.fetch_changelogs(&packages, &pypi_client).await;
+                for pkg in &mut packages {
+                    pkg.changelog = changelogs.remove(&pkg.name);
+                }
+                let _ = tx.send(Enrichment::Packages(packages.clone()));
+            }
+            // For the upgrade confirmation's list of what would break.
+            pypi_client.fetch_pinned_requires(&packages).await;
+            let _ = tx.send(Enrichment::Done);
+        });
+
+        self.enriching = true;
+        self.enrichment_skipped = !enrich;
+        self.mode = AppMode::Display;
+        self.apply_sort();
+        rx
+    }
+
+    pub fn toggle_enrich(&mut self) {
+        self.enrich = !self.enrich;
+    }
+
+    /// Whether enrichment was turned back on after a run that skipped it,
+    /// and the fetchers are free to fill in what's missing.
+    pub fn enrichment_due(&self) -> bool {
+        self.enrich && self.enrichment_skipped && !self.enriching
+    }
+
+    /// Clears everything cached about the package under the cursor and
+    /// fetches its version, advisories, changelog and, if it had them,
+    /// download stats again in the background; with `enrich` off, only its
+    /// version. Other packages are left as they are. The updates arrive on
+    /// `refetch_updates` for the event loop to merge like any enrichment.
+    /// Returns the package's name, or `None` with nothing selected or while
+    /// an enrichment is still running.
+    pub fn start_refetch(&mut self) -> Option<String> {
+        if self.enriching {
+            return None;
+        }
+        let idx = *self.filtered_packages.get(self.selected_index)?;
+        let mut pkg = self.packages[idx].clone();
+        self.pypi_client.forget(&pkg.name);
+        self.security_checker.forget(&pkg.name);
+        self.changelog_fetcher.forget(&pkg.name);
+        self.popularity_checker.forget(&pkg.name);
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let pypi_client = self.pypi_client.clone();
+        let mut security_checker = self.security_checker.clone();
+        let mut changelog_fetcher = self.changelog_fetcher.clone();
+        let popularity_checker = self.popularity_checker.clone();
+        let enrich = self.enrich;
+        let name = pkg.name.clone();
+
+        tokio::spawn(async move {
+            pypi_client.update_package(&mut pkg).await;
+            if enrich {
+                let _ = security_checker.check_package(&mut pkg).await;
+                let mut changelogs = changelog_fetcher
+                    .fetch_changelogs(std::slice::from_ref(&pkg), &pypi_client)
+                    .await;
+                pkg.changelog = changelogs.remove(&pkg.name);
+                if pkg.popularity.is_some() {
+                    if let Ok(Some(popularity)) = popularity_checker.fetch_popularity(&pkg.name).await {
+                        pkg.popularity = Some(popularity);
+                    }
+                }
+            }
+            let _ = tx.send(Enrichment::Packages(vec![pkg]));
+            let _ = tx.send(Enrichment::Done);
+        });
+
+        self.enriching = true;
+        self.refetch_updates = Some(rx);
+        Some(name)
+    }
+
+    /// Merges an enrichment update into the entry with the same name, file and extras.
+    pub fn apply_enrichment(&mut self, update: Enrichment) {
+        match update {
+            Enrichment::Packages(updated) => {
+                for pkg in updated {
+                    let existing = self.packages.iter_mut().find(|p| {
+                        p.name == pkg.name && p.origin_file == pkg.origin_file && p.extras == pkg.extras
+                    });
+                    if let Some(existing) = existing {
+                        merge_fetched(existing, pkg);
+                    }
+                }
+            }
+            Enrichment::Done => {
+                self.enriching = false;
+                self.last_refresh = Instant::now();
+            }
+        }
+        self.retarget();
+        self.apply_sort();
     }
 
     pub fn update_stats(&mut self) {
         self.stats = UpgradeStats::new(&self.packages);
+        self.stats.fixable_cves = self.security_checker.fixable_cves(&self.packages);
+        let resolver = DependencyResolver::new();
+        self.conflicts = resolver.validate_constraints(&self.packages);
+        self.conflicts.extend(resolver.detect_conflicts(&self.packages));
+        let graph = DependencyResolver::from_packages(&self.packages);
+        self.impacts = self
+            .packages
+            .iter()
+            .map(|p| normalize_package_name(&p.name))
+            .map(|name| {
+                let impact = graph.impact(&name);
+                (name, impact)
+            })
+            .collect();
     }
 
     pub fn apply_sort(&mut self) {
+        let cursor = self.get_selected_package_ref().map(|p| p.name.clone());
         match self.sort_by {
             SortBy::Name => {
                 self.packages.sort_by(|a, b| a.name.cmp(&b.name));
             }
             SortBy::Status => {
-                self.packages.sort_by(|a, b| {
-                    let a_priority = a.status.priority();
-                    let b_priority = b.status.priority();
-                    a_priority.cmp(&b_priority)
-                });
+                let order = &self.status_order;
+                let rank = |status: VersionStatus| {
+                    order.iter().position(|s| *s == status).unwrap_or(order.len())
+                };
+                self.packages.sort_by_key(|pkg| rank(pkg.status));
             }
             SortBy::Current => {
                 self.packages
@@ -145,9 +520,61 @@ impl App {
                 });
             }
         }
+        if self.sort_reversed {
+            self.packages.reverse();
+        }
+        if self.group_by_file {
+            self.packages.sort_by(|a, b| a.origin_file.cmp(&b.origin_file));
+        }
+        watched_first(&mut self.packages, &self.watchlist);
+        self.rebuild_filter(cursor);
+    }
+
+    pub fn open_sort_menu(&mut self) {
+        self.sort_menu_index = SortBy::ALL.iter().position(|s| *s == self.sort_by).unwrap_or(0);
+        self.mode = AppMode::SortMenu;
+    }
+
+    pub fn sort_menu_up(&mut self) {
+        self.sort_menu_index = self.sort_menu_index.checked_sub(1).unwrap_or(SortBy::ALL.len() - 1);
+    }
+
+    pub fn sort_menu_down(&mut self) {
+        self.sort_menu_index = (self.sort_menu_index + 1) % SortBy::ALL.len();
+    }
+
+    /// Sorts by the highlighted field and closes the menu.
+    pub fn choose_sort(&mut self) {
+        self.sort_by = SortBy::ALL[self.sort_menu_index];
+        self.apply_sort();
+        self.mode = AppMode::Display;
+    }
+
+    pub fn toggle_sort_direction(&mut self) {
+        self.sort_reversed = !self.sort_reversed;
+        self.apply_sort();
+    }
+
+    /// Sets the watched package names; matching is by normalized name.
+    pub fn set_watchlist(&mut self, names: &[String]) {
+        self.watchlist = names.iter().map(|n| normalize_package_name(n)).collect();
+        self.apply_sort();
+    }
+
+    pub fn toggle_group_by_file(&mut self) {
+        self.group_by_file = !self.group_by_file;
+        self.apply_sort();
+    }
+
+    pub fn toggle_direct_only(&mut self) {
+        self.direct_only = !self.direct_only;
         self.refresh_filtered_packages();
     }
 
+    pub fn toggle_show_only_changed(&mut self) {
+        self.show_only_changed = !self.show_only_changed;
+    }
+
     pub fn get_selected_package(&mut self) -> Option<&mut Package> {
         self.filtered_packages
             .get(self.selected_index)
@@ -162,65 +589,146 @@ impl App {
 
     pub fn toggle_selected(&mut self) {
         if let Some(pkg) = self.get_selected_package() {
-            pkg.selected = !pkg.selected;
+            if !pkg.policy.freeze {
+                pkg.selected = !pkg.selected;
+            }
         }
+        self.retarget();
     }
 
     pub fn select_all(&mut self) {
         for idx in self.filtered_packages.clone() {
             if let Some(pkg) = self.packages.get_mut(idx) {
-                if pkg.latest_version.is_some() {
+                if pkg.latest_version.is_some() && pkg.policy.bulk_selectable() {
                     pkg.selected = true;
                 }
             }
         }
+        self.retarget();
+    }
+
+    pub fn toggle_target_mode(&mut self) {
+        self.target_mode = match self.target_mode {
+            TargetMode::AbsoluteLatest => TargetMode::WithinConstraint,
+            TargetMode::WithinConstraint => TargetMode::AbsoluteLatest,
+        };
+        self.retarget();
+    }
+
+    /// Recomputes the write targets for the current [`TargetMode`]. Selected
+    /// packages get the newest release their constraint allows, and targets
+    /// set this way are cleared again once a package is deselected or the
+    /// mode is switched back; targets chosen any other way are left alone.
+    /// A selected package with nothing newer within its constraint is
+    /// deselected rather than marked up to date. Returns how many were.
+    pub fn apply_target_mode(&mut self) -> usize {
+        let mut deselected = 0;
+        for pkg in &mut self.packages {
+            let key = (pkg.name.clone(), pkg.origin_file.clone());
+            let ours = self.mode_targets.contains(&key);
+            if !ours && pkg.target_version.is_some() {
+                continue;
+            }
+            let target = match self.target_mode {
+                TargetMode::WithinConstraint if pkg.selected => {
+                    let target = crate::resolver::latest_within_constraint(
+                        pkg,
+                        &self.pypi_client.releases(&pkg.name),
+                    );
+                    if target.is_none() {
+                        pkg.selected = false;
+                        deselected += 1;
+                    }
+                    target
+                }
+                _ => None,
+            };
+            if target.is_some() && !ours {
+                self.mode_targets.push(key);
+            } else if target.is_none() && ours {
+                self.mode_targets.retain(|k| *k != key);
+            } else if target == pkg.target_version {
+                continue;
+            }
+            pkg.target_version = target;
+            // A vulnerable badge stays so it doesn't hide an open advisory.
+            if pkg.status != VersionStatus::Vulnerable {
+                if let Some(target) = pkg.upgrade_target() {
+                    pkg.status = crate::models::compare_versions(&pkg.current_version, target);
+                }
+            }
+        }
+        self.update_stats();
+        deselected
+    }
+
+    /// Runs [`App::apply_target_mode`] after a selection or mode change and
+    /// tells the user about packages it had to deselect.
+    fn retarget(&mut self) {
+        let deselected = self.apply_target_mode();
+        if deselected > 0 {
+            self.set_error(format!(
+                "{} package(s) have no newer release within their constraint",
+                deselected
+            ));
+        }
     }
 
     pub fn deselect_all(&mut self) {
         for pkg in &mut self.packages {
             pkg.selected = false;
         }
+        self.retarget();
     }
 
     pub fn select_all_major(&mut self) {
         for idx in self.filtered_packages.clone() {
             if let Some(pkg) = self.packages.get_mut(idx) {
-                if pkg.status == VersionStatus::Major {
+                if pkg.status == VersionStatus::Major && pkg.policy.bulk_selectable() {
                     pkg.selected = true;
                 }
             }
         }
+        self.retarget();
     }
 
     pub fn select_all_minor(&mut self) {
         for idx in self.filtered_packages.clone() {
             if let Some(pkg) = self.packages.get_mut(idx) {
-                if pkg.status == VersionStatus::Minor {
+                if pkg.status == VersionStatus::Minor && pkg.policy.bulk_selectable() {
                     pkg.selected = true;
                 }
             }
         }
+        self.retarget();
     }
 
     pub fn select_all_patch(&mut self) {
         for idx in self.filtered_packages.clone() {
             if let Some(pkg) = self.packages.get_mut(idx) {
-                if pkg.status == VersionStatus::Patch {
+                if pkg.status == VersionStatus::Patch && pkg.policy.bulk_selectable() {
                     pkg.selected = true;
                 }
             }
         }
+        self.retarget();
     }
 
+    /// Wraps around to the bottom from the first row if the keymap wraps.
     pub fn move_up(&mut self) {
         if self.selected_index > 0 {
             self.selected_index -= 1;
+        } else if self.keymap.wrap {
+            self.end();
         }
     }
 
+    /// Wraps around to the top from the last row if the keymap wraps.
     pub fn move_down(&mut self) {
         if self.selected_index < self.filtered_packages.len().saturating_sub(1) {
             self.selected_index += 1;
+        } else if self.keymap.wrap {
+            self.home();
         }
     }
 
@@ -241,6 +749,66 @@ impl App {
         self.selected_index = self.filtered_packages.len().saturating_sub(1);
     }
 
+    /// Runs `key` through the configured keymap. Returns whether it was
+    /// consumed, either by a binding or as the start of a longer one.
+    pub fn handle_keymap(&mut self, key: &KeyEvent) -> bool {
+        match self.keymap.lookup(&self.pending_keys, key) {
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                self.apply_action(action);
+                true
+            }
+            Lookup::Pending => {
+                if let KeyCode::Char(c) = key.code {
+                    self.pending_keys.push(c.to_string());
+                }
+                true
+            }
+            Lookup::Unbound => {
+                // A broken sequence is dropped; the key itself is retried alone.
+                let retry = !self.pending_keys.is_empty();
+                self.pending_keys.clear();
+                retry && self.handle_keymap(key)
+            }
+        }
+    }
+
+    pub fn apply_action(&mut self, action: Action) {
+        match action {
+            Action::Up => self.move_up(),
+            Action::Down => self.move_down(),
+            Action::PageUp => self.page_up(),
+            Action::PageDown => self.page_down(),
+            Action::Top => self.home(),
+            Action::Bottom => self.end(),
+        }
+    }
+
+    /// The rows currently listed, in display order.
+    pub fn visible_packages(&self) -> Vec<&Package> {
+        self.filtered_packages.iter().filter_map(|&idx| self.packages.get(idx)).collect()
+    }
+
+    /// Opens the upgrade confirmation.
+    pub fn open_confirm(&mut self) {
+        self.confirm_warnings =
+            crate::report::transitive_warnings(&self.packages, self.packages.iter().filter(|p| p.selected));
+        let env = crate::markers::Environment::current();
+        for pkg in self.packages.iter().filter(|p| p.selected) {
+            let Some(target) = pkg.upgrade_target() else {
+                continue;
+            };
+            let breaking = self.pypi_client.breaking_dependents(&self.packages, &pkg.name, target, env);
+            self.confirm_warnings.extend(crate::report::breaking_warning(&pkg.name, target, &breaking));
+        }
+        self.confirm_warnings
+            .extend(crate::report::alias_warnings(&self.packages, "Esc, then W switches to it"));
+        self.confirm_simulation = Some(UpgradeSimulator::new().simulate_upgrade(&self.packages));
+        self.confirm_reasons = self.critical_risk_reasons();
+        self.confirm_input.clear();
+        self.mode = AppMode::Confirm;
+    }
+
     pub fn count_selected(&self) -> usize {
         self.packages.iter().filter(|p| p.selected).count()
     }
@@ -253,16 +821,1050 @@ impl App {
         self.packages.iter().filter(|p| p.selected).collect()
     }
 
+    pub fn add_package(&mut self, name: &str) -> bool {
+        let normalized = normalize_package_name(name);
+        if self
+            .packages
+            .iter()
+            .any(|p| normalize_package_name(&p.name) == normalized)
+        {
+            return false;
+        }
+
+        self.packages.push(Package {
+            name: name.to_lowercase(),
+            current_version: "0.0.0".to_string(),
+            ..Package::default()
+        });
+        self.added_packages.push(name.to_lowercase());
+        self.apply_sort();
+        self.update_stats();
+        true
+    }
+
+    /// Removes the package under the cursor from the working set. Packages
+    /// that came from the file are remembered so their lines get dropped on
+    /// write.
+    pub fn remove_selected_package(&mut self) -> Option<String> {
+        let idx = *self.filtered_packages.get(self.selected_index)?;
+        let pkg = self.packages.remove(idx);
+
+        if let Some(pos) = self.added_packages.iter().position(|n| *n == pkg.name) {
+            self.added_packages.remove(pos);
+        } else {
+            self.removed_packages.push((pkg.name.clone(), pkg.origin_file.clone()));
+        }
+
+        self.rebuild_filter(None);
+        self.selected_index = self
+            .selected_index
+            .min(self.filtered_packages.len().saturating_sub(1));
+        self.update_stats();
+        Some(pkg.name)
+    }
+
+    /// Removed requirements declared in `requirements_path`, by name.
+    pub fn removed_from_requirements(&self) -> Vec<String> {
+        self.removed_packages
+            .iter()
+            .filter(|(_, origin)| origin.as_deref().is_none_or(|file| file == self.requirements_path))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Renames the package under the cursor, when it is an alias, to the
+    /// project it stands in for, keeping its constraint, file and extras.
+    /// When that project is already listed in the same file the alias's
+    /// extras join it and the alias is removed instead. Written on upgrade.
+    /// Returns the alias and the canonical name.
+    pub fn switch_to_canonical(&mut self) -> Option<(String, String)> {
+        let idx = *self.filtered_packages.get(self.selected_index)?;
+        let canonical = self.packages[idx].alias_of.clone()?;
+        let alias = self.packages[idx].name.clone();
+        let key = normalize_package_name(&canonical);
+        let existing = self.packages.iter().position(|p| {
+            normalize_package_name(&p.name) == key && p.origin_file == self.packages[idx].origin_file
+        });
+
+        if let Some(existing) = existing {
+            let extras = self.packages[idx].extras.clone();
+            let target = &mut self.packages[existing];
+            for extra in extras {
+                if !target.extras.contains(&extra) {
+                    target.extras.push(extra);
+                }
+            }
+            self.remove_selected_package()?;
+        } else {
+            let pkg = &mut self.packages[idx];
+            pkg.notes.retain(|note| *note != crate::aliases::alias_note(&canonical));
+            pkg.name = canonical.clone();
+            pkg.alias_of = None;
+            self.renamed_packages.push((normalize_package_name(&alias), key.clone()));
+            self.apply_sort();
+            self.update_stats();
+        }
+        if !self.edited_constraints.contains(&key) {
+            self.edited_constraints.push(key);
+        }
+        Some((alias, canonical))
+    }
+
+    /// Opens the constraint editor on the package under the cursor, starting
+    /// from its current spec. Only PyPI requirements have one to edit.
+    pub fn start_constraint_edit(&mut self) -> bool {
+        let Some(pkg) = self.get_selected_package_ref() else {
+            return false;
+        };
+        if !matches!(pkg.source, DependencySource::PyPI) {
+            self.set_error(format!("{} has no version constraint to edit", pkg.name));
+            return false;
+        }
+        self.constraint_input = pkg.constraint.as_str();
+        self.mode = AppMode::EditConstraint;
+        true
+    }
+
+    /// Applies the typed spec to the package under the cursor. An invalid
+    /// spec stays in the editor with an error; a valid one takes effect on
+    /// the working set now and on the file when the upgrade is written.
+    pub fn apply_constraint_edit(&mut self) -> bool {
+        let (constraint, current) = match crate::parser::parse_constraint(&self.constraint_input) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.set_error(format!("Invalid constraint: {}", e));
+                return false;
+            }
+        };
+        let Some(pkg) = self.get_selected_package() else {
+            return false;
+        };
+        pkg.constraint = constraint;
+        pkg.current_version = current;
+        pkg.target_version = None;
+        if let Some(latest) = pkg.latest_version.as_deref() {
+            if pkg.status != VersionStatus::Error {
+                pkg.status = crate::models::compare_versions(&pkg.current_version, latest);
+            }
+        }
+        let name = pkg.name.clone();
+        let spec = pkg.constraint.as_str();
+        let key = normalize_package_name(&name);
+        if !self.edited_constraints.contains(&key) {
+            self.edited_constraints.push(key);
+        }
+        self.update_stats();
+        self.set_success(format!("Constraint for {} set to {} (written on U)", name, spec));
+        self.mode = AppMode::Display;
+        true
+    }
+
+    /// Auto-refresh only fires from the main list so it never interrupts a
+    /// dialog or a search in progress.
+    pub fn refresh_due(&self, now: Instant) -> bool {
+        self.mode == AppMode::Display
+            && !self.enriching
+            && self
+                .refresh_interval
+                .is_some_and(|interval| now.duration_since(self.last_refresh) >= interval)
+    }
+
+    /// An auto-refresh: the same background lookup as at startup.
+    pub fn start_refresh(&mut self, tags: PlatformTags) -> mpsc::UnboundedReceiver<Enrichment> {
+        self.start_enrichment(tags)
+    }
+
+    /// The text a copy action puts on the clipboard for the highlighted package.
+    pub fn clipboard_text(&self, target: CopyTarget) -> Option<String> {
+        let pkg = self.get_selected_package_ref()?;
+        match target {
+            CopyTarget::UpgradeLine => {
+                let version = pkg
+                    .target_version
+                    .as_deref()
+                    .or(pkg.latest_version.as_deref())?;
+                Some(format!("{}=={}", pkg.name, version))
+            }
+            CopyTarget::InstallCommand => pkg.install_command(),
+            CopyTarget::AdvisoryUrl => self
+                .security_checker
+                .advisories(&pkg.name)
+                .first()
+                .map(|advisory| advisory.url.clone()),
+        }
+    }
+
+    /// Copies `text`, opening the system clipboard on first use. Headless
+    /// sessions surface the failure as an error instead of aborting.
+    pub fn copy_to_clipboard(&mut self, text: String) -> anyhow::Result<()> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            clipboard.set_text(text)?;
+        }
+        Ok(())
+    }
+
+    /// Selected packages that would actually change on upgrade.
+    pub fn count_upgrades(&self) -> usize {
+        self.packages
+            .iter()
+            .filter(|p| p.selected && p.has_upgrade() && UpgradeManager::rewrites_line(p, ConstraintUpdate::Keep))
+            .count()
+    }
+
+    /// Why the pending upgrade is critical risk and needs `yes` typed to
+    /// confirm; empty when a plain Enter will do.
+    pub fn critical_risk_reasons(&self) -> Vec<String> {
+        UpgradeSimulator::new().critical_reasons(&self.packages, self.glyph_set.glyphs())
+    }
+
+    /// Enter in the confirm dialog: upgrades, unless the upgrade is critical
+    /// risk and `yes` hasn't been typed yet.
+    pub fn confirm_upgrade(&mut self) -> anyhow::Result<()> {
+        if !self.confirm_input.trim().eq_ignore_ascii_case("yes") && !self.confirm_reasons.is_empty() {
+            self.set_error("Critical risk: type yes, then Enter, to upgrade".to_string());
+            return Ok(());
+        }
+        self.confirm_input.clear();
+        self.apply_upgrade()
+    }
+
+    /// Writes the confirmed upgrade to the requirements file, after backing
+    /// it up. With nothing to change it goes back to the list instead, and
+    /// neither the backup nor the file is written.
+    pub fn apply_upgrade(&mut self) -> anyhow::Result<()> {
+        let upgrade_count = self.count_upgrades();
+        if upgrade_count == 0 && !self.has_pending_edits() {
+            self.set_error(NOTHING_TO_DO.to_string());
+            self.mode = AppMode::Display;
+            return Ok(());
+        }
+        if crate::pyproject::is_pyproject(&self.requirements_path) && self.has_pending_edits() {
+            self.set_error("Adding, removing and editing constraints only work on requirements files".to_string());
+            self.mode = AppMode::Display;
+            return Ok(());
+        }
+        self.mode = AppMode::Upgrading;
+
+        let content = std::fs::read_to_string(&self.requirements_path)?;
+        let backup_path = if !self.dry_run {
+            Some(UpgradeManager::create_backup(&self.requirements_path)?)
+        } else {
+            None
+        };
+
+        self.pypi_client.attach_hashes(&mut self.packages);
+        let edited: Vec<&Package> = self
+            .packages
+            .iter()
+            .filter(|p| self.edited_constraints.contains(&normalize_package_name(&p.name)))
+            .collect();
+        let content = UpgradeManager::rewrite_constraints(&content, &edited, &self.renamed_packages);
+        let new_content = UpgradeManager::generate_upgraded_file(
+            std::path::Path::new(&self.requirements_path),
+            &self.packages,
+            &content,
+            true,
+            ConstraintUpdate::Keep,
+        )?;
+        // `plan` reads requirements lines, so a pyproject.toml's upgrades are
+        // listed from the selection, without lines.
+        let changes = if crate::pyproject::is_pyproject(&self.requirements_path) {
+            self.packages
+                .iter()
+                .filter(|p| p.selected && UpgradeManager::rewrites_line(p, ConstraintUpdate::Keep))
+                .filter_map(|p| {
+                    Some(PlannedChange {
+                        package: p.name.clone(),
+                        from_version: p.current_version.clone(),
+                        to_version: p.upgrade_target()?.to_string(),
+                        old_line: String::new(),
+                        new_line: String::new(),
+                        status: p.status,
+                    })
+                })
+                .collect()
+        } else {
+            UpgradeManager::plan(&self.packages, &content, true, ConstraintUpdate::Keep)?.changes
+        };
+        let skipped: Vec<&str> = self
+            .packages
+            .iter()
+            .filter(|p| p.selected && p.upgrade_target().is_none())
+            .map(|p| p.name.as_str())
+            .collect();
+        let new_content = UpgradeManager::remove_requirements(&new_content, &self.removed_from_requirements());
+        let new_content = UpgradeManager::add_requirements(&new_content, &self.get_added_packages());
+
+        if !self.dry_run {
+            UpgradeManager::write_requirements(&self.requirements_path, &new_content)?;
+        }
+
+        let g = self.glyph_set.glyphs();
+        self.success_message = Some(format!(
+            "{} Successfully upgraded {} package(s){}{}{}",
+            g.icon(g.up_to_date),
+            upgrade_count,
+            if self.has_pending_edits() {
+                format!(
+                    ", added {}, removed {}, edited {}",
+                    self.added_packages.len(),
+                    self.removed_packages.len(),
+                    self.edited_constraints.len()
+                )
+            } else {
+                String::new()
+            },
+            if let Some(backup) = &backup_path {
+                format!("\n{} Backup: {}", g.icon(g.package), backup)
+            } else {
+                String::new()
+            },
+            if skipped.is_empty() {
+                String::new()
+            } else {
+                format!("\n{} Skipped, no known target version: {}", g.icon(g.skipped), skipped.join(", "))
+            }
+        ));
+        self.backup_path = backup_path;
+        self.upgraded_changes = changes;
+
+        self.mode = AppMode::Done;
+        Ok(())
+    }
+
+    pub fn has_pending_edits(&self) -> bool {
+        !self.added_packages.is_empty()
+            || !self.removed_packages.is_empty()
+            || !self.edited_constraints.is_empty()
+    }
+
+    /// Other packages in the working set that depend on `name`.
+    pub fn dependents_of(&self, name: &str) -> Vec<String> {
+        crate::resolver::DependencyResolver::from_packages(&self.packages)
+            .get_dependents(&normalize_package_name(name))
+    }
+
+    /// How many packages in the working set depend on `name`, directly or
+    /// through others, as of the last [`App::update_stats`].
+    pub fn impact_of(&self, name: &str) -> usize {
+        self.impacts.get(&normalize_package_name(name)).copied().unwrap_or(0)
+    }
+
+    /// Packages added this session, to be written as new requirement lines.
+    pub fn get_added_packages(&self) -> Vec<&Package> {
+        self.packages
+            .iter()
+            .filter(|p| self.added_packages.contains(&p.name))
+            .collect()
+    }
+
     pub fn clear_messages(&mut self) {
         self.error_message = None;
         self.success_message = None;
+        self.message_shown_at = None;
+    }
+
+    /// Clears a message set through `set_error`/`set_success` once it has
+    /// been up for [`MESSAGE_TTL`].
+    pub fn expire_messages(&mut self, now: Instant) {
+        if self
+            .message_shown_at
+            .is_some_and(|shown| now.duration_since(shown) >= MESSAGE_TTL)
+        {
+            self.clear_messages();
+        }
     }
 
     pub fn set_error(&mut self, error: String) {
         self.error_message = Some(error);
+        self.message_shown_at = Some(Instant::now());
     }
 
     pub fn set_success(&mut self, message: String) {
         self.success_message = Some(message);
+        self.message_shown_at = Some(Instant::now());
+    }
+}
+
+/// Copies what a background lookup found onto `existing`, which may have
+/// been edited since the lookup took its copy: the selection, constraint and
+/// target set in the meantime are kept.
+fn merge_fetched(existing: &mut Package, fetched: Package) {
+    let repinned = existing.current_version != fetched.current_version;
+    existing.status = match fetched.latest_version.as_deref() {
+        Some(latest) if repinned && fetched.status != VersionStatus::Error => {
+            crate::models::compare_versions(&existing.current_version, latest)
+        }
+        _ => fetched.status,
+    };
+    existing.latest_version = fetched.latest_version;
+    if existing.target_version.is_none() {
+        existing.target_version = fetched.target_version;
+    }
+    existing.error = fetched.error;
+    existing.security_status = fetched.security_status;
+    existing.advisories = fetched.advisories;
+    existing.ignored_advisories = fetched.ignored_advisories;
+    existing.target_hashes = fetched.target_hashes;
+    existing.is_direct = fetched.is_direct;
+    existing.changelog = fetched.changelog.or(existing.changelog.take());
+    existing.popularity = fetched.popularity.or(existing.popularity.take());
+    existing.dependencies = fetched.dependencies;
+    existing.last_release = fetched.last_release;
+    existing.requires_python = fetched.requires_python;
+    existing.drops_python = fetched.drops_python;
+    existing.ahead_of_index = fetched.ahead_of_index;
+    existing.distribution = fetched.distribution;
+    existing.alias_of = fetched.alias_of;
+    for note in fetched.notes {
+        if !existing.notes.contains(&note) {
+            existing.notes.push(note);
+        }
+    }
+}
+
+/// Whether `pkg` is on a watchlist of normalized names.
+pub fn is_watched(watchlist: &[String], pkg: &Package) -> bool {
+    watchlist.contains(&normalize_package_name(&pkg.name))
+}
+
+/// Moves the packages on a watchlist of normalized names to the front,
+/// keeping the order within both halves.
+pub fn watched_first(packages: &mut [Package], watchlist: &[String]) {
+    packages.sort_by_key(|pkg| !is_watched(watchlist, pkg));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SecurityStatus, VersionConstraint, VersionStatus};
+    use crate::test_support::{MockResponse, MockServer};
+
+    fn package(name: &str, status: VersionStatus) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            latest_version: Some("2.0.0".to_string()),
+            status,
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn test_refresh_due_after_interval() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.mode = AppMode::Display;
+        let start = app.last_refresh;
+        assert!(!app.refresh_due(start + Duration::from_secs(3600)));
+
+        app.refresh_interval = Some(Duration::from_secs(300));
+        assert!(!app.refresh_due(start + Duration::from_secs(299)));
+        assert!(app.refresh_due(start + Duration::from_secs(300)));
+
+        app.mode = AppMode::Confirm;
+        assert!(!app.refresh_due(start + Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_refresh_result_keeps_selection_and_reopens_the_interval() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.mode = AppMode::Display;
+        app.refresh_interval = Some(Duration::from_secs(300));
+        app.set_packages(vec![package("requests", VersionStatus::Unknown)]);
+        app.get_selected_package().unwrap().selected = true;
+        app.enriching = true;
+        assert!(!app.refresh_due(Instant::now() + Duration::from_secs(600)));
+
+        let mut refreshed = package("requests", VersionStatus::Minor);
+        refreshed.latest_version = Some("1.1.0".to_string());
+        app.apply_enrichment(Enrichment::Packages(vec![refreshed]));
+        app.apply_enrichment(Enrichment::Done);
+
+        let pkg = app.get_selected_package_ref().unwrap();
+        assert!(pkg.selected);
+        assert_eq!(pkg.status, VersionStatus::Minor);
+        assert_eq!(pkg.latest_version.as_deref(), Some("1.1.0"));
+        assert!(!app.enriching);
+        assert!(app.refresh_due(Instant::now() + Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_clipboard_text_for_selected_package() {
+        let mut app = App::new("requirements.txt".to_string());
+        let mut pkg = package("requests", VersionStatus::Minor);
+        pkg.latest_version = Some("2.32.3".to_string());
+        app.set_packages(vec![pkg]);
+
+        assert_eq!(
+            app.clipboard_text(CopyTarget::UpgradeLine).as_deref(),
+            Some("requests==2.32.3")
+        );
+        app.packages[0].target_version = Some("2.31.0".to_string());
+        assert_eq!(
+            app.clipboard_text(CopyTarget::UpgradeLine).as_deref(),
+            Some("requests==2.31.0")
+        );
+        assert_eq!(app.clipboard_text(CopyTarget::AdvisoryUrl), None);
+    }
+
+    #[tokio::test]
+    async fn test_display_shown_before_enrichment_completes() {
+        // Answers only after the test is over, so enrichment stays pending.
+        let server = MockServer::start(|_| {
+            std::thread::sleep(Duration::from_secs(60));
+            MockResponse::status(503)
+        });
+        let index = server.url("/pypi");
+
+        let mut app = App::new("requirements.txt".to_string());
+        app.pypi_client.apply_options(&[crate::models::GlobalOption::IndexUrl(index)]);
+        app.set_packages(vec![package("requests", VersionStatus::Unknown)]);
+        app.get_selected_package().unwrap().selected = true;
+
+        let tags = PlatformTags {
+            python: None,
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            musl: false,
+        };
+        let mut updates = app.start_enrichment(tags);
+        assert_eq!(app.mode, AppMode::Display);
+        assert!(app.enriching);
+        assert!(!app.refresh_due(Instant::now() + Duration::from_secs(3600)));
+        assert!(updates.try_recv().is_err());
+
+        let mut enriched = package("requests", VersionStatus::Minor);
+        enriched.latest_version = Some("2.31.0".to_string());
+        app.apply_enrichment(Enrichment::Packages(vec![enriched]));
+        let pkg = app.get_selected_package_ref().unwrap();
+        assert_eq!(pkg.status, VersionStatus::Minor);
+        assert!(pkg.selected);
+    }
+
+    #[test]
+    fn test_status_messages_expire() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_success("Copied requests==2.31.0".to_string());
+        let shown = app.message_shown_at.unwrap();
+
+        app.expire_messages(shown + Duration::from_secs(1));
+        assert!(app.success_message.is_some());
+        app.expire_messages(shown + MESSAGE_TTL);
+        assert!(app.success_message.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refetch_updates_only_the_selected_package() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        let released = Arc::new(AtomicBool::new(false));
+        let server = {
+            let released = released.clone();
+            crate::test_support::MockServer::start(move |request| {
+                if request.method == "POST" {
+                    return crate::test_support::MockResponse::json("{}");
+                }
+                let name = request.path.split('/').nth(2).unwrap_or_default();
+                let version = if released.load(Ordering::SeqCst) { "1.1.0" } else { "1.0.1" };
+                crate::test_support::MockResponse::json(format!(
+                    r#"{{"info":{{"name":"{}","version":"{}","summary":"Fixes"}}}}"#,
+                    name, version
+                ))
+            })
+        };
+        let mut app = App::new("requirements.txt".to_string());
+        app.pypi_client.apply_options(&[crate::models::GlobalOption::IndexUrl(server.url("/pypi"))]);
+        app.security_checker = SecurityChecker::new().with_api_url(&server.url("/v1/query"));
+        app.set_packages(vec![package("flask", VersionStatus::Unknown), package("jinja2", VersionStatus::Unknown)]);
+        app.pypi_client.update_packages(&mut app.packages).await;
+        app.get_selected_package().unwrap().selected = true;
+
+        released.store(true, Ordering::SeqCst);
+        assert_eq!(app.start_refetch().as_deref(), Some("flask"));
+        assert!(app.start_refetch().is_none());
+        let mut updates = app.refetch_updates.take().unwrap();
+        while let Some(update) = updates.recv().await {
+            app.apply_enrichment(update);
+        }
+        assert!(!app.enriching);
+
+        let flask = app.packages.iter().find(|p| p.name == "flask").unwrap();
+        assert_eq!(flask.latest_version.as_deref(), Some("1.1.0"));
+        assert_eq!(flask.status, VersionStatus::Minor);
+        assert_eq!(flask.security_status, SecurityStatus::Safe);
+        assert_eq!(flask.changelog.as_ref().map(|c| c.version.as_str()), Some("1.1.0"));
+        assert!(flask.selected);
+        let jinja2 = app.packages.iter().find(|p| p.name == "jinja2").unwrap();
+        assert_eq!(jinja2.latest_version.as_deref(), Some("1.0.1"));
+        assert_eq!(server.hits("/pypi/flask/json"), 2);
+        assert_eq!(server.hits("/pypi/jinja2/json"), 1);
+        assert_eq!(server.hits("/v1/query"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_enrich_skips_security_and_popularity_lookups() {
+        let server = crate::test_support::MockServer::start(|request| {
+            if request.method == "POST" || request.path.starts_with("/stats") {
+                return crate::test_support::MockResponse::json("{}");
+            }
+            let name = request.path.split('/').nth(2).unwrap_or_default();
+            crate::test_support::MockResponse::json(format!(
+                r#"{{"info":{{"name":"{}","version":"1.1.0","summary":""}}}}"#,
+                name
+            ))
+        });
+        let mut app = App::new("requirements.txt".to_string());
+        app.pypi_client.apply_options(&[crate::models::GlobalOption::IndexUrl(server.url("/pypi"))]);
+        app.security_checker = SecurityChecker::new().with_api_url(&server.url("/v1/query"));
+        app.popularity_checker = PopularityChecker::new().with_api_url(&server.url("/stats"));
+        let mut flask = package("flask", VersionStatus::Unknown);
+        flask.popularity = Some(crate::models::PopularityData {
+            downloads_last_month: 0,
+            downloads_trend: Vec::new(),
+            weekly_downloads: 0,
+            package_rank: None,
+        });
+        app.set_packages(vec![flask, package("jinja2", VersionStatus::Unknown)]);
+        app.enrich = false;
+
+        let mut updates = app.start_enrichment(PlatformTags::detect());
+        while let Some(update) = updates.recv().await {
+            app.apply_enrichment(update);
+        }
+        assert!(!app.enriching);
+        assert!(app.packages.iter().all(|p| p.latest_version.as_deref() == Some("1.1.0")));
+        assert!(app.packages.iter().all(|p| p.security_status == SecurityStatus::Unknown));
+
+        assert_eq!(app.start_refetch().as_deref(), Some("flask"));
+        let mut updates = app.refetch_updates.take().unwrap();
+        while let Some(update) = updates.recv().await {
+            app.apply_enrichment(update);
+        }
+        assert_eq!(server.hits("/pypi/flask/json"), 2);
+        assert_eq!(server.hits("/v1/query"), 0);
+        assert_eq!(server.hits("/stats/packages/flask/recent"), 0);
+
+        app.toggle_enrich();
+        assert!(app.enrichment_due());
+    }
+
+    #[tokio::test]
+    async fn test_target_mode_toggle_changes_write_target() {
+        let server = crate::test_support::MockServer::start(|_| {
+            crate::test_support::MockResponse::json(
+                r#"{"info":{"name":"django","version":"2.0.0","summary":""},
+                    "releases":{"1.4.0":[],"1.4.5":[],"1.5.0rc1":[],"1.5.0":[],"2.0.0":[]}}"#,
+            )
+        });
+        let mut app = App::new("requirements.txt".to_string());
+        app.pypi_client
+            .apply_options(&[crate::models::GlobalOption::IndexUrl(server.url("/pypi"))]);
+
+        let mut constrained = package("django", VersionStatus::Unknown);
+        constrained.current_version = "1.4.0".to_string();
+        constrained.constraint = VersionConstraint::Compatible("1.4.0".to_string());
+        constrained.selected = true;
+        let mut chosen = constrained.clone();
+        chosen.name = "flask".to_string();
+        chosen.target_version = Some("1.5.0".to_string());
+        let mut boxed_in = constrained.clone();
+        boxed_in.name = "celery".to_string();
+        boxed_in.current_version = "1.5.0".to_string();
+        boxed_in.constraint = VersionConstraint::Compatible("1.5.0".to_string());
+        let mut packages = vec![constrained, chosen, boxed_in];
+        app.pypi_client.update_packages(&mut packages).await;
+        app.set_packages(packages);
+        app.sort_by = SortBy::Name;
+        app.apply_sort();
+
+        app.toggle_target_mode();
+        assert_eq!(app.target_mode, TargetMode::WithinConstraint);
+        let django = &app.packages[1];
+        assert_eq!(django.upgrade_target(), Some("1.4.5"));
+        assert_eq!(django.status, VersionStatus::Patch);
+        assert_eq!(app.packages[2].target_version.as_deref(), Some("1.5.0"));
+        let celery = &app.packages[0];
+        assert!(!celery.selected);
+        assert_eq!(celery.target_version, None);
+        assert_eq!(celery.status, VersionStatus::Major);
+        assert!(app.error_message.is_some());
+
+        app.home();
+        app.move_down();
+        app.toggle_selected();
+        assert_eq!(app.packages[1].upgrade_target(), Some("2.0.0"));
+        app.toggle_selected();
+        assert_eq!(app.packages[1].upgrade_target(), Some("1.4.5"));
+
+        app.toggle_target_mode();
+        assert_eq!(app.target_mode, TargetMode::AbsoluteLatest);
+        assert_eq!(app.packages[1].upgrade_target(), Some("2.0.0"));
+        assert_eq!(app.packages[1].status, VersionStatus::Major);
+        assert_eq!(app.packages[2].target_version.as_deref(), Some("1.5.0"));
+    }
+
+    #[test]
+    fn test_freeze_directive_excludes_package_from_bulk_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "django==3.2.0  # pyelevate: freeze\nflask==1.0.0  # pyelevate: pin\nrequests==1.0.0\n",
+        )
+        .unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        assert!(packages[0].policy.freeze);
+        assert!(packages[1].policy.pin);
+        for pkg in &mut packages {
+            pkg.latest_version = Some("9.0.0".to_string());
+            pkg.status = VersionStatus::Major;
+        }
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        app.set_packages(packages);
+        let selected = |app: &App| -> Vec<String> {
+            app.get_selected_packages().iter().map(|p| p.name.clone()).collect()
+        };
+
+        app.select_all();
+        assert_eq!(selected(&app), vec!["requests"]);
+        app.deselect_all();
+        app.select_all_major();
+        assert_eq!(selected(&app), vec!["requests"]);
+
+        // Only a pin can still be picked by hand; a freeze can't be selected at all.
+        app.deselect_all();
+        app.toggle_selected();
+        app.move_down();
+        app.toggle_selected();
+        assert_eq!(selected(&app), vec!["flask"]);
+        assert!(!app.packages[0].has_upgrade());
+    }
+
+    #[test]
+    fn test_remove_selected_package() {
+        let mut app = App::new("requirements.txt".to_string());
+        let mut requests = package("requests", VersionStatus::Minor);
+        requests.dependencies = vec!["idna".to_string()];
+        app.set_packages(vec![package("idna", VersionStatus::Patch), requests]);
+
+        assert_eq!(app.dependents_of("idna"), vec!["requests"]);
+        assert_eq!(app.remove_selected_package().as_deref(), Some("idna"));
+        assert_eq!(app.removed_packages, vec![("idna".to_string(), None)]);
+        assert_eq!(app.packages.len(), 1);
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "requests");
+
+        app.add_package("httpx");
+        app.end();
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "httpx");
+        app.remove_selected_package();
+        assert!(app.added_packages.is_empty());
+        assert_eq!(app.removed_packages, vec![("idna".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_removals_are_written_only_to_their_origin_file() {
+        let mut app = App::new("requirements.txt".to_string());
+        let mut dev = package("pytest", VersionStatus::Patch);
+        dev.origin_file = Some("requirements-dev.txt".to_string());
+        let mut idna = package("idna", VersionStatus::Patch);
+        idna.origin_file = Some("requirements.txt".to_string());
+        app.set_packages(vec![idna, dev]);
+
+        app.remove_selected_package();
+        app.remove_selected_package();
+        assert_eq!(app.removed_packages.len(), 2);
+        assert_eq!(app.removed_from_requirements(), vec!["idna"]);
+    }
+
+    #[test]
+    fn test_status_sort_uses_configured_order() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_packages(vec![
+            package("flask", VersionStatus::Patch),
+            package("django", VersionStatus::Major),
+            package("jinja2", VersionStatus::Vulnerable),
+        ]);
+
+        app.sort_by = SortBy::Status;
+        app.apply_sort();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["jinja2", "django", "flask"]);
+
+        let config = crate::config::Config::from_toml(
+            "[sort]\nstatus_order = [\"patch\", \"major\"]\n",
+        )
+        .unwrap();
+        app.status_order = config.sort.status_order().unwrap();
+        app.apply_sort();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["flask", "django", "jinja2"]);
+    }
+
+    #[test]
+    fn test_watched_packages_sort_first() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_packages(vec![
+            package("flask", VersionStatus::Patch),
+            package("django", VersionStatus::Major),
+            package("Jinja2", VersionStatus::Vulnerable),
+            package("attrs", VersionStatus::UpToDate),
+        ]);
+        app.set_watchlist(&["jinja2".to_string(), "attrs".to_string()]);
+
+        app.sort_by = SortBy::Name;
+        app.apply_sort();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Jinja2", "attrs", "django", "flask"]);
+
+        app.sort_by = SortBy::Status;
+        app.apply_sort();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Jinja2", "attrs", "django", "flask"]);
+
+        app.packages[0].origin_file = Some("b.txt".to_string());
+        app.toggle_group_by_file();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["attrs", "Jinja2", "django", "flask"]);
+    }
+
+    #[test]
+    fn test_confirming_without_effective_upgrades_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(&path, "django==1.0.0\n").unwrap();
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        let mut django = package("django", VersionStatus::UpToDate);
+        django.latest_version = Some("1.0.0".to_string());
+        django.selected = true;
+        app.set_packages(vec![django]);
+        app.mode = AppMode::Confirm;
+
+        app.apply_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Display);
+        assert_eq!(app.error_message.as_deref(), Some(NOTHING_TO_DO));
+        assert!(app.backup_path.is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "django==1.0.0\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_critical_risk_upgrade_needs_typed_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(&path, "django==1.0.0\nasgiref==3.0.0\n").unwrap();
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        app.dry_run = true;
+        let mut django = package("django", VersionStatus::Major);
+        django.dependencies = vec!["asgiref".to_string()];
+        django.selected = true;
+        let mut asgiref = package("asgiref", VersionStatus::Minor);
+        asgiref.current_version = "3.0.0".to_string();
+        asgiref.latest_version = Some("3.1.0".to_string());
+        app.set_packages(vec![django, asgiref]);
+        app.open_confirm();
+
+        let reasons = &app.confirm_reasons;
+        assert!(reasons.iter().any(|r| r.starts_with("Conflict: django")));
+        assert!(reasons.contains(&"Major: django 1.0.0 → 2.0.0".to_string()));
+
+        app.confirm_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Confirm);
+        assert!(app.error_message.as_deref().unwrap().contains("type yes"));
+
+        app.confirm_input = "no".to_string();
+        app.confirm_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Confirm);
+
+        app.confirm_input = "yes".to_string();
+        app.confirm_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Done);
+
+        // Below critical, Enter alone is enough.
+        app.packages[0].dependencies.clear();
+        app.open_confirm();
+        assert!(app.confirm_reasons.is_empty());
+        app.confirm_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Done);
+    }
+
+    #[test]
+    fn test_edited_constraint_is_applied_and_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "Django[argon2]==1.4.0  # pyelevate: ignore-security\nflask==2.0.0 --hash=sha256:abc  # web\n",
+        )
+        .unwrap();
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        packages[0].latest_version = Some("2.0.1".to_string());
+        packages[0].status = VersionStatus::Major;
+        app.set_packages(packages);
+        assert!(app.start_constraint_edit());
+        assert_eq!(app.constraint_input, "==1.4.0");
+
+        app.constraint_input = "~=2.0 beta".to_string();
+        assert!(!app.apply_constraint_edit());
+        assert_eq!(app.mode, AppMode::EditConstraint);
+
+        app.constraint_input = "~=2.0".to_string();
+        assert!(app.apply_constraint_edit());
+        assert_eq!(app.mode, AppMode::Display);
+        let django = app.get_selected_package_ref().unwrap();
+        assert!(matches!(&django.constraint, VersionConstraint::Compatible(v) if v == "2.0"));
+        assert_eq!(django.status, VersionStatus::Patch);
+        assert_eq!(app.edited_constraints, vec!["django"]);
+
+        app.move_down();
+        app.constraint_input = ">=2.0".to_string();
+        assert!(app.apply_constraint_edit());
+
+        app.apply_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Done);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "django[argon2]~=2.0  # pyelevate: ignore-security\nflask>=2.0 --hash=sha256:abc  # web\n"
+        );
+    }
+
+    #[test]
+    fn test_show_only_changed_lists_exactly_the_rewritten_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let before = "django==4.2.9\nflask==2.0.0\nrequests==2.31.0\nurllib3==2.0.0";
+        std::fs::write(&path, before).unwrap();
+
+        let mut app = App::new(path.to_string_lossy().to_string());
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        for pkg in &mut packages {
+            if pkg.name != "urllib3" {
+                pkg.latest_version = Some(format!("{}.1", pkg.current_version));
+                pkg.status = VersionStatus::Patch;
+            }
+            pkg.selected = pkg.name != "flask";
+        }
+        app.set_packages(packages);
+        app.apply_upgrade().unwrap();
+        assert_eq!(app.mode, AppMode::Done);
+
+        let after = std::fs::read_to_string(&path).unwrap();
+        let actually_changed: Vec<String> = before
+            .lines()
+            .zip(after.lines())
+            .filter(|(old, new)| old != new)
+            .map(|(old, _)| old.split("==").next().unwrap().to_string())
+            .collect();
+        let listed: Vec<String> = app.upgraded_changes.iter().map(|c| c.package.clone()).collect();
+        assert_eq!(listed, actually_changed);
+        assert_eq!(listed, vec!["django", "requests"]);
+        assert_eq!(app.upgraded_changes[0].from_version, "4.2.9");
+        assert_eq!(app.upgraded_changes[0].to_version, "4.2.9.1");
+
+        assert!(!app.show_only_changed);
+        app.toggle_show_only_changed();
+        assert!(app.show_only_changed);
+    }
+
+    #[test]
+    fn test_vim_keys_move_selection() {
+        use crossterm::event::KeyModifiers;
+        let key = |c: char| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_packages(vec![
+            package("django", VersionStatus::Major),
+            package("flask", VersionStatus::Patch),
+            package("requests", VersionStatus::Minor),
+        ]);
+
+        // Without vim keys, letters are left for type-to-search and the
+        // cursor stops at either end.
+        assert!(!app.handle_keymap(&key('j')));
+        app.move_up();
+        assert_eq!(app.selected_index, 0);
+
+        let config = crate::config::Config::from_toml("[keys]\nvim = true\n").unwrap();
+        app.keymap = Keymap::from_config(&config.keys);
+        assert!(app.handle_keymap(&key('j')));
+        assert!(app.handle_keymap(&key('j')));
+        assert_eq!(app.selected_index, 2);
+        app.handle_keymap(&key('k'));
+        assert_eq!(app.selected_index, 1);
+
+        // `gg` waits for its second key; navigation wraps at either end.
+        assert!(app.handle_keymap(&key('g')));
+        assert_eq!(app.selected_index, 1);
+        app.handle_keymap(&key('g'));
+        assert_eq!(app.selected_index, 0);
+        app.handle_keymap(&key('k'));
+        assert_eq!(app.selected_index, 2);
+        app.handle_keymap(&key('j'));
+        assert_eq!(app.selected_index, 0);
+        app.handle_keymap(&key('G'));
+        assert_eq!(app.selected_index, 2);
+    }
+
+    #[test]
+    fn test_sort_menu_selection_sets_sort_field() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_packages(vec![
+            package("flask", VersionStatus::Patch),
+            package("django", VersionStatus::Major),
+            package("requests", VersionStatus::Minor),
+        ]);
+
+        app.open_sort_menu();
+        assert_eq!(app.mode, AppMode::SortMenu);
+        assert_eq!(SortBy::ALL[app.sort_menu_index], SortBy::Status);
+        app.sort_menu_up();
+        app.choose_sort();
+        assert_eq!(app.sort_by, SortBy::Name);
+        assert_eq!(app.mode, AppMode::Display);
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["django", "flask", "requests"]);
+
+        app.open_sort_menu();
+        app.toggle_sort_direction();
+        let names: Vec<&str> = app.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["requests", "flask", "django"]);
+    }
+
+    #[test]
+    fn test_cursor_follows_package_across_sort_and_filter() {
+        let mut app = App::new("requirements.txt".to_string());
+        app.set_packages(vec![
+            package("flask", VersionStatus::Patch),
+            package("django", VersionStatus::Major),
+            package("requests", VersionStatus::Minor),
+        ]);
+        app.move_down();
+        app.move_down();
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "requests");
+
+        app.sort_by = SortBy::Name;
+        app.apply_sort();
+        assert_eq!(app.selected_index, 2);
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "requests");
+
+        app.sort_by = SortBy::Status;
+        app.apply_sort();
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "requests");
+
+        app.search_query = "req".to_string();
+        app.refresh_filtered_packages();
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "requests");
+
+        app.search_query = "flask".to_string();
+        app.refresh_filtered_packages();
+        assert_eq!(app.selected_index, 0);
+        assert_eq!(app.get_selected_package_ref().unwrap().name, "flask");
     }
 }
@@ -0,0 +1,17 @@
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+/// Entries each lookup cache keeps before evicting the least recently used,
+/// unless `[cache] capacity` says otherwise.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// An LRU cache that clones of its owner share.
+pub type SharedLru<K, V> = Arc<Mutex<LruCache<K, V>>>;
+
+/// An empty LRU cache holding at most `capacity` entries, and at least one.
+pub fn bounded<K: Hash + Eq, V>(capacity: usize) -> SharedLru<K, V> {
+    Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN))))
+}
@@ -1,61 +1,201 @@
-use crate::models::Changelog;
+use crate::models::{compare_release, Changelog, DependencySource, Package};
+use crate::pypi::PyPIClient;
+use crate::version::Pep440Version;
 use anyhow::Result;
+use crate::cache;
 use reqwest::Client;
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Changelog file names looked for in a local source tree, in order.
+const LOCAL_CHANGELOG_FILES: &[&str] = &[
+    "CHANGELOG.md",
+    "CHANGELOG.rst",
+    "CHANGELOG",
+    "CHANGES.md",
+    "CHANGES.rst",
+    "HISTORY.md",
+    "HISTORY.rst",
+    "NEWS.md",
+    "NEWS.rst",
+    "NEWS",
+];
+
+/// Clones share the HTTP client and the changelog cache.
+#[derive(Clone)]
 pub struct ChangelogFetcher {
     client: Client,
-    cache: HashMap<String, Option<Changelog>>,
+    cache: cache::SharedLru<String, Option<Changelog>>,
+    max_concurrent: usize,
+    max_body_bytes: usize,
 }
 
 impl ChangelogFetcher {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
-            cache: HashMap::new(),
+            cache: cache::bounded(cache::DEFAULT_CAPACITY),
+            max_concurrent: DEFAULT_CONCURRENCY,
+            max_body_bytes: crate::http::DEFAULT_MAX_BODY_BYTES,
         }
     }
 
-    pub async fn fetch_changelog(&mut self, package: &str, version: &str) -> Result<Option<Changelog>> {
+    pub fn with_concurrency(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Caps every release response body.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    /// Keeps at most `capacity` changelogs, evicting the least recently used.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = cache::bounded(capacity);
+        self
+    }
+
+    /// Fetches the changelog for each package's upgrade target, keyed by
+    /// package name. Summaries `pypi` already holds are reused; the rest are
+    /// requested from its indexes concurrently, at most `max_concurrent` at a
+    /// time.
+    pub async fn fetch_changelogs(
+        &mut self,
+        packages: &[Package],
+        pypi: &PyPIClient,
+    ) -> HashMap<String, Changelog> {
+        let mut changelogs = HashMap::new();
+        let semaphore = Arc::new(Semaphore::new(self.max_concurrent));
+        let mut handles = vec![];
+
+        for pkg in packages {
+            if let DependencySource::LocalPath { path, .. } = &pkg.source {
+                if let Some(changelog) = local_changelog(Path::new(path.trim_start_matches("file://")), pkg.upgrade_target()) {
+                    changelogs.insert(pkg.name.clone(), changelog);
+                }
+                continue;
+            }
+
+            let Some(version) = pkg.upgrade_target() else {
+                continue;
+            };
+            let cache_key = format!("{}-{}", pkg.name, version);
+
+            if let Some(cached) = self.cache.lock().get(&cache_key) {
+                if let Some(changelog) = cached {
+                    changelogs.insert(pkg.name.clone(), changelog.clone());
+                }
+                continue;
+            }
+
+            if let Some(summary) = pypi.cached_summary(&pkg.name, version) {
+                let changelog = changelog_from_summary(version, &summary);
+                self.cache.lock().put(cache_key, Some(changelog.clone()));
+                changelogs.insert(pkg.name.clone(), changelog);
+                continue;
+            }
+
+            let client = self.client.clone();
+            let indexes = pypi.indexes().to_vec();
+            let semaphore = semaphore.clone();
+            let name = pkg.name.clone();
+            let version = version.to_string();
+            let max_body = self.max_body_bytes;
+
+            handles.push(tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await.ok()?;
+                let changelog = fetch_from_indexes(&client, &indexes, &name, &version, max_body).await.ok();
+                Some((name, version, changelog))
+            }));
+        }
+
+        for handle in handles {
+            if let Ok(Some((name, version, changelog))) = handle.await {
+                self.cache.lock().put(format!("{}-{}", name, version), changelog.clone());
+                if let Some(changelog) = changelog {
+                    changelogs.insert(name, changelog);
+                }
+            }
+        }
+
+        changelogs
+    }
+
+    /// Drops every cached changelog of `package`, for any version.
+    pub fn forget(&self, package: &str) {
+        let prefix = format!("{}-", package);
+        let mut cache = self.cache.lock();
+        let stale: Vec<String> = cache
+            .iter()
+            .map(|(key, _)| key)
+            // A remainder with a dash is another project, e.g. `django-extensions-1.0`.
+            .filter(|key| key.strip_prefix(&prefix).is_some_and(|version| !version.contains('-')))
+            .cloned()
+            .collect();
+        for key in stale {
+            cache.pop(&key);
+        }
+    }
+
+    pub async fn fetch_changelog(
+        &mut self,
+        pypi: &PyPIClient,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<Changelog>> {
         let cache_key = format!("{}-{}", package, version);
         
-        if let Some(cached) = self.cache.get(&cache_key) {
-            return Ok(cached.clone());
+        let cached = self.cache.lock().get(&cache_key).cloned();
+        if let Some(cached) = cached {
+            return Ok(cached);
         }
 
-        let changelog = self.fetch_from_pypi(package, version).await
+        let changelog = fetch_from_indexes(&self.client, pypi.indexes(), package, version, self.max_body_bytes)
+            .await
             .or_else(|_| futures::executor::block_on(self.fetch_from_github(package, version)))
             .ok();
 
-        self.cache.insert(cache_key, changelog.clone());
+        self.cache.lock().put(cache_key, changelog.clone());
         Ok(changelog)
     }
 
-    async fn fetch_from_pypi(&self, package: &str, version: &str) -> Result<Changelog> {
-        let url = format!("https://pypi.org/pypi/{}/{}/json", package, version);
-        let response = self.client.get(&url).send().await?;
-        let data: serde_json::Value = response.json().await?;
-
-        let _home_page = data
-            .get("info")
-            .and_then(|i| i.get("home_page"))
-            .and_then(|h| h.as_str())
-            .unwrap_or("");
-
-        let summary = data
-            .get("info")
-            .and_then(|i| i.get("summary"))
-            .and_then(|s| s.as_str())
-            .unwrap_or("No description available");
-
-        Ok(Changelog {
-            version: version.to_string(),
-            release_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-            changes: vec![summary.to_string()],
-            breaking_changes: detect_breaking_changes(summary),
-            deprecated: detect_deprecated(summary),
-            security_fixes: detect_security_fixes(summary),
-        })
+    /// The changelogs of `package`'s final releases after `from` up to and
+    /// including `to`, newest first. Without `from` only `to` is fetched;
+    /// `to` defaults to the latest release.
+    pub async fn fetch_range(
+        &mut self,
+        pypi: &PyPIClient,
+        package: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<Changelog>> {
+        let latest = pypi.fetch_latest_version(package).await?;
+        let to = to.unwrap_or(&latest).to_string();
+        let mut versions: Vec<String> = match from {
+            Some(from) => pypi
+                .releases(package)
+                .into_iter()
+                .filter(|v| Pep440Version::parse(v).is_some_and(|v| !v.is_prerelease()))
+                .filter(|v| compare_release(v, from).is_gt() && compare_release(v, &to).is_le())
+                .collect(),
+            None => vec![to],
+        };
+        versions.reverse();
+
+        let mut changelogs = Vec::new();
+        for version in versions {
+            if let Some(changelog) = self.fetch_changelog(pypi, package, &version).await? {
+                changelogs.push(changelog);
+            }
+        }
+        Ok(changelogs)
     }
 
     async fn fetch_from_github(&self, _package: &str, _version: &str) -> Result<Changelog> {
@@ -63,8 +203,191 @@ impl ChangelogFetcher {
     }
 }
 
+/// The first changelog any of `indexes` serves for `package` at `version`.
+async fn fetch_from_indexes(
+    client: &Client,
+    indexes: &[String],
+    package: &str,
+    version: &str,
+    max_body: usize,
+) -> Result<Changelog> {
+    let mut last_error = None;
+    for index in indexes {
+        match fetch_from_index(client, index, package, version, max_body).await {
+            Ok(changelog) => return Ok(changelog),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No package index configured")))
+}
+
+async fn fetch_from_index(
+    client: &Client,
+    index: &str,
+    package: &str,
+    version: &str,
+    max_body: usize,
+) -> Result<Changelog> {
+    let url = format!("{}/{}/{}/json", index, package, version);
+    let response = client.get(&url).send().await?.error_for_status()?;
+    let data: serde_json::Value =
+        crate::http::read_json(response, max_body).await?;
+
+    let summary = data
+        .get("info")
+        .and_then(|i| i.get("summary"))
+        .and_then(|s| s.as_str())
+        .unwrap_or("No description available");
+
+    Ok(changelog_from_summary(version, summary))
+}
+
+fn changelog_from_summary(version: &str, summary: &str) -> Changelog {
+    Changelog {
+        version: version.to_string(),
+        release_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        changes: vec![summary.to_string()],
+        breaking_changes: detect_breaking_changes(summary),
+        deprecated: detect_deprecated(summary),
+        security_fixes: detect_security_fixes(summary),
+    }
+}
+
+/// Reads the changelog shipped in a local source tree, without touching the
+/// network. Takes the section for `version`, or the newest section when no
+/// version is known, as local packages usually have none.
+pub fn local_changelog(dir: &Path, version: Option<&str>) -> Option<Changelog> {
+    LOCAL_CHANGELOG_FILES.iter().find_map(|name| {
+        let content = fs::read_to_string(dir.join(name)).ok()?;
+        let section = changelog_section(&content, version)?;
+        let text = section.changes.join("\n");
+        Some(Changelog {
+            version: section.version,
+            release_date: section.release_date,
+            breaking_changes: detect_breaking_changes(&text),
+            deprecated: detect_deprecated(&text),
+            security_fixes: detect_security_fixes(&text),
+            changes: section.changes,
+        })
+    })
+}
+
+#[derive(Debug, PartialEq)]
+struct Section {
+    version: String,
+    release_date: String,
+    changes: Vec<String>,
+}
+
+/// A heading line: its text and nesting level. Markdown levels are the
+/// number of `#`s; reStructuredText levels follow the order in which
+/// underline characters first appear, as Sphinx assigns them.
+struct Heading {
+    line: usize,
+    level: usize,
+    text: String,
+}
+
+fn headings(lines: &[&str]) -> Vec<Heading> {
+    let mut rst_levels: Vec<char> = Vec::new();
+    let mut headings = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let text = line.trim_start_matches('#');
+        let level = line.len() - text.len();
+        if level > 0 && (text.is_empty() || text.starts_with(' ')) {
+            headings.push(Heading { line: i, level, text: text.trim().to_string() });
+        } else if let Some(underline) = lines.get(i + 1).and_then(|next| rst_underline(next, line)) {
+            if !rst_levels.contains(&underline) {
+                rst_levels.push(underline);
+            }
+            let level = 1 + rst_levels.iter().position(|&c| c == underline).unwrap_or(0);
+            headings.push(Heading { line: i, level, text: line.trim().to_string() });
+            i += 1;
+        }
+        i += 1;
+    }
+    headings
+}
+
+/// The adornment character when `next` underlines `title`.
+fn rst_underline(next: &str, title: &str) -> Option<char> {
+    let next = next.trim_end();
+    let c = next.chars().next()?;
+    let uniform = "=-~^*+#'\"`".contains(c) && next.chars().all(|ch| ch == c);
+    (uniform && !title.trim().is_empty() && next.len() >= title.trim().len()).then_some(c)
+}
+
+/// Version-like words in a heading such as `[2.0.0] - 2024-01-31` or
+/// `Version 2.0.0 (2024-01-31)`.
+fn heading_version(text: &str) -> Option<&str> {
+    text.split(|c: char| c.is_whitespace() || "[]()`:,".contains(c))
+        .map(|word| word.trim_start_matches(['v', 'V']))
+        .find(|word| word.contains('.') && Pep440Version::parse(word).is_some())
+}
+
+fn heading_date(text: &str) -> Option<&str> {
+    text.split(|c: char| c.is_whitespace() || "[]()`:,".contains(c))
+        .find(|word| chrono::NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok())
+}
+
+/// The entries under the heading for `version` (the first versioned heading
+/// when `None`), up to the next heading at the same or an outer level.
+/// Bullets become one entry each, with wrapped lines joined on; nested
+/// headings such as Keep a Changelog's `### Fixed` are left out.
+fn changelog_section(content: &str, version: Option<&str>) -> Option<Section> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = headings(&lines);
+    let wanted = version.and_then(Pep440Version::parse);
+
+    let start = headings.iter().position(|h| match heading_version(&h.text) {
+        Some(found) => match (&wanted, version) {
+            (Some(wanted), _) => Pep440Version::parse(found).as_ref() == Some(wanted),
+            (None, Some(version)) => found == version,
+            (None, None) => true,
+        },
+        None => false,
+    })?;
+    let heading = &headings[start];
+    let end = headings[start + 1..]
+        .iter()
+        .find(|h| h.level <= heading.level)
+        .map_or(lines.len(), |h| h.line);
+    let nested: Vec<usize> = headings[start + 1..]
+        .iter()
+        .take_while(|h| h.line < end)
+        .map(|h| h.line)
+        .collect();
+
+    let mut changes: Vec<String> = Vec::new();
+    let mut continues = false;
+    for (i, line) in lines.iter().enumerate().take(end).skip(heading.line + 1) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || nested.contains(&i) || rst_underline(line, "-").is_some() {
+            continues = false;
+            continue;
+        }
+        let bullet = ["- ", "* ", "+ "].iter().find_map(|b| trimmed.strip_prefix(b));
+        match (bullet, changes.last_mut()) {
+            (None, Some(last)) if continues => {
+                last.push(' ');
+                last.push_str(trimmed);
+            }
+            (bullet, _) => changes.push(bullet.unwrap_or(trimmed).trim().to_string()),
+        }
+        continues = true;
+    }
+
+    Some(Section {
+        version: heading_version(&heading.text).unwrap_or_default().to_string(),
+        release_date: heading_date(&heading.text).unwrap_or_default().to_string(),
+        changes,
+    })
+}
+
 fn detect_breaking_changes(text: &str) -> Vec<String> {
-    let keywords = vec![
+    let keywords = [
         "breaking change",
         "breaking changes",
         "removed",
@@ -80,7 +403,7 @@ fn detect_breaking_changes(text: &str) -> Vec<String> {
 }
 
 fn detect_deprecated(text: &str) -> Vec<String> {
-    let keywords = vec!["deprecated", "will be removed"];
+    let keywords = ["deprecated", "will be removed"];
 
     keywords
         .iter()
@@ -90,7 +413,7 @@ fn detect_deprecated(text: &str) -> Vec<String> {
 }
 
 fn detect_security_fixes(text: &str) -> Vec<String> {
-    let keywords = vec![
+    let keywords = [
         "security",
         "cve",
         "vulnerability",
@@ -110,3 +433,116 @@ impl Default for ChangelogFetcher {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{VersionConstraint, VersionStatus, GlobalOption};
+    use crate::test_support::{MockResponse, MockServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn package(name: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            latest_version: Some("2.0.0".to_string()),
+            status: VersionStatus::Major,
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            ..Package::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_changelogs_bounds_concurrency() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let server = {
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+            MockServer::start(move |_| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(150));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                MockResponse::json(r#"{"info":{"summary":"Security fix release"}}"#)
+            })
+        };
+
+        let mut pypi = PyPIClient::new();
+        pypi.apply_options(&[GlobalOption::IndexUrl(server.url("/pypi"))]);
+        let mut fetcher = ChangelogFetcher::new().with_concurrency(2);
+        let packages = vec![package("flask"), package("django"), package("requests")];
+        let changelogs = fetcher.fetch_changelogs(&packages, &pypi).await;
+
+        assert_eq!(changelogs.len(), 3);
+        assert_eq!(changelogs["django"].version, "2.0.0");
+        assert!(!changelogs["django"].security_fixes.is_empty());
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_changelog_range_report_highlights_breaking_change() {
+        let server = MockServer::routes(&[
+            (
+                "/pypi/demo/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "demo", "version": "2.0.0", "summary": ""},
+                        "releases": {"1.0.0": [], "1.1.0": [], "2.0.0rc1": [], "2.0.0": []}}"#,
+                ),
+            ),
+            (
+                "/pypi/demo/1.1.0/json",
+                MockResponse::json(r#"{"info": {"summary": "Faster imports"}}"#),
+            ),
+            (
+                "/pypi/demo/2.0.0/json",
+                MockResponse::json(r#"{"info": {"summary": "Breaking changes: the legacy API is removed"}}"#),
+            ),
+        ]);
+        let mut pypi = PyPIClient::new();
+        pypi.apply_options(&[crate::models::GlobalOption::IndexUrl(server.url("/pypi"))]);
+        let mut fetcher = ChangelogFetcher::new();
+
+        let changelogs = fetcher.fetch_range(&pypi, "demo", Some("1.0.0"), None).await.unwrap();
+        let versions: Vec<&str> = changelogs.iter().map(|c| c.version.as_str()).collect();
+        assert_eq!(versions, vec!["2.0.0", "1.1.0"]);
+
+        let report = crate::report::changelog_report("demo", &changelogs, Default::default());
+        assert!(report.starts_with("demo 2.0.0\n"));
+        assert!(report.contains("  ⚠️ BREAKING: Detected: breaking change"));
+        assert!(report.contains("demo 1.1.0\n  • Faster imports"));
+    }
+
+    #[test]
+    fn test_local_changelog_extracts_version_section() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("CHANGELOG.md"),
+            "# Changelog\n\n\
+             ## [2.1.0] - 2024-03-01\n\n- Add retries\n\n\
+             ## [2.0.0] - 2024-01-31\n\n### Removed\n\n- Drop the legacy API,\n  deprecated since 1.4\n\n\
+             ### Fixed\n\n- Fix a security issue in cookie parsing\n\n\
+             ## [1.4.0] - 2023-11-02\n\n- Deprecate the legacy API\n",
+        )
+        .unwrap();
+
+        let changelog = local_changelog(dir.path(), Some("2.0")).unwrap();
+        assert_eq!(changelog.version, "2.0.0");
+        assert_eq!(changelog.release_date, "2024-01-31");
+        assert_eq!(
+            changelog.changes,
+            vec!["Drop the legacy API, deprecated since 1.4", "Fix a security issue in cookie parsing"]
+        );
+        assert!(!changelog.security_fixes.is_empty());
+        assert_eq!(local_changelog(dir.path(), None).unwrap().changes, vec!["Add retries"]);
+        assert!(local_changelog(dir.path(), Some("3.0.0")).is_none());
+
+        let rst = tempfile::tempdir().unwrap();
+        fs::write(
+            rst.path().join("HISTORY.rst"),
+            "History\n=======\n\n1.1.0 (2024-02-02)\n------------------\n\n* Faster imports\n\n\
+             1.0.0 (2023-12-12)\n------------------\n\n* First release\n",
+        )
+        .unwrap();
+        assert_eq!(local_changelog(rst.path(), Some("1.0.0")).unwrap().changes, vec!["First release"]);
+    }
+}
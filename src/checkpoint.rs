@@ -0,0 +1,179 @@
+use crate::models::{normalize_package_name, Package, VersionStatus};
+use crate::pypi::PyPIClient;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Packages looked up per batch; each finished batch is flushed to disk.
+pub const BATCH_SIZE: usize = 50;
+
+/// Lookup results from an earlier, possibly interrupted, run. Stored as one
+/// JSON package per line and appended after every batch, so an interruption
+/// loses at most the batch in flight. Only the version lookups are
+/// checkpointed; the wheel and security checks after them run in full.
+pub struct Checkpoint {
+    path: PathBuf,
+    completed: HashMap<String, Package>,
+}
+
+impl Checkpoint {
+    /// Opens `path`, loading whatever an earlier run completed. A missing
+    /// file starts an empty checkpoint; a torn last line is ignored.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut completed = HashMap::new();
+        if path.exists() {
+            for line in fs::read_to_string(&path)?.lines() {
+                if let Ok(pkg) = serde_json::from_str::<Package>(line) {
+                    completed.insert(key(&pkg), pkg);
+                }
+            }
+        }
+        Ok(Self { path, completed })
+    }
+
+    pub fn len(&self) -> usize {
+        self.completed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.completed.is_empty()
+    }
+
+    fn restore(&self, pkg: &mut Package) -> bool {
+        match self.completed.get(&key(pkg)) {
+            Some(done) => {
+                pkg.latest_version = done.latest_version.clone();
+                pkg.status = done.status;
+                pkg.last_release = done.last_release;
+                pkg.error = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Failed lookups are not recorded, so a resumed run retries them.
+    fn record(&mut self, packages: &[Package]) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for pkg in packages.iter().filter(|p| p.status != VersionStatus::Error) {
+            writeln!(file, "{}", serde_json::to_string(pkg)?)?;
+            self.completed.insert(key(pkg), pkg.clone());
+        }
+        Ok(())
+    }
+
+    /// Removes the checkpoint once the run it belongs to has completed.
+    pub fn finish(self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Results are keyed by file, extras and pin as well as the name, so a
+/// package listed twice keeps both entries and editing a requirement between
+/// runs invalidates its checkpointed result.
+fn key(pkg: &Package) -> String {
+    format!(
+        "{}[{}]=={}@{}",
+        normalize_package_name(&pkg.name),
+        pkg.extras.join(","),
+        pkg.current_version,
+        pkg.origin_file.as_deref().unwrap_or_default()
+    )
+}
+
+/// Fills in `packages` from the checkpoint, then looks the rest up in
+/// batches of `batch_size`, checkpointing each batch. Returns how many were
+/// restored rather than fetched.
+pub async fn update_resumable(
+    client: &PyPIClient,
+    packages: &mut [Package],
+    checkpoint: &mut Checkpoint,
+    batch_size: usize,
+) -> Result<usize> {
+    let mut pending = Vec::new();
+    for (i, pkg) in packages.iter_mut().enumerate() {
+        if !checkpoint.restore(pkg) {
+            pending.push(i);
+        }
+    }
+    let restored = packages.len() - pending.len();
+
+    for batch in pending.chunks(batch_size.max(1)) {
+        let mut fetched: Vec<Package> = batch.iter().map(|&i| packages[i].clone()).collect();
+        client.update_packages(&mut fetched).await;
+        checkpoint.record(&fetched)?;
+        for (&i, pkg) in batch.iter().zip(fetched) {
+            packages[i] = pkg;
+        }
+    }
+
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GlobalOption, VersionConstraint};
+    use crate::test_support::{MockResponse, MockServer};
+
+    fn package(name: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            ..Package::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_resumes_after_completed_packages() {
+        let server = MockServer::start(|request| {
+            let name = request.path.split('/').nth(2).unwrap_or_default();
+            MockResponse::json(format!(
+                r#"{{"info":{{"name":"{}","version":"1.1.0","summary":""}}}}"#,
+                name
+            ))
+        });
+        let mut client = PyPIClient::new();
+        client.apply_options(&[GlobalOption::IndexUrl(server.url("/pypi"))]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("check.checkpoint");
+        let mut packages: Vec<Package> = ["a", "b", "c", "d", "e"].into_iter().map(package).collect();
+
+        // The first run gets through two batches of one before it is killed.
+        let mut checkpoint = Checkpoint::open(&path).unwrap();
+        update_resumable(&client, &mut packages[..2], &mut checkpoint, 1).await.unwrap();
+        drop(checkpoint);
+
+        let mut checkpoint = Checkpoint::open(&path).unwrap();
+        assert_eq!(checkpoint.len(), 2);
+        let mut fresh: Vec<Package> = ["a", "b", "c", "d", "e"].into_iter().map(package).collect();
+        fresh[0].selected = true;
+        fresh[0].notes = vec!["kept".to_string()];
+        let restored = update_resumable(&client, &mut fresh, &mut checkpoint, 2).await.unwrap();
+
+        assert_eq!(restored, 2);
+        assert!(fresh[0].selected);
+        assert_eq!(fresh[0].notes, vec!["kept"]);
+        assert_eq!(fresh[0].status, VersionStatus::Minor);
+        for name in ["a", "b", "c", "d", "e"] {
+            assert_eq!(server.hits(&format!("/pypi/{}/json", name)), 1, "{}", name);
+        }
+        assert!(fresh.iter().all(|p| p.latest_version.as_deref() == Some("1.1.0")));
+
+        // The same name from another file is a different entry.
+        let mut other = package("a");
+        other.origin_file = Some("requirements-dev.txt".to_string());
+        assert!(!checkpoint.restore(&mut other));
+
+        checkpoint.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
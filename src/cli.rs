@@ -0,0 +1,295 @@
+use crate::glyphs::GlyphSet;
+use crate::models::{ConstraintUpdate, RelaxStrategy, ResolutionStrategy, Severity};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "PyElevate")]
+#[command(about = "Professional-grade Python dependency manager with AI-powered insights", long_about = None)]
+#[command(version = "0.2.0")]
+#[command(author = "Soumalya Das")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    #[arg(short, long)]
+    pub requirements: Option<String>,
+
+    #[arg(short, long)]
+    pub dry_run: bool,
+
+    #[arg(short, long)]
+    pub lock: bool,
+
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    #[arg(long, global = true)]
+    pub summary: bool,
+
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Target Python for wheel and Requires-Python checks, e.g. 3.9 (default: python3 on PATH)
+    #[arg(long, global = true, value_name = "VERSION", value_parser = crate::wheels::parse_python_version)]
+    pub python_version: Option<(u32, u32)>,
+
+    /// Also read this pyproject.toml dependency group; repeatable
+    #[arg(long = "group", value_name = "NAME", global = true)]
+    pub groups: Vec<String>,
+
+    /// Read every pyproject.toml dependency group
+    #[arg(long, global = true)]
+    pub all_groups: bool,
+
+    /// Only look up versions: skip the security, changelog and popularity lookups
+    #[arg(long, global = true)]
+    pub no_enrich: bool,
+
+    /// Symbols to draw with; ascii for terminals or fonts without emoji [default: emoji]
+    #[arg(long, value_enum, global = true)]
+    pub glyphs: Option<GlyphSet>,
+
+    /// Read settings from FILE instead of the user and ./.pyelevate.toml configs
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Re-check PyPI every N minutes while the interactive UI is open
+    #[arg(long, value_name = "MINUTES")]
+    pub refresh_interval: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    Check {
+        #[arg(short, long)]
+        requirements: Option<String>,
+
+        #[arg(long)]
+        retry_errors: bool,
+
+        /// Compare the requirements against `pip freeze` of the active environment
+        #[arg(long, conflicts_with = "porcelain")]
+        against_installed: bool,
+
+        #[arg(long, default_value = "pip")]
+        pip: String,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Stable tab-separated output for scripts: name, current, latest, status, security
+        #[arg(long, conflicts_with = "format")]
+        porcelain: bool,
+
+        /// Cross-check a custom index against pypi.org and warn on differences
+        #[arg(long)]
+        verify_index: bool,
+
+        /// Save results to FILE as they complete and resume from it after an interruption
+        #[arg(long, value_name = "FILE")]
+        checkpoint: Option<String>,
+
+        /// Skip packages whose environment markers exclude this platform and interpreter
+        #[arg(long)]
+        current_env_only: bool,
+
+        /// Exit non-zero if any PyPI package's status is Unknown or Error
+        #[arg(long)]
+        strict: bool,
+
+        /// Don't count advisory ID against its package; repeatable, adds to [security] ignore
+        #[arg(long = "ignore-vuln", value_name = "ID")]
+        ignore_vulns: Vec<String>,
+
+        /// Only advisories this severe or worse mark a package vulnerable; overrides [security] min_severity
+        #[arg(long, value_enum)]
+        min_severity: Option<Severity>,
+
+        /// Skip releases uploaded fewer than DAYS days ago when picking the target
+        #[arg(long, value_name = "DAYS")]
+        min_age: Option<u32>,
+
+        /// Append this run's summary to the history that `trend` compares against
+        #[arg(long)]
+        history: bool,
+    },
+    Upgrade {
+        /// Requirements file to upgrade; repeat to upgrade several at once
+        #[arg(short, long)]
+        requirements: Vec<String>,
+
+        #[arg(short, long)]
+        dry_run: bool,
+
+        #[arg(short, long)]
+        lock: bool,
+
+        #[arg(long, value_enum, default_value_t = ResolutionStrategy::Latest)]
+        strategy: ResolutionStrategy,
+
+        /// How upgraded lines are rewritten: keep the operator, or `>=`, `==` or `~=` the new version
+        #[arg(long, value_enum, default_value_t = ConstraintUpdate::Keep)]
+        update_constraint: ConstraintUpdate,
+
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Target the newest release with a wheel for this platform
+        #[arg(long)]
+        prefer_wheels: bool,
+
+        /// Cross-check a custom index against pypi.org and warn on differences
+        #[arg(long)]
+        verify_index: bool,
+
+        /// Skip packages whose environment markers exclude this platform and interpreter
+        #[arg(long)]
+        current_env_only: bool,
+
+        /// Skip releases uploaded fewer than DAYS days ago when picking the target
+        #[arg(long, value_name = "DAYS")]
+        min_age: Option<u32>,
+    },
+    Simulate {
+        #[arg(short, long)]
+        requirements: Option<String>,
+    },
+    /// Scan for known vulnerabilities only, failing on advisories at or above the threshold
+    Audit {
+        #[arg(short, long)]
+        requirements: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = Severity::Low)]
+        severity_threshold: Severity,
+
+        /// Pass even if some packages could not be checked
+        #[arg(long)]
+        allow_lookup_failures: bool,
+
+        /// Don't count advisory ID against its package; repeatable, adds to [security] ignore
+        #[arg(long = "ignore-vuln", value_name = "ID")]
+        ignore_vulns: Vec<String>,
+    },
+    /// Print what the parser sees in the requirements file, without any network calls
+    List {
+        #[arg(short, long)]
+        requirements: Option<String>,
+    },
+    /// Write requirements.txt.lock from the current requirements
+    Lock {
+        #[arg(short, long)]
+        requirements: Option<String>,
+
+        /// Don't write; exit non-zero if the requirements have drifted from the lock file
+        #[arg(long)]
+        check: bool,
+    },
+    /// Loosen a pinned file into a requirements.in: `==` pins become ranges,
+    /// hashes, annotations and transitive pins are dropped
+    Relax {
+        #[arg(short, long)]
+        requirements: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = RelaxStrategy::Compatible)]
+        strategy: RelaxStrategy,
+
+        /// Write here instead of to stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Print a package's release notes: the latest release, or every release
+    /// after --from up to --to (the latest by default)
+    Changelog {
+        package: String,
+
+        /// Start after this version, e.g. the one you're on
+        #[arg(long, value_name = "VERSION")]
+        from: Option<String>,
+
+        /// End at this version
+        #[arg(long, value_name = "VERSION")]
+        to: Option<String>,
+    },
+    /// Compare the current state against the last `check --history` run
+    Trend {
+        #[arg(short, long)]
+        requirements: Option<String>,
+    },
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the JSON Schema of the `check --format json` report
+    Schema,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+pub fn command() -> clap::Command {
+    Cli::command()
+}
+
+/// Overrides the config's `default_command`.
+pub const DEFAULT_COMMAND_ENV: &str = "PYELEVATE_DEFAULT_CMD";
+
+/// The subcommand a bare invocation runs, from `PYELEVATE_DEFAULT_CMD` or
+/// else the config's `default_command`. `None` means the interactive UI.
+pub fn default_command(env: Option<&str>, config: Option<&str>) -> anyhow::Result<Option<Commands>> {
+    let Some(spec) = env.or(config).map(str::trim).filter(|spec| !spec.is_empty()) else {
+        return Ok(None);
+    };
+    if spec.eq_ignore_ascii_case("tui") {
+        return Ok(None);
+    }
+    let args = std::iter::once("pyelevate").chain(spec.split_whitespace());
+    let cli = Cli::try_parse_from(args)
+        .map_err(|e| anyhow::anyhow!("Invalid default command `{}`: {}", spec, e.kind()))?;
+    Ok(cli.command)
+}
+
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+    clap_complete::generate(shell, &mut command(), "pyelevate", out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_against_installed_is_rejected_with_porcelain() {
+        use clap::Parser;
+        assert!(Cli::try_parse_from(["pyelevate", "check", "--against-installed", "--porcelain"]).is_err());
+        assert!(Cli::try_parse_from(["pyelevate", "check", "--against-installed", "--format", "json"]).is_ok());
+    }
+
+    #[test]
+    fn test_default_command_from_config_routes_to_check() {
+        let config = crate::config::Config::from_toml("default_command = \"check --strict\"\n").unwrap();
+        let command = default_command(None, config.default_command.as_deref()).unwrap();
+        assert!(matches!(command, Some(Commands::Check { strict: true, requirements: None, .. })));
+
+        assert!(default_command(None, None).unwrap().is_none());
+        assert!(default_command(Some("tui"), Some("check")).unwrap().is_none());
+        assert!(matches!(default_command(Some("audit"), Some("check")).unwrap(), Some(Commands::Audit { .. })));
+        assert!(default_command(None, Some("frobnicate")).is_err());
+    }
+
+    #[test]
+    fn test_bash_completions_list_subcommands() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+
+        assert!(!script.is_empty());
+        for subcommand in ["check", "upgrade", "simulate", "completions", "schema"] {
+            assert!(script.contains(subcommand), "missing {}", subcommand);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::glyphs::GlyphSet;
+use crate::keymap::KeysConfig;
+use crate::models::{Severity, VersionStatus};
+use clap::ValueEnum;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub sort: SortConfig,
+    pub http: HttpConfig,
+    pub watch: WatchConfig,
+    pub security: SecurityConfig,
+    pub keys: KeysConfig,
+    pub list: ListConfig,
+    pub cache: CacheConfig,
+    /// `emoji` (the default), `nerd-font` or `ascii`; `--glyphs` overrides it.
+    pub glyphs: Option<GlyphSet>,
+    /// What a bare `pyelevate` runs: `tui` (the default) or a subcommand
+    /// with its flags, e.g. `"check --strict"`.
+    pub default_command: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SortConfig {
+    pub status_order: Vec<String>,
+}
+
+/// `[http]`: limits on what the index, advisory and stats servers send back.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// The largest response body read before a lookup fails, in bytes.
+    pub max_body_bytes: Option<usize>,
+}
+
+impl HttpConfig {
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes.unwrap_or(crate::http::DEFAULT_MAX_BODY_BYTES)
+    }
+}
+
+/// Packages to keep an eye on: listed first and highlighted in the UI.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    /// Accepted advisory ids, e.g. `GHSA-xxxx-xxxx-xxxx` or `CVE-2023-1234`.
+    pub ignore: Vec<String>,
+    /// The least severe advisory that marks a package vulnerable, e.g.
+    /// `"high"`; anything below it is only noted.
+    #[serde(deserialize_with = "severity_name")]
+    pub min_severity: Option<Severity>,
+}
+
+/// `[list]`: how the package list is drawn.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ListConfig {
+    /// Two lines per package, with security and downloads inline.
+    pub two_line_rows: bool,
+}
+
+/// `[cache]`: how much the interactive UI keeps from its lookups.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Entries per lookup cache (PyPI, advisories, changelogs, downloads)
+    /// before the least recently used are evicted.
+    pub capacity: Option<usize>,
+}
+
+impl CacheConfig {
+    pub fn capacity(&self) -> usize {
+        self.capacity.unwrap_or(crate::cache::DEFAULT_CAPACITY)
+    }
+}
+
+/// The per-project config, looked for in the working directory.
+pub const LOCAL_CONFIG: &str = ".pyelevate.toml";
+
+impl Config {
+    /// The user config: `$XDG_CONFIG_HOME/pyelevate/config.toml` on Linux,
+    /// `~/Library/Application Support/pyelevate/config.toml` on macOS and
+    /// `%APPDATA%\pyelevate\config.toml` on Windows.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("pyelevate").join("config.toml"))
+    }
+
+    /// Loads `explicit` alone when given (`--config`). Otherwise merges the
+    /// user config with `./.pyelevate.toml`, the local file winning key by
+    /// key; the defaults fill in whatever neither sets.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        if let Some(path) = explicit {
+            return Self::from_file(path);
+        }
+        let layers: Vec<PathBuf> = Self::path()
+            .into_iter()
+            .chain(std::iter::once(PathBuf::from(LOCAL_CONFIG)))
+            .filter(|path| path.exists())
+            .collect();
+        Self::from_files(&layers)
+    }
+
+    /// Merges `paths` in order, later files overriding earlier ones.
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self> {
+        let mut merged = toml::Value::Table(Default::default());
+        for path in paths {
+            let content = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Cannot read config {}: {}", path.display(), e))?;
+            let layer: toml::Value =
+                toml::from_str(&content).map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+            merge(&mut merged, layer);
+        }
+        merged
+            .try_into()
+            .map_err(|e| anyhow!("Invalid config: {}", e))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_files(&[path.to_path_buf()])
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+/// Overlays `layer` onto `base`: tables merge recursively, anything else
+/// (including arrays) is replaced.
+fn merge(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+impl SortConfig {
+    /// The configured statuses first, then any left out in their default
+    /// priority order.
+    pub fn status_order(&self) -> Result<Vec<VersionStatus>> {
+        let mut order = Vec::new();
+        for name in &self.status_order {
+            let status = VersionStatus::from_name(name)
+                .ok_or_else(|| anyhow!("Unknown status in [sort] status_order: {}", name))?;
+            if !order.contains(&status) {
+                order.push(status);
+            }
+        }
+
+        for status in VersionStatus::default_order() {
+            if !order.contains(&status) {
+                order.push(status);
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+impl SecurityConfig {
+    pub fn min_severity(&self) -> Severity {
+        self.min_severity.unwrap_or(Severity::Low)
+    }
+}
+
+/// Reads a severity by the name `--min-severity` takes, e.g. `"high"`.
+fn severity_name<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Severity>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Severity::from_str(&name, true)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown severity: {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_order_from_config() {
+        let config = Config::from_toml("[sort]\nstatus_order = [\"major\", \"vulnerable\"]\n").unwrap();
+        let order = config.sort.status_order().unwrap();
+        assert_eq!(&order[..3], &[VersionStatus::Major, VersionStatus::Vulnerable, VersionStatus::Error]);
+        assert_eq!(order.len(), VersionStatus::default_order().len());
+
+        assert_eq!(Config::default().sort.status_order().unwrap(), VersionStatus::default_order());
+        assert!(Config::from_toml("[sort]\nstatus_order = [\"bogus\"]\n")
+            .unwrap()
+            .sort
+            .status_order()
+            .is_err());
+    }
+
+    #[test]
+    fn test_max_body_bytes_from_config() {
+        let config = Config::from_toml("[http]\nmax_body_bytes = 1048576\n").unwrap();
+        assert_eq!(config.http.max_body_bytes(), 1048576);
+        assert_eq!(Config::default().http.max_body_bytes(), crate::http::DEFAULT_MAX_BODY_BYTES);
+    }
+
+    #[test]
+    fn test_watch_packages_from_config() {
+        let config = Config::from_toml("[watch]\npackages = [\"Django\", \"requests\"]\n").unwrap();
+        assert_eq!(config.watch.packages, vec!["Django", "requests"]);
+        assert!(Config::default().watch.packages.is_empty());
+    }
+
+    #[test]
+    fn test_min_severity_from_config() {
+        let config = Config::from_toml("[security]\nmin_severity = \"high\"\n").unwrap();
+        assert_eq!(config.security.min_severity(), Severity::High);
+        assert_eq!(Config::default().security.min_severity(), Severity::Low);
+        assert!(Config::from_toml("[security]\nmin_severity = \"severe\"\n").is_err());
+    }
+
+    #[test]
+    fn test_local_config_overrides_global() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("config.toml");
+        let local = dir.path().join(LOCAL_CONFIG);
+        fs::write(
+            &global,
+            "default_command = \"check\"\n[security]\nmin_severity = \"low\"\nignore = [\"CVE-2023-1\"]\n",
+        )
+        .unwrap();
+        fs::write(&local, "[security]\nmin_severity = \"high\"\n").unwrap();
+
+        let config = Config::from_files(&[global.clone(), local]).unwrap();
+        assert_eq!(config.security.min_severity(), Severity::High);
+        assert_eq!(config.security.ignore, vec!["CVE-2023-1"]);
+        assert_eq!(config.default_command.as_deref(), Some("check"));
+
+        let explicit = Config::load(Some(&global)).unwrap();
+        assert_eq!(explicit.security.min_severity(), Severity::Low);
+        assert!(Config::load(Some(&dir.path().join("missing.toml"))).is_err());
+    }
+}
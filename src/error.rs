@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+/// Failures from the PyPI client, the requirements parser and the security
+/// checker. The binary reports them through `anyhow`; library callers can
+/// match on the kind instead of parsing messages.
+#[derive(Debug, Error)]
+pub enum PyElevateError {
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("{0} was not found on the index")]
+    NotFound(String),
+
+    #[error("rate limited by {0}")]
+    RateLimited(String),
+
+    #[error("response from {0} exceeds the {1}-byte limit")]
+    TooLarge(String, usize),
+
+    #[error("parse error: {0}")]
+    Parse(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<serde_json::Error> for PyElevateError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, PyElevateError>;
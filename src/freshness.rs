@@ -0,0 +1,116 @@
+use crate::models::{compare_release, Package};
+use crate::version::Pep440Version;
+use chrono::{DateTime, Duration, Utc};
+
+/// How far a pin trails the latest release, with the project's release
+/// cadence for context: three versions behind a project that ships weekly
+/// is a few weeks, behind one that ships yearly it's years.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Freshness {
+    /// Final releases newer than the pin, up to and including the latest.
+    pub versions_behind: usize,
+    /// Between the pin's upload and the latest's, when both are known.
+    pub time_behind: Option<Duration>,
+    /// Average time between consecutive final releases.
+    pub cadence: Option<Duration>,
+    pub releases: usize,
+}
+
+/// Measures `pkg` against its `(version, first upload)` release history.
+pub fn measure(pkg: &Package, releases: &[(String, DateTime<Utc>)]) -> Freshness {
+    let finals: Vec<&(String, DateTime<Utc>)> = releases
+        .iter()
+        .filter(|(version, _)| Pep440Version::parse(version).is_some_and(|v| !v.is_prerelease()))
+        .collect();
+    let uploaded = |version: &str| {
+        releases
+            .iter()
+            .find(|(v, _)| compare_release(v, version).is_eq())
+            .map(|(_, at)| *at)
+    };
+
+    let latest = pkg.latest_version.as_deref();
+    let versions_behind = finals
+        .iter()
+        .filter(|(v, _)| compare_release(v, &pkg.current_version).is_gt())
+        .filter(|(v, _)| latest.is_none_or(|latest| compare_release(v, latest).is_le()))
+        .count();
+    let time_behind = match (uploaded(&pkg.current_version), latest.and_then(uploaded)) {
+        (Some(current), Some(latest)) if latest > current => Some(latest - current),
+        (Some(_), Some(_)) => Some(Duration::zero()),
+        _ => None,
+    };
+
+    Freshness {
+        versions_behind,
+        time_behind,
+        cadence: release_cadence(releases),
+        releases: finals.len(),
+    }
+}
+
+/// Average gap between consecutive final releases by upload date; `None`
+/// with fewer than two.
+pub fn release_cadence(releases: &[(String, DateTime<Utc>)]) -> Option<Duration> {
+    let mut dates: Vec<DateTime<Utc>> = releases
+        .iter()
+        .filter(|(version, _)| Pep440Version::parse(version).is_some_and(|v| !v.is_prerelease()))
+        .map(|(_, at)| *at)
+        .collect();
+    dates.sort();
+    let (first, last) = (dates.first()?, dates.last()?);
+    let gaps = i32::try_from(dates.len() - 1).ok().filter(|&gaps| gaps > 0)?;
+    Some((*last - *first) / gaps)
+}
+
+/// `12 days`, `5 months`, `2.5 years`.
+pub fn describe(duration: Duration) -> String {
+    let days = duration.num_days();
+    match days {
+        0 => "under a day".to_string(),
+        1 => "1 day".to_string(),
+        2..=59 => format!("{} days", days),
+        60..=729 => format!("{} months", days / 30),
+        _ => format!("{:.1} years", days as f64 / 365.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{VersionConstraint, VersionStatus};
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_release_cadence_averages_gaps_between_final_releases() {
+        let releases = vec![
+            ("1.2.0".to_string(), at(2024, 3, 31)),
+            ("1.0.0".to_string(), at(2024, 1, 1)),
+            ("1.1.0".to_string(), at(2024, 1, 31)),
+            ("1.2.0rc1".to_string(), at(2024, 3, 1)),
+        ];
+        assert_eq!(release_cadence(&releases), Some(Duration::days(45)));
+        assert_eq!(release_cadence(&releases[..1]), None);
+
+        let mut pkg = Package {
+            name: "demo".to_string(),
+            current_version: "1.0.0".to_string(),
+            latest_version: Some("1.2.0".to_string()),
+            status: VersionStatus::Minor,
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            ..Package::default()
+        };
+        let freshness = measure(&pkg, &releases);
+        assert_eq!(freshness.versions_behind, 2);
+        assert_eq!(freshness.time_behind, Some(Duration::days(90)));
+        assert_eq!(freshness.releases, 3);
+        assert_eq!(describe(freshness.time_behind.unwrap()), "3 months");
+
+        pkg.current_version = "1.2.0".to_string();
+        assert_eq!(measure(&pkg, &releases).versions_behind, 0);
+    }
+}
@@ -0,0 +1,285 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use unicode_width::UnicodeWidthChar;
+
+/// The symbols the UI and reports draw with. Emoji need a color emoji font,
+/// Nerd Font icons a patched font, and ASCII works anywhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GlyphSet {
+    #[default]
+    Emoji,
+    NerdFont,
+    Ascii,
+}
+
+impl GlyphSet {
+    pub fn glyphs(self) -> &'static Glyphs {
+        match self {
+            GlyphSet::Emoji => &EMOJI,
+            GlyphSet::NerdFont => &NERD_FONT,
+            GlyphSet::Ascii => &ASCII,
+        }
+    }
+}
+
+/// One glyph set. The icons at the top lead a line and come padded to a
+/// common width through [`Glyphs::icon`]; the rest sit inside text.
+#[derive(Debug)]
+pub struct Glyphs {
+    pub app: &'static str,
+    pub package: &'static str,
+    pub major: &'static str,
+    pub minor: &'static str,
+    pub patch: &'static str,
+    pub up_to_date: &'static str,
+    pub prerelease: &'static str,
+    pub unknown: &'static str,
+    pub warning: &'static str,
+    pub error: &'static str,
+    pub critical: &'static str,
+    pub shield: &'static str,
+    pub lock: &'static str,
+    pub report: &'static str,
+    pub chart: &'static str,
+    pub impact: &'static str,
+    pub search: &'static str,
+    pub remove: &'static str,
+    pub edit: &'static str,
+    pub busy: &'static str,
+    pub done: &'static str,
+    pub changelog: &'static str,
+    pub clock: &'static str,
+    pub backup: &'static str,
+    pub skipped: &'static str,
+    pub unchanged: &'static str,
+    pub folder: &'static str,
+    pub deprecated: &'static str,
+    /// The widest icon above, in terminal columns.
+    pub icon_width: usize,
+
+    pub check: &'static str,
+    pub arrow: &'static str,
+    pub up_down: &'static str,
+    pub bullet: &'static str,
+    pub dot: &'static str,
+    pub star: &'static str,
+    pub transitive: &'static str,
+    pub downloads: &'static str,
+    pub dash: &'static str,
+    pub rule: &'static str,
+    pub branch: &'static str,
+    pub ellipsis: &'static str,
+    pub times: &'static str,
+    /// Box corners (top left, top right, bottom left, bottom right), then
+    /// the horizontal and vertical edges.
+    pub frame: [&'static str; 6],
+}
+
+impl Glyphs {
+    /// `icon` padded to [`Glyphs::icon_width`] columns, so the text after
+    /// it lines up whichever icon leads.
+    pub fn icon(&self, icon: &str) -> String {
+        pad(icon, self.icon_width)
+    }
+}
+
+/// Columns `text` takes up in a terminal; emoji and CJK count two.
+pub fn width(text: &str) -> usize {
+    cells(text).map(|(_, columns)| columns).sum()
+}
+
+/// `text` with spaces after it to fill `columns`, counting display width
+/// rather than characters.
+pub fn pad(text: &str, columns: usize) -> String {
+    format!("{}{}", text, " ".repeat(columns.saturating_sub(width(text))))
+}
+
+/// `text` cut to at most `columns`, ending in `ellipsis` when shortened.
+pub fn truncate(text: &str, columns: usize, ellipsis: &str) -> String {
+    if width(text) <= columns {
+        return text.to_string();
+    }
+    let budget = columns.saturating_sub(width(ellipsis));
+    let mut kept = String::new();
+    let mut used = 0;
+    for (cell, w) in cells(text) {
+        if used + w > budget {
+            break;
+        }
+        used += w;
+        kept.push_str(cell);
+    }
+    format!("{}{}", kept, ellipsis)
+}
+
+/// `text` split into what a terminal draws as one unit, with its width. A
+/// character followed by VS16 (U+FE0F) asks for emoji presentation, which
+/// terminals draw two columns wide although `unicode-width` counts one.
+fn cells(text: &str) -> impl Iterator<Item = (&str, usize)> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let mut len = c.len_utf8();
+        let mut columns = c.width().unwrap_or(0);
+        if rest[len..].starts_with('\u{fe0f}') {
+            len += '\u{fe0f}'.len_utf8();
+            columns = 2;
+        }
+        let (cell, tail) = rest.split_at(len);
+        rest = tail;
+        Some((cell, columns))
+    })
+}
+
+pub static EMOJI: Glyphs = Glyphs {
+    app: "🚀",
+    package: "📦",
+    major: "🔴",
+    minor: "🟡",
+    patch: "🟢",
+    up_to_date: "✅",
+    prerelease: "🧪",
+    unknown: "❔",
+    warning: "⚠️",
+    error: "❌",
+    critical: "❗",
+    shield: "🛡️",
+    lock: "🔒",
+    report: "📋",
+    chart: "📊",
+    impact: "🧭",
+    search: "🔍",
+    remove: "🗑️",
+    edit: "✏️",
+    busy: "⏳",
+    done: "🎉",
+    changelog: "📝",
+    clock: "⏱️",
+    backup: "💾",
+    skipped: "⏭",
+    unchanged: "➖",
+    folder: "📁",
+    deprecated: "🗑️",
+    icon_width: 2,
+    check: "✓",
+    arrow: "→",
+    up_down: "↑↓",
+    bullet: "•",
+    dot: "●",
+    star: "★",
+    transitive: "↳",
+    downloads: "⬇",
+    dash: "—",
+    rule: "─",
+    branch: "└─",
+    ellipsis: "…",
+    times: "×",
+    frame: ["╔", "╗", "╚", "╝", "═", "║"],
+};
+
+pub static NERD_FONT: Glyphs = Glyphs {
+    app: "\u{f135}",
+    package: "\u{f1b2}",
+    major: "\u{f06a}",
+    minor: "\u{f05a}",
+    patch: "\u{f058}",
+    up_to_date: "\u{f00c}",
+    prerelease: "\u{f0c3}",
+    unknown: "\u{f059}",
+    warning: "\u{f071}",
+    error: "\u{f057}",
+    critical: "\u{f12a}",
+    shield: "\u{f132}",
+    lock: "\u{f023}",
+    report: "\u{f0ea}",
+    chart: "\u{f080}",
+    impact: "\u{f14e}",
+    search: "\u{f002}",
+    remove: "\u{f1f8}",
+    edit: "\u{f040}",
+    busy: "\u{f021}",
+    done: "\u{f091}",
+    changelog: "\u{f15c}",
+    clock: "\u{f017}",
+    backup: "\u{f0c7}",
+    skipped: "\u{f04e}",
+    unchanged: "\u{f068}",
+    folder: "\u{f07b}",
+    deprecated: "\u{f1f8}",
+    icon_width: 1,
+    check: "\u{f00c}",
+    arrow: "→",
+    up_down: "↑↓",
+    bullet: "•",
+    dot: "●",
+    star: "\u{f005}",
+    transitive: "↳",
+    downloads: "\u{f019}",
+    dash: "—",
+    rule: "─",
+    branch: "└─",
+    ellipsis: "…",
+    times: "×",
+    frame: ["╔", "╗", "╚", "╝", "═", "║"],
+};
+
+pub static ASCII: Glyphs = Glyphs {
+    app: ">>",
+    package: "*",
+    major: "!!",
+    minor: "!",
+    patch: "+",
+    up_to_date: "ok",
+    prerelease: "pre",
+    unknown: "?",
+    warning: "!",
+    error: "x",
+    critical: "!!",
+    shield: "+",
+    lock: "#",
+    report: "==",
+    chart: "==",
+    impact: ">",
+    search: "/",
+    remove: "-",
+    edit: "~",
+    busy: "...",
+    done: "**",
+    changelog: "==",
+    clock: "@",
+    backup: "#",
+    skipped: ">>",
+    unchanged: "-",
+    folder: "#",
+    deprecated: "-",
+    icon_width: 3,
+    check: "ok",
+    arrow: "->",
+    up_down: "Up/Down",
+    bullet: "-",
+    dot: "o",
+    star: "*",
+    transitive: "^",
+    downloads: "dl",
+    dash: "-",
+    rule: "-",
+    branch: "`-",
+    ellipsis: "~",
+    times: "x",
+    frame: ["+", "+", "+", "+", "=", "|"],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_presentation_selector_counts_two_columns() {
+        assert_eq!(width("⚠️"), 2);
+        assert_eq!(width("🛡️ ok"), 5);
+        assert_eq!(EMOJI.icon(EMOJI.warning), "⚠️");
+        assert_eq!(pad("✓", 2), "✓ ");
+        assert_eq!(truncate("⚠️⚠️⚠️", 5, "…"), "⚠️⚠️…");
+    }
+}
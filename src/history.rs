@@ -0,0 +1,175 @@
+use crate::glyphs::Glyphs;
+use crate::models::{Package, UpgradeStats};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The summary of one `check --history` run, stored one JSON object per
+/// line so runs for every requirements file share a single history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub timestamp: DateTime<Utc>,
+    pub requirements: String,
+    pub stats: UpgradeStats,
+    /// Names of the packages with open advisories.
+    pub vulnerable: Vec<String>,
+    /// Names of the packages with an upgrade available.
+    pub outdated: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn capture(requirements: &str, packages: &[Package], stats: &UpgradeStats, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            timestamp,
+            requirements: history_key(requirements),
+            stats: stats.clone(),
+            vulnerable: packages
+                .iter()
+                .filter(|p| p.security_status.is_vulnerable())
+                .map(|p| p.name.clone())
+                .collect(),
+            outdated: packages.iter().filter(|p| p.has_upgrade()).map(|p| p.name.clone()).collect(),
+        }
+    }
+}
+
+/// Runs are matched by absolute path, so `check` from another directory
+/// still finds them.
+fn history_key(requirements: &str) -> String {
+    fs::canonicalize(requirements)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| requirements.to_string())
+}
+
+pub fn default_path() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("pyelevate").join("history.jsonl"))
+        .ok_or_else(|| anyhow!("No data directory to keep the run history in"))
+}
+
+pub fn append(path: &Path, snapshot: &Snapshot) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+    Ok(())
+}
+
+/// The newest stored run for `requirements`, if any. Unreadable lines are
+/// skipped.
+pub fn latest(path: &Path, requirements: &str) -> Result<Option<Snapshot>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let key = history_key(requirements);
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Snapshot>(line).ok())
+        .filter(|snapshot| snapshot.requirements == key)
+        .max_by_key(|snapshot| snapshot.timestamp))
+}
+
+/// What changed between two runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trend {
+    pub newly_vulnerable: Vec<String>,
+    pub no_longer_vulnerable: Vec<String>,
+    pub newly_outdated: Vec<String>,
+    pub caught_up: Vec<String>,
+}
+
+pub fn compare(previous: &Snapshot, current: &Snapshot) -> Trend {
+    let added = |before: &[String], after: &[String]| -> Vec<String> {
+        after.iter().filter(|item| !before.contains(item)).cloned().collect()
+    };
+    Trend {
+        newly_vulnerable: added(&previous.vulnerable, &current.vulnerable),
+        no_longer_vulnerable: added(&current.vulnerable, &previous.vulnerable),
+        newly_outdated: added(&previous.outdated, &current.outdated),
+        caught_up: added(&current.outdated, &previous.outdated),
+    }
+}
+
+pub fn trend_report(previous: &Snapshot, current: &Snapshot, g: &Glyphs) -> String {
+    let trend = compare(previous, current);
+    let days = (current.timestamp - previous.timestamp).num_days();
+    let mut lines = vec![format!(
+        "Since the run on {} ({}):",
+        previous.timestamp.format("%Y-%m-%d %H:%M UTC"),
+        match days {
+            0 => "today".to_string(),
+            1 => "1 day ago".to_string(),
+            days => format!("{} days ago", days),
+        }
+    )];
+    lines.push(format!(
+        "  Vulnerable: {} {} {}",
+        previous.vulnerable.len(),
+        g.arrow,
+        current.vulnerable.len()
+    ));
+    lines.push(format!(
+        "  Outdated: {} {} {}",
+        previous.outdated.len(),
+        g.arrow,
+        current.outdated.len()
+    ));
+
+    for (title, items) in [
+        ("New vulnerabilities", &trend.newly_vulnerable),
+        ("No longer vulnerable", &trend.no_longer_vulnerable),
+        ("Newly outdated", &trend.newly_outdated),
+        ("Caught up", &trend.caught_up),
+    ] {
+        if !items.is_empty() {
+            lines.push(format!("  {} ({}): {}", title, items.len(), items.join(", ")));
+        }
+    }
+    if trend == Trend::default() {
+        lines.push("  No packages changed".to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot(day: u32, vulnerable: &[&str], outdated: &[&str]) -> Snapshot {
+        Snapshot {
+            timestamp: Utc.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap(),
+            requirements: "requirements.txt".to_string(),
+            stats: UpgradeStats::new(&[]),
+            vulnerable: vulnerable.iter().map(|s| s.to_string()).collect(),
+            outdated: outdated.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare_stored_snapshots_reports_delta() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        append(&path, &snapshot(1, &["django"], &["django", "flask"])).unwrap();
+        append(&path, &snapshot(7, &["django", "pyyaml"], &["django", "requests"])).unwrap();
+        assert!(latest(&path, "other.txt").unwrap().is_none());
+
+        let previous = latest(&path, "requirements.txt").unwrap().unwrap();
+        assert_eq!(previous.timestamp.format("%d").to_string(), "07");
+        let current = snapshot(14, &["pyyaml", "urllib3"], &["requests", "urllib3"]);
+
+        let trend = compare(&previous, &current);
+        assert_eq!(trend.newly_vulnerable, vec!["urllib3"]);
+        assert_eq!(trend.no_longer_vulnerable, vec!["django"]);
+        assert_eq!(trend.newly_outdated, vec!["urllib3"]);
+        assert_eq!(trend.caught_up, vec!["django"]);
+
+        let report = trend_report(&previous, &current, &crate::glyphs::EMOJI);
+        assert!(report.starts_with("Since the run on 2026-10-07 09:00 UTC (7 days ago):"));
+        assert!(report.contains("  New vulnerabilities (1): urllib3"));
+    }
+}
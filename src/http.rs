@@ -0,0 +1,49 @@
+//! Bounded reads of index and advisory responses, so a broken or hostile
+//! server can't exhaust memory or bury the cause of a failure.
+
+use crate::error::{PyElevateError, Result};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+/// Default cap on a response body, well above PyPI's largest project pages.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+/// How much of a malformed body is quoted in the parse error.
+const SNIPPET_CHARS: usize = 120;
+
+/// Reads the body as text, giving up as soon as it grows past `limit` bytes
+/// (or immediately, when `Content-Length` already says it will).
+pub async fn read_body(mut response: Response, limit: usize) -> Result<String> {
+    let url = response.url().to_string();
+    if response.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(PyElevateError::TooLarge(url, limit));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(PyElevateError::TooLarge(url, limit));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Parses `body`, quoting its start on failure so an HTML error page or a
+/// truncated reply is recognisable from the message alone.
+pub fn parse_json<T: DeserializeOwned>(url: &str, body: &str) -> Result<T> {
+    serde_json::from_str(body).map_err(|e| {
+        let mut snippet: String = body.chars().take(SNIPPET_CHARS).collect();
+        if body.chars().count() > SNIPPET_CHARS {
+            snippet.push('…');
+        }
+        PyElevateError::Parse(format!("invalid JSON from {}: {} (body: {:?})", url, e, snippet))
+    })
+}
+
+/// [`read_body`] followed by [`parse_json`].
+pub async fn read_json<T: DeserializeOwned>(response: Response, limit: usize) -> Result<T> {
+    let url = response.url().to_string();
+    let body = read_body(response, limit).await?;
+    parse_json(&url, &body)
+}
@@ -0,0 +1,187 @@
+use crate::models::{compare_release, normalize_package_name, Package, VersionConstraint};
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Drift {
+    VersionMismatch {
+        name: String,
+        required: String,
+        installed: String,
+    },
+    NotInstalled {
+        name: String,
+        required: String,
+    },
+    NotRequired {
+        name: String,
+        installed: String,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::VersionMismatch { name, required, installed } => {
+                write!(f, "{}: requires {} but {} is installed", name, required, installed)
+            }
+            Drift::NotInstalled { name, required } => {
+                write!(f, "{}: requires {} but it is not installed", name, required)
+            }
+            Drift::NotRequired { name, installed } => {
+                write!(f, "{}: {} is installed but not required", name, installed)
+            }
+        }
+    }
+}
+
+/// Runs `<pip> freeze` and returns installed versions keyed by normalized name.
+pub fn pip_freeze(pip: &str) -> Result<BTreeMap<String, String>> {
+    let output = Command::new(pip)
+        .arg("freeze")
+        .output()
+        .map_err(|e| anyhow!("Failed to run `{} freeze`: {}", pip, e))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "`{} freeze` failed: {}",
+            pip,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(parse_freeze(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `name==version` lines; editable and direct-URL installs have no
+/// comparable version and are recorded with the raw requirement instead.
+pub fn parse_freeze(output: &str) -> BTreeMap<String, String> {
+    let mut installed = BTreeMap::new();
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("-e") {
+            continue;
+        }
+
+        if let Some((name, version)) = line.split_once("==") {
+            installed.insert(normalize_package_name(name.trim()), version.trim().to_string());
+        } else if let Some((name, source)) = line.split_once(" @ ") {
+            installed.insert(normalize_package_name(name.trim()), source.trim().to_string());
+        }
+    }
+
+    installed
+}
+
+pub fn compare_installed(packages: &[Package], installed: &BTreeMap<String, String>) -> Vec<Drift> {
+    let mut drifts = Vec::new();
+    let mut required = Vec::new();
+
+    for pkg in packages {
+        let name = normalize_package_name(&pkg.name);
+        let spec = match pkg.constraint.as_str() {
+            spec if spec.is_empty() => "any version".to_string(),
+            spec => spec,
+        };
+        match installed.get(&name) {
+            Some(version) => {
+                if let VersionConstraint::Pinned(pinned) = &pkg.constraint {
+                    if compare_release(pinned, version).is_ne() {
+                        drifts.push(Drift::VersionMismatch {
+                            name: pkg.name.clone(),
+                            required: spec,
+                            installed: version.clone(),
+                        });
+                    }
+                }
+            }
+            None => drifts.push(Drift::NotInstalled {
+                name: pkg.name.clone(),
+                required: spec,
+            }),
+        }
+        required.push(name);
+    }
+
+    for (name, version) in installed {
+        if !required.contains(name) {
+            drifts.push(Drift::NotRequired {
+                name: name.clone(),
+                installed: version.clone(),
+            });
+        }
+    }
+
+    drifts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_requirements;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reports_drift_against_fake_pip() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pip = dir.path().join("pip");
+        std::fs::write(
+            &pip,
+            "#!/bin/sh\nprintf 'Flask==2.0.0\\nrequests==2.31.0\\nsix==1.16.0\\n'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&pip, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let requirements = dir.path().join("requirements.txt");
+        std::fs::write(&requirements, "flask==2.0\nrequests==2.28.0\nhttpx>=0.24\n").unwrap();
+        let packages = parse_requirements(&requirements).unwrap().packages;
+
+        let installed = pip_freeze(pip.to_str().unwrap()).unwrap();
+        let drifts = compare_installed(&packages, &installed);
+
+        assert_eq!(
+            drifts,
+            vec![
+                Drift::NotInstalled {
+                    name: "httpx".to_string(),
+                    required: ">=0.24".to_string(),
+                },
+                Drift::VersionMismatch {
+                    name: "requests".to_string(),
+                    required: "==2.28.0".to_string(),
+                    installed: "2.31.0".to_string(),
+                },
+                Drift::NotRequired {
+                    name: "six".to_string(),
+                    installed: "1.16.0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pins_compare_as_pep440_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let requirements = dir.path().join("requirements.txt");
+        std::fs::write(&requirements, "torch==2.1\nnumpy==1.26.0rc1\n").unwrap();
+        let packages = parse_requirements(&requirements).unwrap().packages;
+
+        let installed = parse_freeze("torch==2.1.0\nnumpy==1.26.0\n");
+        assert_eq!(
+            compare_installed(&packages, &installed),
+            vec![Drift::VersionMismatch {
+                name: "numpy".to_string(),
+                required: "==1.26.0rc1".to_string(),
+                installed: "1.26.0".to_string(),
+            }]
+        );
+    }
+}
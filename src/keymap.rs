@@ -0,0 +1,138 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Cursor movements a key binding can trigger in the package list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// `[keys]` in the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// Start from the vim bindings: `j`/`k`, `Ctrl+d`/`Ctrl+u`, `gg`/`G`.
+    pub vim: bool,
+    /// Extra bindings, e.g. `"ctrl+n" = "down"`; they override the preset.
+    pub bindings: HashMap<String, Action>,
+}
+
+/// Key sequences checked before the built-in shortcuts. A sequence is one
+/// `ctrl+x` chord, or a run of plain characters such as `gg`.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Vec<String>, Action)>,
+    /// Whether an unbound letter starts a search, as it does by default.
+    /// Vim bindings turn this off; `/` still searches.
+    pub type_to_search: bool,
+    /// Whether moving past either end of the list wraps to the other, as it
+    /// does with vim bindings.
+    pub wrap: bool,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Vec::new(),
+            type_to_search: true,
+            wrap: false,
+        }
+    }
+}
+
+/// What a key does given the keys pressed before it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup {
+    Action(Action),
+    /// The key starts a longer sequence; wait for the next one.
+    Pending,
+    Unbound,
+}
+
+impl Keymap {
+    pub fn vim() -> Self {
+        let mut keymap = Self {
+            type_to_search: false,
+            wrap: true,
+            ..Self::default()
+        };
+        for (keys, action) in [
+            ("j", Action::Down),
+            ("k", Action::Up),
+            ("ctrl+d", Action::PageDown),
+            ("ctrl+u", Action::PageUp),
+            ("gg", Action::Top),
+            ("G", Action::Bottom),
+        ] {
+            keymap.bind(keys, action);
+        }
+        keymap
+    }
+
+    pub fn from_config(config: &KeysConfig) -> Self {
+        let mut keymap = if config.vim { Self::vim() } else { Self::default() };
+        for (keys, action) in &config.bindings {
+            keymap.bind(keys, *action);
+        }
+        keymap
+    }
+
+    pub fn bind(&mut self, keys: &str, action: Action) {
+        let sequence = parse_sequence(keys);
+        self.bindings.retain(|(existing, _)| *existing != sequence);
+        self.bindings.push((sequence, action));
+    }
+
+    /// Whether `keys` is bound, e.g. `G` under the vim preset.
+    pub fn is_bound(&self, keys: &str) -> bool {
+        let sequence = parse_sequence(keys);
+        self.bindings.iter().any(|(existing, _)| *existing == sequence)
+    }
+
+    /// Looks up `key` following the still-pending keys in `pending`.
+    pub fn lookup(&self, pending: &[String], key: &KeyEvent) -> Lookup {
+        let Some(name) = key_name(key) else {
+            return Lookup::Unbound;
+        };
+        let mut sequence = pending.to_vec();
+        sequence.push(name);
+
+        let mut lookup = Lookup::Unbound;
+        for (keys, action) in &self.bindings {
+            if *keys == sequence {
+                return Lookup::Action(*action);
+            }
+            if keys.starts_with(&sequence) {
+                lookup = Lookup::Pending;
+            }
+        }
+        lookup
+    }
+}
+
+fn parse_sequence(keys: &str) -> Vec<String> {
+    if keys.contains('+') {
+        vec![keys.to_lowercase()]
+    } else {
+        keys.chars().map(|c| c.to_string()).collect()
+    }
+}
+
+/// `j`, `G` or `ctrl+d`; `None` for keys bindings can't name.
+fn key_name(key: &KeyEvent) -> Option<String> {
+    let KeyCode::Char(c) = key.code else {
+        return None;
+    };
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        Some(format!("ctrl+{}", c.to_ascii_lowercase()))
+    } else {
+        Some(c.to_string())
+    }
+}
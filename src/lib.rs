@@ -1,7 +1,19 @@
+pub mod aliases;
 pub mod app;
+pub mod cache;
+pub mod checkpoint;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod glyphs;
+pub mod history;
+pub mod http;
+pub mod keymap;
+pub mod markers;
 pub mod models;
 pub mod parser;
 pub mod pypi;
+pub mod pyproject;
 pub mod styles;
 pub mod ui;
 pub mod upgrade;
@@ -11,12 +23,21 @@ pub mod popularity;
 pub mod resolver;
 pub mod simulator;
 pub mod panels;
+pub mod version;
+pub mod wheels;
+pub mod installed;
+pub mod freshness;
+pub mod report;
+
+#[cfg(test)]
+mod test_support;
 
 pub use app::App;
+pub use error::PyElevateError;
 pub use models::{Package, VersionStatus};
 pub use parser::parse_requirements;
 pub use pypi::PyPIClient;
-pub use upgrade::{UpgradeManager, UpgradeResult};
+pub use upgrade::{UpgradeManager, UpgradePlan, UpgradeResult};
 pub use security::SecurityChecker;
 pub use changelog::ChangelogFetcher;
 pub use popularity::PopularityChecker;
@@ -1,19 +1,5 @@
-mod app;
-mod models;
-mod parser;
-mod pypi;
-mod styles;
-mod ui;
-mod upgrade;
-mod security;
-mod changelog;
-mod popularity;
-mod resolver;
-mod simulator;
-mod panels;
-
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::Parser;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
@@ -23,98 +9,232 @@ use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
-use std::io;
+use std::io::{self, IsTerminal};
 use std::path::Path;
+use std::time::Instant;
 use tracing::info;
 
-use app::App;
-use parser::parse_requirements;
-use ui::draw;
-use upgrade::UpgradeManager;
-
-#[derive(Parser)]
-#[command(name = "PyElevate")]
-#[command(about = "Professional-grade Python dependency manager with AI-powered insights", long_about = None)]
-#[command(version = "0.2.0")]
-#[command(author = "Soumalya Das")]
-struct Cli {
-    #[command(subcommand)]
-    command: Option<Commands>,
-
-    #[arg(short, long)]
-    requirements: Option<String>,
-
-    #[arg(short, long)]
-    dry_run: bool,
-
-    #[arg(short, long)]
-    lock: bool,
-
-    #[arg(short, long)]
-    verbose: bool,
-}
-
-#[derive(Subcommand)]
-enum Commands {
-    Check {
-        #[arg(short, long)]
-        requirements: Option<String>,
-    },
-    Upgrade {
-        #[arg(short, long)]
-        requirements: Option<String>,
-
-        #[arg(short, long)]
-        dry_run: bool,
-
-        #[arg(short, long)]
-        lock: bool,
-    },
-    Simulate {
-        #[arg(short, long)]
-        requirements: Option<String>,
-    },
-}
+use pyelevate::app::{self, App};
+use pyelevate::cli::{Cli, Commands, OutputFormat};
+use pyelevate::checkpoint::{self, Checkpoint};
+use pyelevate::config::Config;
+use pyelevate::glyphs::{GlyphSet, Glyphs};
+use pyelevate::keymap::Keymap;
+use pyelevate::models::{
+    normalize_package_name, ConstraintUpdate, DependencySource, GlobalOption, Package, RelaxStrategy, RequirementsFile,
+    ResolutionStrategy, Severity, UpgradeStats, VersionConstraint, VersionStatus,
+};
+use pyelevate::parser::parse_requirements;
+use pyelevate::pyproject::{self, GroupSelection};
+use pyelevate::resolver::{self, apply_min_age, apply_strategy, DependencyResolver};
+use pyelevate::security::SecurityChecker;
+use pyelevate::ui::draw;
+use pyelevate::upgrade::UpgradeManager;
+use pyelevate::{aliases, history, installed, markers, pypi, report, security, simulator, wheels};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    // Only a bare invocation needs the config this early; completions and
+    // the schema must keep working with a broken one.
+    let mut config = None;
+    if cli.command.is_none() {
+        let loaded = Config::load(cli.config.as_deref())?;
+        let env = std::env::var(pyelevate::cli::DEFAULT_COMMAND_ENV).ok();
+        cli.command = pyelevate::cli::default_command(env.as_deref(), loaded.default_command.as_deref())?;
+        config = Some(loaded);
+    }
+
+    match cli.command {
+        Some(Commands::Completions { shell }) => {
+            pyelevate::cli::write_completions(shell, &mut io::stdout());
+            return Ok(());
+        }
+        Some(Commands::Schema) => {
+            println!("{}", report::CheckReport::json_schema()?);
+            return Ok(());
+        }
+        _ => {}
+    }
+    let config = match config {
+        Some(config) => config,
+        None => Config::load(cli.config.as_deref())?,
+    };
+    let max_body_bytes = config.http.max_body_bytes();
+    let glyph_set = cli.glyphs.or(config.glyphs).unwrap_or_default();
+    let g = glyph_set.glyphs();
 
     if cli.verbose {
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::DEBUG)
+            .with_writer(io::stderr)
+            .init();
+    } else if cli.quiet {
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::WARN)
+            .with_writer(io::stderr)
             .init();
     } else {
         tracing_subscriber::fmt()
             .with_max_level(tracing::Level::INFO)
+            .with_writer(io::stderr)
             .init();
     }
 
+    let groups = GroupSelection {
+        groups: cli.groups.clone(),
+        all: cli.all_groups,
+    };
+
+    if let Some(Commands::Changelog { package, from, to }) = &cli.command {
+        // A requirements file isn't needed, but its index options apply when there is one.
+        let options = match determine_requirements_path(cli.requirements.as_deref()) {
+            Ok(path) => load_requirements(&path, &groups)?.options,
+            Err(_) => Vec::new(),
+        };
+        return changelog_command(package, from.as_deref(), to.as_deref(), &options, max_body_bytes, glyph_set).await;
+    }
+
     let requirements_path = determine_requirements_path(cli.requirements.as_deref())?;
     
-    info!("🚀 PyElevate v0.2.0 - Starting with {}", requirements_path);
+    info!("{} PyElevate v0.2.0 - Starting with {}", g.app, requirements_path);
 
     match cli.command {
-        Some(Commands::Check { requirements }) => {
+        Some(Commands::Check {
+            requirements,
+            retry_errors,
+            against_installed,
+            pip,
+            format,
+            porcelain,
+            verify_index,
+            checkpoint,
+            current_env_only,
+            strict,
+            ignore_vulns,
+            min_severity,
+            min_age,
+            history,
+        }) => {
+            let path = requirements.as_deref().unwrap_or(&requirements_path);
+            let pip = against_installed.then_some(pip.as_str());
+            let options = CheckOptions {
+                retry_errors,
+                against_installed: pip,
+                format,
+                porcelain,
+                verify_index,
+                checkpoint: checkpoint.as_deref(),
+                current_env_only,
+                strict,
+                max_body_bytes,
+                watchlist: &config.watch.packages,
+                python_version: cli.python_version,
+                ignore_vulns: ignore_list(&config, ignore_vulns),
+                min_severity: min_severity.unwrap_or(config.security.min_severity()),
+                min_age,
+                history,
+                groups,
+                enrich: !cli.no_enrich,
+                glyphs: glyph_set,
+            };
+            if !check_command(path, options, cli.quiet, cli.summary).await? {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Upgrade {
+            requirements,
+            dry_run,
+            lock,
+            strategy,
+            output,
+            prefer_wheels,
+            verify_index,
+            current_env_only,
+            update_constraint,
+            min_age,
+        }) => {
+            let paths = if requirements.is_empty() {
+                vec![requirements_path.clone()]
+            } else {
+                requirements
+            };
+            let options = UpgradeOptions {
+                dry_run,
+                lock,
+                strategy,
+                prefer_wheels,
+                verify_index,
+                current_env_only,
+                update_constraint,
+                max_body_bytes,
+                python_version: cli.python_version,
+                min_age,
+                groups,
+                glyphs: glyph_set,
+            };
+            upgrade_command(&paths, output.as_deref(), options, cli.quiet, cli.summary).await?;
+        }
+        Some(Commands::Audit { requirements, severity_threshold, allow_lookup_failures, ignore_vulns }) => {
+            let path = requirements.as_deref().unwrap_or(&requirements_path);
+            let ignored = ignore_list(&config, ignore_vulns);
+            let report_options = report::ReportOptions {
+                quiet: cli.quiet,
+                color: report::color_enabled(&io::stdout()),
+                glyphs: glyph_set,
+            };
+            if !audit_command(path, &groups, severity_threshold, allow_lookup_failures, &ignored, max_body_bytes, report_options).await? {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::List { requirements }) => {
+            let path = requirements.as_deref().unwrap_or(&requirements_path);
+            list_command(path, &groups, glyph_set)?;
+        }
+        Some(Commands::Lock { requirements, check }) => {
+            let path = requirements.as_deref().unwrap_or(&requirements_path);
+            if !lock_command(path, &groups, check, max_body_bytes, glyph_set).await? {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Relax {
+            requirements,
+            strategy,
+            output,
+        }) => {
             let path = requirements.as_deref().unwrap_or(&requirements_path);
-            check_command(path).await?;
+            relax_command(path, strategy, output.as_deref())?;
         }
-        Some(Commands::Upgrade { requirements, dry_run, lock }) => {
+        Some(Commands::Trend { requirements }) => {
             let path = requirements.as_deref().unwrap_or(&requirements_path);
-            upgrade_command(path, dry_run, lock).await?;
+            trend_command(path, &groups, &config, glyph_set).await?;
         }
         Some(Commands::Simulate { requirements }) => {
             let path = requirements.as_deref().unwrap_or(&requirements_path);
-            simulate_command(path).await?;
+            simulate_command(path, &groups, max_body_bytes, glyph_set).await?;
+        }
+        Some(Commands::Completions { .. } | Commands::Schema | Commands::Changelog { .. }) => {
+            unreachable!("handled before resolving the requirements path")
         }
         None => {
-            run_interactive_tui(&requirements_path, cli.dry_run).await?;
+            let refresh = cli.refresh_interval.map(|m| std::time::Duration::from_secs(m * 60));
+            let options = TuiOptions {
+                dry_run: cli.dry_run,
+                refresh_interval: refresh,
+                enrich: !cli.no_enrich,
+                python_version: cli.python_version,
+                groups,
+                glyphs: glyph_set,
+            };
+            run_interactive_tui(&requirements_path, options, &config).await?;
         }
     }
 
     Ok(())
 }
 
+/// The path as the user gave it, for messages; backups and writes resolve
+/// symlinks themselves.
 fn determine_requirements_path(provided: Option<&str>) -> Result<String> {
     if let Some(path) = provided {
         return Ok(path.to_string());
@@ -130,114 +250,706 @@ fn determine_requirements_path(provided: Option<&str>) -> Result<String> {
     ))
 }
 
-async fn check_command(requirements_path: &str) -> Result<()> {
-    let req_file = parse_requirements(requirements_path)?;
-    let mut app = App::new(requirements_path.to_string());
+struct CheckOptions<'a> {
+    retry_errors: bool,
+    against_installed: Option<&'a str>,
+    format: OutputFormat,
+    porcelain: bool,
+    verify_index: bool,
+    checkpoint: Option<&'a str>,
+    current_env_only: bool,
+    strict: bool,
+    max_body_bytes: usize,
+    watchlist: &'a [String],
+    python_version: Option<(u32, u32)>,
+    ignore_vulns: Vec<String>,
+    min_severity: Severity,
+    min_age: Option<u32>,
+    history: bool,
+    groups: GroupSelection,
+    enrich: bool,
+    glyphs: GlyphSet,
+}
+
+/// Parses a requirements file, or the selected groups of a pyproject.toml.
+fn load_requirements(path: &str, groups: &GroupSelection) -> Result<RequirementsFile> {
+    if pyproject::is_pyproject(path) {
+        Ok(pyproject::parse_pyproject(path, groups)?)
+    } else {
+        Ok(parse_requirements(path)?)
+    }
+}
+
+/// `[security] ignore` from the config plus any `--ignore-vuln` flags.
+fn ignore_list(config: &Config, flags: Vec<String>) -> Vec<String> {
+    let mut ids = config.security.ignore.clone();
+    ids.extend(flags);
+    ids
+}
+
+/// Returns whether the check passed, which only `--strict` can make false.
+async fn check_command(
+    requirements_path: &str,
+    options: CheckOptions<'_>,
+    quiet: bool,
+    summary: bool,
+) -> Result<bool> {
+    let CheckOptions {
+        retry_errors,
+        against_installed,
+        format,
+        porcelain,
+        verify_index,
+        checkpoint,
+        current_env_only,
+        strict,
+        max_body_bytes,
+        watchlist,
+        python_version,
+        ignore_vulns,
+        min_severity,
+        min_age,
+        history,
+        groups,
+        enrich,
+        glyphs,
+    } = options;
+    let g = glyphs.glyphs();
+    let started = Instant::now();
+    let mut req_file = load_requirements(requirements_path, &groups)?;
+    let compiled_files: Vec<String> = req_file.compiled.then(|| req_file.path.clone()).into_iter().collect();
+    if !req_file.warnings.is_empty() {
+        eprintln!("{} {} line(s) could not be parsed:", g.icon(g.warning), req_file.warnings.len());
+        for warning in &req_file.warnings {
+            eprintln!("  {}", warning);
+        }
+    }
+    let hash_violations = pyelevate::parser::hash_violations(&req_file.raw_lines);
+    if !hash_violations.is_empty() {
+        eprintln!(
+            "{} {} requirement(s) would fail pip's hash-checking mode:",
+            g.icon(g.warning),
+            hash_violations.len()
+        );
+        for violation in &hash_violations {
+            eprintln!("  {}", violation);
+        }
+    }
+    if current_env_only {
+        markers::retain_applicable(&mut req_file.packages, markers::Environment::current());
+    }
+    if format == OutputFormat::Text && !porcelain {
+        if let Some(message) = report::empty_state(requirements_path, &req_file.packages) {
+            println!("{}", message);
+            return Ok(true);
+        }
+    }
+    let mut app = App::new(requirements_path.to_string())
+        .with_max_body_bytes(max_body_bytes)
+        .with_ignored_advisories(&ignore_vulns)
+        .with_min_severity(min_severity);
+    app.pypi_client.apply_options(&req_file.options);
     app.set_packages(req_file.packages);
 
     info!("Fetching latest versions from PyPI...");
-    app.pypi_client.update_packages(&mut app.packages).await;
-
-    println!("╔══════════════════════════════════════════════╗");
-    println!("║  PyElevate v0.2.0 - Dependency Check Report │");
-    println!("╚══════════════════════════════════════════════╝\n");
-    println!("📦 Total packages:          {}", app.stats.total);
-    println!("🟢 Patch updates:          {}", app.stats.patch_available);
-    println!("🟡 Minor updates:          {}", app.stats.minor_available);
-    println!("🔴 Major updates:          {}", app.stats.major_available);
-    println!("✅ Up to date:             {}", app.stats.up_to_date);
-    println!("⚠️  Vulnerable:            {}", app.stats.vulnerable);
-    println!("❌ Errors:                 {}\n", app.stats.errors);
-
-    println!("{:<30} {:<15} {:<15} {:<15}", "Package", "Current", "Latest", "Status");
-    println!("{}", "─".repeat(75));
-
-    for pkg in &app.packages {
-        let latest = pkg.latest_version.as_ref().map(|v| v.as_str()).unwrap_or("N/A");
-        let status = pkg.status.as_str();
-        println!(
-            "{:<30} {:<15} {:<15} {:<15}",
-            &pkg.name[..pkg.name.len().min(30)],
-            pkg.current_version,
-            latest,
-            status
+    match checkpoint {
+        Some(path) => {
+            let mut checkpoint = Checkpoint::open(path)?;
+            let restored = checkpoint::update_resumable(
+                &app.pypi_client,
+                &mut app.packages,
+                &mut checkpoint,
+                checkpoint::BATCH_SIZE,
+            )
+            .await?;
+            if restored > 0 {
+                info!("Resumed {} package(s) from {}", restored, path);
+            }
+            checkpoint.finish()?;
+        }
+        None => app.pypi_client.update_packages(&mut app.packages).await,
+    }
+    app.pypi_client.mark_transitive(&mut app.packages, &compiled_files);
+    if retry_errors && app.packages.iter().any(|p| p.status == VersionStatus::Error) {
+        info!("Retrying failed lookups...");
+        app.pypi_client.retry_errors(&mut app.packages).await;
+    }
+    if verify_index {
+        warn_index_mismatches(&app.pypi_client, &app.packages, max_body_bytes, g).await;
+    }
+    let ahead: Vec<&Package> = app.packages.iter().filter(|p| p.ahead_of_index).collect();
+    if !ahead.is_empty() {
+        eprintln!(
+            "{} {} pin(s) newer than the index's latest; is the index stale or misconfigured?",
+            g.icon(g.warning),
+            ahead.len()
         );
+        for pkg in ahead {
+            let latest = pkg.latest_version.as_deref().unwrap_or("-");
+            eprintln!("  {} {} (index latest {})", pkg.name, pkg.current_version, latest);
+        }
+    }
+    let tags = wheels::PlatformTags::detect().with_python(python_version);
+    wheels::apply_wheel_compatibility(&mut app.packages, &app.pypi_client, &tags, false);
+    wheels::apply_python_support(&mut app.packages, tags.python);
+    wheels::apply_backport_notes(&mut app.packages, tags.python);
+    aliases::apply_alias_notes(&mut app.packages);
+    if let Some(days) = min_age {
+        let min_age = chrono::Duration::days(days.into());
+        apply_min_age(&mut app.packages, &app.pypi_client, min_age, chrono::Utc::now());
+    }
+    if enrich {
+        info!("Checking for security vulnerabilities...");
+        for pkg in &mut app.packages {
+            let _ = app.security_checker.check_package(pkg).await;
+        }
+    }
+    app.update_stats();
+    if history {
+        let snapshot = history::Snapshot::capture(requirements_path, &app.packages, &app.stats, chrono::Utc::now());
+        history::append(&history::default_path()?, &snapshot)?;
+    }
+
+    let watchlist: Vec<String> = watchlist.iter().map(|n| normalize_package_name(n)).collect();
+    app::watched_first(&mut app.packages, &watchlist);
+
+    let conflicts = DependencyResolver::new().validate_constraints(&app.packages);
+    let drifts = match against_installed {
+        Some(pip) => Some(installed::compare_installed(&app.packages, &installed::pip_freeze(pip)?)),
+        None => None,
+    };
+    if porcelain {
+        let output = report::porcelain_report(&app.packages);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+    } else if format == OutputFormat::Json {
+        let mut report = report::CheckReport::new(requirements_path, &app.packages, &conflicts);
+        report.stats = app.stats.clone();
+        report.installed_drift = drifts;
+        report.watched = app
+            .packages
+            .iter()
+            .filter(|p| app::is_watched(&watchlist, p))
+            .map(|p| p.name.clone())
+            .collect();
+        println!("{}", report.to_json()?);
+    } else {
+        let options = report::ReportOptions {
+            quiet,
+            color: report::color_enabled(&io::stdout()),
+            glyphs,
+        };
+        println!("{}", report::check_report(&app.packages, &app.stats, &conflicts, options));
+
+        if let Some(drifts) = drifts {
+            if drifts.is_empty() {
+                println!("\nInstalled environment matches the requirements");
+            } else {
+                println!("\nInstalled environment drift: {}", drifts.len());
+                for drift in &drifts {
+                    println!("  {}", drift);
+                }
+            }
+        }
+
+        if summary {
+            println!("\n{}", app.stats.summary_line(started.elapsed()));
+        }
+    }
+
+    if !strict {
+        return Ok(true);
+    }
+    // On stderr, so the listing doesn't corrupt JSON or porcelain output.
+    let unresolved = report::unresolved_packages(&app.packages);
+    if !unresolved.is_empty() {
+        eprintln!("--strict: {} package(s) could not be resolved", unresolved.len());
+        for line in &unresolved {
+            eprintln!("  {}", line);
+        }
+    }
+    Ok(unresolved.is_empty())
+}
+
+/// Warnings go to stderr so they survive `--quiet` and `--format json`.
+async fn warn_index_mismatches(client: &pypi::PyPIClient, packages: &[Package], max_body_bytes: usize, g: &Glyphs) {
+    if !client.uses_custom_index() {
+        eprintln!("--verify-index: no custom index configured, nothing to cross-check");
+        return;
+    }
+    info!("Cross-checking the index against pypi.org...");
+    let reference = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+    for mismatch in client.cross_check(&reference, packages).await {
+        eprintln!("{} Index mismatch: {}", g.icon(g.warning), mismatch);
+    }
+}
+
+/// Returns whether the audit passed, i.e. nothing reached `threshold` and
+/// no lookup failed unless `allow_lookup_failures` is set.
+async fn audit_command(
+    requirements_path: &str,
+    groups: &GroupSelection,
+    threshold: Severity,
+    allow_lookup_failures: bool,
+    ignored: &[String],
+    max_body_bytes: usize,
+    options: report::ReportOptions,
+) -> Result<bool> {
+    let req_file = load_requirements(requirements_path, groups)?;
+    if let Some(message) = report::empty_state(requirements_path, &req_file.packages) {
+        println!("{}", message);
+        return Ok(true);
+    }
+    let mut packages = req_file.packages;
+    if packages.iter().any(|p| !p.extras.is_empty()) {
+        info!("Resolving packages pulled in by extras...");
+        let mut pypi_client = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+        pypi_client.apply_options(&req_file.options);
+        let extra_packages = pypi_client
+            .expand_extras(&packages, markers::Environment::current())
+            .await;
+        packages.extend(extra_packages);
     }
+    info!("Scanning for known vulnerabilities...");
+    let (findings, failed) = SecurityChecker::new()
+        .with_max_body_bytes(max_body_bytes)
+        .with_ignored(ignored)
+        .audit(&mut packages)
+        .await;
+
+    println!("{}", report::audit_report(&findings, &failed, options));
+    Ok(security::audit_passed(&findings, &failed, threshold, allow_lookup_failures))
+}
 
+fn list_command(requirements_path: &str, groups: &GroupSelection, glyphs: GlyphSet) -> Result<()> {
+    let req_file = load_requirements(requirements_path, groups)?;
+    if !req_file.warnings.is_empty() {
+        let g = glyphs.glyphs();
+        eprintln!("{} {} line(s) could not be parsed:", g.icon(g.warning), req_file.warnings.len());
+        for warning in &req_file.warnings {
+            eprintln!("  {}", warning);
+        }
+    }
+    match report::empty_state(requirements_path, &req_file.packages) {
+        Some(message) => println!("{}", message),
+        None => println!("{}", report::list_report(&req_file.packages, glyphs)),
+    }
     Ok(())
 }
 
-async fn upgrade_command(requirements_path: &str, dry_run: bool, lock: bool) -> Result<()> {
+/// Returns whether the lock is up to date, which only `--check` can make false.
+async fn lock_command(
+    requirements_path: &str,
+    groups: &GroupSelection,
+    check: bool,
+    max_body_bytes: usize,
+    glyphs: GlyphSet,
+) -> Result<bool> {
+    let req_file = load_requirements(requirements_path, groups)?;
+    if !check {
+        let g = glyphs.glyphs();
+        // Pins lock as they are; ranges lock at the newest release they allow.
+        let (mut ranged, mut packages): (Vec<Package>, Vec<Package>) =
+            req_file.packages.into_iter().partition(|p| {
+                matches!(p.source, DependencySource::PyPI) && !matches!(p.constraint, VersionConstraint::Pinned(_))
+            });
+        let mut pypi_client = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+        pypi_client.apply_options(&req_file.options);
+        pypi_client.update_packages(&mut ranged).await;
+        let mut unresolved = Vec::new();
+        for mut pkg in ranged {
+            match resolver::newest_allowed(&pkg, &pypi_client.releases(&pkg.name)) {
+                Some(version) => {
+                    pkg.current_version = version;
+                    pkg.latest_version = None;
+                    pkg.target_version = None;
+                    packages.push(pkg);
+                }
+                None => unresolved.push(pkg.name),
+            }
+        }
+        if !unresolved.is_empty() {
+            anyhow::bail!("No release found for {}; the lock file was not written", unresolved.join(", "));
+        }
+        let lock_path = UpgradeManager::write_lock_file(requirements_path, &packages)?;
+        println!("{} Lock file: {}", g.icon(g.lock), lock_path);
+        return Ok(true);
+    }
+
+    let lock_path = format!("{}.lock", requirements_path);
+    let lock = std::fs::read_to_string(&lock_path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {} ({}); run `pyelevate lock` first", lock_path, e))?;
+    let diff = UpgradeManager::diff_lock(&req_file.packages, &lock);
+    if diff.is_empty() {
+        println!("{} is up to date with {}", lock_path, requirements_path);
+        return Ok(true);
+    }
+    println!("{} has drifted from {}:", requirements_path, lock_path);
+    for name in &diff.added {
+        println!("  + {} (not locked)", name);
+    }
+    for name in &diff.removed {
+        println!("  - {} (locked, no longer required)", name);
+    }
+    for (name, locked, requirement) in &diff.changed {
+        println!("  ~ {}: locked {}, requires {}", name, locked, requirement);
+    }
+    println!("Run `pyelevate lock` to update it");
+    Ok(false)
+}
+
+fn relax_command(requirements_path: &str, strategy: RelaxStrategy, output: Option<&str>) -> Result<()> {
+    if pyproject::is_pyproject(requirements_path) {
+        anyhow::bail!("{} already holds loose ranges; relax works on pinned requirements files", requirements_path);
+    }
+    let content = std::fs::read_to_string(requirements_path)?;
     let req_file = parse_requirements(requirements_path)?;
-    let mut packages = req_file.packages;
-    let pypi_client = pypi::PyPIClient::new();
+    let relaxed = UpgradeManager::relax_pins(&content, &req_file.packages, strategy);
+    match output {
+        Some(path) => {
+            UpgradeManager::write_requirements(path, &relaxed)?;
+            info!("Wrote {}", path);
+        }
+        None => print!("{}", relaxed),
+    }
+    Ok(())
+}
+
+async fn changelog_command(
+    package: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    options: &[GlobalOption],
+    max_body_bytes: usize,
+    glyphs: GlyphSet,
+) -> Result<()> {
+    let mut pypi_client = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+    pypi_client.apply_options(options);
+    let changelogs = pyelevate::ChangelogFetcher::new()
+        .with_max_body_bytes(max_body_bytes)
+        .fetch_range(&pypi_client, package, from, to)
+        .await?;
+    let options = report::ReportOptions {
+        quiet: false,
+        color: report::color_enabled(&io::stdout()),
+        glyphs,
+    };
+    println!("{}", report::changelog_report(package, &changelogs, options));
+    Ok(())
+}
+
+async fn trend_command(
+    requirements_path: &str,
+    groups: &GroupSelection,
+    config: &Config,
+    glyphs: GlyphSet,
+) -> Result<()> {
+    let Some(previous) = history::latest(&history::default_path()?, requirements_path)? else {
+        anyhow::bail!(
+            "No stored runs for {}; run `pyelevate check --history` first",
+            requirements_path
+        );
+    };
+
+    let req_file = load_requirements(requirements_path, groups)?;
+    let mut app = App::new(requirements_path.to_string())
+        .with_max_body_bytes(config.http.max_body_bytes())
+        .with_ignored_advisories(&config.security.ignore)
+        .with_min_severity(config.security.min_severity());
+    app.pypi_client.apply_options(&req_file.options);
+    app.set_packages(req_file.packages);
+    info!("Fetching latest versions from PyPI...");
+    app.pypi_client.update_packages(&mut app.packages).await;
+    info!("Checking for security vulnerabilities...");
+    for pkg in &mut app.packages {
+        let _ = app.security_checker.check_package(pkg).await;
+    }
+    app.update_stats();
+
+    let current = history::Snapshot::capture(requirements_path, &app.packages, &app.stats, chrono::Utc::now());
+    println!("{}", history::trend_report(&previous, &current, glyphs.glyphs()));
+    Ok(())
+}
+
+struct UpgradeOptions {
+    dry_run: bool,
+    lock: bool,
+    strategy: ResolutionStrategy,
+    prefer_wheels: bool,
+    verify_index: bool,
+    current_env_only: bool,
+    update_constraint: ConstraintUpdate,
+    max_body_bytes: usize,
+    python_version: Option<(u32, u32)>,
+    min_age: Option<u32>,
+    groups: GroupSelection,
+    glyphs: GlyphSet,
+}
+
+async fn upgrade_command(
+    requirements_paths: &[String],
+    output: Option<&str>,
+    options: UpgradeOptions,
+    quiet: bool,
+    summary: bool,
+) -> Result<()> {
+    let UpgradeOptions {
+        dry_run,
+        lock,
+        strategy,
+        prefer_wheels,
+        verify_index,
+        current_env_only,
+        update_constraint,
+        min_age,
+        glyphs, max_body_bytes, python_version, groups,
+    } = options;
+    let g = glyphs.glyphs();
+    if output.is_some() && requirements_paths.len() > 1 {
+        anyhow::bail!("--output can only be used with a single requirements file");
+    }
+    let started = Instant::now();
+    let mut packages = Vec::new();
+    let mut pypi_client = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+    let mut compiled_files = Vec::new();
+    for path in requirements_paths {
+        let req_file = load_requirements(path, &groups)?;
+        pypi_client.apply_options(&req_file.options);
+        if req_file.compiled {
+            compiled_files.push(req_file.path.clone());
+        }
+        packages.extend(req_file.packages);
+    }
+    if current_env_only {
+        markers::retain_applicable(&mut packages, markers::Environment::current());
+    }
+    if let Some(message) = report::empty_state(&requirements_paths.join(", "), &packages) {
+        println!("{}", message);
+        return Ok(());
+    }
+    let mut security_checker = SecurityChecker::new().with_max_body_bytes(max_body_bytes);
+
+    if !quiet {
+        println!("{}", report::banner(report::UPGRADE_TITLE, g));
+        println!("Fetching latest versions from PyPI...");
+    }
 
-    println!("╔════════════════════════════════════════════╗");
-    println!("║  PyElevate v0.2.0 - Dependency Upgrade    │");
-    println!("╚════════════════════════════════════════════╝\n");
-    println!("Fetching latest versions from PyPI...");
-    
     pypi_client.update_packages(&mut packages).await;
+    pypi_client.mark_transitive(&mut packages, &compiled_files);
+    if verify_index {
+        warn_index_mismatches(&pypi_client, &packages, max_body_bytes, g).await;
+    }
+
+    if strategy == ResolutionStrategy::MinimalSecure {
+        if !quiet {
+            println!("Scanning for known vulnerabilities...");
+        }
+        for pkg in &mut packages {
+            let _ = security_checker.check_package(pkg).await;
+        }
+    }
+    apply_strategy(strategy, &mut packages, &pypi_client, &security_checker);
+    let tags = wheels::PlatformTags::detect().with_python(python_version);
+    wheels::apply_wheel_compatibility(&mut packages, &pypi_client, &tags, prefer_wheels);
+    wheels::apply_python_support(&mut packages, tags.python);
+    wheels::apply_backport_notes(&mut packages, tags.python);
+    aliases::apply_alias_notes(&mut packages);
+    if let Some(days) = min_age {
+        let min_age = chrono::Duration::days(days.into());
+        apply_min_age(&mut packages, &pypi_client, min_age, chrono::Utc::now());
+    }
+    pypi_client.attach_hashes(&mut packages);
 
     let upgradable: Vec<_> = packages
         .iter()
-        .filter(|p| p.latest_version.is_some())
+        .filter(|p| p.has_upgrade() && !p.policy.pin && UpgradeManager::rewrites_line(p, update_constraint))
         .collect();
 
-    println!("\n📋 Available upgrades: {}\n", upgradable.len());
+    if !quiet {
+        println!("\n{} Available upgrades: {}\n", g.icon(g.report), upgradable.len());
+    }
     for pkg in &upgradable {
-        let latest = pkg.latest_version.as_ref().unwrap();
         println!(
-            "  {} {} → {} ({})",
+            "  {} {} {} {} ({}){}",
             pkg.name,
             pkg.current_version,
-            latest,
-            pkg.status.as_str()
+            g.arrow,
+            pkg.upgrade_target().unwrap_or_default(),
+            pkg.status.as_str(),
+            if pkg.notes.is_empty() {
+                String::new()
+            } else {
+                format!(" {} {}", g.dash, pkg.notes.join("; "))
+            }
         );
     }
+    for warning in report::transitive_warnings(&packages, upgradable.iter().copied()) {
+        eprintln!("{} {}", g.icon(g.warning), warning);
+    }
+    for warning in report::alias_warnings(&packages, "rename it, or press W in the TUI") {
+        eprintln!("{} {}", g.icon(g.warning), warning);
+    }
+    if !upgradable.is_empty() {
+        pypi_client.fetch_pinned_requires(&packages).await;
+    }
+    for pkg in &upgradable {
+        let target = pkg.upgrade_target().unwrap_or_default();
+        let breaking = pypi_client.breaking_dependents(&packages, &pkg.name, target, markers::Environment::current());
+        if let Some(warning) = report::breaking_warning(&pkg.name, target, &breaking) {
+            eprintln!("{} {}", g.icon(g.warning), warning);
+        }
+    }
 
     if dry_run {
-        println!("\n🔍 Dry-run mode: No files will be modified");
+        if !quiet {
+            println!("\n{} Dry-run mode: No files will be modified\n", g.icon(g.search));
+        }
+        for path in requirements_paths {
+            let declared = packages_from(&packages, path);
+            let preview = UpgradeManager::preview_upgrade(path, &declared, false, update_constraint)?;
+            println!("{}", preview);
+        }
     } else if !upgradable.is_empty() {
-        let backup_path = UpgradeManager::create_backup(requirements_path)?;
-        println!("\n💾 Backup created: {}", backup_path);
+        match output {
+            Some(output) => {
+                write_single_output(&requirements_paths[0], output, &packages, lock, update_constraint, g)?
+            }
+            None => write_in_place(requirements_paths, &packages, lock, update_constraint, g)?,
+        }
+    }
 
-        let new_content = UpgradeManager::generate_upgraded_content(
-            &packages,
-            &std::fs::read_to_string(requirements_path)?,
-            false,
-        )?;
+    if summary {
+        println!("\n{}", UpgradeStats::new(&packages).summary_line(started.elapsed()));
+    }
 
-        UpgradeManager::write_requirements(requirements_path, &new_content)?;
-        println!("✅ Updated: {}", requirements_path);
+    Ok(())
+}
 
-        if lock {
-            let lock_path = UpgradeManager::write_lock_file(requirements_path, &packages)?;
-            println!("🔒 Lock file: {}", lock_path);
+/// `--output` only applies to a single input file, so this keeps the
+/// original one-file flow.
+fn write_single_output(
+    requirements_path: &str,
+    output: &str,
+    packages: &[Package],
+    lock: bool,
+    constraint: ConstraintUpdate,
+    g: &Glyphs,
+) -> Result<()> {
+    let output_path = Some(Path::new(output));
+    let backup_path =
+        UpgradeManager::write_upgraded(requirements_path, output_path, packages, false, constraint)?;
+    let written_path = match &backup_path {
+        Some(backup_path) => {
+            println!("\n{} Backup created: {}", g.icon(g.backup), backup_path);
+            requirements_path
         }
+        None => output,
+    };
+    println!("{} Updated: {}", g.icon(g.up_to_date), written_path);
+
+    if lock {
+        let lock_path = UpgradeManager::write_lock_file(written_path, packages)?;
+        println!("{} Lock file: {}", g.icon(g.lock), lock_path);
     }
+    Ok(())
+}
 
+fn write_in_place(
+    requirements_paths: &[String],
+    packages: &[Package],
+    lock: bool,
+    constraint: ConstraintUpdate,
+    g: &Glyphs,
+) -> Result<()> {
+    let results = UpgradeManager::write_upgraded_files(requirements_paths, packages, false, constraint)?;
+    println!();
+    for result in &results {
+        match &result.backup_path {
+            Some(backup_path) => {
+                println!(
+                    "{} Updated: {} ({} package(s))",
+                    g.icon(g.up_to_date),
+                    result.path,
+                    result.upgraded
+                );
+                println!("   {} Backup created: {}", g.icon(g.backup), backup_path);
+            }
+            None => println!("{} Unchanged: {}", g.icon(g.unchanged), result.path),
+        }
+        if lock {
+            let declared = packages_from(packages, &result.path);
+            let lock_path = UpgradeManager::write_lock_file(&result.path, &declared)?;
+            println!("   {} Lock file: {}", g.icon(g.lock), lock_path);
+        }
+    }
+    if results.len() > 1 {
+        let changed = results.iter().filter(|r| r.backup_path.is_some()).count();
+        println!("\n{} {} of {} files changed", g.icon(g.folder), changed, results.len());
+    }
     Ok(())
 }
 
-async fn simulate_command(requirements_path: &str) -> Result<()> {
-    let req_file = parse_requirements(requirements_path)?;
+fn packages_from(packages: &[Package], path: &str) -> Vec<Package> {
+    packages
+        .iter()
+        .filter(|p| p.origin_file.as_deref() == Some(path))
+        .cloned()
+        .collect()
+}
+
+async fn simulate_command(
+    requirements_path: &str,
+    groups: &GroupSelection,
+    max_body_bytes: usize,
+    glyphs: GlyphSet,
+) -> Result<()> {
+    let req_file = load_requirements(requirements_path, groups)?;
     let mut packages = req_file.packages;
-    let pypi_client = pypi::PyPIClient::new();
+    let mut pypi_client = pypi::PyPIClient::new().with_max_body_bytes(max_body_bytes);
+    pypi_client.apply_options(&req_file.options);
 
     pypi_client.update_packages(&mut packages).await;
 
     let simulator = simulator::UpgradeSimulator::new();
-    println!("{}", simulator.generate_report(&packages));
+    println!("{}", simulator.generate_report(&packages, glyphs.glyphs()));
 
     Ok(())
 }
 
-async fn run_interactive_tui(requirements_path: &str, dry_run: bool) -> Result<()> {
-    let req_file = parse_requirements(requirements_path)?;
-    let mut app = App::new(requirements_path.to_string());
-    app.dry_run = dry_run;
+struct TuiOptions {
+    dry_run: bool,
+    refresh_interval: Option<std::time::Duration>,
+    enrich: bool,
+    python_version: Option<(u32, u32)>,
+    groups: GroupSelection,
+    glyphs: GlyphSet,
+}
+
+async fn run_interactive_tui(requirements_path: &str, options: TuiOptions, config: &Config) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        return Err(anyhow::anyhow!(
+            "The interactive UI needs a terminal, but stdout is not one. Use `pyelevate check` or `pyelevate upgrade` instead."
+        ));
+    }
+
+    let req_file = load_requirements(requirements_path, &options.groups)?;
+    let mut app = App::new(requirements_path.to_string())
+        .with_max_body_bytes(config.http.max_body_bytes())
+        .with_cache_capacity(config.cache.capacity())
+        .with_ignored_advisories(&config.security.ignore)
+        .with_min_severity(config.security.min_severity());
+    app.dry_run = options.dry_run;
+    app.refresh_interval = options.refresh_interval.filter(|i| !i.is_zero());
+    app.enrich = options.enrich;
+    app.glyph_set = options.glyphs;
+    app.status_order = config.sort.status_order()?;
+    app.pypi_client.apply_options(&req_file.options);
     app.set_packages(req_file.packages);
+    app.set_watchlist(&config.watch.packages);
+    app.compiled_files = req_file.compiled.then(|| req_file.path.clone()).into_iter().collect();
+    app.keymap = Keymap::from_config(&config.keys);
+    app.two_line_rows = config.list.two_line_rows;
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -246,7 +958,8 @@ async fn run_interactive_tui(requirements_path: &str, dry_run: bool) -> Result<(
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let result = run_app(&mut terminal, &mut app).await;
+    let tags = wheels::PlatformTags::detect().with_python(options.python_version);
+    let result = run_app(&mut terminal, &mut app, tags).await;
 
     disable_raw_mode()?;
     execute!(
@@ -259,25 +972,27 @@ async fn run_interactive_tui(requirements_path: &str, dry_run: bool) -> Result<(
     result
 }
 
-async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App) -> Result<()> {
+async fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+    tags: wheels::PlatformTags,
+) -> Result<()> {
     let tick_rate = std::time::Duration::from_millis(250);
     let mut last_tick = std::time::Instant::now();
 
     app.loading_message = "Fetching package intelligence from PyPI...".to_string();
-    terminal.draw(|f| draw(f, app))?;
-
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-
-    app.pypi_client.update_packages(&mut app.packages).await;
-    
-    for pkg in &mut app.packages {
-        let _ = app.security_checker.check_package(pkg).await;
-    }
-
-    app.apply_sort();
-    app.mode = app::AppMode::Display;
+    let mut enrichment = app.start_enrichment(tags.clone());
 
     loop {
+        if let Some(updates) = app.refetch_updates.take() {
+            enrichment = updates;
+        }
+        while let Ok(update) = enrichment.try_recv() {
+            app.apply_enrichment(update);
+        }
+        if app.enrichment_due() {
+            enrichment = app.start_enrichment(tags.clone());
+        }
         terminal.draw(|f| draw(f, app))?;
 
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
@@ -285,7 +1000,7 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mu
             if let Event::Key(key) = event::read()? {
                 handle_input(app, key).await?;
 
-                if app.mode == app::AppMode::Done {
+                if app.should_quit {
                     break;
                 }
             }
@@ -293,6 +1008,11 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mu
 
         if last_tick.elapsed() >= tick_rate {
             last_tick = std::time::Instant::now();
+            app.expire_messages(last_tick);
+
+            if app.refresh_due(last_tick) {
+                enrichment = app.start_refresh(tags.clone());
+            }
         }
     }
 
@@ -304,19 +1024,31 @@ async fn handle_input(app: &mut App, key: KeyEvent) -> Result<()> {
         app::AppMode::Display => handle_display_mode(app, key).await?,
         app::AppMode::Search => handle_search_mode(app, key).await?,
         app::AppMode::Confirm => handle_confirm_mode(app, key).await?,
+        app::AppMode::ConfirmRemove => handle_confirm_remove_mode(app, key),
         app::AppMode::GraphView => handle_graph_mode(app, key).await?,
         app::AppMode::ChangelogView => handle_changelog_mode(app, key).await?,
+        app::AppMode::FreshnessView => handle_freshness_mode(app, key),
+        app::AppMode::PackageSearch => handle_package_search_mode(app, key).await?,
+        app::AppMode::EditConstraint => handle_edit_constraint_mode(app, key),
+        app::AppMode::SortMenu => handle_sort_menu_mode(app, key),
         app::AppMode::Upgrading => {}
         app::AppMode::Loading => {}
-        app::AppMode::Done => {
-            app.mode = app::AppMode::Done;
-            std::process::exit(0);
-        }
+        app::AppMode::Done => handle_done_mode(app, key),
     }
     Ok(())
 }
 
+fn handle_done_mode(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char('v') | KeyCode::Char('V') => app.toggle_show_only_changed(),
+        _ => app.should_quit = true,
+    }
+}
+
 async fn handle_display_mode(app: &mut App, key: KeyEvent) -> Result<()> {
+    if app.handle_keymap(&key) {
+        return Ok(());
+    }
     match (key.code, key.modifiers) {
         (KeyCode::Char('c'), KeyModifiers::CONTROL) | (KeyCode::Esc, _) => {
             std::process::exit(0);
@@ -361,42 +1093,128 @@ async fn handle_display_mode(app: &mut App, key: KeyEvent) -> Result<()> {
             app.mode = app::AppMode::Search;
             app.search_query.clear();
         }
-        (KeyCode::Char('g') | KeyCode::Char('G'), _) => {
+        (KeyCode::Char('g') | KeyCode::Char('G') | KeyCode::Char('v') | KeyCode::Char('V'), _) => {
             app.mode = app::AppMode::GraphView;
         }
+        (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+            app.toggle_enrich();
+            let shown = if app.enrich { "on" } else { "off (versions only)" };
+            app.set_success(format!("Security, changelog and popularity lookups {}", shown));
+        }
         (KeyCode::Char('c') | KeyCode::Char('C'), _) => {
             app.mode = app::AppMode::ChangelogView;
         }
+        (KeyCode::Char('b') | KeyCode::Char('B'), _) if app.get_selected_package_ref().is_some() => {
+            app.mode = app::AppMode::FreshnessView;
+        }
+        (KeyCode::Char('+') | KeyCode::Char('n'), _) => {
+            app.package_search = app::PackageSearchState::default();
+            app.mode = app::AppMode::PackageSearch;
+        }
+        (KeyCode::Char('e') | KeyCode::Char('E'), _) => {
+            app.start_constraint_edit();
+        }
+        (KeyCode::Char('f') | KeyCode::Char('F'), _) => {
+            app.toggle_group_by_file();
+        }
+        (KeyCode::Char('l') | KeyCode::Char('L'), _) => {
+            app.two_line_rows = !app.two_line_rows;
+        }
+        (KeyCode::Char('h') | KeyCode::Char('H'), _) => {
+            app.toggle_direct_only();
+            let shown = if app.direct_only { "direct dependencies only" } else { "all dependencies" };
+            app.set_success(format!("Showing {}", shown));
+        }
+        (KeyCode::Char('t') | KeyCode::Char('T'), _) => {
+            app.toggle_target_mode();
+            app.set_success(format!("Upgrade target: {}", app.target_mode.as_str()));
+        }
+        (KeyCode::Char('y'), _) => {
+            copy_selected(app, app::CopyTarget::UpgradeLine);
+        }
+        (KeyCode::Char('Y'), _) => {
+            copy_selected(app, app::CopyTarget::InstallCommand);
+        }
+        (KeyCode::Char('o') | KeyCode::Char('O'), _) => {
+            copy_selected(app, app::CopyTarget::AdvisoryUrl);
+        }
+        (KeyCode::Delete, _) | (KeyCode::Char('x'), _)
+            if app.get_selected_package_ref().is_some() =>
+        {
+            app.mode = app::AppMode::ConfirmRemove;
+        }
+        (KeyCode::Char('W'), _) => match app.switch_to_canonical() {
+            Some((alias, canonical)) => {
+                // The lookups so far were of the alias.
+                if app.get_selected_package_ref().is_some_and(|p| p.name == canonical) {
+                    app.start_refetch();
+                }
+                app.set_success(format!("Switched {} to {} (written on U)", alias, canonical));
+            }
+            None => app.set_error("Not an alias of another package".to_string()),
+        },
+        (KeyCode::Char('r'), _) => match app.start_refetch() {
+            Some(name) => app.set_success(format!("Refetching {}...", name)),
+            None if app.enriching => app.set_error("Still loading; try again once it finishes".to_string()),
+            None => {}
+        },
+        (KeyCode::Char('R'), _) => {
+            let failed = app
+                .packages
+                .iter()
+                .filter(|p| p.status == VersionStatus::Error)
+                .count();
+            if failed > 0 {
+                let remaining = app.pypi_client.retry_errors(&mut app.packages).await;
+                app.update_stats();
+                app.refresh_filtered_packages();
+                if remaining == 0 {
+                    app.set_success(format!("Retried {} failed lookup(s)", failed));
+                } else {
+                    app.set_error(format!("{} of {} lookup(s) still failing", remaining, failed));
+                }
+            }
+        }
         (KeyCode::Char('s') | KeyCode::Char('S'), _) => {
-            app.sort_by = match app.sort_by {
-                app::SortBy::Name => app::SortBy::Status,
-                app::SortBy::Status => app::SortBy::Current,
-                app::SortBy::Current => app::SortBy::Latest,
-                app::SortBy::Latest => app::SortBy::Popularity,
-                app::SortBy::Popularity => app::SortBy::Name,
-            };
-            app.apply_sort();
+            app.open_sort_menu();
         }
         (KeyCode::Char('u') | KeyCode::Char('U'), _) => {
-            if app.count_selected() > 0 {
-                app.mode = app::AppMode::Confirm;
+            if app.count_upgrades() > 0 || app.has_pending_edits() {
+                app.open_confirm();
+            } else if app.count_selected() > 0 {
+                app.set_error(app::NOTHING_TO_DO.to_string());
             } else if app.has_upgradable_packages() {
                 app.set_error("Select packages first (Space to select)".to_string());
             }
         }
-        (KeyCode::Char(c), _) => {
-            if c.is_ascii_alphabetic() || c.is_ascii_digit() || c == '-' || c == '_' {
-                app.search_query.clear();
-                app.search_query.push(c);
-                app.mode = app::AppMode::Search;
-                app.refresh_filtered_packages();
-            }
+        (KeyCode::Char(c), _)
+            if app.keymap.type_to_search && (c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            app.search_query.clear();
+            app.search_query.push(c);
+            app.mode = app::AppMode::Search;
+            app.refresh_filtered_packages();
         }
         _ => {}
     }
     Ok(())
 }
 
+fn copy_selected(app: &mut App, target: app::CopyTarget) {
+    let Some(text) = app.clipboard_text(target) else {
+        app.set_error(match target {
+            app::CopyTarget::UpgradeLine => "No version to copy for this package".to_string(),
+            app::CopyTarget::InstallCommand => "No install command for this package".to_string(),
+            app::CopyTarget::AdvisoryUrl => "No advisory for this package".to_string(),
+        });
+        return;
+    };
+    match app.copy_to_clipboard(text.clone()) {
+        Ok(()) => app.set_success(format!("Copied {}", text)),
+        Err(e) => app.set_error(format!("Clipboard unavailable: {}", e)),
+    }
+}
+
 async fn handle_search_mode(app: &mut App, key: KeyEvent) -> Result<()> {
     match key.code {
         KeyCode::Esc => {
@@ -418,9 +1236,6 @@ async fn handle_search_mode(app: &mut App, key: KeyEvent) -> Result<()> {
         KeyCode::Down => {
             app.move_down();
         }
-        KeyCode::Char(' ') => {
-            app.toggle_selected();
-        }
         _ => {}
     }
     Ok(())
@@ -429,50 +1244,74 @@ async fn handle_search_mode(app: &mut App, key: KeyEvent) -> Result<()> {
 async fn handle_confirm_mode(app: &mut App, key: KeyEvent) -> Result<()> {
     match key.code {
         KeyCode::Enter => {
-            app.mode = app::AppMode::Upgrading;
-
-            let content = std::fs::read_to_string(&app.requirements_path)?;
-            let backup_path = if !app.dry_run {
-                Some(UpgradeManager::create_backup(&app.requirements_path)?)
-            } else {
-                None
-            };
+            app.confirm_upgrade()?;
+        }
+        KeyCode::Esc => {
+            app.confirm_input.clear();
+            app.clear_messages();
+            app.mode = app::AppMode::Display;
+        }
+        KeyCode::Backspace => {
+            app.confirm_input.pop();
+        }
+        KeyCode::Char(c) => {
+            app.confirm_input.push(c);
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-            let new_content = UpgradeManager::generate_upgraded_content(
-                &app.packages,
-                &content,
-                true,
-            )?;
+fn handle_edit_constraint_mode(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Enter => {
+            app.apply_constraint_edit();
+        }
+        KeyCode::Esc => {
+            app.clear_messages();
+            app.mode = app::AppMode::Display;
+        }
+        KeyCode::Backspace => {
+            app.constraint_input.pop();
+        }
+        KeyCode::Char(c) => {
+            app.constraint_input.push(c);
+        }
+        _ => {}
+    }
+}
 
-            if !app.dry_run && !new_content.is_empty() {
-                UpgradeManager::write_requirements(&app.requirements_path, &new_content)?;
+fn handle_confirm_remove_mode(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
+            if let Some(name) = app.remove_selected_package() {
+                app.set_success(format!("Removed {} (written on U)", name));
             }
-
-            let upgrade_count = app.count_selected();
-            app.success_message = Some(format!(
-                "✅ Successfully upgraded {} package(s){}",
-                upgrade_count,
-                if let Some(backup) = &backup_path {
-                    format!("\n📦 Backup: {}", backup)
-                } else {
-                    String::new()
-                }
-            ));
-            app.backup_path = backup_path;
-
-            app.mode = app::AppMode::Done;
+            app.mode = app::AppMode::Display;
         }
-        KeyCode::Esc => {
+        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+            app.mode = app::AppMode::Display;
+        }
+        _ => {}
+    }
+}
+
+fn handle_sort_menu_mode(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => app.sort_menu_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.sort_menu_down(),
+        KeyCode::Enter => app.choose_sort(),
+        KeyCode::Char('r') | KeyCode::Char('R') | KeyCode::Left | KeyCode::Right => app.toggle_sort_direction(),
+        KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('S') => {
             app.mode = app::AppMode::Display;
         }
         _ => {}
     }
-    Ok(())
 }
 
 async fn handle_graph_mode(app: &mut App, key: KeyEvent) -> Result<()> {
     match key.code {
-        KeyCode::Char('g') | KeyCode::Char('G') | KeyCode::Esc => {
+        KeyCode::Char('g') | KeyCode::Char('G') | KeyCode::Char('v') | KeyCode::Char('V') | KeyCode::Esc => {
             app.mode = app::AppMode::Display;
         }
         _ => {}
@@ -489,3 +1328,79 @@ async fn handle_changelog_mode(app: &mut App, key: KeyEvent) -> Result<()> {
     }
     Ok(())
 }
+
+fn handle_freshness_mode(app: &mut App, key: KeyEvent) {
+    if matches!(key.code, KeyCode::Char('b') | KeyCode::Char('B') | KeyCode::Esc) {
+        app.mode = app::AppMode::Display;
+    }
+}
+
+async fn handle_package_search_mode(app: &mut App, key: KeyEvent) -> Result<()> {
+    match key.code {
+        KeyCode::Esc => {
+            app.mode = app::AppMode::Display;
+        }
+        KeyCode::Backspace => {
+            app.package_search.query.pop();
+            autocomplete_package_search(app);
+        }
+        KeyCode::Up => {
+            app.package_search.selected = app.package_search.selected.saturating_sub(1);
+        }
+        KeyCode::Down => {
+            let max = app.package_search.results.len().saturating_sub(1);
+            app.package_search.selected = (app.package_search.selected + 1).min(max);
+        }
+        KeyCode::Enter => {
+            let search = &app.package_search;
+            if search.query != search.searched_query || search.results.is_empty() {
+                let query = search.query.clone();
+                match app.pypi_client.search(&query).await {
+                    Ok(results) => {
+                        app.package_search.results = results;
+                        app.package_search.selected = 0;
+                    }
+                    Err(e) => app.set_error(format!("PyPI search failed: {}", e)),
+                }
+                app.package_search.searched_query = query;
+            } else if let Some(result) = search.results.get(search.selected) {
+                let name = result.name.clone();
+                if app.add_package(&name) {
+                    pin_added_package(app, &name).await;
+                    app.set_success(format!("Added {} to the working set", name));
+                } else {
+                    app.set_error(format!("{} is already in the working set", name));
+                }
+                app.mode = app::AppMode::Display;
+            }
+        }
+        KeyCode::Char(c) => {
+            app.package_search.query.push(c);
+            autocomplete_package_search(app);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn autocomplete_package_search(app: &mut App) {
+    let query = app.package_search.query.clone();
+    if let Some(results) = app.pypi_client.search_cached(&query) {
+        app.package_search.results = results;
+        app.package_search.selected = 0;
+        app.package_search.searched_query = query;
+    }
+}
+
+async fn pin_added_package(app: &mut App, name: &str) {
+    let name = name.to_lowercase();
+    if let Some(pkg) = app.packages.iter_mut().find(|p| p.name == name) {
+        app.pypi_client.update_package(pkg).await;
+        if let Some(latest) = pkg.latest_version.clone() {
+            pkg.current_version = latest.clone();
+            pkg.constraint = VersionConstraint::Pinned(latest);
+            pkg.status = VersionStatus::UpToDate;
+        }
+    }
+    app.update_stats();
+}
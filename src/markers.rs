@@ -0,0 +1,257 @@
+use crate::models::Package;
+use crate::version::Pep440Version;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+/// Values of the PEP 508 marker variables for one interpreter and platform.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    values: HashMap<&'static str, String>,
+}
+
+static CURRENT: Lazy<Environment> = Lazy::new(|| {
+    Environment::new(
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        crate::wheels::detect_python_version(),
+    )
+});
+
+impl Environment {
+    /// The environment for a Rust-style `os`/`arch` pair and a CPython
+    /// `(major, minor)`. Variables that can't be derived are left unset.
+    pub fn new(os: &str, arch: &str, python: Option<(u32, u32)>) -> Self {
+        let (sys_platform, platform_system, os_name) = match os {
+            "windows" => ("win32", "Windows", "nt"),
+            "macos" => ("darwin", "Darwin", "posix"),
+            "linux" => ("linux", "Linux", "posix"),
+            other => (other, other, "posix"),
+        };
+        let platform_machine = match (os, arch) {
+            ("windows", "x86_64") => "AMD64",
+            ("macos", "aarch64") => "arm64",
+            (_, arch) => arch,
+        };
+
+        let mut values = HashMap::from([
+            ("sys_platform", sys_platform.to_string()),
+            ("platform_system", platform_system.to_string()),
+            ("os_name", os_name.to_string()),
+            ("platform_machine", platform_machine.to_string()),
+            ("implementation_name", "cpython".to_string()),
+            ("platform_python_implementation", "CPython".to_string()),
+        ]);
+        if let Some((major, minor)) = python {
+            values.insert("python_version", format!("{}.{}", major, minor));
+        }
+        Self { values }
+    }
+
+    /// The interpreter on `PATH` and the platform pyelevate runs on.
+    pub fn current() -> &'static Environment {
+        &CURRENT
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Drops the packages whose marker rules them out for `env`, as
+/// `--current-env-only` does.
+pub fn retain_applicable(packages: &mut Vec<Package>, env: &Environment) {
+    packages.retain(|pkg| pkg.applies_to(env));
+}
+
+/// Evaluates a marker expression. Anything that can't be decided, such as
+/// `extra` or a variable the environment doesn't know, counts as satisfied,
+/// so packages are only excluded when a marker clearly rules them out.
+pub fn evaluate(marker: &str, env: &Environment) -> bool {
+    let tokens = tokenize(marker);
+    let mut parser = Parser { tokens: &tokens, pos: 0, env };
+    parser.or_expr().unwrap_or(true)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Op(String),
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let literal: String = chars.by_ref().take_while(|&ch| ch != c).collect();
+                tokens.push(Token::Str(literal));
+            }
+            '<' | '>' | '=' | '!' | '~' => {
+                let mut op = String::new();
+                while let Some(&ch) = chars.peek().filter(|ch| "<>=!~".contains(**ch)) {
+                    op.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek().filter(|ch| ch.is_alphanumeric() || "._".contains(**ch)) {
+                    word.push(ch);
+                    chars.next();
+                }
+                if word.is_empty() {
+                    chars.next();
+                    continue;
+                }
+                match word.as_str() {
+                    "in" => tokens.push(Token::Op("in".to_string())),
+                    "not" => tokens.push(Token::Op("not".to_string())),
+                    _ => tokens.push(Token::Ident(word)),
+                }
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    env: &'a Environment,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek_ident(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Ident(w)) if w == word)
+    }
+
+    fn or_expr(&mut self) -> Option<bool> {
+        let mut value = self.and_expr()?;
+        while self.peek_ident("or") {
+            self.pos += 1;
+            value |= self.and_expr()?;
+        }
+        Some(value)
+    }
+
+    fn and_expr(&mut self) -> Option<bool> {
+        let mut value = self.atom()?;
+        while self.peek_ident("and") {
+            self.pos += 1;
+            value &= self.atom()?;
+        }
+        Some(value)
+    }
+
+    fn atom(&mut self) -> Option<bool> {
+        if self.tokens.get(self.pos) == Some(&Token::Open) {
+            self.pos += 1;
+            let value = self.or_expr()?;
+            (self.next()? == &Token::Close).then_some(value)
+        } else {
+            let lhs = self.operand()?;
+            let op = match self.next()? {
+                Token::Op(op) if op == "not" => match self.next()? {
+                    Token::Op(next) if next == "in" => "not in".to_string(),
+                    _ => return None,
+                },
+                Token::Op(op) => op.clone(),
+                _ => return None,
+            };
+            let rhs = self.operand()?;
+            Some(compare(lhs.as_deref(), &op, rhs.as_deref()))
+        }
+    }
+
+    /// A quoted literal, or the value of a marker variable (`None` when the
+    /// environment doesn't define it).
+    fn operand(&mut self) -> Option<Option<String>> {
+        let env = self.env;
+        match self.next()? {
+            Token::Str(s) => Some(Some(s.clone())),
+            Token::Ident(name) => Some(env.get(name).map(|v| v.to_string())),
+            _ => None,
+        }
+    }
+}
+
+fn compare(lhs: Option<&str>, op: &str, rhs: Option<&str>) -> bool {
+    let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+        return true;
+    };
+    if let (Some(l), Some(r)) = (Pep440Version::parse(lhs), Pep440Version::parse(rhs)) {
+        match op {
+            "==" | "===" => return l == r,
+            "!=" => return l != r,
+            "<" => return l < r,
+            "<=" => return l <= r,
+            ">" => return l > r,
+            ">=" => return l >= r,
+            _ => {}
+        }
+    }
+    match op {
+        "==" | "===" => lhs == rhs,
+        "!=" => lhs != rhs,
+        "in" => rhs.contains(lhs),
+        "not in" => !rhs.contains(lhs),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_markers() {
+        let linux = Environment::new("linux", "x86_64", Some((3, 11)));
+        assert!(!evaluate(r#"sys_platform == "win32""#, &linux));
+        assert!(evaluate(r#"sys_platform != 'win32'"#, &linux));
+        assert!(evaluate(r#"python_version >= "3.8" and platform_machine == 'x86_64'"#, &linux));
+        assert!(!evaluate(r#"python_version < "3.10""#, &linux));
+        assert!(evaluate(r#"(os_name == "nt" or sys_platform == 'linux') and python_version > '3.9'"#, &linux));
+        assert!(evaluate(r#"'linux' in sys_platform"#, &linux));
+        assert!(!evaluate(r#"platform_system not in "Linux Darwin""#, &linux));
+        assert!(evaluate(r#"extra == "socks""#, &linux));
+
+        let windows = Environment::new("windows", "x86_64", None);
+        assert!(evaluate(r#"sys_platform == "win32" and platform_machine == "AMD64""#, &windows));
+        assert!(evaluate(r#"python_version < "3.8""#, &windows));
+    }
+
+    #[test]
+    fn test_retain_applicable_keeps_unmarked_and_matching_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "requests==2.31.0\npywin32==306 ; sys_platform == \"win32\"\nuvloop==0.19.0; sys_platform != 'win32'\n",
+        )
+        .unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+
+        retain_applicable(&mut packages, &Environment::new("linux", "x86_64", Some((3, 11))));
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["requests", "uvloop"]);
+    }
+}
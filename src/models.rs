@@ -1,10 +1,14 @@
+use chrono::{DateTime, Utc};
+use crate::version::Pep440Version;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Package {
     pub name: String,
     pub current_version: String,
     pub latest_version: Option<String>,
+    pub target_version: Option<String>,
     pub status: VersionStatus,
     pub selected: bool,
     pub extras: Vec<String>,
@@ -15,14 +19,284 @@ pub struct Package {
     pub changelog: Option<Changelog>,
     pub popularity: Option<PopularityData>,
     pub dependencies: Vec<String>,
+    pub origin_file: Option<String>,
+    pub last_release: Option<DateTime<Utc>>,
+    pub notes: Vec<String>,
+    /// PEP 508 environment marker, e.g. `sys_platform == "win32"`.
+    #[serde(default)]
+    pub marker: Option<String>,
+    #[serde(default)]
+    pub policy: PackagePolicy,
+    /// `Requires-Python` of the latest release, e.g. `>=3.8`.
+    #[serde(default)]
+    pub requires_python: Option<String>,
+    /// The target Python, e.g. `3.9`, that `requires_python` excludes.
+    #[serde(default)]
+    pub drops_python: Option<String>,
+    /// The pin is newer than the index's latest release, which usually
+    /// means a stale or misconfigured index.
+    #[serde(default)]
+    pub ahead_of_index: bool,
+    /// Advisories from the last security check of `current_version`.
+    #[serde(default)]
+    pub advisories: Vec<SecurityAdvisory>,
+    /// Advisories on the ignore list; they don't make the package vulnerable.
+    #[serde(default)]
+    pub ignored_advisories: Vec<SecurityAdvisory>,
+    /// `--hash` values for the upgrade target, written back when a hashed
+    /// pin is upgraded.
+    #[serde(default)]
+    pub target_hashes: Vec<String>,
+    /// False for pins that are only there because another package needs
+    /// them, as told by `# via` annotations or `Requires-Dist`.
+    #[serde(default = "direct_by_default")]
+    pub is_direct: bool,
+    /// The pyproject.toml dependency group the package came from, e.g.
+    /// `dev`; `None` for main dependencies and requirements files.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Which kinds of file the latest release ships.
+    #[serde(default)]
+    pub distribution: Option<Distribution>,
+    /// The project this name only stands in for, e.g. `scikit-learn` for
+    /// `sklearn`.
+    #[serde(default)]
+    pub alias_of: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+fn direct_by_default() -> bool {
+    true
+}
+
+/// Single-quotes `value` for a POSIX shell, closing and reopening the quotes
+/// around any `'` inside it.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Exceptions declared with a trailing `# pyelevate: freeze|pin|ignore-security`
+/// comment on the requirement line.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct PackagePolicy {
+    /// Never selected or upgraded.
+    pub freeze: bool,
+    /// Left out of bulk selections and `pyelevate upgrade`, but can still be
+    /// selected individually in the TUI.
+    pub pin: bool,
+    /// Not checked against the vulnerability database.
+    pub ignore_security: bool,
+}
+
+impl PackagePolicy {
+    pub fn bulk_selectable(&self) -> bool {
+        !self.freeze && !self.pin
+    }
+}
+
+impl Default for Package {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            current_version: String::new(),
+            latest_version: None,
+            target_version: None,
+            status: VersionStatus::Unknown,
+            selected: false,
+            extras: Vec::new(),
+            constraint: VersionConstraint::Unspecified,
+            error: None,
+            source: DependencySource::PyPI,
+            security_status: SecurityStatus::Unknown,
+            changelog: None,
+            popularity: None,
+            dependencies: Vec::new(),
+            origin_file: None,
+            last_release: None,
+            notes: Vec::new(),
+            marker: None,
+            policy: PackagePolicy::default(),
+            requires_python: None,
+            drops_python: None,
+            ahead_of_index: false,
+            advisories: Vec::new(),
+            ignored_advisories: Vec::new(),
+            target_hashes: Vec::new(),
+            is_direct: true,
+            group: None,
+            distribution: None,
+            alias_of: None,
+        }
+    }
+}
+
+impl Package {
+    pub fn applies_to(&self, env: &crate::markers::Environment) -> bool {
+        self.marker.as_deref().is_none_or(|marker| crate::markers::evaluate(marker, env))
+    }
+
+    pub fn upgrade_target(&self) -> Option<&str> {
+        self.target_version.as_deref().or(self.latest_version.as_deref())
+    }
+
+    /// The `pip install` command that gets this package to its upgrade
+    /// target, or its current pin without one, for running by hand. Git
+    /// and URL sources install from the reference they name.
+    pub fn install_command(&self) -> Option<String> {
+        let extras = if self.extras.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", self.extras.join(","))
+        };
+        let spec = match &self.source {
+            DependencySource::PyPI => {
+                let version = self
+                    .upgrade_target()
+                    .or(Some(self.current_version.as_str()).filter(|v| !v.is_empty()))?;
+                format!("{}{}=={}", self.name, extras, version)
+            }
+            DependencySource::Git { url, ref_spec, editable: true } => {
+                let reference = ref_spec.as_ref().map(|r| format!("@{}", r)).unwrap_or_default();
+                let spec = format!("git+{}{}#egg={}", url, reference, self.name);
+                return Some(format!("pip install -e {}", shell_quote(&spec)));
+            }
+            DependencySource::LocalPath { path, editable: true } => {
+                return Some(format!("pip install -e {}", shell_quote(path)))
+            }
+            _ => self.direct_reference()?,
+        };
+        let spec = match &self.marker {
+            Some(marker) => format!("{} ; {}", spec, marker),
+            None => spec,
+        };
+        Some(format!("pip install {}", shell_quote(&spec)))
+    }
+
+    /// How a git, URL or local path requirement names its source, the way a
+    /// requirements line would; `None` for packages from an index.
+    pub fn direct_reference(&self) -> Option<String> {
+        let extras = if self.extras.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", self.extras.join(","))
+        };
+        match &self.source {
+            DependencySource::Git { url, ref_spec, editable } => {
+                let reference = ref_spec.as_ref().map(|r| format!("@{}", r)).unwrap_or_default();
+                if *editable {
+                    return Some(format!("-e git+{}{}#egg={}", url, reference, self.name));
+                }
+                Some(format!("{}{} @ git+{}{}", self.name, extras, url, reference))
+            }
+            DependencySource::Url { url } => Some(format!("{}{} @ {}", self.name, extras, url)),
+            DependencySource::LocalPath { path, editable: true } => Some(format!("-e {}", path)),
+            DependencySource::LocalPath { path, editable: false } => Some(path.clone()),
+            DependencySource::PyPI | DependencySource::Unknown => None,
+        }
+    }
+
+    /// Combines maintenance signals into a single triage badge.
+    ///
+    /// An open vulnerability is always `Poor`. Otherwise each known signal
+    /// counts +1 or -1: a release within the last 365 days is +1 and none in
+    /// 730 days is -1; a rank within the top 1,000 or 100k+ weekly downloads is
+    /// +1, and a rank beyond 10,000 or under 1,000 weekly downloads is -1.
+    /// A total of 2 is `Good`, -2 is `Poor`, anything else is `Caution`.
+    pub fn health(&self) -> Health {
+        self.health_at(Utc::now())
+    }
+
+    pub fn health_at(&self, now: DateTime<Utc>) -> Health {
+        if self.security_status.is_vulnerable() || self.status == VersionStatus::Vulnerable {
+            return Health::Poor;
+        }
+
+        let age_signal = self.last_release.map(|released| {
+            let days = (now - released).num_days();
+            if days <= 365 {
+                1
+            } else if days > 730 {
+                -1
+            } else {
+                0
+            }
+        });
+
+        let popularity_signal = self.popularity.as_ref().map(|pop| match pop.package_rank {
+            Some(rank) if rank <= 1_000 => 1,
+            Some(rank) if rank > 10_000 => -1,
+            Some(_) => 0,
+            None if pop.weekly_downloads >= 100_000 => 1,
+            None if pop.weekly_downloads < 1_000 => -1,
+            None => 0,
+        });
+
+        if age_signal.is_none()
+            && popularity_signal.is_none()
+            && self.security_status == SecurityStatus::Unknown
+        {
+            return Health::Unknown;
+        }
+
+        match age_signal.unwrap_or(0) + popularity_signal.unwrap_or(0) {
+            score if score >= 2 => Health::Good,
+            score if score <= -2 => Health::Poor,
+            _ => Health::Caution,
+        }
+    }
+
+    pub fn has_upgrade(&self) -> bool {
+        !self.policy.freeze
+            && self
+                .upgrade_target()
+                .is_some_and(|target| compare_release(target, &self.current_version).is_gt())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum Distribution {
+    WheelOnly,
+    SdistOnly,
+    Both,
+}
+
+impl Distribution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Distribution::WheelOnly => "wheel only",
+            Distribution::SdistOnly => "sdist only",
+            Distribution::Both => "wheel + sdist",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Health {
+    Good,
+    Caution,
+    Poor,
+    Unknown,
+}
+
+impl Health {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Health::Good => "Good",
+            Health::Caution => "Caution",
+            Health::Poor => "Poor",
+            Health::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum DependencySource {
     PyPI,
     Git {
         url: String,
         ref_spec: Option<String>,
+        /// Installed with `-e`, from a checkout pip keeps in `src/`.
+        #[serde(default)]
+        editable: bool,
     },
     LocalPath {
         path: String,
@@ -48,11 +322,12 @@ impl DependencySource {
     pub fn description(&self) -> String {
         match self {
             DependencySource::PyPI => "Python Package Index".to_string(),
-            DependencySource::Git { url, ref_spec } => {
+            DependencySource::Git { url, ref_spec, editable } => {
                 format!(
-                    "Git Repository: {}\n{}",
+                    "Git Repository: {}\n{}{}",
                     url,
-                    ref_spec.as_ref().map(|r| format!("Branch/Tag: {}", r)).unwrap_or_default()
+                    ref_spec.as_ref().map(|r| format!("Branch/Tag: {}", r)).unwrap_or_default(),
+                    if *editable { "\nEditable Install" } else { "" }
                 )
             }
             DependencySource::LocalPath { path, editable } => {
@@ -68,7 +343,7 @@ impl DependencySource {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum VersionStatus {
     Patch,
     Minor,
@@ -94,6 +369,36 @@ impl VersionStatus {
         }
     }
 
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "patch" => Some(Self::Patch),
+            "minor" => Some(Self::Minor),
+            "major" => Some(Self::Major),
+            "prerelease" => Some(Self::Prerelease),
+            "unknown" => Some(Self::Unknown),
+            "uptodate" => Some(Self::UpToDate),
+            "error" => Some(Self::Error),
+            "vulnerable" => Some(Self::Vulnerable),
+            _ => None,
+        }
+    }
+
+    /// Every status, ordered by `priority`.
+    pub fn default_order() -> Vec<Self> {
+        let mut order = vec![
+            Self::Patch,
+            Self::Minor,
+            Self::Major,
+            Self::Prerelease,
+            Self::Unknown,
+            Self::UpToDate,
+            Self::Error,
+            Self::Vulnerable,
+        ];
+        order.sort_by_key(|status| status.priority());
+        order
+    }
+
     pub fn priority(&self) -> u8 {
         match self {
             Self::Vulnerable => 0,
@@ -108,7 +413,36 @@ impl VersionStatus {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How `relax` loosens an `==` pin: `compatible` emits `~=` on the
+/// pin's major.minor, `floor` emits `>=` the pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RelaxStrategy {
+    #[default]
+    Compatible,
+    Floor,
+}
+
+/// How the operator of an upgraded requirement is rewritten: `keep` leaves
+/// it as written, `floor` emits `>=`, `pin` emits `==` and `compatible`
+/// emits `~=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ConstraintUpdate {
+    #[default]
+    Keep,
+    Floor,
+    Pin,
+    Compatible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ResolutionStrategy {
+    #[default]
+    Latest,
+    MinimalSecure,
+    CompatibleOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum VersionConstraint {
     Pinned(String),
     GreaterEqual(String),
@@ -136,9 +470,71 @@ pub struct RequirementsFile {
     pub path: String,
     pub packages: Vec<Package>,
     pub raw_lines: Vec<String>,
+    pub options: Vec<GlobalOption>,
+    pub warnings: Vec<ParseWarning>,
+    /// The file carries `# via` annotations, which already tell direct pins
+    /// from transitive ones.
+    pub compiled: bool,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// A problem with one line of a requirements file, such as one
+/// `parse_requirements` skipped because it couldn't be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseWarning {
+    /// 1-based, as editors count.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum GlobalOption {
+    IndexUrl(String),
+    ExtraIndexUrl(String),
+    FindLinks(String),
+    /// Comma-separated package names, `:all:` or `:none:`.
+    OnlyBinary(String),
+    NoBinary(String),
+    PreferBinary,
+}
+
+impl GlobalOption {
+    pub fn flag(&self) -> &'static str {
+        match self {
+            GlobalOption::IndexUrl(_) => "--index-url",
+            GlobalOption::ExtraIndexUrl(_) => "--extra-index-url",
+            GlobalOption::FindLinks(_) => "--find-links",
+            GlobalOption::OnlyBinary(_) => "--only-binary",
+            GlobalOption::NoBinary(_) => "--no-binary",
+            GlobalOption::PreferBinary => "--prefer-binary",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            GlobalOption::IndexUrl(v)
+            | GlobalOption::ExtraIndexUrl(v)
+            | GlobalOption::FindLinks(v)
+            | GlobalOption::OnlyBinary(v)
+            | GlobalOption::NoBinary(v) => v,
+            GlobalOption::PreferBinary => "",
+        }
+    }
+
+    pub fn as_line(&self) -> String {
+        match self.value() {
+            "" => self.flag().to_string(),
+            value => format!("{} {}", self.flag(), value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum SecurityStatus {
     Vulnerable { cve_count: usize },
     Safe,
@@ -151,7 +547,7 @@ impl SecurityStatus {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SecurityAdvisory {
     pub id: String,
     pub title: String,
@@ -159,9 +555,22 @@ pub struct SecurityAdvisory {
     pub affected_versions: Vec<String>,
     pub fixed_version: Option<String>,
     pub url: String,
+    /// Other ids OSV lists for the same vulnerability, e.g. the CVE behind a GHSA.
+    #[serde(default)]
+    pub aliases: Vec<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+impl SecurityAdvisory {
+    /// The CVE this advisory describes, falling back to its own id.
+    pub fn cve_id(&self) -> &str {
+        std::iter::once(&self.id)
+            .chain(&self.aliases)
+            .find(|id| id.starts_with("CVE-"))
+            .unwrap_or(&self.id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema, clap::ValueEnum)]
 pub enum Severity {
     Critical,
     High,
@@ -169,7 +578,19 @@ pub enum Severity {
     Low,
 }
 
+impl SecurityAdvisory {
+    /// Whether `id` names this advisory, by its own id or an alias.
+    pub fn matches_id(&self, id: &str) -> bool {
+        std::iter::once(&self.id)
+            .chain(&self.aliases)
+            .any(|own| own.eq_ignore_ascii_case(id))
+    }
+}
+
 impl Severity {
+    /// Most severe first.
+    pub const ALL: [Severity; 4] = [Severity::Critical, Severity::High, Severity::Medium, Severity::Low];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Severity::Critical => "CRITICAL",
@@ -178,9 +599,23 @@ impl Severity {
             Severity::Low => "LOW",
         }
     }
+
+    /// Whether this is as severe as `threshold` or worse.
+    pub fn at_least(&self, threshold: Severity) -> bool {
+        self.rank() >= threshold.rank()
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Severity::Critical => 3,
+            Severity::High => 2,
+            Severity::Medium => 1,
+            Severity::Low => 0,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Changelog {
     pub version: String,
     pub release_date: String,
@@ -208,7 +643,7 @@ impl Changelog {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PopularityData {
     pub downloads_last_month: u64,
     pub downloads_trend: Vec<(String, u64)>,
@@ -216,16 +651,21 @@ pub struct PopularityData {
     pub package_rank: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct UpgradeStats {
     pub total: usize,
     pub patch_available: usize,
     pub minor_available: usize,
     pub major_available: usize,
     pub up_to_date: usize,
+    pub prerelease: usize,
+    pub unknown: usize,
     pub errors: usize,
     pub vulnerable: usize,
     pub conflicts: usize,
+    /// CVEs closed by upgrading to the target versions; filled in by callers
+    /// that have run the security checks.
+    pub fixable_cves: usize,
 }
 
 impl UpgradeStats {
@@ -236,9 +676,12 @@ impl UpgradeStats {
             minor_available: 0,
             major_available: 0,
             up_to_date: 0,
+            prerelease: 0,
+            unknown: 0,
             errors: 0,
             vulnerable: 0,
             conflicts: 0,
+            fixable_cves: 0,
         };
 
         for pkg in packages {
@@ -249,7 +692,8 @@ impl UpgradeStats {
                 VersionStatus::UpToDate => stats.up_to_date += 1,
                 VersionStatus::Error => stats.errors += 1,
                 VersionStatus::Vulnerable => stats.vulnerable += 1,
-                _ => {}
+                VersionStatus::Prerelease => stats.prerelease += 1,
+                VersionStatus::Unknown => stats.unknown += 1,
             }
         }
 
@@ -259,6 +703,18 @@ impl UpgradeStats {
     pub fn total_upgradable(&self) -> usize {
         self.patch_available + self.minor_available + self.major_available
     }
+
+    pub fn summary_line(&self, elapsed: std::time::Duration) -> String {
+        format!(
+            "pyelevate: {} checked, {} upgradable, {} vulnerable, {} error{} in {:.1}s",
+            self.total,
+            self.total_upgradable(),
+            self.vulnerable,
+            self.errors,
+            if self.errors == 1 { "" } else { "s" },
+            elapsed.as_secs_f64()
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -268,6 +724,8 @@ pub struct UpgradeSimulation {
     pub conflicts_detected: usize,
     pub security_fixes: usize,
     pub risk_level: RiskLevel,
+    /// Selected packages other packages depend on, with how many, most first.
+    pub impacts: Vec<(String, usize)>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -289,7 +747,64 @@ impl RiskLevel {
     }
 }
 
+pub fn normalize_package_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut last_was_separator = false;
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !last_was_separator {
+                normalized.push('-');
+            }
+            last_was_separator = true;
+        } else {
+            normalized.extend(c.to_lowercase());
+            last_was_separator = false;
+        }
+    }
+    normalized
+}
+
+pub fn release_parts(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+pub fn compare_release(a: &str, b: &str) -> std::cmp::Ordering {
+    if let (Some(a), Some(b)) = (Pep440Version::parse(a), Pep440Version::parse(b)) {
+        return a.cmp(&b);
+    }
+
+    let (a, b) = (release_parts(a), release_parts(b));
+    let len = a.len().max(b.len());
+    for i in 0..len {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+    std::cmp::Ordering::Equal
+}
+
 pub fn compare_versions(current: &str, latest: &str) -> VersionStatus {
+    if let (Some(curr), Some(latest_ver)) = (Pep440Version::parse(current), Pep440Version::parse(latest)) {
+        return if latest_ver <= curr {
+            VersionStatus::UpToDate
+        } else if latest_ver.is_prerelease() {
+            VersionStatus::Prerelease
+        } else if latest_ver.major() > curr.major() {
+            VersionStatus::Major
+        } else if latest_ver.minor() > curr.minor() {
+            VersionStatus::Minor
+        } else {
+            VersionStatus::Patch
+        };
+    }
+
     match (semver::Version::parse(current), semver::Version::parse(latest)) {
         (Ok(curr), Ok(latest_ver)) => {
             if latest_ver <= curr {
@@ -311,3 +826,114 @@ pub fn compare_versions(current: &str, latest: &str) -> VersionStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, status: VersionStatus) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            status,
+            ..Package::default()
+        }
+    }
+
+    fn popularity(rank: usize) -> PopularityData {
+        PopularityData {
+            downloads_last_month: 0,
+            downloads_trend: vec![],
+            weekly_downloads: 0,
+            package_rank: Some(rank),
+        }
+    }
+
+    #[test]
+    fn test_health_badges() {
+        let now = Utc::now();
+
+        let mut healthy = package("requests", VersionStatus::UpToDate);
+        healthy.security_status = SecurityStatus::Safe;
+        healthy.popularity = Some(popularity(5));
+        healthy.last_release = Some(now - chrono::Duration::days(30));
+        assert_eq!(healthy.health_at(now), Health::Good);
+
+        let mut stale = package("oldlib", VersionStatus::UpToDate);
+        stale.security_status = SecurityStatus::Safe;
+        stale.popularity = Some(popularity(250_000));
+        stale.last_release = Some(now - chrono::Duration::days(1_500));
+        assert_eq!(stale.health_at(now), Health::Poor);
+
+        healthy.security_status = SecurityStatus::Vulnerable { cve_count: 1 };
+        assert_eq!(healthy.health_at(now), Health::Poor);
+
+        assert_eq!(package("fresh", VersionStatus::Unknown).health_at(now), Health::Unknown);
+    }
+
+    #[test]
+    fn test_install_command_quotes_every_source() {
+        let mut requests = package("requests", VersionStatus::Minor);
+        requests.extras = vec!["socks".to_string(), "security".to_string()];
+        requests.latest_version = Some("2.32.3".to_string());
+        assert_eq!(
+            requests.install_command().as_deref(),
+            Some("pip install 'requests[socks,security]==2.32.3'")
+        );
+
+        let git = crate::parser::parse_requirement_line("mylib @ git+https://github.com/user/mylib.git@v2.1").unwrap();
+        assert_eq!(
+            git.install_command().as_deref(),
+            Some("pip install 'mylib @ git+https://github.com/user/mylib.git@v2.1'")
+        );
+
+        let mut pinned = package("click", VersionStatus::Unknown);
+        pinned.current_version = "8.1.7".to_string();
+        assert_eq!(pinned.install_command().as_deref(), Some("pip install 'click==8.1.7'"));
+
+        let local = crate::parser::parse_requirement_line("-e ./it's here").unwrap();
+        assert_eq!(local.install_command().as_deref(), Some(r"pip install -e './it'\''s here'"));
+    }
+
+    #[test]
+    fn test_status_categories_sum_to_total() {
+        let packages: Vec<Package> = VersionStatus::default_order()
+            .into_iter()
+            .chain([VersionStatus::Minor, VersionStatus::Unknown])
+            .enumerate()
+            .map(|(i, status)| package(&format!("pkg{}", i), status))
+            .collect();
+        let stats = UpgradeStats::new(&packages);
+
+        assert_eq!(stats.prerelease, 1);
+        assert_eq!(stats.unknown, 2);
+        assert_eq!(stats.minor_available, 2);
+        assert_eq!(
+            stats.total_upgradable()
+                + stats.up_to_date
+                + stats.prerelease
+                + stats.unknown
+                + stats.errors
+                + stats.vulnerable,
+            stats.total
+        );
+    }
+
+    #[test]
+    fn test_summary_line_counts() {
+        let packages = vec![
+            package("a", VersionStatus::Patch),
+            package("b", VersionStatus::Minor),
+            package("c", VersionStatus::Major),
+            package("d", VersionStatus::UpToDate),
+            package("e", VersionStatus::Vulnerable),
+            package("f", VersionStatus::Error),
+        ];
+
+        let stats = UpgradeStats::new(&packages);
+        assert_eq!(
+            stats.summary_line(std::time::Duration::from_millis(3240)),
+            "pyelevate: 6 checked, 3 upgradable, 1 vulnerable, 1 error in 3.2s"
+        );
+    }
+}
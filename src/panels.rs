@@ -1,4 +1,6 @@
-use crate::models::{Package, PopularityData, Changelog};
+use crate::glyphs::{self, Glyphs};
+use crate::models::{Package, PopularityData, Changelog, SecurityStatus};
+use crate::resolver::Conflict;
 use ratatui::{
     layout::Rect,
     text::{Line, Span},
@@ -7,12 +9,73 @@ use ratatui::{
     Frame,
 };
 
+/// How the dependency list is laid out and drawn.
+#[derive(Clone, Copy)]
+pub struct ListOptions<'a> {
+    pub group_by_file: bool,
+    pub watchlist: &'a [String],
+    pub two_line: bool,
+    pub glyphs: &'a Glyphs,
+}
+
 pub fn render_dependency_list(
     f: &mut Frame,
     area: Rect,
-    packages: &[Package],
+    packages: &[&Package],
     selected_idx: usize,
+    options: ListOptions,
 ) {
+    let lines = dependency_list_lines(packages, selected_idx, options);
+    let selected_line = row_screen_line(packages, selected_idx, options.group_by_file, options.two_line);
+    let viewport = usize::from(area.height.saturating_sub(2));
+
+    let widget = Paragraph::new(lines)
+        .block(Block::default().title(" Dependencies ").borders(Borders::ALL))
+        .scroll((list_scroll(selected_line, row_height(options.two_line), viewport), 0));
+    f.render_widget(widget, area);
+}
+
+fn row_height(two_line: bool) -> usize {
+    if two_line {
+        2
+    } else {
+        1
+    }
+}
+
+/// The line, counting the column header as 0, that package `idx`'s row
+/// starts on, past any file headers and the taller rows before it.
+pub fn row_screen_line(packages: &[&Package], idx: usize, group_by_file: bool, two_line: bool) -> usize {
+    let mut line = 1;
+    let mut current_file: Option<Option<&str>> = None;
+    for (i, pkg) in packages.iter().enumerate() {
+        let origin = pkg.origin_file.as_deref();
+        if group_by_file && current_file != Some(origin) {
+            line += 1;
+            current_file = Some(origin);
+        }
+        if i == idx {
+            break;
+        }
+        line += row_height(two_line);
+    }
+    line
+}
+
+/// How far to scroll a list `viewport` lines tall so the whole row of
+/// `row_height` lines starting at `selected_line` is on screen.
+pub fn list_scroll(selected_line: usize, row_height: usize, viewport: usize) -> u16 {
+    let bottom = selected_line + row_height;
+    u16::try_from(bottom.saturating_sub(viewport)).unwrap_or(u16::MAX)
+}
+
+pub fn dependency_list_lines(packages: &[&Package], selected_idx: usize, options: ListOptions) -> Vec<Line<'static>> {
+    let ListOptions {
+        group_by_file,
+        watchlist,
+        two_line,
+        glyphs: g,
+    } = options;
     let mut lines = vec![
         Line::from(vec![
             Span::styled("NAME", Style::default().add_modifier(Modifier::BOLD)),
@@ -25,8 +88,27 @@ pub fn render_dependency_list(
         ])
     ];
 
+    // Watched packages are sorted ahead of the files, so they get a group of their own.
+    let mut current_group: Option<String> = None;
     for (idx, pkg) in packages.iter().enumerate() {
-        let marker = if idx == selected_idx { "→ " } else { "  " };
+        let group = if crate::app::is_watched(watchlist, pkg) {
+            format!("{} watched", g.star)
+        } else {
+            pkg.origin_file.clone().unwrap_or_else(|| "(unsaved)".to_string())
+        };
+        if group_by_file && current_group.as_ref() != Some(&group) {
+            lines.push(Line::from(Span::styled(
+                format!("{1}{1} {0} {1}{1}", group, g.rule),
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            )));
+            current_group = Some(group);
+        }
+
+        let marker = if idx == selected_idx {
+            format!("{} ", g.arrow)
+        } else {
+            " ".repeat(glyphs::width(g.arrow) + 1)
+        };
         let style = if idx == selected_idx {
             Style::default().bg(Color::DarkGray)
         } else {
@@ -41,10 +123,54 @@ pub fn render_dependency_list(
             _ => Color::Gray,
         };
 
-        let latest = pkg.latest_version.as_ref().map(|v| v.as_str()).unwrap_or("N/A");
+        let (watch_marker, name_style) = if crate::app::is_watched(watchlist, pkg) {
+            (g.star, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        } else if !pkg.is_direct {
+            (g.transitive, Style::default().fg(Color::DarkGray))
+        } else {
+            ("", Style::default())
+        };
+        let watch_marker = glyphs::pad(watch_marker, 2);
+        let dot = format!("{} ", g.dot);
+
+        let latest = pkg.latest_version.as_deref().unwrap_or("N/A");
+        if two_line {
+            let security = match pkg.security_status {
+                SecurityStatus::Vulnerable { cve_count } => {
+                    Span::styled(format!("  {} {} CVE(s)", g.warning, cve_count), Style::default().fg(Color::Magenta))
+                }
+                SecurityStatus::Safe => {
+                    Span::styled(format!("  {} no known CVEs", g.check), Style::default().fg(Color::Green))
+                }
+                SecurityStatus::Unknown => Span::raw(""),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(marker.clone(), style),
+                Span::styled(dot.clone(), Style::default().fg(crate::styles::health_color(pkg.health()))),
+                Span::styled(watch_marker.clone(), name_style),
+                Span::styled(pkg.name.clone(), name_style.add_modifier(Modifier::BOLD)),
+                security,
+            ]));
+            let downloads = pkg
+                .popularity
+                .as_ref()
+                .map(|p| format!(" | {} {}/wk", g.downloads, p.weekly_downloads))
+                .unwrap_or_default();
+            lines.push(Line::from(vec![
+                Span::styled(marker, style),
+                Span::raw(format!("    {} {} ", pkg.current_version, g.arrow)),
+                Span::styled(latest.to_string(), Style::default().fg(status_color)),
+                Span::raw(" | "),
+                Span::styled(pkg.status.as_str(), Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
+                Span::raw(downloads),
+            ]));
+            continue;
+        }
         let line = Line::from(vec![
             Span::styled(marker, style),
-            Span::raw(format!("{:<20} | ", &pkg.name[..pkg.name.len().min(20)])),
+            Span::styled(dot, Style::default().fg(crate::styles::health_color(pkg.health()))),
+            Span::styled(watch_marker, name_style),
+            Span::styled(format!("{} | ", glyphs::pad(&glyphs::truncate(&pkg.name, 20, ""), 20)), name_style),
             Span::raw(format!("{:<8} | ", pkg.current_version)),
             Span::styled(format!("{:<8} | ", latest), Style::default().fg(status_color)),
             Span::styled(pkg.status.as_str(), Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
@@ -52,16 +178,15 @@ pub fn render_dependency_list(
         lines.push(line);
     }
 
-    let widget = Paragraph::new(lines)
-        .block(Block::default().title(" Dependencies ").borders(Borders::ALL))
-        .scroll((0, 0));
-    f.render_widget(widget, area);
+    lines
 }
 
+/// `impact` is how many listed packages depend on `package`.
 pub fn render_info_panel(
     f: &mut Frame,
     area: Rect,
     package: Option<&Package>,
+    impact: usize,
 ) {
     let content = if let Some(pkg) = package {
         vec![
@@ -77,6 +202,28 @@ pub fn render_info_panel(
                 Span::styled("Source: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(pkg.source.source_type()),
             ]),
+            Line::from(vec![
+                Span::styled("Requested: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if pkg.is_direct { "directly" } else { "transitively" }),
+            ]),
+            Line::from(vec![
+                Span::styled("Impact: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("{} dependent(s)", impact),
+                    if impact >= crate::simulator::HIGH_IMPACT_DEPENDENTS {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default()
+                    },
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Health: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    pkg.health().as_str(),
+                    Style::default().fg(crate::styles::health_color(pkg.health())),
+                ),
+            ]),
             Line::from(vec![
                 Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::styled(
@@ -89,9 +236,33 @@ pub fn render_info_panel(
                     }),
                 ),
             ]),
+            Line::from(vec![
+                Span::styled("Python: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    pkg.requires_python.clone().unwrap_or_else(|| "any".to_string()),
+                    if pkg.drops_python.is_some() {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default()
+                    },
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Files: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(pkg.distribution.map_or("unknown", |d| d.as_str())),
+            ]),
+            Line::from(vec![
+                Span::styled("Install: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(pkg.install_command().unwrap_or_else(|| "-".to_string())),
+            ]),
             Line::from(""),
             Line::from(pkg.source.description()),
         ]
+        .into_iter()
+        .chain(pkg.notes.iter().map(|note| {
+            Line::from(Span::styled(format!("Note: {}", note), Style::default().fg(Color::Yellow)))
+        }))
+        .collect()
     } else {
         vec![Line::from("Select a package")]
     };
@@ -137,6 +308,7 @@ pub fn render_changelog_panel(
     f: &mut Frame,
     area: Rect,
     changelog: Option<&Changelog>,
+    g: &Glyphs,
 ) {
     let content = if let Some(cl) = changelog {
         vec![
@@ -166,12 +338,12 @@ pub fn render_changelog_panel(
             if !cl.breaking_changes.is_empty() {
                 vec![
                     Line::from(Span::styled(
-                        "⚠️  Breaking Changes:",
+                        format!("{} Breaking Changes:", g.icon(g.warning)),
                         Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
                     )),
                 ]
                 .into_iter()
-                .chain(cl.breaking_changes.iter().take(3).map(|c| Line::from(format!("  • {}", c))))
+                .chain(cl.breaking_changes.iter().take(3).map(|c| Line::from(format!("  {} {}", g.bullet, c))))
                 .collect::<Vec<_>>()
             } else {
                 vec![]
@@ -186,3 +358,142 @@ pub fn render_changelog_panel(
         .block(Block::default().title(" Changelog ").borders(Borders::ALL));
     f.render_widget(widget, area);
 }
+
+pub fn render_conflicts_panel(f: &mut Frame, area: Rect, conflicts: &[Conflict], g: &Glyphs) {
+    let widget = Paragraph::new(conflict_lines(conflicts, g))
+        .block(Block::default().title(format!(" Conflicts ({}) ", conflicts.len())).borders(Borders::ALL));
+    f.render_widget(widget, area);
+}
+
+pub fn conflict_lines<'a>(conflicts: &'a [Conflict], g: &Glyphs) -> Vec<Line<'a>> {
+    if conflicts.is_empty() {
+        return vec![Line::from(Span::styled("No conflicts detected", Style::default().fg(Color::Green)))];
+    }
+
+    conflicts
+        .iter()
+        .flat_map(|conflict| {
+            let color = crate::styles::severity_color(conflict.severity);
+            [
+                Line::from(vec![
+                    Span::styled(
+                        format!("[{}] ", conflict.severity.as_str()),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(&conflict.package, Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(": {} {} {}", conflict.current, g.arrow, conflict.required)),
+                ]),
+                Line::from(Span::styled(format!("  {}", conflict.reason), Style::default().fg(color))),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::VersionConstraint;
+
+    fn package(name: &str, origin: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            origin_file: Some(origin.to_string()),
+            ..Package::default()
+        }
+    }
+
+    fn options(group_by_file: bool, two_line: bool) -> ListOptions<'static> {
+        ListOptions {
+            group_by_file,
+            watchlist: &[],
+            two_line,
+            glyphs: &glyphs::EMOJI,
+        }
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_grouped_list_has_header_per_origin_file() {
+        let packages = [
+            package("django", "requirements.txt"),
+            package("flask", "requirements.txt"),
+            package("pytest", "requirements-dev.txt"),
+        ];
+
+        let lines = dependency_list_lines(&packages.iter().collect::<Vec<_>>(), 0, options(true, false));
+        let headers: Vec<String> = lines
+            .iter()
+            .map(line_text)
+            .filter(|text| text.starts_with("──"))
+            .collect();
+        assert_eq!(headers, vec!["── requirements.txt ──", "── requirements-dev.txt ──"]);
+        assert_eq!(lines.len(), 1 + headers.len() + packages.len());
+
+        let watched = [packages[2].clone(), packages[0].clone(), packages[1].clone()];
+        let watchlist = ["pytest".to_string()];
+        let headers: Vec<String> = dependency_list_lines(
+            &watched.iter().collect::<Vec<_>>(),
+            0,
+            ListOptions { watchlist: &watchlist, ..options(true, false) },
+        )
+        .iter()
+        .map(line_text)
+        .filter(|text| text.starts_with("──"))
+        .collect();
+        assert_eq!(headers, vec!["── ★ watched ──", "── requirements.txt ──"]);
+
+        let ungrouped = dependency_list_lines(&packages.iter().collect::<Vec<_>>(), 0, options(false, false));
+        assert_eq!(ungrouped.len(), 1 + packages.len());
+    }
+
+    #[test]
+    fn test_detected_conflict_appears_in_conflicts_panel() {
+        let mut pinned = package("django", "requirements.txt");
+        pinned.constraint = VersionConstraint::Pinned("3.2.0".to_string());
+        let mut floor = package("django", "requirements-dev.txt");
+        floor.constraint = VersionConstraint::GreaterEqual("4.0.0".to_string());
+
+        let conflicts = crate::resolver::DependencyResolver::new().validate_constraints(&[pinned, floor]);
+        assert_eq!(conflicts.len(), 1);
+
+        let lines = conflict_lines(&conflicts, &glyphs::EMOJI);
+        let text: Vec<String> = lines.iter().map(line_text).collect();
+        assert!(text[0].starts_with("[HIGH] django: "), "{}", text[0]);
+        assert!(text[1].contains("cannot be satisfied together"), "{}", text[1]);
+        assert_eq!(lines[0].spans[0].style.fg, Some(Color::Red));
+
+        let empty = conflict_lines(&[], &glyphs::EMOJI);
+        assert_eq!(line_text(&empty[0]), "No conflicts detected");
+    }
+
+    #[test]
+    fn test_two_line_rows_map_to_screen_lines() {
+        let packages = [
+            package("django", "requirements.txt"),
+            package("flask", "requirements.txt"),
+            package("pytest", "requirements-dev.txt"),
+        ];
+        let refs: Vec<&Package> = packages.iter().collect();
+
+        // Header, file header, django (2), flask (2), file header, pytest.
+        assert_eq!(row_screen_line(&refs, 0, true, true), 2);
+        assert_eq!(row_screen_line(&refs, 1, true, true), 4);
+        assert_eq!(row_screen_line(&refs, 2, true, true), 7);
+        assert_eq!(row_screen_line(&refs, 2, false, true), 5);
+        assert_eq!(row_screen_line(&refs, 2, false, false), 3);
+
+        let lines = dependency_list_lines(&refs, 2, options(true, true));
+        assert_eq!(lines.len(), 1 + 2 + 2 * packages.len());
+        assert!(line_text(&lines[7]).contains("pytest"));
+        assert!(line_text(&lines[8]).contains("→"));
+
+        // Only scrolls once the selected row's second line would fall off.
+        assert_eq!(list_scroll(7, 2, 10), 0);
+        assert_eq!(list_scroll(7, 2, 8), 1);
+    }
+}
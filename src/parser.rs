@@ -1,26 +1,74 @@
-use crate::models::{Package, RequirementsFile, VersionConstraint, VersionStatus, DependencySource};
-use anyhow::{anyhow, Result};
+use crate::models::{
+    normalize_package_name, DependencySource, GlobalOption, Package, PackagePolicy, ParseWarning,
+    RequirementsFile, VersionConstraint,
+};
+use crate::error::{PyElevateError, Result};
+use once_cell::sync::Lazy;
 use regex::Regex;
 use std::fs;
 use std::path::Path;
 use url::Url;
 
+// Compiled once: `normalize_version` runs for every requirement parsed.
+static THREE_PART_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)\.(\d+)\.(\d+)(.*)$").unwrap());
+static TWO_PART_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)\.(\d+)$").unwrap());
+static ONE_PART_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)$").unwrap());
+
 pub fn parse_requirements<P: AsRef<Path>>(path: P) -> Result<RequirementsFile> {
     let content = fs::read_to_string(&path)?;
+    let content = strip_bom(&content);
     let path_str = path.as_ref().to_string_lossy().to_string();
     
     let mut packages = Vec::new();
+    let mut options = Vec::new();
+    let mut warnings = Vec::new();
     let raw_lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
 
-    for line in content.lines() {
-        let line = line.trim();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim().trim_end_matches('\\').trim_end();
         
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
 
-        if let Ok(package) = parse_requirement_line(line) {
-            packages.push(package);
+        if let Some(option) = parse_global_option(line) {
+            options.push(option);
+            continue;
+        }
+
+        // Other pip options (`-r`, `-c`, `--hash` continuations, ...) aren't
+        // requirements and aren't interpreted.
+        if line.starts_with('-') && !line.starts_with("-e") {
+            continue;
+        }
+
+        match parse_requirement_line(line) {
+            Ok(mut package) => {
+                package.origin_file = Some(path_str.clone());
+                packages.push(package);
+            }
+            Err(e) => warnings.push(ParseWarning {
+                line: index + 1,
+                message: format!("{} ({})", parse_message(&e), line),
+            }),
+        }
+    }
+
+    let compiled = is_compiled(content);
+    if compiled {
+        let transitive = transitive_packages(content);
+        for package in &mut packages {
+            package.is_direct = !transitive.contains(&normalize_package_name(&package.name));
+        }
+        for (child, parent) in parse_via_annotations(content) {
+            if let Some(parent) = packages
+                .iter_mut()
+                .find(|p| normalize_package_name(&p.name) == normalize_package_name(&parent))
+            {
+                if !parent.dependencies.contains(&child) {
+                    parent.dependencies.push(child);
+                }
+            }
         }
     }
 
@@ -30,14 +78,268 @@ pub fn parse_requirements<P: AsRef<Path>>(path: P) -> Result<RequirementsFile> {
         path: path_str,
         packages,
         raw_lines,
+        options,
+        warnings,
+        compiled,
     })
 }
 
-fn parse_requirement_line(line: &str) -> Result<Package> {
-    let line = line.split('#').next().unwrap_or(line).trim();
+/// Requirements pip's hash-checking mode would reject. The mode is on when
+/// any requirement carries a `--hash` or the file sets `--require-hashes`,
+/// and then every requirement must be `==`-pinned and hashed.
+pub fn hash_violations(lines: &[String]) -> Vec<ParseWarning> {
+    let mut requirements = Vec::new();
+    let mut lines = lines.iter().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let mut logical = line.trim_end().trim_end_matches('\\').to_string();
+        let mut continued = line.trim_end().ends_with('\\');
+        while continued {
+            let Some((_, next)) = lines.next() else { break };
+            continued = next.trim_end().ends_with('\\');
+            logical.push(' ');
+            logical.push_str(next.trim().trim_end_matches('\\'));
+        }
+        let logical = logical.split(" #").next().unwrap_or_default().trim().to_string();
+        if !logical.is_empty() && !logical.starts_with('#') {
+            requirements.push((index + 1, logical));
+        }
+    }
+
+    let enabled = requirements.iter().any(|(_, line)| {
+        line.split_whitespace()
+            .any(|token| token.starts_with("--hash") || token == "--require-hashes")
+    });
+    if !enabled {
+        return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+    for (line, logical) in &requirements {
+        if logical.starts_with('-') && !logical.starts_with("-e") {
+            continue;
+        }
+        let Ok(package) = parse_requirement_line(logical) else { continue };
+        let mut problems = Vec::new();
+        if !matches!(package.constraint, VersionConstraint::Pinned(_)) || logical.starts_with("-e") {
+            problems.push("is not pinned with ==");
+        }
+        if !logical.split_whitespace().any(|token| token.starts_with("--hash")) {
+            problems.push("has no --hash");
+        }
+        if !problems.is_empty() {
+            violations.push(ParseWarning {
+                line: *line,
+                message: format!("{} {}", package.name, problems.join(" and ")),
+            });
+        }
+    }
+    violations
+}
+
+pub(crate) fn parse_message(error: &PyElevateError) -> String {
+    match error {
+        PyElevateError::Parse(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn parse_global_option(line: &str) -> Option<GlobalOption> {
+    let line = line.split(" #").next().unwrap_or(line).trim();
+    if line == "--prefer-binary" {
+        return Some(GlobalOption::PreferBinary);
+    }
+    let (flag, value) = match line.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") && !flag.contains(' ') => (flag, value),
+        _ => line.split_once(char::is_whitespace)?,
+    };
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        return None;
+    }
+
+    match flag {
+        "-i" | "--index-url" => Some(GlobalOption::IndexUrl(value)),
+        "--extra-index-url" => Some(GlobalOption::ExtraIndexUrl(value)),
+        "-f" | "--find-links" => Some(GlobalOption::FindLinks(value)),
+        "--only-binary" => Some(GlobalOption::OnlyBinary(value)),
+        "--no-binary" => Some(GlobalOption::NoBinary(value)),
+        _ => None,
+    }
+}
+
+/// `content` without its UTF-8 byte order mark. Files exported by some
+/// Windows tools start with one, which would otherwise end up in the first
+/// package name.
+pub fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// The line ending `content` uses, so rewritten files keep their CRLFs.
+pub fn line_ending(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// The package a requirements line declares, if it declares one.
+pub fn requirement_name(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || parse_global_option(line).is_some() {
+        return None;
+    }
+    parse_requirement_line(line).ok().map(|p| p.name)
+}
+
+/// pip-compile and `uv pip compile` annotate every pin with the packages that
+/// pulled it in, so a `# via` comment is a reliable marker of their output.
+pub fn is_compiled(content: &str) -> bool {
+    content.lines().any(|line| {
+        line.split_once('#')
+            .is_some_and(|(_, comment)| comment.trim_start().starts_with("via"))
+    })
+}
+
+/// Extracts `(dependency, dependent)` pairs from `# via` annotations, in both
+/// the single-line (`# via a, b`) and the indented multi-line form. Sources
+/// such as `-r requirements.in` are skipped.
+pub fn parse_via_annotations(content: &str) -> Vec<(String, String)> {
+    via_entries(content)
+        .into_iter()
+        .filter(|(_, parent)| !parent.starts_with('-'))
+        .map(|(child, parent)| {
+            let parent = parent.split('[').next().unwrap_or(&parent).trim().to_string();
+            (child, parent)
+        })
+        .collect()
+}
+
+/// Packages a compiled file only has because something else requires them:
+/// annotated `# via` other packages but not via a source such as
+/// `-r requirements.in`.
+pub fn transitive_packages(content: &str) -> Vec<String> {
+    let entries = via_entries(content);
+    let mut transitive: Vec<String> = entries.iter().map(|(child, _)| normalize_package_name(child)).collect();
+    transitive.dedup();
+    transitive.retain(|name| {
+        !entries
+            .iter()
+            .any(|(child, parent)| normalize_package_name(child) == *name && parent.starts_with('-'))
+    });
+    transitive
+}
+
+/// Every `(requirement, via entry)` pair, sources included.
+fn via_entries(content: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut current: Option<String> = None;
+    let mut in_via_list = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let (requirement, comment) = match trimmed.split_once('#') {
+            Some((requirement, comment)) => (requirement.trim(), Some(comment.trim())),
+            None => (trimmed, None),
+        };
+
+        if !requirement.is_empty() {
+            in_via_list = false;
+            current = if parse_global_option(requirement).is_none() {
+                parse_requirement_line(requirement).ok().map(|p| p.name)
+            } else {
+                None
+            };
+        }
+
+        let Some(comment) = comment else {
+            continue;
+        };
+        let parents = if let Some(rest) = comment.strip_prefix("via") {
+            if rest.trim().is_empty() {
+                in_via_list = true;
+                continue;
+            }
+            rest.split(',').map(str::trim).collect::<Vec<_>>()
+        } else if in_via_list && requirement.is_empty() {
+            vec![comment]
+        } else {
+            in_via_list = false;
+            continue;
+        };
+
+        if let Some(child) = &current {
+            for parent in parents.into_iter().filter(|parent| !parent.is_empty()) {
+                pairs.push((child.clone(), parent.to_string()));
+            }
+        }
+    }
+
+    pairs
+}
+
+pub(crate) fn parse_requirement_line(line: &str) -> Result<Package> {
+    let policy = parse_policy(line);
+    let line = strip_comment(line).trim();
+    // Per-requirement options such as `--hash=sha256:...` follow the spec.
+    let line = line.split(" --").next().unwrap_or(line).trim();
     
     if line.is_empty() {
-        return Err(anyhow!("Empty line"));
+        return Err(PyElevateError::Parse("empty line".to_string()));
+    }
+
+    let (requirement, marker) = match line.split_once(';') {
+        Some((requirement, marker)) => (requirement.trim(), Some(marker.trim())),
+        None => (line, None),
+    };
+    let mut package = parse_requirement(requirement)?;
+    package.marker = marker.filter(|m| !m.is_empty()).map(|m| m.to_string());
+    package.policy = policy;
+    Ok(package)
+}
+
+/// `line` up to its comment. As in pip, a `#` only starts one at the start
+/// of the line or after whitespace, so URL fragments such as `#egg=` stay.
+fn strip_comment(line: &str) -> &str {
+    let start = line
+        .char_indices()
+        .find(|&(i, c)| c == '#' && line[..i].chars().next_back().is_none_or(char::is_whitespace))
+        .map_or(line.len(), |(i, _)| i);
+    &line[..start]
+}
+
+/// The trailing `# pyelevate: ...` directive of a requirement line, if any.
+pub fn policy_comment(line: &str) -> Option<&str> {
+    let start = line
+        .match_indices('#')
+        .map(|(i, _)| i)
+        .find(|&i| line[i + 1..].trim_start().starts_with("pyelevate:"))?;
+    Some(line[start..].trim_end())
+}
+
+/// Reads `# pyelevate: freeze, ignore-security` style directives. Unknown
+/// words are ignored so newer directives don't break older versions.
+pub fn parse_policy(line: &str) -> PackagePolicy {
+    let mut policy = PackagePolicy::default();
+    let Some(comment) = policy_comment(line) else {
+        return policy;
+    };
+    let directives = comment[1..].trim_start().trim_start_matches("pyelevate:");
+    let directives = directives.split('#').next().unwrap_or_default();
+    for directive in directives.split(|c: char| c == ',' || c.is_whitespace()) {
+        match directive {
+            "freeze" => policy.freeze = true,
+            "pin" => policy.pin = true,
+            "ignore-security" => policy.ignore_security = true,
+            _ => {}
+        }
+    }
+    policy
+}
+
+fn parse_requirement(line: &str) -> Result<Package> {
+    if let Some(package) = parse_direct_reference(line) {
+        return package;
     }
 
     if let Some(rest) = line.strip_prefix("git+") {
@@ -55,111 +357,152 @@ fn parse_requirement_line(line: &str) -> Result<Package> {
     parse_pypi_requirement(line)
 }
 
+/// PEP 508 `name[extras] @ url`. The explicit name wins over anything that
+/// could be derived from the URL. Returns `None` when the line isn't one,
+/// which keeps `git+https://host/repo@ref` on the plain git path.
+fn parse_direct_reference(line: &str) -> Option<Result<Package>> {
+    let (name_part, url) = line.split_once('@')?;
+    let (name, extras) = extract_extras(name_part.trim());
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    let url = url.trim();
+    if !valid_name || !url.contains("://") {
+        return None;
+    }
+
+    let package = match url.strip_prefix("git+") {
+        Some(rest) => parse_git_requirement(rest),
+        None => parse_url_requirement(url),
+    };
+    Some(package.map(|mut package| {
+        package.name = name.to_lowercase();
+        package.extras = extras;
+        package
+    }))
+}
+
 fn parse_pypi_requirement(line: &str) -> Result<Package> {
     let (name_part, version_spec) = extract_version_spec(line)?;
+    if name_part.contains('[') && !name_part.trim_end().ends_with(']') {
+        return Err(PyElevateError::Parse("unclosed extras bracket".to_string()));
+    }
     let (name, extras) = extract_extras(&name_part);
+    let name = name.trim().to_string();
+    if !is_valid_name(&name) {
+        return Err(PyElevateError::Parse(format!("invalid package name '{}'", name)));
+    }
+    if !version_spec.is_empty()
+        && !version_spec.trim_start_matches(['=', '>', '<', '~', '!']).chars().any(|c| c.is_ascii_alphanumeric())
+    {
+        return Err(PyElevateError::Parse(format!("missing version after '{}'", version_spec.trim())));
+    }
 
     let (constraint, current_version) = parse_version_spec(&version_spec);
 
     Ok(Package {
         name: name.to_lowercase(),
         current_version,
-        latest_version: None,
-        status: VersionStatus::Unknown,
-        selected: false,
         extras,
         constraint,
-        error: None,
-        source: DependencySource::PyPI,
-        security_status: crate::models::SecurityStatus::Unknown,
-        changelog: None,
-        popularity: None,
-        dependencies: Vec::new(),
+        ..Package::default()
     })
 }
 
+/// PEP 508 names: ASCII letters, digits, `.`, `-` and `_`, starting and
+/// ending with a letter or digit.
+fn is_valid_name(name: &str) -> bool {
+    let edges_ok = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    edges_ok(name.chars().next())
+        && edges_ok(name.chars().last())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// The package pip installs from a URL with an `#egg=name` fragment.
+fn egg_name(fragment: &str) -> Option<String> {
+    fragment
+        .split('&')
+        .find_map(|part| part.strip_prefix("egg="))
+        .filter(|name| is_valid_name(name))
+        .map(str::to_string)
+}
+
+/// `https://host/repo.git@ref#egg=name`: the `#egg=` name, when given,
+/// wins over the one derived from the repository.
 fn parse_git_requirement(rest: &str) -> Result<Package> {
+    let (rest, fragment) = rest.split_once('#').unwrap_or((rest, ""));
     let parts: Vec<&str> = rest.split('@').collect();
     let url = parts[0].to_string();
     let ref_spec = parts.get(1).map(|s| s.to_string());
 
-    let name = extract_package_name_from_git(&url)
-        .unwrap_or_else(|| format!("git-{}", uuid::Uuid::new_v4().to_string()[0..8].to_string()));
+    let name = egg_name(fragment)
+        .or_else(|| extract_package_name_from_git(&url))
+        .unwrap_or_else(|| format!("git-{}", &uuid::Uuid::new_v4().to_string()[0..8]));
 
     Ok(Package {
         name: name.to_lowercase(),
         current_version: "git-source".to_string(),
-        latest_version: None,
-        status: VersionStatus::Unknown,
-        selected: false,
-        extras: Vec::new(),
-        constraint: VersionConstraint::Unspecified,
-        error: None,
-        source: DependencySource::Git { url, ref_spec },
-        security_status: crate::models::SecurityStatus::Unknown,
-        changelog: None,
-        popularity: None,
-        dependencies: Vec::new(),
+        source: DependencySource::Git {
+            url,
+            ref_spec,
+            editable: false,
+        },
+        ..Package::default()
     })
 }
 
 fn parse_editable_requirement(rest: &str) -> Result<Package> {
     let path = rest.trim_start_matches('-').trim();
+    if let Some(vcs) = path.strip_prefix("git+") {
+        let mut package = parse_git_requirement(vcs)?;
+        if let DependencySource::Git { editable, .. } = &mut package.source {
+            *editable = true;
+        }
+        return Ok(package);
+    }
 
     let name = Path::new(path)
         .file_name()
         .and_then(|n| n.to_str())
         .map(|s| s.to_string())
-        .unwrap_or_else(|| format!("local-{}", uuid::Uuid::new_v4().to_string()[0..8].to_string()));
+        .unwrap_or_else(|| format!("local-{}", &uuid::Uuid::new_v4().to_string()[0..8]));
 
     Ok(Package {
         name: name.to_lowercase(),
         current_version: "local".to_string(),
-        latest_version: None,
-        status: VersionStatus::Unknown,
-        selected: false,
-        extras: Vec::new(),
-        constraint: VersionConstraint::Unspecified,
-        error: None,
         source: DependencySource::LocalPath {
             path: path.to_string(),
             editable: true,
         },
-        security_status: crate::models::SecurityStatus::Unknown,
-        changelog: None,
-        popularity: None,
-        dependencies: Vec::new(),
+        ..Package::default()
     })
 }
 
 fn parse_url_requirement(line: &str) -> Result<Package> {
     if let Ok(url) = Url::parse(line) {
+        let derived = || {
+            url.path_segments()
+                .and_then(|mut segments| segments.next())
+                .and_then(|s| s.split('.').next())
+                .map(|s| s.to_string())
+        };
         let name = url
-            .path_segments()
-            .and_then(|mut segments| segments.next())
-            .and_then(|s| s.split('.').next())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| format!("url-{}", uuid::Uuid::new_v4().to_string()[0..8].to_string()));
+            .fragment()
+            .and_then(egg_name)
+            .or_else(derived)
+            .unwrap_or_else(|| format!("url-{}", &uuid::Uuid::new_v4().to_string()[0..8]));
 
         Ok(Package {
             name: name.to_lowercase(),
             current_version: "url-source".to_string(),
-            latest_version: None,
-            status: VersionStatus::Unknown,
-            selected: false,
-            extras: Vec::new(),
-            constraint: VersionConstraint::Unspecified,
-            error: None,
             source: DependencySource::Url {
                 url: line.to_string(),
             },
-            security_status: crate::models::SecurityStatus::Unknown,
-            changelog: None,
-            popularity: None,
-            dependencies: Vec::new(),
+            ..Package::default()
         })
     } else {
-        Err(anyhow!("Invalid URL requirement"))
+        Err(PyElevateError::Parse(format!("invalid URL requirement: {}", line)))
     }
 }
 
@@ -191,6 +534,28 @@ fn extract_extras(name_part: &str) -> (String, Vec<String>) {
     }
 }
 
+/// A constraint typed by hand, such as `>=2.0` or `~=1.4`. Unlike a file's
+/// specs, anything `parse_version_spec` can't place, or whose versions
+/// don't parse, is rejected rather than read as unconstrained.
+pub fn parse_constraint(spec: &str) -> Result<(VersionConstraint, String)> {
+    let (constraint, current) = parse_version_spec(spec);
+    let versions = match &constraint {
+        VersionConstraint::Pinned(v)
+        | VersionConstraint::GreaterEqual(v)
+        | VersionConstraint::Less(v)
+        | VersionConstraint::Compatible(v) => vec![v.as_str()],
+        VersionConstraint::Range(low, high) => vec![low.as_str(), high.as_str()],
+        VersionConstraint::Unspecified if spec.trim().is_empty() => Vec::new(),
+        VersionConstraint::Unspecified => {
+            return Err(PyElevateError::Parse(format!("unsupported constraint: {}", spec.trim())));
+        }
+    };
+    if let Some(bad) = versions.iter().find(|v| crate::version::Pep440Version::parse(v).is_none()) {
+        return Err(PyElevateError::Parse(format!("invalid version in {}: {}", spec.trim(), bad)));
+    }
+    Ok((constraint, current))
+}
+
 fn parse_version_spec(spec: &str) -> (VersionConstraint, String) {
     let spec = spec.trim();
 
@@ -198,6 +563,18 @@ fn parse_version_spec(spec: &str) -> (VersionConstraint, String) {
         return (VersionConstraint::Unspecified, "0.0.0".to_string());
     }
 
+    if let Some((first, second)) = spec.split_once(',') {
+        let (first, second) = (first.trim(), second.trim());
+        let (low, high) = if first.starts_with('<') { (second, first) } else { (first, second) };
+        let high = high.strip_prefix('<').filter(|h| !h.starts_with('='));
+        if let (Some(low), Some(high)) = (low.strip_prefix(">="), high) {
+            let low = low.trim().to_string();
+            let high = high.trim().to_string();
+            let current = normalize_version(&low);
+            return (VersionConstraint::Range(low, high), current);
+        }
+    }
+
     if let Some(version) = spec.strip_prefix("==") {
         let version = version.trim().to_string();
         (
@@ -228,17 +605,15 @@ fn parse_version_spec(spec: &str) -> (VersionConstraint, String) {
 }
 
 fn normalize_version(version: &str) -> String {
-    let re = Regex::new(r"^(\d+)\.(\d+)\.(\d+)(.*)$").unwrap();
-    
-    if let Some(caps) = re.captures(version) {
+    if let Some(caps) = THREE_PART_VERSION.captures(version) {
         format!(
             "{}.{}.{}{}",
             &caps[1], &caps[2], &caps[3],
             caps.get(4).map(|m| m.as_str()).unwrap_or("")
         )
-    } else if let Some(caps) = Regex::new(r"^(\d+)\.(\d+)$").unwrap().captures(version) {
+    } else if let Some(caps) = TWO_PART_VERSION.captures(version) {
         format!("{}.{}.0", &caps[1], &caps[2])
-    } else if let Some(caps) = Regex::new(r"^(\d+)$").unwrap().captures(version) {
+    } else if let Some(caps) = ONE_PART_VERSION.captures(version) {
         format!("{}.0.0", &caps[1])
     } else {
         version.to_string()
@@ -247,7 +622,7 @@ fn normalize_version(version: &str) -> String {
 
 fn extract_package_name_from_git(url: &str) -> Option<String> {
     url.split('/')
-        .last()
+        .next_back()
         .and_then(|name| name.strip_suffix(".git"))
         .map(|s| s.to_string())
 }
@@ -256,6 +631,22 @@ fn extract_package_name_from_git(url: &str) -> Option<String> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_hash_violations_only_in_hash_checking_mode() {
+        let lines = |content: &str| content.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+        assert!(hash_violations(&lines("requests>=2.0\nflask\n")).is_empty());
+
+        let content = "requests==2.28.0 \\\n    --hash=sha256:aaa  # pinned\nflask>=2.0 --hash=sha256:bbb\nclick==8.1.0\n";
+        let violations = hash_violations(&lines(content));
+        assert_eq!(
+            violations.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            vec!["line 3: flask is not pinned with ==", "line 4: click has no --hash"]
+        );
+
+        let violations = hash_violations(&lines("--require-hashes\nclick\n"));
+        assert_eq!(violations[0].to_string(), "line 2: click is not pinned with == and has no --hash");
+    }
+
     #[test]
     fn test_parse_pinned_version() {
         let pkg = parse_pypi_requirement("requests==2.28.1").unwrap();
@@ -270,6 +661,308 @@ mod tests {
         assert_eq!(pkg.extras.len(), 2);
     }
 
+    #[test]
+    fn test_parse_range() {
+        let pkg = parse_pypi_requirement("django>=2.0,<3.0").unwrap();
+        assert!(matches!(
+            pkg.constraint,
+            VersionConstraint::Range(ref low, ref high) if low == "2.0" && high == "3.0"
+        ));
+    }
+
+    #[test]
+    fn test_broken_lines_are_reported_with_line_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "# deps\nflask==2.0.0\nrequests=2.28.0\n-r base.txt\n\ndjango[argon2==4.2\nhttpx==\n",
+        )
+        .unwrap();
+
+        let req_file = parse_requirements(&path).unwrap();
+        let names: Vec<&str> = req_file.packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["flask"]);
+        let lines: Vec<usize> = req_file.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, vec![3, 6, 7]);
+        assert_eq!(
+            req_file.warnings[0].to_string(),
+            "line 3: invalid package name 'requests=2.28.0' (requests=2.28.0)"
+        );
+    }
+
+    #[test]
+    fn test_parse_compiled_via_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "\
+# This file is autogenerated by pip-compile with Python 3.11
+certifi==2023.7.22
+    # via requests
+charset-normalizer==3.2.0
+    # via
+    #   -r requirements.in
+    #   requests
+idna==3.4  # via requests, httpx
+requests==2.31.0
+    # via -r requirements.in
+",
+        )
+        .unwrap();
+
+        let req_file = parse_requirements(&path).unwrap();
+        let requests = req_file.packages.iter().find(|p| p.name == "requests").unwrap();
+        assert_eq!(requests.dependencies, vec!["certifi", "charset-normalizer", "idna"]);
+        assert!(req_file
+            .packages
+            .iter()
+            .filter(|p| p.name != "requests")
+            .all(|p| p.dependencies.is_empty()));
+
+        assert!(!is_compiled("requests==2.31.0\n# pinned for py3.8\n"));
+    }
+
+    #[test]
+    fn test_via_annotated_package_is_transitive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "flask==3.0.0\n    # via -r requirements.in\nitsdangerous==2.1.2\n    # via flask\n\
+             jinja2==3.1.2\n    # via\n    #   -r requirements.in\n    #   flask\n",
+        )
+        .unwrap();
+
+        let packages = parse_requirements(&path).unwrap().packages;
+        let direct: Vec<(&str, bool)> = packages.iter().map(|p| (p.name.as_str(), p.is_direct)).collect();
+        assert_eq!(direct, vec![("flask", true), ("itsdangerous", false), ("jinja2", true)]);
+    }
+
+    #[test]
+    fn test_parse_global_options() {
+        assert_eq!(
+            parse_global_option("--extra-index-url https://download.example.com/simple"),
+            Some(GlobalOption::ExtraIndexUrl("https://download.example.com/simple".to_string()))
+        );
+        assert_eq!(
+            parse_global_option("--index-url=https://mirror.example.com/simple"),
+            Some(GlobalOption::IndexUrl("https://mirror.example.com/simple".to_string()))
+        );
+        assert_eq!(
+            parse_global_option("-f ./wheels"),
+            Some(GlobalOption::FindLinks("./wheels".to_string()))
+        );
+        assert_eq!(parse_global_option("-e ./local"), None);
+    }
+
+    #[test]
+    fn test_binary_directives_are_parsed_and_preserved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "--only-binary :all:\n--no-binary django\n--prefer-binary\ndjango==4.2.9\nnumpy==1.26.0";
+        fs::write(&path, content).unwrap();
+
+        let mut req_file = parse_requirements(&path).unwrap();
+        assert_eq!(req_file.packages.len(), 2);
+        assert_eq!(
+            req_file.options,
+            vec![
+                GlobalOption::OnlyBinary(":all:".to_string()),
+                GlobalOption::NoBinary("django".to_string()),
+                GlobalOption::PreferBinary,
+            ]
+        );
+        let lines: Vec<String> = req_file.options.iter().map(GlobalOption::as_line).collect();
+        assert_eq!(lines, content.lines().take(3).collect::<Vec<_>>());
+
+        for pkg in &mut req_file.packages {
+            pkg.latest_version = Some(format!("{}.1", pkg.current_version));
+        }
+        let upgraded = crate::upgrade::UpgradeManager::generate_upgraded_content(
+            &req_file.packages,
+            content,
+            false,
+            crate::models::ConstraintUpdate::Keep,
+        )
+        .unwrap();
+        assert_eq!(
+            upgraded,
+            "--only-binary :all:\n--no-binary django\n--prefer-binary\ndjango==4.2.9.1\nnumpy==1.26.0.1"
+        );
+
+        use crate::wheels::{binary_rule, BinaryRule};
+        assert_eq!(binary_rule(&req_file.options, "numpy"), BinaryRule::OnlyBinary);
+        assert_eq!(binary_rule(&req_file.options, "Django"), BinaryRule::NoBinary);
+        let cleared = [
+            GlobalOption::OnlyBinary(":all:".to_string()),
+            GlobalOption::OnlyBinary(":none:".to_string()),
+        ];
+        assert_eq!(binary_rule(&cleared, "numpy"), BinaryRule::Any);
+    }
+
+    #[test]
+    fn test_extra_index_url_is_parsed_and_preserved() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "--extra-index-url https://download.example.com/simple\nrequests==2.28.0";
+        fs::write(&path, content).unwrap();
+
+        let mut req_file = parse_requirements(&path).unwrap();
+        assert_eq!(req_file.packages.len(), 1);
+        assert_eq!(
+            req_file.options,
+            vec![GlobalOption::ExtraIndexUrl("https://download.example.com/simple".to_string())]
+        );
+
+        req_file.packages[0].latest_version = Some("2.31.0".to_string());
+        let upgraded = crate::upgrade::UpgradeManager::generate_upgraded_content(
+            &req_file.packages,
+            content,
+            false,
+            crate::models::ConstraintUpdate::Keep,
+        )
+        .unwrap();
+        assert!(upgraded.starts_with("--extra-index-url https://download.example.com/simple\n"));
+        assert!(upgraded.contains("requests==2.31.0"));
+
+        let mut client = crate::pypi::PyPIClient::new();
+        client.apply_options(&req_file.options);
+        assert_eq!(
+            client.indexes(),
+            ["https://pypi.org/pypi", "https://download.example.com/pypi"]
+        );
+    }
+
+    #[test]
+    fn test_parse_bom_and_crlf() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "\u{feff}requests==2.28.0\r\nflask==2.0.0\r\n";
+        fs::write(&path, content).unwrap();
+
+        let mut req_file = parse_requirements(&path).unwrap();
+        assert_eq!(req_file.packages[1].name, "requests");
+        assert_eq!(req_file.packages[1].current_version, "2.28.0");
+
+        req_file.packages[1].latest_version = Some("2.31.0".to_string());
+        let upgraded = crate::upgrade::UpgradeManager::generate_upgraded_content(
+            &req_file.packages,
+            content,
+            false,
+            crate::models::ConstraintUpdate::Keep,
+        )
+        .unwrap();
+        assert_eq!(upgraded, "\u{feff}requests==2.31.0\r\nflask==2.0.0\r\n");
+
+        let removed = crate::upgrade::UpgradeManager::remove_requirements(
+            content,
+            &["flask".to_string()],
+        );
+        assert_eq!(removed, "\u{feff}requests==2.28.0\r\n");
+    }
+
+    #[test]
+    fn test_parse_direct_reference_to_wheel() {
+        let pkg = parse_requirement_line(
+            "my-pkg[cli] @ https://files.example.com/packages/other_name-1.0-py3-none-any.whl",
+        )
+        .unwrap();
+        assert_eq!(pkg.name, "my-pkg");
+        assert_eq!(pkg.extras, vec!["cli"]);
+        assert_eq!(
+            pkg.source,
+            DependencySource::Url {
+                url: "https://files.example.com/packages/other_name-1.0-py3-none-any.whl".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_direct_reference_to_git() {
+        let pkg = parse_requirement_line(
+            "django-extras @ git+https://github.com/user/repo.git@v2.1 ; python_version >= '3.8'",
+        )
+        .unwrap();
+        assert_eq!(pkg.name, "django-extras");
+        assert_eq!(
+            pkg.source,
+            DependencySource::Git {
+                url: "https://github.com/user/repo.git".to_string(),
+                ref_spec: Some("v2.1".to_string()),
+                editable: false,
+            }
+        );
+
+        let plain = parse_requirement_line("git+https://github.com/user/repo.git@main").unwrap();
+        assert_eq!(plain.name, "repo");
+    }
+
+    #[test]
+    fn test_current_env_only_excludes_windows_package_on_linux() {
+        let pkg = parse_requirement_line(r#"pywin32==306.0.0 ; sys_platform == "win32""#).unwrap();
+        assert_eq!(pkg.name, "pywin32");
+        assert_eq!(pkg.current_version, "306.0.0");
+        assert_eq!(pkg.marker.as_deref(), Some(r#"sys_platform == "win32""#));
+
+        let linux = crate::markers::Environment::new("linux", "x86_64", Some((3, 11)));
+        let windows = crate::markers::Environment::new("windows", "x86_64", Some((3, 11)));
+        assert!(!pkg.applies_to(&linux));
+        assert!(pkg.applies_to(&windows));
+
+        let unmarked = parse_requirement_line("requests==2.31.0").unwrap();
+        assert!(unmarked.applies_to(&linux));
+    }
+
+    #[test]
+    fn test_large_file_parses_with_shared_version_regexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content: String = (0..10_000).map(|i| format!("pkg{}==1.{}\n", i, i % 50)).collect();
+        fs::write(&path, content).unwrap();
+
+        let file = parse_requirements(&path).unwrap();
+        assert_eq!(file.packages.len(), 10_000);
+        assert!(file.warnings.is_empty());
+        let pkg49 = file.packages.iter().find(|p| p.name == "pkg49").unwrap();
+        assert_eq!(pkg49.current_version, "1.49.0");
+        // Statics, compiled on first use and reused for every line after.
+        assert!(Lazy::get(&THREE_PART_VERSION).is_some());
+        assert!(Lazy::get(&TWO_PART_VERSION).is_some());
+    }
+
+    #[test]
+    fn test_editable_git_install_keeps_egg_name() {
+        let pkg = parse_requirement_line("-e git+https://github.com/user/repo.git#egg=mypkg").unwrap();
+        assert_eq!(pkg.name, "mypkg");
+        assert_eq!(
+            pkg.source,
+            DependencySource::Git {
+                url: "https://github.com/user/repo.git".to_string(),
+                ref_spec: None,
+                editable: true,
+            }
+        );
+
+        let pinned = parse_requirement_line("git+https://github.com/user/repo.git@v1.0#egg=mypkg").unwrap();
+        assert_eq!(pinned.name, "mypkg");
+        assert!(matches!(
+            pinned.source,
+            DependencySource::Git { ref ref_spec, editable: false, .. } if ref_spec.as_deref() == Some("v1.0")
+        ));
+    }
+
+    #[test]
+    fn test_egg_fragment_names_git_and_url_sources() {
+        let git = parse_requirement_line("git+https://host/weird-repo.git#egg=realname").unwrap();
+        assert_eq!(git.name, "realname");
+
+        let url = parse_requirement_line("https://example.com/downloads/archive.zip#egg=realname").unwrap();
+        assert_eq!(url.name, "realname");
+        assert!(matches!(url.source, DependencySource::Url { .. }));
+    }
+
     #[test]
     fn test_parse_git() {
         let pkg = parse_git_requirement("https://github.com/user/repo.git@main").unwrap();
@@ -1,35 +1,68 @@
 use crate::models::PopularityData;
 use anyhow::Result;
+use crate::cache;
 use reqwest::Client;
-use std::collections::HashMap;
 
+const PYPISTATS_API: &str = "https://pypistats.org/api";
+
+/// Clones share the HTTP client and the download-stats cache.
+#[derive(Clone)]
 pub struct PopularityChecker {
     client: Client,
-    cache: HashMap<String, Option<PopularityData>>,
+    cache: cache::SharedLru<String, Option<PopularityData>>,
+    max_body_bytes: usize,
+    api_url: String,
 }
 
 impl PopularityChecker {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
-            cache: HashMap::new(),
+            cache: cache::bounded(cache::DEFAULT_CAPACITY),
+            max_body_bytes: crate::http::DEFAULT_MAX_BODY_BYTES,
+            api_url: PYPISTATS_API.to_string(),
         }
     }
 
-    pub async fn fetch_popularity(&mut self, package: &str) -> Result<Option<PopularityData>> {
-        if let Some(cached) = self.cache.get(package) {
-            return Ok(cached.clone());
+    /// Caps every download-stats response body.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Keeps download stats for at most `capacity` packages, evicting the
+    /// least recently used.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = cache::bounded(capacity);
+        self
+    }
+
+    /// Drops the cached download stats for `package`.
+    pub fn forget(&self, package: &str) {
+        self.cache.lock().pop(package);
+    }
+
+    pub async fn fetch_popularity(&self, package: &str) -> Result<Option<PopularityData>> {
+        let cached = self.cache.lock().get(package).cloned();
+        if let Some(cached) = cached {
+            return Ok(cached);
         }
 
         let popularity = self.fetch_from_pypi_stats(package).await.ok();
-        self.cache.insert(package.to_string(), popularity.clone());
+        self.cache.lock().put(package.to_string(), popularity.clone());
         Ok(popularity)
     }
 
     async fn fetch_from_pypi_stats(&self, package: &str) -> Result<PopularityData> {
-        let url = format!("https://pypistats.org/api/packages/{}/recent", package);
+        let url = format!("{}/packages/{}/recent", self.api_url, package);
         let response = self.client.get(&url).send().await?;
-        let data: serde_json::Value = response.json().await?;
+        let data: serde_json::Value =
+            crate::http::read_json(response, self.max_body_bytes).await?;
 
         let mut trend = Vec::new();
         if let Some(rows) = data.get("data").and_then(|d| d.as_array()) {
@@ -1,73 +1,346 @@
-use crate::models::Package;
-use anyhow::Result;
-use reqwest::Client;
-use std::collections::HashMap;
+use crate::models::{compare_release, normalize_package_name, GlobalOption, Package};
+use crate::error::{PyElevateError, Result};
+use crate::markers::{self, Environment};
+use chrono::{DateTime, Utc};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use crate::cache;
 use parking_lot::RwLock;
 
 const PYPI_API: &str = "https://pypi.org/pypi";
 const PYPI_STATS: &str = "https://pypistats.org/api/packages";
+const PYPI_SIMPLE: &str = "https://pypi.org/simple/";
+const SEARCH_LIMIT: usize = 20;
+/// Names this many edits or fewer from a missing package are offered as
+/// "did you mean" suggestions.
+const SUGGESTION_DISTANCE: usize = 2;
+const SUGGESTION_LIMIT: usize = 3;
+/// Starts the note on a package pinned newer than the index's latest
+/// release; see [`Package::ahead_of_index`].
+pub const AHEAD_OF_INDEX_NOTE: &str = "pin is newer than the index's latest";
 
+/// A package whose latest version on the configured index differs from the
+/// canonical one, which points at a stale or tampered mirror.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexMismatch {
+    pub package: String,
+    pub mirror: String,
+    pub canonical: String,
+}
+
+impl std::fmt::Display for IndexMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: index reports {}, pypi.org reports {}",
+            self.package, self.mirror, self.canonical
+        )
+    }
+}
+
+/// Clones share the HTTP client and the lookup caches.
+#[derive(Clone)]
 pub struct PyPIClient {
     client: Client,
+    /// The listed packages as [`PyPIClient::update_packages`] fetched them.
+    /// Never evicted, since their releases and metadata are read back later.
     cache: Arc<RwLock<HashMap<String, CachedPackage>>>,
+    /// One-off lookups such as a package being added, least recently used
+    /// evicted first.
+    lookups: cache::SharedLru<String, CachedPackage>,
+    indexes: Vec<String>,
+    simple_index: String,
+    project_names: Arc<RwLock<Vec<String>>>,
+    /// `requires_dist` of specific releases, keyed by `name/version`.
+    release_requires: Arc<RwLock<HashMap<String, Vec<String>>>>,
+    max_body_bytes: usize,
+    /// The file's `--only-binary`, `--no-binary` and `--prefer-binary`
+    /// directives, in order.
+    binary_options: Vec<GlobalOption>,
+}
+
+/// A package whose requirements `expand_extras` still has to follow: those
+/// gated by `extras`, plus the unconditional ones for newly added packages.
+struct Expansion {
+    name: String,
+    extras: Vec<String>,
+    unconditional: bool,
+    via: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub name: String,
+    pub score: i64,
 }
 
 #[derive(Clone, Debug)]
 struct CachedPackage {
-    name: String,
     latest: Option<String>,
-    metadata: Option<PyPIMetadata>,
+    releases: Vec<String>,
+    latest_upload: Option<DateTime<Utc>>,
+    summary: String,
+    files: HashMap<String, Vec<String>>,
+    requires_dist: Vec<String>,
+    requires_python: Option<String>,
+    /// When each release was first uploaded.
+    uploads: HashMap<String, DateTime<Utc>>,
+    /// `sha256:<digest>` of every non-yanked file, per release.
+    hashes: HashMap<String, Vec<String>>,
+}
+
+impl CachedPackage {
+    fn from_response(response: PyPIResponse) -> Self {
+        let latest_upload = response
+            .releases
+            .get(&response.info.version)
+            .and_then(|files| latest_upload_time(files));
+        let files: HashMap<String, Vec<String>> = response
+            .releases
+            .iter()
+            .map(|(version, files)| (version.clone(), release_filenames(files)))
+            .collect();
+        let uploads: HashMap<String, DateTime<Utc>> = response
+            .releases
+            .iter()
+            .filter_map(|(version, files)| Some((version.clone(), first_upload_time(files)?)))
+            .collect();
+        let hashes: HashMap<String, Vec<String>> = response
+            .releases
+            .iter()
+            .map(|(version, files)| (version.clone(), release_hashes(files)))
+            .collect();
+        let mut releases: Vec<String> = response.releases.into_keys().collect();
+        releases.sort_by(|a, b| crate::models::compare_release(a, b));
+        Self {
+            latest: Some(response.info.version),
+            releases,
+            latest_upload,
+            summary: response.info.summary,
+            files,
+            requires_dist: response.info.requires_dist.unwrap_or_default(),
+            requires_python: response.info.requires_python.filter(|spec| !spec.trim().is_empty()),
+            uploads,
+            hashes,
+        }
+    }
+}
+
+fn release_filenames(files: &[serde_json::Value]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|file| !file.get("yanked").and_then(|y| y.as_bool()).unwrap_or(false))
+        .filter_map(|file| file.get("filename")?.as_str())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+fn release_hashes(files: &[serde_json::Value]) -> Vec<String> {
+    files
+        .iter()
+        .filter(|file| !file.get("yanked").and_then(|y| y.as_bool()).unwrap_or(false))
+        .filter_map(|file| file.get("digests")?.get("sha256")?.as_str())
+        .map(|digest| format!("sha256:{}", digest))
+        .collect()
+}
+
+fn upload_times(files: &[serde_json::Value]) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+    files
+        .iter()
+        .filter_map(|file| file.get("upload_time_iso_8601")?.as_str())
+        .filter_map(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn latest_upload_time(files: &[serde_json::Value]) -> Option<DateTime<Utc>> {
+    upload_times(files).max()
+}
+
+fn first_upload_time(files: &[serde_json::Value]) -> Option<DateTime<Utc>> {
+    upload_times(files).min()
 }
 
 #[derive(Clone, Debug, serde::Deserialize)]
 pub struct PyPIMetadata {
     pub name: String,
+    /// Empty when the index leaves it out or sends `null`; see
+    /// `PyPIResponse::fill_missing_version`.
+    #[serde(default, deserialize_with = "null_as_empty")]
     pub version: String,
     pub summary: String,
     pub home_page: Option<String>,
     pub author: Option<String>,
     pub license: Option<String>,
     pub project_urls: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub requires_dist: Option<Vec<String>>,
+    #[serde(default)]
+    pub requires_python: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct PyPIResponse {
     pub info: PyPIMetadata,
+    #[serde(default)]
     pub releases: HashMap<String, Vec<serde_json::Value>>,
 }
 
+impl PyPIResponse {
+    /// Falls back to the newest release listed under `releases` when
+    /// `info.version` is missing, preferring final releases that still have
+    /// files that aren't yanked.
+    fn fill_missing_version(&mut self) {
+        if !self.info.version.trim().is_empty() {
+            return;
+        }
+        let newest = |versions: Vec<&String>| {
+            versions
+                .into_iter()
+                .max_by(|a, b| crate::models::compare_release(a, b))
+                .cloned()
+        };
+        let available: Vec<&String> = self
+            .releases
+            .iter()
+            .filter(|(_, files)| !release_filenames(files).is_empty())
+            .map(|(version, _)| version)
+            .collect();
+        let finals: Vec<&String> = available
+            .iter()
+            .copied()
+            .filter(|v| crate::version::Pep440Version::parse(v).is_some_and(|v| !v.is_prerelease()))
+            .collect();
+        if let Some(version) = newest(finals)
+            .or_else(|| newest(available))
+            .or_else(|| newest(self.releases.keys().collect()))
+        {
+            self.info.version = version;
+        }
+    }
+}
+
+fn null_as_empty<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    Ok(<Option<String> as serde::Deserialize>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 impl PyPIClient {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
             cache: Arc::new(RwLock::new(HashMap::new())),
+            lookups: cache::bounded(cache::DEFAULT_CAPACITY),
+            indexes: vec![PYPI_API.to_string()],
+            simple_index: PYPI_SIMPLE.to_string(),
+            project_names: Arc::new(RwLock::new(Vec::new())),
+            release_requires: Arc::new(RwLock::new(HashMap::new())),
+            max_body_bytes: crate::http::DEFAULT_MAX_BODY_BYTES,
+            binary_options: Vec::new(),
+        }
+    }
+
+    /// Caps every response body read from the index.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    /// Keeps at most `capacity` one-off lookups, evicting the least recently
+    /// used. Starts that cache afresh.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.lookups = cache::bounded(capacity);
+        self
+    }
+
+    pub fn with_simple_index(mut self, url: &str) -> Self {
+        self.simple_index = url.to_string();
+        self
+    }
+
+    pub fn indexes(&self) -> &[String] {
+        &self.indexes
+    }
+
+    pub fn binary_options(&self) -> &[GlobalOption] {
+        &self.binary_options
+    }
+
+    pub fn uses_custom_index(&self) -> bool {
+        self.indexes.iter().any(|index| index != PYPI_API)
+    }
+
+    /// Looks `packages` up again on `canonical` and reports every one whose
+    /// latest version disagrees with what this client already resolved.
+    /// Packages missing from either side are left to the usual error path.
+    pub async fn cross_check(&self, canonical: &PyPIClient, packages: &[Package]) -> Vec<IndexMismatch> {
+        let mut reference = packages.to_vec();
+        canonical.update_packages(&mut reference).await;
+
+        packages
+            .iter()
+            .zip(&reference)
+            .filter_map(|(pkg, canonical_pkg)| {
+                let mirror = pkg.latest_version.as_ref()?;
+                let canonical = canonical_pkg.latest_version.as_ref()?;
+                compare_release(mirror, canonical).is_ne().then(|| IndexMismatch {
+                    package: pkg.name.clone(),
+                    mirror: mirror.clone(),
+                    canonical: canonical.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn apply_options(&mut self, options: &[GlobalOption]) {
+        for option in options {
+            match option {
+                GlobalOption::IndexUrl(url) => self.indexes[0] = json_api_url(url),
+                GlobalOption::ExtraIndexUrl(url) => {
+                    let url = json_api_url(url);
+                    if !self.indexes.contains(&url) {
+                        self.indexes.push(url);
+                    }
+                }
+                GlobalOption::FindLinks(_) => {}
+                GlobalOption::OnlyBinary(_) | GlobalOption::NoBinary(_) | GlobalOption::PreferBinary => {
+                    self.binary_options.push(option.clone());
+                }
+            }
         }
     }
 
     pub async fn update_packages(&self, packages: &mut [Package]) {
         let mut handles = vec![];
+        let failures = Arc::new(RwLock::new(HashMap::new()));
 
-        for pkg in packages.iter_mut() {
-            if !matches!(pkg.source, crate::models::DependencySource::PyPI) {
-                continue;
+        // The same project can be listed more than once (several files, or
+        // with different extras); fetch it once and fan the result out below.
+        let mut unique = HashMap::new();
+        for pkg in packages.iter() {
+            if matches!(pkg.source, crate::models::DependencySource::PyPI) {
+                unique
+                    .entry(normalize_package_name(&pkg.name))
+                    .or_insert_with(|| pkg.name.clone());
             }
+        }
 
+        for (key, name) in unique {
             let client = self.client.clone();
-            let name = pkg.name.clone();
             let cache = self.cache.clone();
+            let indexes = self.indexes.clone();
+            let failures = failures.clone();
+            let max_body = self.max_body_bytes;
 
             let handle = tokio::spawn(async move {
-                if let Ok(cached) = Self::fetch_package_info(&client, &name).await {
-                    let mut c = cache.write();
-                    c.insert(
-                        name.clone(),
-                        CachedPackage {
-                            name: name.clone(),
-                            latest: Some(cached.version.clone()),
-                            metadata: None,
-                        },
-                    );
+                match Self::fetch_package_info(&client, &indexes, &name, max_body).await {
+                    Ok(response) => {
+                        cache.write().insert(key, CachedPackage::from_response(response));
+                    }
+                    Err(e) => {
+                        failures.write().insert(key, e);
+                    }
                 }
             });
 
@@ -78,98 +351,1215 @@ impl PyPIClient {
             let _ = handle.await;
         }
 
+        let failures: Vec<_> = failures.write().drain().collect();
+        let mut errors = HashMap::new();
+        for (key, error) in failures {
+            let message = match error {
+                PyElevateError::NotFound(name) => self.not_found_message(&name).await,
+                other => other.to_string(),
+            };
+            errors.insert(key, message);
+        }
+
         let cache = self.cache.read();
-        for pkg in packages.iter_mut() {
-            if let Some(cached) = cache.get(&pkg.name) {
-                if let Some(latest) = &cached.latest {
-                    pkg.latest_version = Some(latest.clone());
-                    pkg.status = crate::models::compare_versions(&pkg.current_version, latest);
-                }
+        for pkg in packages
+            .iter_mut()
+            .filter(|p| matches!(p.source, crate::models::DependencySource::PyPI))
+        {
+            let key = normalize_package_name(&pkg.name);
+            if let Some(cached) = cache.get(&key) {
+                apply_cached(pkg, cached);
+            } else if let Some(error) = errors.get(&key) {
+                pkg.status = crate::models::VersionStatus::Error;
+                pkg.error = Some(error.clone());
             }
         }
     }
 
-    pub async fn fetch_latest_version(&self, package: &str) -> Result<Option<String>> {
-        let cache = self.cache.read();
-        if let Some(cached) = cache.get(package) {
-            if let Some(latest) = &cached.latest {
-                return Ok(Some(latest.clone()));
-            }
-        }
-        drop(cache);
-
-        if let Ok(response) = Self::fetch_package_info(&self.client, package).await {
-            let version = response.version;
-            let mut cache = self.cache.write();
-            cache.insert(
-                package.to_string(),
-                CachedPackage {
-                    name: package.to_string(),
-                    latest: Some(version.clone()),
-                    metadata: None,
-                },
-            );
-            Ok(Some(version))
+    pub async fn update_package(&self, pkg: &mut Package) {
+        match Self::fetch_package_info(&self.client, &self.indexes, &pkg.name, self.max_body_bytes).await {
+            Ok(response) => {
+                let cached = CachedPackage::from_response(response);
+                apply_cached(pkg, &cached);
+                self.cache.write().insert(normalize_package_name(&pkg.name), cached);
+            }
+            Err(PyElevateError::NotFound(name)) => {
+                pkg.status = crate::models::VersionStatus::Error;
+                pkg.error = Some(self.not_found_message(&name).await);
+            }
+            Err(e) => {
+                pkg.status = crate::models::VersionStatus::Error;
+                pkg.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// The error shown for a 404, naming close matches from the simple index
+    /// since a missing package is usually a typo or a typosquat lookalike.
+    async fn not_found_message(&self, name: &str) -> String {
+        let error = PyElevateError::NotFound(name.to_string()).to_string();
+        if self.load_project_names().await.is_err() {
+            return error;
+        }
+        let suggestions = near_matches(&self.project_names.read(), name);
+        if suggestions.is_empty() {
+            error
         } else {
-            Ok(None)
+            format!("{}; did you mean {}?", error, suggestions.join(", "))
+        }
+    }
+
+    /// Re-fetches only the packages whose last lookup failed. Returns how many
+    /// of them still failed.
+    pub async fn retry_errors(&self, packages: &mut [Package]) -> usize {
+        let mut remaining = 0;
+        for pkg in packages
+            .iter_mut()
+            .filter(|p| p.status == crate::models::VersionStatus::Error)
+        {
+            self.update_package(pkg).await;
+            if pkg.status == crate::models::VersionStatus::Error {
+                remaining += 1;
+            }
+        }
+        remaining
+    }
+
+    pub async fn fetch_latest_version(&self, package: &str) -> Result<String> {
+        if let Some(latest) = self.cached(package, |cached| cached.latest.clone()).flatten() {
+            return Ok(latest);
+        }
+
+        let response = Self::fetch_package_info(&self.client, &self.indexes, package, self.max_body_bytes).await?;
+        let version = response.info.version.clone();
+        self.lookups
+            .lock()
+            .put(normalize_package_name(package), CachedPackage::from_response(response));
+        Ok(version)
+    }
+
+    async fn fetch_package_info(
+        client: &Client,
+        indexes: &[String],
+        package: &str,
+        max_body: usize,
+    ) -> Result<PyPIResponse> {
+        let mut best: Option<PyPIResponse> = None;
+        let mut last_error = None;
+
+        for index in indexes {
+            match Self::fetch_from_index(client, index, package, max_body).await {
+                Ok(response) => {
+                    let newer = best.as_ref().is_none_or(|b| {
+                        crate::models::compare_release(&response.info.version, &b.info.version).is_gt()
+                    });
+                    if newer {
+                        best = Some(response);
+                    }
+                }
+                Err(e) => last_error = Some(e),
+            }
         }
+
+        best.ok_or_else(|| last_error.unwrap_or_else(|| PyElevateError::NotFound(package.to_string())))
     }
 
-    async fn fetch_package_info(client: &Client, package: &str) -> Result<PyPIMetadata> {
-        let url = format!("{}/{}/json", PYPI_API, package);
+    async fn fetch_from_index(
+        client: &Client,
+        index: &str,
+        package: &str,
+        max_body: usize,
+    ) -> Result<PyPIResponse> {
+        let url = format!("{}/{}/json", index, package);
         let response = client
             .get(&url)
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await?;
+        let response = match response.status() {
+            StatusCode::NOT_FOUND => return Err(PyElevateError::NotFound(package.to_string())),
+            StatusCode::TOO_MANY_REQUESTS => return Err(PyElevateError::RateLimited(index.to_string())),
+            _ => response.error_for_status()?,
+        };
+
+        let mut response: PyPIResponse = crate::http::read_json(response, max_body).await?;
+        response.fill_missing_version();
+        if response.info.version.is_empty() {
+            return Err(PyElevateError::Parse(format!("{} lists no version or releases", package)));
+        }
+        Ok(response)
+    }
+
+    /// Ranks against the project list if a previous search already loaded it.
+    pub fn search_cached(&self, query: &str) -> Option<Vec<SearchResult>> {
+        let names = self.project_names.read();
+        if names.is_empty() {
+            None
+        } else {
+            Some(rank_projects(&names, query))
+        }
+    }
 
-        let data: PyPIResponse = response.json().await?;
-        Ok(data.info)
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        self.load_project_names().await?;
+        Ok(rank_projects(&self.project_names.read(), query))
+    }
+
+    async fn load_project_names(&self) -> Result<()> {
+        if self.project_names.read().is_empty() {
+            let response = self
+                .client
+                .get(&self.simple_index)
+                .header("Accept", "application/vnd.pypi.simple.v1+json")
+                .timeout(std::time::Duration::from_secs(30))
+                .send()
+                .await?;
+            let body = crate::http::read_body(response, self.max_body_bytes).await?;
+            *self.project_names.write() = parse_project_list(&body)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the cached lookup for `package`, so the next one refetches it.
+    pub fn forget(&self, package: &str) {
+        let key = normalize_package_name(package);
+        self.cache.write().remove(&key);
+        self.lookups.lock().pop(&key);
+    }
+
+    /// Reads `package`'s cached lookup, from the listed packages or else the
+    /// one-off lookups.
+    fn cached<T>(&self, package: &str, read: impl FnOnce(&CachedPackage) -> T) -> Option<T> {
+        let key = normalize_package_name(package);
+        if let Some(cached) = self.cache.read().get(&key) {
+            return Some(read(cached));
+        }
+        self.lookups.lock().get(&key).map(read)
+    }
+
+    /// The project summary from an earlier lookup, if `version` is the release
+    /// that lookup described.
+    pub fn cached_summary(&self, package: &str, version: &str) -> Option<String> {
+        self.cached(package, |cached| {
+            (cached.latest.as_deref() == Some(version)).then(|| cached.summary.clone())
+        })
+        .flatten()
+    }
+
+    /// Distribution filenames of every non-yanked release, oldest first.
+    pub fn release_files(&self, package: &str) -> Vec<(String, Vec<String>)> {
+        self.cached(package, |cached| {
+                cached
+                    .releases
+                    .iter()
+                    .map(|version| {
+                        let files = cached.files.get(version).cloned().unwrap_or_default();
+                        (version.clone(), files)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sets `target_hashes` on every package with an upgrade target to the sha256
+    /// digests of that release's files, from earlier lookups.
+    pub fn attach_hashes(&self, packages: &mut [Package]) {
+        for pkg in packages.iter_mut() {
+            pkg.target_hashes = pkg
+                .upgrade_target()
+                .and_then(|target| self.cached(&pkg.name, |cached| cached.hashes.get(target).cloned()).flatten())
+                .unwrap_or_default();
+        }
+    }
+
+    /// Marks each package that another package from the same file requires
+    /// unconditionally as transitive. Packages from `compiled_files` keep what
+    /// their `# via` annotations said.
+    pub fn mark_transitive(&self, packages: &mut [Package], compiled_files: &[String]) {
+        let mut required: HashMap<Option<String>, HashSet<String>> = HashMap::new();
+        for pkg in packages.iter() {
+            required.entry(pkg.origin_file.clone()).or_default().extend(
+                self.requires_dist(&pkg.name)
+                    .iter()
+                    .filter(|entry| !entry.contains(';'))
+                    .filter_map(|entry| parse_requires_dist(entry).map(|(name, _)| normalize_package_name(&name))),
+            );
+        }
+        for pkg in packages.iter_mut() {
+            if pkg.origin_file.as_ref().is_some_and(|file| compiled_files.contains(file)) {
+                continue;
+            }
+            pkg.is_direct = !required[&pkg.origin_file].contains(&normalize_package_name(&pkg.name));
+        }
+    }
+
+    /// Packages that installing the requested extras brings in, followed
+    /// through their own requirements and the extras those request in turn.
+    /// A pinned requirement's dependencies are read from the pinned release;
+    /// packages brought in are resolved to their latest release, which is
+    /// what a fresh install would get. Requirements whose marker rules them
+    /// out for `env` are skipped, and anything already in `packages` is left
+    /// out.
+    pub async fn expand_extras(&self, packages: &[Package], env: &Environment) -> Vec<Package> {
+        // The release each known package's requirements are read from ("" for
+        // the latest), and the extras already followed per package.
+        let mut versions: HashMap<String, String> = packages
+            .iter()
+            .map(|p| {
+                let version = match &p.constraint {
+                    crate::models::VersionConstraint::Pinned(_) => p.current_version.clone(),
+                    _ => String::new(),
+                };
+                (normalize_package_name(&p.name), version)
+            })
+            .collect();
+        let mut followed: HashSet<(String, String)> = HashSet::new();
+        let mut queue: Vec<Expansion> = packages
+            .iter()
+            .filter(|p| !p.extras.is_empty())
+            .map(|p| Expansion {
+                name: p.name.clone(),
+                extras: p.extras.clone(),
+                unconditional: false,
+                via: format!("via {}[{}]", p.name, p.extras.join(",")),
+            })
+            .collect();
+
+        let mut expanded = Vec::new();
+        while !queue.is_empty() {
+            let mut added: Vec<(Package, Vec<String>)> = Vec::new();
+            let mut next = Vec::new();
+            for node in std::mem::take(&mut queue) {
+                let key = normalize_package_name(&node.name);
+                let extras: Vec<String> = node
+                    .extras
+                    .iter()
+                    .map(|e| normalize_package_name(e))
+                    .filter(|e| followed.insert((key.clone(), e.clone())))
+                    .collect();
+                if extras.is_empty() && !node.unconditional {
+                    continue;
+                }
+
+                let version = versions.get(&key).cloned().unwrap_or_default();
+                for entry in self.release_requires_dist(&node.name, &version).await {
+                    let Some((name, extra)) = parse_requires_dist(&entry) else {
+                        continue;
+                    };
+                    let wanted = match extra {
+                        Some(extra) => extras.contains(&normalize_package_name(&extra)),
+                        None => node.unconditional,
+                    };
+                    let applies = entry
+                        .split_once(';')
+                        .is_none_or(|(_, marker)| markers::evaluate(marker, env));
+                    if !wanted || !applies {
+                        continue;
+                    }
+
+                    let requested = requires_dist_extras(&entry);
+                    let dep_key = normalize_package_name(&name);
+                    if versions.contains_key(&dep_key) {
+                        if !requested.is_empty() {
+                            next.push(Expansion {
+                                name,
+                                extras: requested,
+                                unconditional: false,
+                                via: node.via.clone(),
+                            });
+                        }
+                    } else if let Some((_, pending)) =
+                        added.iter_mut().find(|(p, _)| normalize_package_name(&p.name) == dep_key)
+                    {
+                        pending.extend(requested);
+                    } else {
+                        added.push((required_package(&name, &node.via), requested));
+                    }
+                }
+            }
+
+            let (mut resolved, requested): (Vec<Package>, Vec<Vec<String>>) = added.into_iter().unzip();
+            self.update_packages(&mut resolved).await;
+            for (mut pkg, extras) in resolved.into_iter().zip(requested) {
+                if let Some(latest) = pkg.latest_version.clone() {
+                    pkg.current_version = latest;
+                    pkg.status = crate::models::VersionStatus::UpToDate;
+                }
+                versions.insert(normalize_package_name(&pkg.name), String::new());
+                next.push(Expansion {
+                    name: pkg.name.clone(),
+                    extras,
+                    unconditional: true,
+                    via: pkg.notes.join("; "),
+                });
+                expanded.push(pkg);
+            }
+            queue = next;
+        }
+        expanded
+    }
+
+    /// The `requires_dist` of one release, fetched once per version. An
+    /// empty `version` reads the latest release.
+    async fn release_requires_dist(&self, package: &str, version: &str) -> Vec<String> {
+        if version.is_empty() && self.cached(package, |_| ()).is_none() {
+            let _ = self.fetch_latest_version(package).await;
+        }
+        let latest = self.cached(package, |cached| cached.latest.clone()).flatten();
+        if version.is_empty() || latest.as_deref() == Some(version) {
+            return self.requires_dist(package);
+        }
+
+        let release = format!("{}/{}", package, version);
+        if let Some(requires) = self.release_requires.read().get(&release) {
+            return requires.clone();
+        }
+        let mut requires = Vec::new();
+        for index in &self.indexes {
+            if let Ok(response) = Self::fetch_from_index(&self.client, index, &release, self.max_body_bytes).await {
+                requires = response.info.requires_dist.unwrap_or_default();
+                break;
+            }
+        }
+        self.release_requires.write().insert(release, requires.clone());
+        requires
+    }
+
+    /// Fetches the `requires_dist` of each listed PyPI package's pinned
+    /// release, for [`PyPIClient::breaking_dependents`] to read.
+    pub async fn fetch_pinned_requires(&self, packages: &[Package]) {
+        let fetches = packages
+            .iter()
+            .filter(|p| matches!(p.source, crate::models::DependencySource::PyPI))
+            .map(|p| self.release_requires_dist(&p.name, &p.current_version));
+        futures::future::join_all(fetches).await;
+    }
+
+    /// The cached `requires_dist` of one release; `None` until
+    /// [`PyPIClient::fetch_pinned_requires`] or a lookup of the latest
+    /// release has fetched it.
+    fn cached_release_requires(&self, package: &str, version: &str) -> Option<Vec<String>> {
+        let latest = self.cached(package, |cached| cached.latest.clone()).flatten();
+        if latest.as_deref() == Some(version) {
+            return Some(self.requires_dist(package));
+        }
+        self.release_requires.read().get(&format!("{}/{}", package, version)).cloned()
+    }
+
+    /// Listed packages that an upgrade of `dependency` to `target` would
+    /// break, as `(package, why)`, read from the metadata of their pinned
+    /// releases. A package breaks when its requirement excludes `target`
+    /// (`needs <2.0,>=1.5`) or when it requires one that breaks
+    /// (`via celery`), followed until nothing more breaks. Requirements
+    /// gated on an extra, or on a marker `env` rules out, are left out.
+    pub fn breaking_dependents(
+        &self,
+        packages: &[Package],
+        dependency: &str,
+        target: &str,
+        env: &Environment,
+    ) -> Vec<(String, String)> {
+        let dependency = normalize_package_name(dependency);
+        let requirements: Vec<(&Package, Vec<(String, String)>)> = packages
+            .iter()
+            .filter(|p| normalize_package_name(&p.name) != dependency)
+            .map(|pkg| {
+                let entries = self.cached_release_requires(&pkg.name, &pkg.current_version).unwrap_or_default();
+                let requires = entries
+                    .iter()
+                    .filter(|entry| {
+                        entry
+                            .split_once(';')
+                            .is_none_or(|(_, marker)| markers::evaluate(marker, env))
+                    })
+                    .filter_map(|entry| match parse_requires_dist(entry)? {
+                        (name, None) => Some((normalize_package_name(&name), requires_dist_specifier(entry))),
+                        _ => None,
+                    })
+                    .collect();
+                (pkg, requires)
+            })
+            .collect();
+
+        let mut breaking: Vec<(String, String)> = Vec::new();
+        let mut broken: Vec<String> = Vec::new();
+        for (pkg, requires) in &requirements {
+            let excluded = requires.iter().find(|(name, specifier)| {
+                *name == dependency && !specifier.is_empty() && !crate::version::specifier_allows(specifier, target)
+            });
+            if let Some((_, specifier)) = excluded {
+                breaking.push((pkg.name.clone(), format!("needs {}", specifier)));
+                broken.push(normalize_package_name(&pkg.name));
+            }
+        }
+        // Whatever requires a broken package breaks with it.
+        loop {
+            let mut changed = false;
+            for (pkg, requires) in &requirements {
+                let key = normalize_package_name(&pkg.name);
+                if broken.contains(&key) {
+                    continue;
+                }
+                let Some(index) = requires.iter().find_map(|(name, _)| broken.iter().position(|b| b == name)) else {
+                    continue;
+                };
+                breaking.push((pkg.name.clone(), format!("via {}", breaking[index].0)));
+                broken.push(key);
+                changed = true;
+            }
+            if !changed {
+                return breaking;
+            }
+        }
+    }
+
+    fn requires_dist(&self, package: &str) -> Vec<String> {
+        self.cached(package, |cached| cached.requires_dist.clone()).unwrap_or_default()
+    }
+
+    /// `(version, first upload)` for each cached release with a known date.
+    pub fn release_dates(&self, package: &str) -> Vec<(String, DateTime<Utc>)> {
+        self.cached(package, |cached| cached.uploads.iter().map(|(v, t)| (v.clone(), *t)).collect()).unwrap_or_default()
+    }
+
+    pub fn releases(&self, package: &str) -> Vec<String> {
+        self.cached(package, |cached| cached.releases.clone()).unwrap_or_default()
     }
 
     pub async fn fetch_popularity(&self, package: &str) -> Result<Option<crate::models::PopularityData>> {
         let url = format!("{}/{}/recent", PYPI_STATS, package);
         
-        match self
+        if let Ok(response) = self
             .client
             .get(&url)
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await
         {
-            Ok(response) => {
-                if let Ok(data) = response.json::<serde_json::Value>().await {
-                    let mut trend = Vec::new();
-                    if let Some(rows) = data.get("data").and_then(|d| d.as_array()) {
-                        for row in rows.iter().take(7) {
-                            if let (Some(date), Some(count)) = (
-                                row.get("date").and_then(|d| d.as_str()),
-                                row.get("downloads").and_then(|c| c.as_u64()),
-                            ) {
-                                trend.push((date.to_string(), count));
-                            }
+            if let Ok(data) = crate::http::read_json::<serde_json::Value>(response, self.max_body_bytes).await {
+                let mut trend = Vec::new();
+                if let Some(rows) = data.get("data").and_then(|d| d.as_array()) {
+                    for row in rows.iter().take(7) {
+                        if let (Some(date), Some(count)) = (
+                            row.get("date").and_then(|d| d.as_str()),
+                            row.get("downloads").and_then(|c| c.as_u64()),
+                        ) {
+                            trend.push((date.to_string(), count));
                         }
                     }
+                }
 
-                    let weekly = trend.iter().map(|(_, c)| c).sum::<u64>();
+                let weekly = trend.iter().map(|(_, c)| c).sum::<u64>();
 
-                    return Ok(Some(crate::models::PopularityData {
-                        downloads_last_month: weekly.saturating_mul(4),
-                        downloads_trend: trend,
-                        weekly_downloads: weekly,
-                        package_rank: None,
-                    }));
-                }
+                return Ok(Some(crate::models::PopularityData {
+                    downloads_last_month: weekly.saturating_mul(4),
+                    downloads_trend: trend,
+                    weekly_downloads: weekly,
+                    package_rank: None,
+                }));
             }
-            Err(_) => {}
         }
 
         Ok(None)
     }
 }
 
+/// Splits a `requires_dist` entry such as `PySocks!=1.5.7,>=1.5.6; extra == "socks"`
+/// into the project name and the extra that gates it, if any.
+fn parse_requires_dist(entry: &str) -> Option<(String, Option<String>)> {
+    let (requirement, marker) = entry.split_once(';').unwrap_or((entry, ""));
+    let name: String = requirement
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    if name.is_empty() {
+        return None;
+    }
+
+    let extra = marker.find("extra").and_then(|start| {
+        let rest = marker[start + "extra".len()..].trim_start().strip_prefix("==")?;
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        rest[1..].split(quote).next().map(|extra| extra.to_string())
+    });
+    Some((name, extra))
+}
+
+/// The extras a `requires_dist` entry asks for, e.g. `redis` in
+/// `kombu[redis]>=5.3; extra == "redis"`.
+fn requires_dist_extras(entry: &str) -> Vec<String> {
+    let requirement = entry.split(';').next().unwrap_or(entry);
+    requirement
+        .split_once('[')
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(extras, _)| {
+            extras
+                .split(',')
+                .map(|e| e.trim().to_string())
+                .filter(|e| !e.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The version specifier of a `requires_dist` entry: `<2.0,>=1.0` for both
+/// `kombu<2.0,>=1.0` and the older `kombu[redis] (<2.0,>=1.0)`.
+fn requires_dist_specifier(entry: &str) -> String {
+    let requirement = entry.split(';').next().unwrap_or(entry).trim();
+    let rest = requirement
+        .trim_start_matches(|c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .trim_start();
+    let rest = match rest.strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map_or("", |(_, rest)| rest),
+        None => rest,
+    };
+    rest.trim().trim_start_matches('(').trim_end_matches(')').replace(' ', "")
+}
+
+fn required_package(name: &str, via: &str) -> Package {
+    Package {
+        name: name.to_lowercase(),
+        notes: vec![via.to_string()],
+        ..Package::default()
+    }
+}
+
+fn apply_cached(pkg: &mut Package, cached: &CachedPackage) {
+    if let Some(latest) = &cached.latest {
+        pkg.latest_version = Some(latest.clone());
+        pkg.status = crate::models::compare_versions(&pkg.current_version, latest);
+        pkg.notes.retain(|note| !note.starts_with(AHEAD_OF_INDEX_NOTE));
+        pkg.ahead_of_index = crate::models::compare_release(latest, &pkg.current_version).is_lt();
+        if pkg.ahead_of_index {
+            pkg.notes.push(format!("{} ({} > {})", AHEAD_OF_INDEX_NOTE, pkg.current_version, latest));
+        }
+    }
+    pkg.last_release = cached.latest_upload;
+    pkg.requires_python = cached.requires_python.clone();
+    pkg.error = None;
+}
+
+fn parse_project_list(body: &str) -> Result<Vec<String>> {
+    let data: serde_json::Value = serde_json::from_str(body)?;
+    let projects = data
+        .get("projects")
+        .and_then(|p| p.as_array())
+        .ok_or_else(|| PyElevateError::Parse("simple index response has no project list".to_string()))?;
+
+    Ok(projects
+        .iter()
+        .filter_map(|p| p.get("name").and_then(|n| n.as_str()))
+        .map(|n| n.to_string())
+        .collect())
+}
+
+/// Project names within [`SUGGESTION_DISTANCE`] edits of `name`, closest
+/// first, compared in normalized form.
+fn near_matches(names: &[String], name: &str) -> Vec<String> {
+    let wanted = normalize_package_name(name);
+    let mut matches: Vec<(usize, &String)> = names
+        .iter()
+        .filter_map(|candidate| {
+            let normalized = normalize_package_name(candidate);
+            if normalized.len().abs_diff(wanted.len()) > SUGGESTION_DISTANCE {
+                return None;
+            }
+            let distance = levenshtein(&normalized, &wanted);
+            (distance > 0 && distance <= SUGGESTION_DISTANCE).then_some((distance, candidate))
+        })
+        .collect();
+    matches.sort();
+    matches
+        .into_iter()
+        .take(SUGGESTION_LIMIT)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn rank_projects(names: &[String], query: &str) -> Vec<SearchResult> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let matcher = SkimMatcherV2::default();
+    let mut results: Vec<SearchResult> = names
+        .iter()
+        .filter_map(|name| {
+            let lower = name.to_lowercase();
+            let bonus = if lower == query {
+                10_000
+            } else if lower.starts_with(&query) {
+                1_000
+            } else {
+                0
+            };
+            matcher.fuzzy_match(&lower, &query).map(|score| SearchResult {
+                name: name.clone(),
+                score: score + bonus - lower.len() as i64,
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.name.cmp(&b.name)));
+    results.truncate(SEARCH_LIMIT);
+    results
+}
+
+fn json_api_url(index_url: &str) -> String {
+    let url = index_url.trim_end_matches('/');
+    match url.strip_suffix("/simple") {
+        Some(base) => format!("{}/pypi", base),
+        None => url.to_string(),
+    }
+}
+
 impl Default for PyPIClient {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DependencySource, VersionConstraint, VersionStatus};
+    use crate::test_support::{MockResponse, MockServer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn package(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: version.to_string(),
+            constraint: VersionConstraint::Pinned(version.to_string()),
+            ..Package::default()
+        }
+    }
+
+    /// Serves `/pypi/<name>/json`, reporting `version` as the latest
+    /// release. `flaky` fails its first request and `missing` is a 404.
+    fn spawn_index(version: &'static str) -> MockServer {
+        let flaky_failed = AtomicBool::new(false);
+        MockServer::start(move |request| {
+            let name = request.path.split('/').nth(2).unwrap_or_default();
+            match name {
+                "flaky" if !flaky_failed.swap(true, Ordering::SeqCst) => MockResponse::status(503),
+                "missing" => MockResponse::status(404),
+                _ => MockResponse::json(format!(
+                    r#"{{"info":{{"name":"{}","version":"{}","summary":""}}}}"#,
+                    name, version
+                )),
+            }
+        })
+    }
+
+    fn index_option(server: &MockServer) -> GlobalOption {
+        GlobalOption::IndexUrl(server.url("/pypi"))
+    }
+
+    #[tokio::test]
+    async fn test_retry_errors_refetches_only_failed_packages() {
+        let server = spawn_index("2.0.0");
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut packages = vec![package("stable", "1.0.0"), package("flaky", "1.0.0")];
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].status, VersionStatus::Major);
+        assert_eq!(packages[1].status, VersionStatus::Error);
+        assert!(packages[1].error.is_some());
+
+        assert_eq!(client.retry_errors(&mut packages).await, 0);
+        assert_eq!(packages[1].status, VersionStatus::Major);
+        assert_eq!(packages[1].latest_version.as_deref(), Some("2.0.0"));
+        assert!(packages[1].error.is_none());
+        assert_eq!(server.hits("/pypi/stable/json"), 1);
+        assert_eq!(server.hits("/pypi/flaky/json"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cross_check_warns_on_divergent_index() {
+        let (mirror_index, canonical_index) = (spawn_index("2.0.0"), spawn_index("2.1.0"));
+        let mut mirror = PyPIClient::new();
+        mirror.apply_options(&[index_option(&mirror_index)]);
+        let mut canonical = PyPIClient::new();
+        canonical.apply_options(&[index_option(&canonical_index)]);
+        assert!(mirror.uses_custom_index());
+
+        let mut packages = vec![package("requests", "1.0.0")];
+        mirror.update_packages(&mut packages).await;
+        let mismatches = mirror.cross_check(&canonical, &packages).await;
+        assert_eq!(
+            mismatches,
+            vec![IndexMismatch {
+                package: "requests".to_string(),
+                mirror: "2.0.0".to_string(),
+                canonical: "2.1.0".to_string(),
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "requests: index reports 2.0.0, pypi.org reports 2.1.0"
+        );
+
+        assert!(mirror.cross_check(&mirror, &packages).await.is_empty());
+        let padded_index = spawn_index("2.0");
+        let mut padded = PyPIClient::new();
+        padded.apply_options(&[index_option(&padded_index)]);
+        assert!(mirror.cross_check(&padded, &packages).await.is_empty());
+
+        assert!(!PyPIClient::new().uses_custom_index());
+        let mut extra = PyPIClient::new();
+        extra.apply_options(&[GlobalOption::ExtraIndexUrl("https://mirror.example/simple".to_string())]);
+        assert!(extra.uses_custom_index());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_packages_fetched_once() {
+        let server = spawn_index("2.0.0");
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut with_extras = package("stable", "1.5.0");
+        with_extras.extras = vec!["socks".to_string()];
+        let mut packages = vec![package("stable", "1.0.0"), with_extras];
+        client.update_packages(&mut packages).await;
+
+        assert_eq!(server.hits("/pypi/stable/json"), 1);
+        assert!(packages
+            .iter()
+            .all(|p| p.latest_version.as_deref() == Some("2.0.0") && p.status == VersionStatus::Major));
+    }
+
+    #[tokio::test]
+    async fn test_same_named_packages_all_receive_version() {
+        let server = spawn_index("2.0.0");
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut git = package("stable", "git-source");
+        git.source = DependencySource::Git {
+            url: "https://github.com/example/stable".to_string(),
+            ref_spec: None,
+            editable: false,
+        };
+        let mut packages = vec![package("stable", "1.0.0"), git, package("stable", "1.1.0")];
+        client.update_packages(&mut packages).await;
+
+        assert_eq!(packages[0].latest_version.as_deref(), Some("2.0.0"));
+        assert_eq!(packages[2].latest_version.as_deref(), Some("2.0.0"));
+        assert_eq!(packages[1].latest_version, None);
+        assert_eq!(packages[1].status, VersionStatus::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_dependent_capping_below_new_major_is_breaking() {
+        let server = MockServer::routes(&[
+            (
+                "/pypi/kombu/json",
+                MockResponse::json(r#"{"info": {"name": "kombu", "version": "2.0.0", "summary": ""}}"#),
+            ),
+            (
+                "/pypi/celery/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "celery", "version": "5.4.0", "summary": "", "requires_dist": ["kombu>=1.5"]}}"#,
+                ),
+            ),
+            (
+                "/pypi/celery/5.3.0/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "celery", "version": "5.3.0", "summary": "", "requires_dist": [
+                        "kombu (<2.0,>=1.5)",
+                        "kombu[sqs]<1.0; extra == \"sqs\""
+                    ]}}"#,
+                ),
+            ),
+            (
+                "/pypi/flower/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "flower", "version": "2.0.1", "summary": "", "requires_dist": [
+                        "celery>=5.0",
+                        "kombu<1.0; sys_platform == \"win32\""
+                    ]}}"#,
+                ),
+            ),
+            (
+                "/pypi/redis/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "redis", "version": "5.0.0", "summary": "", "requires_dist": ["kombu>=1.0"]}}"#,
+                ),
+            ),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+        let mut packages = vec![
+            package("kombu", "1.5.0"),
+            package("celery", "5.3.0"),
+            package("flower", "2.0.1"),
+            package("redis", "5.0.0"),
+        ];
+        client.update_packages(&mut packages).await;
+        client.fetch_pinned_requires(&packages).await;
+
+        let linux = Environment::new("linux", "x86_64", Some((3, 11)));
+        assert_eq!(
+            client.breaking_dependents(&packages, "kombu", "2.0.0", &linux),
+            vec![
+                ("celery".to_string(), "needs <2.0,>=1.5".to_string()),
+                ("flower".to_string(), "via celery".to_string()),
+            ]
+        );
+        assert!(client.breaking_dependents(&packages, "kombu", "1.6.0", &linux).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expand_extras_adds_extra_dependencies() {
+        let server = MockServer::routes(&[
+            (
+                "/pypi/requests/2.31.0/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "requests", "version": "2.31.0", "summary": "", "requires_dist": [
+                        "idna<4,>=2.5",
+                        "PySocks!=1.5.7,>=1.5.6; extra == \"socks\"",
+                        "chardet<6,>=3.0.2; extra == 'use-chardet-on-py3'"
+                    ]}}"#,
+                ),
+            ),
+            (
+                "/pypi/pysocks/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "PySocks", "version": "1.7.1", "summary": "", "requires_dist": ["win-inet-pton; sys_platform == 'win32'"]}}"#,
+                ),
+            ),
+            (
+                "/pypi/win-inet-pton/json",
+                MockResponse::json(r#"{"info": {"name": "win-inet-pton", "version": "1.1.0", "summary": "", "requires_dist": null}}"#),
+            ),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut requests = package("requests", "2.31.0");
+        requests.extras = vec!["socks".to_string()];
+        let packages = [requests, package("idna", "3.4")];
+        let linux = Environment::new("linux", "x86_64", Some((3, 11)));
+        let expanded = client.expand_extras(&packages, &linux).await;
+
+        let names: Vec<&str> = expanded.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["pysocks"]);
+        let windows = Environment::new("windows", "x86_64", Some((3, 11)));
+        let names: Vec<String> = client.expand_extras(&packages, &windows).await.into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["pysocks", "win-inet-pton"]);
+        assert_eq!(expanded[0].current_version, "1.7.1");
+        assert_eq!(expanded[0].notes, vec!["via requests[socks]"]);
+        assert_eq!(server.hits("/pypi/chardet/json"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_expand_extras_reads_the_pinned_release_and_follows_nested_extras() {
+        let server = MockServer::routes(&[
+            (
+                "/pypi/celery/json",
+                MockResponse::json(r#"{"info": {"name": "celery", "version": "5.4.0", "summary": "", "requires_dist": ["kombu"]}}"#),
+            ),
+            (
+                "/pypi/celery/5.3.0/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "celery", "version": "5.3.0", "summary": "", "requires_dist": [
+                        "kombu<6.0,>=5.3.0",
+                        "kombu[redis]; extra == \"redis\"",
+                        "pytest-celery; extra == 'pytest'"
+                    ]}}"#,
+                ),
+            ),
+            (
+                "/pypi/kombu/json",
+                MockResponse::json(
+                    r#"{"info": {"name": "kombu", "version": "5.3.4", "summary": "", "requires_dist": [
+                        "amqp<6.0.0,>=5.1.1",
+                        "redis!=4.5.5,<6.0.0,>=4.5.2; extra == 'redis'"
+                    ]}}"#,
+                ),
+            ),
+            (
+                "/pypi/amqp/json",
+                MockResponse::json(r#"{"info": {"name": "amqp", "version": "5.2.0", "summary": "", "requires_dist": ["vine<6.0.0,>=5.0.0"]}}"#),
+            ),
+            (
+                "/pypi/vine/json",
+                MockResponse::json(r#"{"info": {"name": "vine", "version": "5.1.0", "summary": "", "requires_dist": null}}"#),
+            ),
+            (
+                "/pypi/redis/json",
+                MockResponse::json(r#"{"info": {"name": "redis", "version": "5.0.1", "summary": "", "requires_dist": null}}"#),
+            ),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut celery = package("celery", "5.3.0");
+        celery.extras = vec!["redis".to_string()];
+        let linux = Environment::new("linux", "x86_64", Some((3, 11)));
+        let expanded = client.expand_extras(&[celery], &linux).await;
+
+        let names: Vec<&str> = expanded.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["kombu", "amqp", "redis", "vine"]);
+        assert!(expanded.iter().all(|p| p.notes == vec!["via celery[redis]"]));
+        assert_eq!(server.hits("/pypi/celery/json"), 0);
+        assert_eq!(server.hits("/pypi/pytest-celery/json"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_transitive_pins_are_marked_per_file() {
+        let info = |name: &str, requires: &str| {
+            MockResponse::json(format!(
+                r#"{{"info": {{"name": "{}", "version": "1.0.0", "summary": "", "requires_dist": {}}}}}"#,
+                name, requires
+            ))
+        };
+        let server = MockServer::routes(&[
+            ("/pypi/flask/json", info("flask", r#"["itsdangerous", "werkzeug"]"#)),
+            ("/pypi/itsdangerous/json", info("itsdangerous", "null")),
+            ("/pypi/werkzeug/json", info("werkzeug", "null")),
+            ("/pypi/pytest/json", info("pytest", "null")),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let from = |name: &str, file: &str| Package {
+            origin_file: Some(file.to_string()),
+            ..package(name, "1.0.0")
+        };
+        let mut pytest = from("pytest", "requirements-dev.txt");
+        pytest.is_direct = false;
+        let mut packages = vec![
+            from("flask", "requirements.txt"),
+            from("itsdangerous", "requirements.txt"),
+            from("werkzeug", "requirements-ci.txt"),
+            pytest,
+        ];
+        client.update_packages(&mut packages).await;
+        client.mark_transitive(&mut packages, &["requirements-dev.txt".to_string()]);
+
+        let direct: Vec<(&str, bool)> = packages.iter().map(|p| (p.name.as_str(), p.is_direct)).collect();
+        assert_eq!(
+            direct,
+            vec![("flask", true), ("itsdangerous", false), ("werkzeug", true), ("pytest", false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_and_malformed_bodies_are_rejected() {
+        let huge = format!(r#"{{"info":{{"name":"huge","version":"1.0.0","summary":"{}"}}}}"#, "x".repeat(4096));
+        let server = MockServer::routes(&[
+            ("/pypi/huge/json", MockResponse::json(huge)),
+            ("/pypi/broken/json", MockResponse::json("<html>502 Bad Gateway</html>")),
+        ]);
+        let mut client = PyPIClient::new().with_max_body_bytes(1024);
+        client.apply_options(&[index_option(&server)]);
+
+        let error = client.fetch_latest_version("huge").await.unwrap_err();
+        assert!(matches!(error, PyElevateError::TooLarge(_, 1024)));
+        assert!(error.to_string().contains("exceeds the 1024-byte limit"), "{}", error);
+
+        let error = client.fetch_latest_version("broken").await.unwrap_err();
+        assert!(matches!(error, PyElevateError::Parse(_)));
+        assert!(error.to_string().contains("<html>502 Bad Gateway</html>"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_missing_package_is_not_found() {
+        let server = spawn_index("2.0.0");
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        match client.fetch_latest_version("missing").await {
+            Err(PyElevateError::NotFound(name)) => assert_eq!(name, "missing"),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+        assert_eq!(client.fetch_latest_version("requests").await.unwrap(), "2.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_stubbed_json_response_is_parsed() {
+        let server = MockServer::routes(&[(
+            "/pypi/django/json",
+            MockResponse::json(
+                r#"{
+                    "info": {"name": "Django", "version": "5.0.1", "summary": "A web framework"},
+                    "releases": {
+                        "4.2.9": [{"filename": "Django-4.2.9-py3-none-any.whl", "upload_time_iso_8601": "2024-01-02T15:00:00.000000Z"}],
+                        "5.0.1": [{"filename": "Django-5.0.1-py3-none-any.whl", "upload_time_iso_8601": "2024-01-02T16:00:00.000000Z"}],
+                        "5.1a1": []
+                    }
+                }"#,
+            ),
+        )]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut packages = vec![package("django", "4.2.9")];
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].latest_version.as_deref(), Some("5.0.1"));
+        assert_eq!(packages[0].status, VersionStatus::Major);
+        assert!(packages[0].last_release.is_some());
+        assert_eq!(client.cached_summary("django", "5.0.1").as_deref(), Some("A web framework"));
+        assert!(client.releases("django").contains(&"4.2.9".to_string()));
+        assert_eq!(server.hits("/pypi/django/json"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_latest_derived_from_releases_without_info_version() {
+        let server = MockServer::routes(&[
+            (
+                "/pypi/oddpkg/json",
+                MockResponse::json(
+                    r#"{
+                        "info": {"name": "oddpkg", "summary": "No version field"},
+                        "releases": {
+                            "1.9.0": [{"filename": "oddpkg-1.9.0.tar.gz"}],
+                            "1.10.0": [{"filename": "oddpkg-1.10.0.tar.gz"}],
+                            "2.0.0rc1": [{"filename": "oddpkg-2.0.0rc1.tar.gz"}],
+                            "2.0.0": [{"filename": "oddpkg-2.0.0.tar.gz", "yanked": true}]
+                        }
+                    }"#,
+                ),
+            ),
+            (
+                "/pypi/nullpkg/json",
+                MockResponse::json(r#"{"info": {"name": "nullpkg", "version": null, "summary": ""}, "releases": {"0.1": []}}"#),
+            ),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut packages = vec![package("oddpkg", "1.9.0")];
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].error, None);
+        assert_eq!(packages[0].latest_version.as_deref(), Some("1.10.0"));
+        assert_eq!(packages[0].status, VersionStatus::Minor);
+        assert_eq!(client.fetch_latest_version("nullpkg").await.unwrap(), "0.1");
+    }
+
+    #[tokio::test]
+    async fn test_pin_newer_than_index_latest_is_flagged() {
+        let server = spawn_index("1.5");
+        let mut client = PyPIClient::new();
+        client.apply_options(&[index_option(&server)]);
+
+        let mut packages = vec![package("requests", "2.0"), package("flask", "1.0")];
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].status, VersionStatus::UpToDate);
+        assert!(packages[0].ahead_of_index);
+        assert_eq!(packages[0].notes, vec!["pin is newer than the index's latest (2.0 > 1.5)"]);
+        assert!(!packages[1].ahead_of_index);
+        assert!(packages[1].notes.is_empty());
+
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].notes.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_not_found_suggests_near_matches() {
+        let server = MockServer::routes(&[(
+            "/simple/",
+            MockResponse::json(
+                r#"{"projects": [{"name": "requests"}, {"name": "Requests-OAuthlib"}, {"name": "httpx"}]}"#,
+            ),
+        )]);
+        let mut client = PyPIClient::new().with_simple_index(&server.url("/simple/"));
+        client.apply_options(&[index_option(&server)]);
+
+        let mut packages = vec![package("reqeusts", "2.31.0")];
+        client.update_packages(&mut packages).await;
+
+        assert_eq!(packages[0].status, VersionStatus::Error);
+        let error = packages[0].error.as_deref().unwrap();
+        assert!(error.ends_with("did you mean requests?"), "{}", error);
+        assert_eq!(levenshtein("reqeusts", "requests"), 2);
+        assert!(near_matches(&["httpx".to_string()], "reqeusts").is_empty());
+    }
+
+    #[test]
+    fn test_search_ranks_candidates_from_simple_index() {
+        let body = r#"{
+            "meta": {"api-version": "1.1"},
+            "projects": [
+                {"name": "requests-oauthlib"},
+                {"name": "Requests"},
+                {"name": "httpx"},
+                {"name": "types-requests"}
+            ]
+        }"#;
+
+        let names = parse_project_list(body).unwrap();
+        let results = rank_projects(&names, "requests");
+        let ranked: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(ranked, vec!["Requests", "requests-oauthlib", "types-requests"]);
+        assert!(rank_projects(&names, "").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_fetches_the_simple_index_once() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(r#"{"projects": [{"name": "flask"}, {"name": "Flask-Login"}, {"name": "django"}]}"#)
+        });
+        let client = PyPIClient::new().with_simple_index(&server.url("/simple/"));
+        assert!(client.search_cached("flask").is_none());
+
+        let ranked: Vec<String> = client.search("flask").await.unwrap().into_iter().map(|r| r.name).collect();
+        assert_eq!(ranked, vec!["flask", "Flask-Login"]);
+        assert_eq!(client.search_cached("django").unwrap()[0].name, "django");
+        client.search("login").await.unwrap();
+        assert_eq!(server.hits("/simple/"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_beyond_capacity_evicts_least_recently_used() {
+        let server = MockServer::start(|request| {
+            let name = request.path.split('/').nth(2).unwrap_or_default();
+            MockResponse::json(format!(r#"{{"info":{{"name":"{}","version":"1.0.0","summary":""}}}}"#, name))
+        });
+        let mut client = PyPIClient::new().with_cache_capacity(2);
+        client.apply_options(&[GlobalOption::IndexUrl(server.url("/pypi"))]);
+
+        for name in ["alpha", "beta", "alpha", "gamma"] {
+            client.fetch_latest_version(name).await.unwrap();
+        }
+        assert_eq!(server.hits("/pypi/alpha/json"), 1);
+        assert!(client.cached_summary("beta", "1.0.0").is_none());
+        assert!(client.cached_summary("gamma", "1.0.0").is_some());
+
+        client.fetch_latest_version("beta").await.unwrap();
+        assert_eq!(server.hits("/pypi/beta/json"), 2);
+
+        // The listed packages outlast the capacity, so nothing fetched is lost.
+        let mut packages: Vec<Package> = ["delta", "epsilon", "zeta"]
+            .iter()
+            .map(|name| Package {
+                name: name.to_string(),
+                current_version: "0.9.0".to_string(),
+                ..Package::default()
+            })
+            .collect();
+        client.update_packages(&mut packages).await;
+        assert!(packages.iter().all(|p| p.latest_version.as_deref() == Some("1.0.0")));
+        assert!(client.cached_summary("delta", "1.0.0").is_some());
+        assert!(client.cached_summary("zeta", "1.0.0").is_some());
+    }
+}
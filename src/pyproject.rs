@@ -0,0 +1,371 @@
+use crate::error::{PyElevateError, Result};
+use crate::models::{normalize_package_name, ParseWarning, RequirementsFile};
+use crate::parser::{parse_message, parse_requirement_line};
+use std::fs;
+use std::path::Path;
+use toml::Value;
+
+/// The optional dependency groups to read besides the main dependencies:
+/// PEP 735 `[dependency-groups]` and Poetry's `[tool.poetry.group.*]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupSelection {
+    pub groups: Vec<String>,
+    pub all: bool,
+}
+
+impl GroupSelection {
+    fn includes(&self, group: &str) -> bool {
+        self.all
+            || self
+                .groups
+                .iter()
+                .any(|wanted| normalize_package_name(wanted) == normalize_package_name(group))
+    }
+}
+
+pub fn is_pyproject<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().file_name().is_some_and(|name| name == "pyproject.toml")
+}
+
+/// One dependency declaration and where it sits in the file.
+pub(crate) struct Entry {
+    pub group: Option<String>,
+    /// The declaration as a PEP 508 requirement.
+    pub spec: String,
+    /// 1-based line of the declaration, or of its table when it can't be
+    /// told apart; 0 when neither is found.
+    pub line: usize,
+    /// Poetry's version string, e.g. `^24.1`; `None` for PEP 508 strings.
+    pub poetry_version: Option<String>,
+}
+
+/// Reads `[project] dependencies` and Poetry's `[tool.poetry.dependencies]`,
+/// plus whichever groups `selection` asks for, tagging those packages with
+/// their group. Asking for a group the file doesn't define is an error, so
+/// a typo doesn't silently check nothing.
+pub fn parse_pyproject<P: AsRef<Path>>(path: P, selection: &GroupSelection) -> Result<RequirementsFile> {
+    let content = fs::read_to_string(&path)?;
+    let path_str = path.as_ref().to_string_lossy().to_string();
+
+    let mut packages = Vec::new();
+    let mut warnings = Vec::new();
+    for entry in entries(&content, &path_str, selection)? {
+        match parse_requirement_line(&entry.spec) {
+            Ok(mut package) => {
+                package.origin_file = Some(path_str.clone());
+                package.group = entry.group;
+                packages.push(package);
+            }
+            Err(e) => warnings.push(ParseWarning {
+                line: entry.line,
+                message: format!("{} ({})", parse_message(&e), entry.spec),
+            }),
+        }
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(RequirementsFile {
+        path: path_str,
+        packages,
+        raw_lines: content.lines().map(|l| l.to_string()).collect(),
+        options: Vec::new(),
+        warnings,
+        compiled: false,
+    })
+}
+
+/// The dependency declarations of a pyproject.toml: the main ones plus
+/// those of the groups `selection` asks for.
+pub(crate) fn entries(content: &str, path: &str, selection: &GroupSelection) -> Result<Vec<Entry>> {
+    let doc: Value = toml::from_str(content).map_err(|e| PyElevateError::Parse(format!("{}: {}", path, e)))?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut entries = Vec::new();
+    let mut defined: Vec<String> = Vec::new();
+    let pep508 = |table: &str, group: Option<String>, spec: String| {
+        let quoted = [format!("\"{}\"", spec), format!("'{}'", spec)];
+        Entry {
+            line: line_in_table(&lines, table, |l| quoted.iter().any(|q| l.contains(q.as_str()))),
+            group,
+            spec,
+            poetry_version: None,
+        }
+    };
+
+    if let Some(deps) = doc.get("project").and_then(|p| p.get("dependencies")).and_then(Value::as_array) {
+        for spec in deps.iter().filter_map(Value::as_str) {
+            entries.push(pep508("project", None, spec.to_string()));
+        }
+    }
+
+    if let Some(groups) = doc.get("dependency-groups").and_then(Value::as_table) {
+        for name in groups.keys() {
+            defined.push(name.clone());
+            if selection.includes(name) {
+                let mut seen = Vec::new();
+                for spec in group_requirements(groups, name, &mut seen) {
+                    entries.push(pep508("dependency-groups", Some(name.clone()), spec));
+                }
+            }
+        }
+    }
+
+    if let Some(poetry) = doc.get("tool").and_then(|t| t.get("poetry")) {
+        let tables = poetry
+            .get("dependencies")
+            .map(|deps| (None, "tool.poetry.dependencies".to_string(), deps))
+            .into_iter()
+            .chain(
+                poetry
+                    .get("dev-dependencies")
+                    .map(|deps| (Some("dev".to_string()), "tool.poetry.dev-dependencies".to_string(), deps)),
+            )
+            .chain(
+                poetry
+                    .get("group")
+                    .and_then(Value::as_table)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(name, group)| {
+                        let table = format!("tool.poetry.group.{}.dependencies", name);
+                        Some((Some(name.clone()), table, group.get("dependencies")?))
+                    }),
+            );
+        for (group, table, deps) in tables {
+            if let Some(name) = &group {
+                defined.push(name.clone());
+                if !selection.includes(name) {
+                    continue;
+                }
+            }
+            for (name, value) in deps.as_table().into_iter().flatten() {
+                if name == "python" {
+                    continue;
+                }
+                let (version, extras) = poetry_parts(value);
+                entries.push(Entry {
+                    group: group.clone(),
+                    spec: poetry_requirement(name, version, &extras),
+                    line: line_in_table(&lines, &table, |l| toml_key(l) == Some(name.as_str())),
+                    poetry_version: Some(version.to_string()),
+                });
+            }
+        }
+    }
+
+    if let Some(missing) = selection
+        .groups
+        .iter()
+        .find(|wanted| !defined.iter().any(|name| normalize_package_name(name) == normalize_package_name(wanted)))
+    {
+        return Err(PyElevateError::Parse(format!(
+            "{} defines no dependency group named {}",
+            path, missing
+        )));
+    }
+    Ok(entries)
+}
+
+/// The 1-based line under the `[table]` header for which `matches` holds,
+/// falling back to the header's own line.
+fn line_in_table(lines: &[&str], table: &str, matches: impl Fn(&str) -> bool) -> usize {
+    let Some(header) = lines.iter().position(|l| header_name(l).as_deref() == Some(table)) else {
+        return 0;
+    };
+    lines[header + 1..]
+        .iter()
+        .take_while(|l| header_name(l).is_none())
+        .position(|l| matches(l))
+        .map_or(header + 1, |i| header + i + 2)
+}
+
+/// `tool.poetry.dependencies` for a `[tool.poetry.dependencies]` line.
+fn header_name(line: &str) -> Option<String> {
+    let line = line.split('#').next().unwrap_or(line).trim();
+    let name = line.strip_prefix('[')?.strip_suffix(']')?;
+    if name.starts_with('[') || name.is_empty() {
+        return None;
+    }
+    let name: String = name.chars().filter(|c| !c.is_whitespace() && *c != '"' && *c != '\'').collect();
+    name.chars()
+        .all(|c| c.is_alphanumeric() || "-_.".contains(c))
+        .then_some(name)
+}
+
+/// The key a `key = value` line assigns, unquoted.
+fn toml_key(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    Some(key.trim().trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// A PEP 735 group's requirements, following `{include-group = "..."}`
+/// entries. `seen` stops include cycles.
+fn group_requirements(groups: &toml::map::Map<String, Value>, name: &str, seen: &mut Vec<String>) -> Vec<String> {
+    if seen.iter().any(|s| s == name) {
+        return Vec::new();
+    }
+    seen.push(name.to_string());
+    let mut specs = Vec::new();
+    for entry in groups.get(name).and_then(Value::as_array).into_iter().flatten() {
+        match entry {
+            Value::String(spec) => specs.push(spec.clone()),
+            Value::Table(table) => {
+                if let Some(included) = table.get("include-group").and_then(Value::as_str) {
+                    specs.extend(group_requirements(groups, included, seen));
+                }
+            }
+            _ => {}
+        }
+    }
+    specs
+}
+
+/// The version string and extras of a Poetry dependency, which is either a
+/// bare version or a table with `version` and `extras`.
+fn poetry_parts(value: &Value) -> (&str, Vec<&str>) {
+    match value {
+        Value::String(version) => (version.trim(), Vec::new()),
+        Value::Table(table) => (
+            table.get("version").and_then(Value::as_str).unwrap_or("*").trim(),
+            table
+                .get("extras")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect(),
+        ),
+        _ => ("*", Vec::new()),
+    }
+}
+
+/// A Poetry dependency as a PEP 508 requirement. Caret and tilde ranges
+/// become the bounded range they stand for; `*` is unconstrained.
+fn poetry_requirement(name: &str, version: &str, extras: &[&str]) -> String {
+    let extras = if extras.is_empty() {
+        String::new()
+    } else {
+        format!("[{}]", extras.join(","))
+    };
+    let spec = if let Some(floor) = version.strip_prefix('^') {
+        bounded(floor.trim(), caret_cap)
+    } else if let Some(floor) = version.strip_prefix('~').filter(|v| !v.starts_with('=')) {
+        bounded(floor.trim(), tilde_cap)
+    } else if version == "*" {
+        String::new()
+    } else if version.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("=={}", version)
+    } else {
+        version.to_string()
+    };
+    format!("{}{}{}", name, extras, spec)
+}
+
+fn bounded(floor: &str, cap: fn(&[u64]) -> Vec<u64>) -> String {
+    let parts = crate::models::release_parts(floor);
+    let cap: Vec<String> = cap(&parts).iter().map(|part| part.to_string()).collect();
+    format!(">={},<{}", floor, cap.join("."))
+}
+
+/// `^1.2.3` allows anything up to the next change of the leftmost non-zero
+/// part: `<2.0.0`, and `<0.3.0` for `^0.2.3`.
+fn caret_cap(parts: &[u64]) -> Vec<u64> {
+    let bump = parts.iter().position(|&part| part != 0).unwrap_or(parts.len().saturating_sub(1));
+    cap_at(parts, bump)
+}
+
+/// `~1.2.3` allows patch changes (`<1.3.0`); `~1` allows minor ones (`<2`).
+fn tilde_cap(parts: &[u64]) -> Vec<u64> {
+    cap_at(parts, if parts.len() > 1 { 1 } else { 0 })
+}
+
+fn cap_at(parts: &[u64], bump: usize) -> Vec<u64> {
+    let mut cap = vec![0; parts.len().max(1)];
+    cap[..bump].copy_from_slice(&parts[..bump]);
+    cap[bump] = parts.get(bump).copied().unwrap_or(0) + 1;
+    cap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PYPROJECT: &str = r#"
+[project]
+name = "demo"
+dependencies = ["django>=4.2", "requests[socks]==2.31.0"]
+
+[dependency-groups]
+test = ["pytest>=8.0"]
+dev = ["ruff==0.4.0", {include-group = "test"}]
+docs = ["sphinx>=7"]
+
+[tool.poetry.group.lint.dependencies]
+black = "^24.1"
+isort = { version = "~5.13.2", extras = ["colors"] }
+mypy = "not a version"
+"#;
+
+    #[test]
+    fn test_group_selection_includes_only_requested_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pyproject.toml");
+        fs::write(&path, PYPROJECT).unwrap();
+        let names = |file: &RequirementsFile| -> Vec<(String, Option<String>)> {
+            file.packages.iter().map(|p| (p.name.clone(), p.group.clone())).collect()
+        };
+
+        let main = parse_pyproject(&path, &GroupSelection::default()).unwrap();
+        assert_eq!(
+            names(&main),
+            vec![("django".to_string(), None), ("requests".to_string(), None)]
+        );
+
+        let dev = GroupSelection {
+            groups: vec!["dev".to_string()],
+            all: false,
+        };
+        let with_dev = parse_pyproject(&path, &dev).unwrap();
+        assert_eq!(
+            names(&with_dev),
+            vec![
+                ("django".to_string(), None),
+                ("pytest".to_string(), Some("dev".to_string())),
+                ("requests".to_string(), None),
+                ("ruff".to_string(), Some("dev".to_string())),
+            ]
+        );
+
+        let all = GroupSelection { groups: Vec::new(), all: true };
+        let everything = parse_pyproject(&path, &all).unwrap();
+        assert_eq!(everything.packages.len(), 8);
+        let black = everything.packages.iter().find(|p| p.name == "black").unwrap();
+        assert_eq!(black.constraint.as_str(), ">=24.1,<25.0");
+        assert_eq!(black.group.as_deref(), Some("lint"));
+        let isort = everything.packages.iter().find(|p| p.name == "isort").unwrap();
+        assert_eq!(isort.constraint.as_str(), ">=5.13.2,<5.14.0");
+        assert_eq!(isort.extras, vec!["colors"]);
+        assert_eq!(everything.warnings.len(), 1);
+        assert_eq!(everything.warnings[0].line, 14);
+
+        let typo = GroupSelection {
+            groups: vec!["dve".to_string()],
+            all: false,
+        };
+        assert!(parse_pyproject(&path, &typo).is_err());
+    }
+
+    #[test]
+    fn test_poetry_carets_and_tildes_keep_their_upper_bound() {
+        let spec = |version| poetry_requirement("pkg", version, &[]);
+        assert_eq!(spec("^1.2.3"), "pkg>=1.2.3,<2.0.0");
+        assert_eq!(spec("^0.2.3"), "pkg>=0.2.3,<0.3.0");
+        assert_eq!(spec("^0.0.3"), "pkg>=0.0.3,<0.0.4");
+        assert_eq!(spec("^0"), "pkg>=0,<1");
+        assert_eq!(spec("~1.2.3"), "pkg>=1.2.3,<1.3.0");
+        assert_eq!(spec("~1"), "pkg>=1,<2");
+        assert_eq!(spec("~=1.2"), "pkg~=1.2");
+        assert_eq!(spec("1.0"), "pkg==1.0");
+        assert_eq!(spec("*"), "pkg");
+    }
+}
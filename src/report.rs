@@ -0,0 +1,728 @@
+use crate::glyphs::{self, GlyphSet, Glyphs};
+use crate::installed::Drift;
+use crate::models::{Changelog, Package, SecurityAdvisory, SecurityStatus, Severity, UpgradeStats, VersionStatus};
+use crate::resolver::Conflict;
+use crate::security::AuditFinding;
+use colored::Color;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::IsTerminal;
+
+pub const CHECK_TITLE: &str = "PyElevate v0.2.0 - Dependency Check Report";
+pub const UPGRADE_TITLE: &str = "PyElevate v0.2.0 - Dependency Upgrade";
+
+/// `title` in a double-lined box, or a `+===+` one with ASCII glyphs.
+pub fn banner(title: &str, glyphs: &Glyphs) -> String {
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = glyphs.frame;
+    let line = horizontal.repeat(glyphs::width(title) + 4);
+    format!(
+        "{}{}{}\n{}  {}  {}\n{}{}{}\n",
+        top_left, line, top_right, vertical, title, vertical, bottom_left, line, bottom_right
+    )
+}
+
+/// The machine-readable `check` report, as emitted by `--format json`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CheckReport {
+    pub requirements: String,
+    pub packages: Vec<Package>,
+    pub stats: UpgradeStats,
+    pub conflicts: Vec<Conflict>,
+    /// Differences from the installed environment; `null` without
+    /// `--against-installed`.
+    pub installed_drift: Option<Vec<Drift>>,
+    /// Names from the `[watch]` list, which lead `packages`.
+    pub watched: Vec<String>,
+}
+
+impl CheckReport {
+    pub fn new(requirements: &str, packages: &[Package], conflicts: &[Conflict]) -> Self {
+        Self {
+            requirements: requirements.to_string(),
+            packages: packages.to_vec(),
+            stats: UpgradeStats::new(packages),
+            conflicts: conflicts.to_vec(),
+            installed_drift: None,
+            watched: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn json_schema() -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&schemars::schema_for!(CheckReport))?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReportOptions {
+    pub quiet: bool,
+    pub color: bool,
+    pub glyphs: GlyphSet,
+}
+
+/// Color is only worth emitting when the target is a terminal and the user
+/// hasn't opted out via `NO_COLOR`.
+pub fn color_enabled(target: &impl IsTerminal) -> bool {
+    target.is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn status_color(status: VersionStatus) -> Color {
+    match status {
+        VersionStatus::UpToDate | VersionStatus::Patch => Color::Green,
+        VersionStatus::Minor => Color::Yellow,
+        VersionStatus::Major | VersionStatus::Error => Color::Red,
+        VersionStatus::Vulnerable => Color::Magenta,
+        VersionStatus::Prerelease => Color::Cyan,
+        VersionStatus::Unknown => Color::White,
+    }
+}
+
+/// `text` wrapped in the ANSI escape for `color`. Written out directly so
+/// the result depends only on [`ReportOptions::color`], not on `colored`'s
+/// process-wide override and terminal detection.
+fn paint(text: &str, color: Color) -> String {
+    format!("\x1b[{}m{}\x1b[0m", color.to_fg_str(), text)
+}
+
+/// The widest a table column grows; longer cells are cut short with an ellipsis.
+pub const MAX_COLUMN_WIDTH: usize = 40;
+
+/// A plain-text table whose columns are as wide as their longest cell, up to
+/// [`MAX_COLUMN_WIDTH`]. Widths count terminal columns, so wide characters
+/// don't push later columns out, and cells come back padded but unstyled,
+/// so color can be added after layout without skewing it.
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    glyphs: &'static Glyphs,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+            glyphs: &glyphs::EMOJI,
+        }
+    }
+
+    /// Draws the rule and cut-off marks with `glyphs`.
+    pub fn with_glyphs(mut self, glyphs: &'static Glyphs) -> Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    pub fn push_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        (0..self.headers.len())
+            .map(|column| {
+                std::iter::once(&self.headers)
+                    .chain(&self.rows)
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| glyphs::width(cell))
+                    .max()
+                    .unwrap_or(0)
+                    .min(MAX_COLUMN_WIDTH)
+            })
+            .collect()
+    }
+
+    /// Each cell fitted to its column. The last column isn't padded, so
+    /// lines carry no trailing spaces.
+    fn fit(&self, row: &[String], widths: &[usize]) -> Vec<String> {
+        widths
+            .iter()
+            .enumerate()
+            .map(|(column, &width)| {
+                let cell = row.get(column).map(String::as_str).unwrap_or_default();
+                let cell = glyphs::truncate(cell, width, self.glyphs.ellipsis);
+                if column + 1 == widths.len() {
+                    cell
+                } else {
+                    glyphs::pad(&cell, width)
+                }
+            })
+            .collect()
+    }
+
+    pub fn header(&self) -> String {
+        self.fit(&self.headers, &self.widths()).join(" ")
+    }
+
+    pub fn rule(&self) -> String {
+        let widths = self.widths();
+        self.glyphs.rule.repeat(widths.iter().sum::<usize>() + widths.len().saturating_sub(1))
+    }
+
+    pub fn rows(&self) -> Vec<Vec<String>> {
+        let widths = self.widths();
+        self.rows.iter().map(|row| self.fit(row, &widths)).collect()
+    }
+}
+
+/// What to show instead of an empty table when `path` declares no
+/// packages, e.g. a file of only comments and options.
+pub fn empty_state(path: &str, packages: &[Package]) -> Option<String> {
+    packages.is_empty().then(|| format!("No dependencies found in {}", path))
+}
+
+/// Renders the `check` report. In quiet mode only the package table and any
+/// constraint conflicts are emitted.
+pub fn check_report(
+    packages: &[Package],
+    stats: &UpgradeStats,
+    conflicts: &[Conflict],
+    options: ReportOptions,
+) -> String {
+    let mut out = Vec::new();
+    let g = options.glyphs.glyphs();
+    let stat = |icon: &str, label: &str, value: usize| format!("{} {:<24}{}", g.icon(icon), label, value);
+
+    if !options.quiet {
+        out.push(banner(CHECK_TITLE, g));
+        out.push(stat(g.package, "Total packages:", stats.total));
+        out.push(stat(g.patch, "Patch updates:", stats.patch_available));
+        out.push(stat(g.minor, "Minor updates:", stats.minor_available));
+        out.push(stat(g.major, "Major updates:", stats.major_available));
+        out.push(stat(g.up_to_date, "Up to date:", stats.up_to_date));
+        out.push(stat(g.prerelease, "Prerelease:", stats.prerelease));
+        out.push(stat(g.unknown, "Unknown:", stats.unknown));
+        out.push(stat(g.warning, "Vulnerable:", stats.vulnerable));
+        if packages.iter().any(|p| !p.advisories.is_empty()) {
+            let advisories = packages.iter().flat_map(|p| &p.advisories);
+            out.push(format!("{}  {}", " ".repeat(g.icon_width), severity_breakdown(advisories)));
+        }
+        if stats.fixable_cves > 0 {
+            out.push(stat(g.shield, "Fixable CVEs:", stats.fixable_cves));
+        }
+        out.push(format!("{}\n", stat(g.error, "Errors:", stats.errors)));
+    }
+
+    let mut table = Table::new(&["Package", "Current", "Latest", "Status"]).with_glyphs(g);
+    for pkg in packages {
+        table.push_row(vec![
+            pkg.name.clone(),
+            pkg.current_version.clone(),
+            pkg.latest_version.clone().unwrap_or_else(|| "N/A".to_string()),
+            pkg.status.as_str().to_string(),
+        ]);
+    }
+    out.push(table.header());
+    out.push(table.rule());
+    for (pkg, mut cells) in packages.iter().zip(table.rows()) {
+        if options.color {
+            if let Some(status) = cells.last_mut() {
+                *status = paint(status, status_color(pkg.status));
+            }
+        }
+        out.push(cells.join(" "));
+    }
+
+    let noted: Vec<&Package> = packages.iter().filter(|p| !p.notes.is_empty()).collect();
+    if !noted.is_empty() {
+        out.push("\nNotes:".to_string());
+        for pkg in noted {
+            out.push(format!("  {} {} {}", pkg.name, g.dash, pkg.notes.join("; ")));
+        }
+    }
+
+    let ignored: Vec<(&Package, &SecurityAdvisory)> = packages
+        .iter()
+        .flat_map(|p| p.ignored_advisories.iter().map(move |a| (p, a)))
+        .collect();
+    if !ignored.is_empty() {
+        out.push(format!("\nIgnored advisories: {}", ignored.len()));
+        for (pkg, advisory) in ignored {
+            out.push(format!("  {} {}  {}  {}", pkg.name, pkg.current_version, advisory.id, advisory.title));
+        }
+    }
+
+    if !conflicts.is_empty() {
+        let heading = format!("Constraint conflicts: {}", conflicts.len());
+        if options.quiet {
+            out.push(format!("\n{}", heading));
+        } else {
+            out.push(format!("\n{} {}", g.icon(g.warning), heading));
+        }
+        for conflict in conflicts {
+            out.push(format!("  {} {} {}", conflict.package, g.dash, conflict.reason));
+        }
+    }
+
+    out.join("\n")
+}
+
+/// The `audit` report: findings grouped from the most to the least severe.
+pub fn audit_report(findings: &[AuditFinding], failed: &[String], options: ReportOptions) -> String {
+    let mut out = Vec::new();
+    let (ignored, findings): (Vec<&AuditFinding>, Vec<&AuditFinding>) = findings.iter().partition(|f| f.ignored);
+    let packages: std::collections::HashSet<&str> = findings.iter().map(|f| f.package.as_str()).collect();
+    if !options.quiet {
+        out.push(format!(
+            "{} known vulnerabilit{} in {} package(s)",
+            findings.len(),
+            if findings.len() == 1 { "y" } else { "ies" },
+            packages.len()
+        ));
+    }
+
+    if !options.quiet && !findings.is_empty() {
+        out.push(severity_breakdown(findings.iter().map(|f| &f.advisory)));
+    }
+
+    for severity in Severity::ALL {
+        let group: Vec<&AuditFinding> = findings.iter().copied().filter(|f| f.advisory.severity == severity).collect();
+        if group.is_empty() {
+            continue;
+        }
+        let heading = format!("{} ({})", severity.as_str(), group.len());
+        out.push(if options.color {
+            format!("\n{}", paint(&heading, severity_color(severity)))
+        } else {
+            format!("\n{}", heading)
+        });
+        for finding in group {
+            out.push(format!("  {}", finding_line(finding)));
+        }
+    }
+
+    if !ignored.is_empty() {
+        out.push(format!("\nIgnored ({})", ignored.len()));
+        for finding in ignored {
+            out.push(format!("  {}", finding_line(finding)));
+        }
+    }
+
+    if !failed.is_empty() {
+        out.push(format!("\nCould not check: {}", failed.join(", ")));
+    }
+
+    out.join("\n")
+}
+
+fn finding_line(finding: &AuditFinding) -> String {
+    let fix = finding
+        .advisory
+        .fixed_version
+        .as_deref()
+        .map(|v| format!(" (fixed in {})", v))
+        .unwrap_or_default();
+    format!(
+        "{} {}  {}  {}{}",
+        finding.package, finding.version, finding.advisory.id, finding.advisory.title, fix
+    )
+}
+
+/// How many advisories there are at each severity, most severe first.
+pub fn severity_counts<'a>(advisories: impl IntoIterator<Item = &'a SecurityAdvisory>) -> [(Severity, usize); 4] {
+    let mut counts = Severity::ALL.map(|severity| (severity, 0));
+    for advisory in advisories {
+        if let Some((_, count)) = counts.iter_mut().find(|(s, _)| *s == advisory.severity) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// `Critical: 1, High: 2, Medium: 0, Low: 3`
+pub fn severity_breakdown<'a>(advisories: impl IntoIterator<Item = &'a SecurityAdvisory>) -> String {
+    severity_counts(advisories)
+        .iter()
+        .map(|(severity, count)| {
+            let name = severity.as_str();
+            format!("{}{}: {}", &name[..1], name[1..].to_lowercase(), count)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `name: reason` for each PyPI package whose status couldn't be settled,
+/// which `check --strict` treats as a failure. Git, URL and local sources
+/// are never looked up, so they don't count.
+pub fn unresolved_packages(packages: &[Package]) -> Vec<String> {
+    packages
+        .iter()
+        .filter(|p| matches!(p.source, crate::models::DependencySource::PyPI))
+        .filter(|p| matches!(p.status, VersionStatus::Unknown | VersionStatus::Error))
+        .map(|p| {
+            let reason = p.error.as_deref().unwrap_or("version status could not be determined");
+            format!("{}: {}", p.name, reason)
+        })
+        .collect()
+}
+
+/// A warning for each of `upgrading` that is only pinned because other
+/// packages need it: the versions they allow decide whether the upgrade holds.
+pub fn transitive_warnings<'a>(packages: &[Package], upgrading: impl IntoIterator<Item = &'a Package>) -> Vec<String> {
+    let resolver = crate::resolver::DependencyResolver::from_packages(packages);
+    upgrading
+        .into_iter()
+        .filter(|pkg| !pkg.is_direct)
+        .map(|pkg| {
+            let dependents = resolver.get_dependents(&crate::models::normalize_package_name(&pkg.name));
+            let via = if dependents.is_empty() {
+                String::new()
+            } else {
+                format!(" (required by {})", dependents.join(", "))
+            };
+            format!("{} is a transitive pin{}; it is constrained by what requires it", pkg.name, via)
+        })
+        .collect()
+}
+
+/// A warning for each alias in `packages`, naming its canonical project and
+/// `how` to switch to it.
+pub fn alias_warnings(packages: &[Package], how: &str) -> Vec<String> {
+    packages
+        .iter()
+        .filter_map(|pkg| {
+            let canonical = pkg.alias_of.as_deref()?;
+            Some(format!("{} is an alias of {}; {}", pkg.name, canonical, how))
+        })
+        .collect()
+}
+
+/// `kombu 2.0.0 breaks celery (needs <2.0,>=1.5), flower (via celery)`, from
+/// [`PyPIClient::breaking_dependents`](crate::pypi::PyPIClient::breaking_dependents).
+pub fn breaking_warning(name: &str, target: &str, breaking: &[(String, String)]) -> Option<String> {
+    if breaking.is_empty() {
+        return None;
+    }
+    let dependents: Vec<String> = breaking
+        .iter()
+        .map(|(dependent, why)| format!("{} ({})", dependent, why))
+        .collect();
+    Some(format!("{} {} breaks {}", name, target, dependents.join(", ")))
+}
+
+/// The `changelog` command's output: a heading per release, then its
+/// notes, with breaking changes called out.
+pub fn changelog_report(package: &str, changelogs: &[Changelog], options: ReportOptions) -> String {
+    if changelogs.is_empty() {
+        return format!("No release notes found for {}", package);
+    }
+    let g = options.glyphs.glyphs();
+    let mut out = Vec::new();
+    for changelog in changelogs {
+        let heading = format!("{} {}", package, changelog.version);
+        out.push(if options.color { paint(&heading, Color::Cyan) } else { heading });
+        for change in &changelog.changes {
+            out.push(format!("  {} {}", g.bullet, change));
+        }
+        for breaking in &changelog.breaking_changes {
+            let line = format!("  {} BREAKING: {}", g.icon(g.warning), breaking);
+            out.push(if options.color { paint(&line, Color::Red) } else { line });
+        }
+        for deprecated in &changelog.deprecated {
+            out.push(format!("  {} Deprecated: {}", g.icon(g.deprecated), deprecated));
+        }
+        for fix in &changelog.security_fixes {
+            out.push(format!("  {} Security: {}", g.icon(g.lock), fix));
+        }
+        out.push(String::new());
+    }
+    out.pop();
+    out.join("\n")
+}
+
+/// `check --porcelain`: one `name\tcurrent\tlatest\tstatus\tsecurity` line
+/// per package, with `-` for an unknown latest version. The fields and their
+/// spellings are a stable interface for scripts; anything new goes at the end.
+pub fn porcelain_report(packages: &[Package]) -> String {
+    packages
+        .iter()
+        .map(|pkg| {
+            let status = match pkg.status {
+                VersionStatus::Patch => "patch",
+                VersionStatus::Minor => "minor",
+                VersionStatus::Major => "major",
+                VersionStatus::Prerelease => "prerelease",
+                VersionStatus::Unknown => "unknown",
+                VersionStatus::UpToDate => "up-to-date",
+                VersionStatus::Error => "error",
+                VersionStatus::Vulnerable => "vulnerable",
+            };
+            let security = match pkg.security_status {
+                SecurityStatus::Vulnerable { .. } => "vulnerable",
+                SecurityStatus::Safe => "safe",
+                SecurityStatus::Unknown => "unknown",
+            };
+            [
+                pkg.name.as_str(),
+                pkg.current_version.as_str(),
+                pkg.latest_version.as_deref().unwrap_or("-"),
+                status,
+                security,
+            ]
+            .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `list`: what the parser made of each requirement, without looking
+/// anything up. Empty constraints, extras and groups show as `-`.
+pub fn list_report(packages: &[Package], glyphs: GlyphSet) -> String {
+    let or_dash = |text: String| if text.is_empty() { "-".to_string() } else { text };
+    let mut table =
+        Table::new(&["Package", "Constraint", "Extras", "Source", "Group", "File"]).with_glyphs(glyphs.glyphs());
+    for pkg in packages {
+        table.push_row(vec![
+            pkg.name.clone(),
+            or_dash(pkg.constraint.as_str()),
+            or_dash(pkg.extras.join(",")),
+            pkg.source.source_type().to_string(),
+            or_dash(pkg.group.clone().unwrap_or_default()),
+            or_dash(pkg.origin_file.clone().unwrap_or_default()),
+        ]);
+    }
+    let mut out = vec![table.header(), table.rule()];
+    out.extend(table.rows().into_iter().map(|cells| cells.join(" ")));
+    out.join("\n")
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Critical => Color::Magenta,
+        Severity::High => Color::Red,
+        Severity::Medium => Color::Yellow,
+        Severity::Low => Color::Cyan,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DependencySource, VersionConstraint, VersionStatus};
+
+    fn package(name: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "1.0.0".to_string(),
+            latest_version: Some("1.1.0".to_string()),
+            status: VersionStatus::Minor,
+            constraint: VersionConstraint::Pinned("1.0.0".to_string()),
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn test_comment_only_file_shows_empty_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(&path, "# managed by hand\n\n--index-url https://pypi.org/simple\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        assert_eq!(empty_state(&path, &packages), Some(format!("No dependencies found in {}", path)));
+        assert_eq!(empty_state(&path, &[package("flask")]), None);
+    }
+
+    #[test]
+    fn test_long_package_name_keeps_columns_aligned() {
+        let mut long = package("a-package-with-an-exceptionally-long-distribution-name");
+        long.current_version = "2024.10.14.post1+local.build".to_string();
+        let short = package("six");
+        let packages = vec![long, short];
+        let options = ReportOptions { quiet: true, ..Default::default() };
+        let report = check_report(&packages, &UpgradeStats::new(&packages), &[], options);
+
+        let lines: Vec<&str> = report.lines().collect();
+        let column = |line: &str, cell: &str| line.find(cell).map(|byte| line[..byte].chars().count());
+        let latest = column(lines[0], "Latest").unwrap();
+        assert_eq!(column(lines[2], "1.1.0"), Some(latest));
+        assert_eq!(column(lines[3], "1.1.0"), Some(latest));
+        assert!(lines[2].starts_with(&format!("{}…", "a-package-with-an-exceptionally-long-di")));
+        assert_eq!(glyphs::truncate("żółw-żółw", 5, "…"), "żółw…");
+    }
+
+    #[test]
+    fn test_list_shows_parsed_source_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(
+            &path,
+            "django[argon2]==4.2.0\ngit+https://github.com/psf/requests.git@main#egg=requests\n",
+        )
+        .unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        let report = list_report(&packages, GlyphSet::Emoji);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let cells = |line: &str| line.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(cells(lines[2])[..4], ["django", "==4.2.0", "argon2", "PyPI"]);
+        assert_eq!(cells(lines[3])[..4], ["requests", "-", "-", "Git"]);
+        assert!(lines[2].ends_with(&glyphs::truncate(&path, MAX_COLUMN_WIDTH, "…")));
+    }
+
+    #[test]
+    fn test_quiet_check_report_omits_banner() {
+        let packages = vec![package("requests")];
+        let stats = UpgradeStats::new(&packages);
+
+        let loud = check_report(&packages, &stats, &[], ReportOptions::default());
+        assert!(loud.contains('╔'));
+
+        let options = ReportOptions { quiet: true, ..Default::default() };
+        let quiet = check_report(&packages, &stats, &[], options);
+        assert!(!quiet.contains('╔') && !quiet.contains('║') && !quiet.contains('╚'));
+        assert!(!quiet.contains("Total packages"));
+        assert!(quiet.starts_with("Package"));
+        assert!(quiet.contains("requests"));
+    }
+
+    #[test]
+    fn test_ascii_glyphs_emit_only_ascii() {
+        let mut major = package("a-package-with-a-rather-long-name-to-truncate");
+        major.status = VersionStatus::Major;
+        major.latest_version = Some("2.0.0".to_string());
+        major.notes = vec!["Requires Python >=3.12".to_string()];
+        let packages = vec![package("requests"), major];
+        let stats = UpgradeStats::new(&packages);
+        let conflicts = vec![Conflict {
+            package: "requests".to_string(),
+            reason: "pinned below the required floor".to_string(),
+            current: "1.0.0".to_string(),
+            required: ">=2.0".to_string(),
+            severity: Severity::High,
+        }];
+
+        let options = ReportOptions {
+            glyphs: GlyphSet::Ascii,
+            ..Default::default()
+        };
+        let check = check_report(&packages, &stats, &conflicts, options);
+        assert!(check.is_ascii(), "{}", check);
+        let list = list_report(&packages, GlyphSet::Ascii);
+        assert!(list.is_ascii(), "{}", list);
+        let simulation = crate::simulator::UpgradeSimulator::new().generate_report(&packages, GlyphSet::Ascii.glyphs());
+        assert!(simulation.is_ascii(), "{}", simulation);
+        let list_options = crate::panels::ListOptions {
+            group_by_file: true,
+            watchlist: &["requests".to_string()],
+            two_line: true,
+            glyphs: GlyphSet::Ascii.glyphs(),
+        };
+        let refs: Vec<&Package> = packages.iter().collect();
+        for line in crate::panels::dependency_list_lines(&refs, 1, list_options) {
+            let text: String = line.spans.iter().map(|span| span.content.as_ref()).collect();
+            assert!(text.is_ascii(), "{}", text);
+        }
+
+        let emoji = check_report(&packages, &stats, &conflicts, ReportOptions::default());
+        assert!(!emoji.is_ascii());
+    }
+
+    #[test]
+    fn test_strict_fails_on_errored_package() {
+        let mut errored = package("private-pkg");
+        errored.status = VersionStatus::Error;
+        errored.error = Some("private-pkg was not found on the index".to_string());
+        let mut git = package("internal");
+        git.status = VersionStatus::Unknown;
+        git.source = DependencySource::Git {
+            url: "https://github.com/example/internal".to_string(),
+            ref_spec: None,
+            editable: false,
+        };
+
+        assert!(unresolved_packages(&[package("requests"), git.clone()]).is_empty());
+        assert_eq!(
+            unresolved_packages(&[package("requests"), errored, git]),
+            vec!["private-pkg: private-pkg was not found on the index"]
+        );
+    }
+
+    #[test]
+    fn test_porcelain_fields_and_order() {
+        let mut vulnerable = package("django");
+        vulnerable.security_status = SecurityStatus::Vulnerable { cve_count: 2 };
+        let mut unknown = package("private-pkg");
+        unknown.latest_version = None;
+        unknown.status = VersionStatus::Error;
+
+        let output = porcelain_report(&[package("requests"), vulnerable, unknown]);
+        let rows: Vec<Vec<&str>> = output.lines().map(|l| l.split('\t').collect()).collect();
+        assert!(rows.iter().all(|row| row.len() == 5));
+        assert_eq!(rows[0], vec!["requests", "1.0.0", "1.1.0", "minor", "unknown"]);
+        assert_eq!(rows[1], vec!["django", "1.0.0", "1.1.0", "minor", "vulnerable"]);
+        assert_eq!(rows[2], vec!["private-pkg", "1.0.0", "-", "error", "unknown"]);
+        assert!(output.is_ascii());
+    }
+
+    #[test]
+    fn test_severity_tally_matches_advisories() {
+        let advisory = |id: &str, severity: Severity| SecurityAdvisory {
+            id: id.to_string(),
+            aliases: Vec::new(),
+            title: String::new(),
+            severity,
+            affected_versions: Vec::new(),
+            fixed_version: None,
+            url: String::new(),
+        };
+        let mut django = package("django");
+        django.advisories = vec![
+            advisory("GHSA-1", Severity::Critical),
+            advisory("GHSA-2", Severity::High),
+            advisory("GHSA-3", Severity::Low),
+        ];
+        django.security_status = SecurityStatus::Vulnerable { cve_count: 3 };
+        let mut pyyaml = package("pyyaml");
+        pyyaml.advisories = vec![
+            advisory("GHSA-4", Severity::High),
+            advisory("GHSA-5", Severity::Low),
+            advisory("GHSA-6", Severity::Low),
+        ];
+        pyyaml.security_status = SecurityStatus::Vulnerable { cve_count: 3 };
+        let packages = vec![django, pyyaml, package("requests")];
+
+        let advisories = packages.iter().flat_map(|p| &p.advisories);
+        assert_eq!(
+            severity_counts(advisories),
+            [(Severity::Critical, 1), (Severity::High, 2), (Severity::Medium, 0), (Severity::Low, 3)]
+        );
+        let report = check_report(&packages, &UpgradeStats::new(&packages), &[], ReportOptions::default());
+        assert!(report.contains("Critical: 1, High: 2, Medium: 0, Low: 3"));
+    }
+
+    #[test]
+    fn test_color_disabled_for_non_terminal_target() {
+        let file = tempfile::tempfile().unwrap();
+        assert!(!color_enabled(&file));
+
+        let packages = vec![package("requests")];
+        let stats = UpgradeStats::new(&packages);
+        let options = ReportOptions { quiet: true, color: color_enabled(&file), ..Default::default() };
+        assert!(!check_report(&packages, &stats, &[], options).contains('\x1b'));
+
+        let colored_report =
+            check_report(&packages, &stats, &[], ReportOptions { quiet: true, color: true, ..Default::default() });
+        assert!(colored_report.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_report_schema_declares_top_level_properties() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&CheckReport::json_schema().unwrap()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        assert!(properties.contains_key("packages"));
+        assert!(properties.contains_key("stats"));
+
+        let packages = vec![package("requests")];
+        let report: serde_json::Value =
+            serde_json::from_str(&CheckReport::new("requirements.txt", &packages, &[]).to_json().unwrap())
+                .unwrap();
+        for key in properties.keys() {
+            assert!(report.get(key).is_some(), "report is missing {}", key);
+        }
+    }
+}
@@ -1,5 +1,12 @@
-use crate::models::Package;
+use crate::models::{
+    compare_release, compare_versions, normalize_package_name, release_parts, Package,
+    ResolutionStrategy, SecurityAdvisory, Severity, VersionConstraint,
+};
+use crate::pypi::PyPIClient;
+use crate::security::SecurityChecker;
+use chrono::{DateTime, Utc};
 use petgraph::graph::{DiGraph, NodeIndex};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 pub struct DependencyResolver {
@@ -7,12 +14,15 @@ pub struct DependencyResolver {
     nodes: HashMap<String, NodeIndex>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, schemars::JsonSchema)]
 pub struct Conflict {
     pub package: String,
     pub reason: String,
     pub current: String,
     pub required: String,
+    /// `High` when the constraints can't be satisfied at all, `Medium` when an
+    /// upgrade merely risks breaking a dependent.
+    pub severity: Severity,
 }
 
 impl DependencyResolver {
@@ -23,6 +33,20 @@ impl DependencyResolver {
         }
     }
 
+    /// Builds the graph from each package's known dependencies, keyed by
+    /// normalized name.
+    pub fn from_packages(packages: &[Package]) -> Self {
+        let mut resolver = Self::new();
+        for pkg in packages {
+            let name = normalize_package_name(&pkg.name);
+            resolver.add_package(&name);
+            for dep in &pkg.dependencies {
+                resolver.add_dependency(&name, &normalize_package_name(dep));
+            }
+        }
+        resolver
+    }
+
     pub fn add_package(&mut self, name: &str) {
         if !self.nodes.contains_key(name) {
             let node = self.graph.add_node(name.to_string());
@@ -55,6 +79,7 @@ impl DependencyResolver {
                                 ),
                                 current: dep_pkg.current_version.clone(),
                                 required: latest.clone(),
+                                severity: Severity::Medium,
                             });
                         }
                     }
@@ -65,6 +90,64 @@ impl DependencyResolver {
         conflicts
     }
 
+    pub fn validate_constraints(&self, packages: &[Package]) -> Vec<Conflict> {
+        let mut by_name: HashMap<String, Vec<&VersionConstraint>> = HashMap::new();
+        for pkg in packages {
+            by_name
+                .entry(normalize_package_name(&pkg.name))
+                .or_default()
+                .push(&pkg.constraint);
+        }
+
+        let mut names: Vec<_> = by_name.keys().cloned().collect();
+        names.sort();
+
+        let mut conflicts = Vec::new();
+        for name in names {
+            let constraints = &by_name[&name];
+            let mut lower: Option<Bound> = None;
+            let mut upper: Option<Bound> = None;
+
+            for constraint in constraints {
+                let (low, high) = constraint_bounds(constraint);
+                if let Some(low) = low {
+                    if lower.as_ref().is_none_or(|l| low.is_tighter_lower_than(l)) {
+                        lower = Some(low);
+                    }
+                }
+                if let Some(high) = high {
+                    if upper.as_ref().is_none_or(|u| high.is_tighter_upper_than(u)) {
+                        upper = Some(high);
+                    }
+                }
+            }
+
+            if let (Some(low), Some(high)) = (&lower, &upper) {
+                let satisfiable = match compare_release(&low.version, &high.version) {
+                    Ordering::Less => true,
+                    Ordering::Equal => low.inclusive && high.inclusive,
+                    Ordering::Greater => false,
+                };
+
+                if !satisfiable {
+                    let specs: Vec<String> = constraints.iter().map(|c| c.as_str()).collect();
+                    conflicts.push(Conflict {
+                        package: name.clone(),
+                        reason: format!(
+                            "Constraints {} cannot be satisfied together",
+                            specs.join(", ")
+                        ),
+                        current: low.spec.clone(),
+                        required: high.spec.clone(),
+                        severity: Severity::High,
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+
     pub fn get_dependents(&self, package: &str) -> Vec<String> {
         if let Some(&node_idx) = self.nodes.get(package) {
             self.graph
@@ -76,6 +159,24 @@ impl DependencyResolver {
         }
     }
 
+    /// How many packages depend on `package`, directly or through others:
+    /// the breakage surface of upgrading it.
+    pub fn impact(&self, package: &str) -> usize {
+        let Some(&start) = self.nodes.get(package) else {
+            return 0;
+        };
+        let mut seen = std::collections::HashSet::from([start]);
+        let mut frontier = vec![start];
+        while let Some(node) = frontier.pop() {
+            for dependent in self.graph.neighbors_directed(node, petgraph::Direction::Incoming) {
+                if seen.insert(dependent) {
+                    frontier.push(dependent);
+                }
+            }
+        }
+        seen.len() - 1
+    }
+
     pub fn get_dependencies(&self, package: &str) -> Vec<String> {
         if let Some(&node_idx) = self.nodes.get(package) {
             self.graph
@@ -88,8 +189,381 @@ impl DependencyResolver {
     }
 }
 
+pub fn select_target_version(
+    strategy: ResolutionStrategy,
+    pkg: &Package,
+    releases: &[String],
+    advisories: &[SecurityAdvisory],
+) -> Option<String> {
+    let newer = releases
+        .iter()
+        .filter(|r| is_final_release(r) && compare_release(r, &pkg.current_version).is_gt());
+
+    match strategy {
+        ResolutionStrategy::Latest => pkg.latest_version.clone(),
+        ResolutionStrategy::MinimalSecure => {
+            let required = advisories
+                .iter()
+                .filter_map(|a| a.fixed_version.as_deref())
+                .max_by(|a, b| compare_release(a, b))?;
+            newer
+                .filter(|r| compare_release(r, required).is_ge())
+                .min_by(|a, b| compare_release(a, b))
+                .cloned()
+        }
+        ResolutionStrategy::CompatibleOnly => {
+            let major = release_parts(&pkg.current_version).first().copied().unwrap_or(0);
+            newer
+                .filter(|r| release_parts(r).first().copied().unwrap_or(0) == major)
+                .max_by(|a, b| compare_release(a, b))
+                .cloned()
+        }
+    }
+}
+
+/// The newest final release the package's own constraint still allows.
+/// A pin is the thing being rewritten, so it doesn't cap the target; `None`
+/// means nothing newer fits.
+pub fn latest_within_constraint(pkg: &Package, releases: &[String]) -> Option<String> {
+    let upper = match pkg.constraint {
+        VersionConstraint::Pinned(_) => None,
+        ref constraint => constraint_bounds(constraint).1,
+    };
+    releases
+        .iter()
+        .filter(|r| is_final_release(r) && compare_release(r, &pkg.current_version).is_gt())
+        .filter(|r| {
+            upper.as_ref().is_none_or(|upper| match compare_release(r, &upper.version) {
+                Ordering::Less => true,
+                Ordering::Equal => upper.inclusive,
+                Ordering::Greater => false,
+            })
+        })
+        .max_by(|a, b| compare_release(a, b))
+        .cloned()
+}
+
+/// The newest final release `pkg`'s constraint allows, where a pin only
+/// allows itself; `None` when no known release fits.
+pub fn newest_allowed(pkg: &Package, releases: &[String]) -> Option<String> {
+    let spec = pkg.constraint.as_str();
+    releases
+        .iter()
+        .filter(|r| is_final_release(r) && crate::version::specifier_allows(&spec, r))
+        .max_by(|a, b| compare_release(a, b))
+        .cloned()
+}
+
+pub fn apply_strategy(
+    strategy: ResolutionStrategy,
+    packages: &mut [Package],
+    pypi_client: &PyPIClient,
+    security_checker: &SecurityChecker,
+) {
+    if strategy == ResolutionStrategy::Latest {
+        return;
+    }
+
+    for pkg in packages.iter_mut().filter(|p| p.latest_version.is_some()) {
+        let releases = pypi_client.releases(&pkg.name);
+        let advisories = security_checker.advisories(&pkg.name);
+        let target = select_target_version(strategy, pkg, &releases, &advisories)
+            .unwrap_or_else(|| pkg.current_version.clone());
+        pkg.status = compare_versions(&pkg.current_version, &target);
+        pkg.target_version = Some(target);
+    }
+}
+
+/// The newest final release no newer than `ceiling` that was uploaded at
+/// or before `cutoff`.
+pub fn newest_settled_release(
+    releases: &[(String, DateTime<Utc>)],
+    ceiling: &str,
+    cutoff: DateTime<Utc>,
+) -> Option<String> {
+    releases
+        .iter()
+        .filter(|(version, uploaded)| {
+            is_final_release(version) && *uploaded <= cutoff && compare_release(version, ceiling).is_le()
+        })
+        .map(|(version, _)| version)
+        .max_by(|a, b| compare_release(a, b))
+        .cloned()
+}
+
+/// Holds each upgrade target back to a release that has been out for at
+/// least `min_age`, since brand-new releases are the likeliest to be yanked
+/// or patched. Targets whose upload date is unknown are left alone.
+pub fn apply_min_age(
+    packages: &mut [Package],
+    pypi_client: &PyPIClient,
+    min_age: chrono::Duration,
+    now: DateTime<Utc>,
+) {
+    let cutoff = now - min_age;
+    for pkg in packages.iter_mut() {
+        let Some(target) = pkg.upgrade_target().map(str::to_string) else {
+            continue;
+        };
+        let dates = pypi_client.release_dates(&pkg.name);
+        let too_new = dates.iter().any(|(version, uploaded)| *version == target && *uploaded > cutoff);
+        if !too_new {
+            continue;
+        }
+
+        let settled = newest_settled_release(&dates, &target, cutoff)
+            .filter(|v| compare_release(v, &pkg.current_version).is_gt());
+        match settled {
+            Some(settled) => {
+                pkg.notes.push(format!("{} is under {} day(s) old", target, min_age.num_days()));
+                pkg.status = compare_versions(&pkg.current_version, &settled);
+                pkg.target_version = Some(settled);
+            }
+            None => {
+                // Keep the status so the pending update stays visible; only the
+                // target is held at the current version.
+                pkg.notes.push(format!(
+                    "no release after {} is {} day(s) old yet",
+                    pkg.current_version,
+                    min_age.num_days()
+                ));
+                pkg.target_version = Some(pkg.current_version.clone());
+            }
+        }
+    }
+}
+
+fn is_final_release(version: &str) -> bool {
+    version.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+struct Bound {
+    version: String,
+    inclusive: bool,
+    spec: String,
+}
+
+impl Bound {
+    fn new(version: &str, inclusive: bool, spec: String) -> Self {
+        Self {
+            version: version.to_string(),
+            inclusive,
+            spec,
+        }
+    }
+
+    fn is_tighter_lower_than(&self, other: &Bound) -> bool {
+        match compare_release(&self.version, &other.version) {
+            Ordering::Greater => true,
+            Ordering::Equal => !self.inclusive && other.inclusive,
+            Ordering::Less => false,
+        }
+    }
+
+    fn is_tighter_upper_than(&self, other: &Bound) -> bool {
+        match compare_release(&self.version, &other.version) {
+            Ordering::Less => true,
+            Ordering::Equal => !self.inclusive && other.inclusive,
+            Ordering::Greater => false,
+        }
+    }
+}
+
+fn constraint_bounds(constraint: &VersionConstraint) -> (Option<Bound>, Option<Bound>) {
+    let spec = constraint.as_str();
+    match constraint {
+        VersionConstraint::Pinned(v) => (
+            Some(Bound::new(v, true, spec.clone())),
+            Some(Bound::new(v, true, spec)),
+        ),
+        VersionConstraint::GreaterEqual(v) => (Some(Bound::new(v, true, spec)), None),
+        VersionConstraint::Less(v) => (None, Some(Bound::new(v, false, spec))),
+        VersionConstraint::Range(low, high) => (
+            Some(Bound::new(low, true, spec.clone())),
+            Some(Bound::new(high, false, spec)),
+        ),
+        VersionConstraint::Compatible(v) => {
+            let mut parts = release_parts(v);
+            if parts.len() > 1 {
+                parts.pop();
+            }
+            if let Some(last) = parts.last_mut() {
+                *last += 1;
+            }
+            let ceiling = parts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(".");
+            (
+                Some(Bound::new(v, true, spec.clone())),
+                Some(Bound::new(&ceiling, false, spec)),
+            )
+        }
+        VersionConstraint::Unspecified => (None, None),
+    }
+}
+
 impl Default for DependencyResolver {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GlobalOption, VersionStatus};
+    use crate::test_support::{MockResponse, MockServer};
+
+
+    fn package(name: &str, constraint: VersionConstraint) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: "0.0.0".to_string(),
+            constraint,
+            ..Package::default()
+        }
+    }
+
+    #[test]
+    fn test_impact_counts_reverse_dependents() {
+        let mut django = package("django", VersionConstraint::Pinned("3.2.0".to_string()));
+        django.status = VersionStatus::Major;
+        django.selected = true;
+        let mut packages = vec![django];
+        for name in ["djangorestframework", "django-filter", "django-cors-headers"] {
+            let mut dependent = package(name, VersionConstraint::Unspecified);
+            dependent.dependencies = vec!["Django".to_string()];
+            packages.push(dependent);
+        }
+
+        let resolver = DependencyResolver::from_packages(&packages);
+        assert_eq!(resolver.impact("django"), 3);
+        assert_eq!(resolver.impact("django-filter"), 0);
+
+        // Alongside two patch upgrades, one major would be medium risk; three
+        // dependents on it make it high.
+        for pkg in &mut packages[1..] {
+            pkg.status = VersionStatus::Patch;
+            pkg.selected = true;
+        }
+        packages[3].selected = false;
+        let simulation = crate::simulator::UpgradeSimulator::new().simulate_upgrade(&packages);
+        assert_eq!(simulation.impacts, vec![("django".to_string(), 3)]);
+        assert_eq!(simulation.risk_level, crate::models::RiskLevel::High);
+    }
+
+    #[test]
+    fn test_contradictory_constraints_are_reported() {
+        let packages = vec![
+            package("Django", VersionConstraint::GreaterEqual("2.0".to_string())),
+            package("django", VersionConstraint::Less("1.5".to_string())),
+            package("requests", VersionConstraint::Pinned("2.28.1".to_string())),
+        ];
+
+        let conflicts = DependencyResolver::new().validate_constraints(&packages);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].package, "django");
+        assert_eq!(conflicts[0].current, ">=2.0");
+        assert_eq!(conflicts[0].required, "<1.5");
+    }
+
+    fn advisory(id: &str, fixed: &str) -> SecurityAdvisory {
+        SecurityAdvisory {
+            id: id.to_string(),
+            title: "test advisory".to_string(),
+            severity: crate::models::Severity::High,
+            affected_versions: vec![],
+            fixed_version: Some(fixed.to_string()),
+            url: format!("https://osv.dev/{}", id),
+            aliases: Vec::new(),
+        }
+    }
+
+    fn versions(list: &[&str]) -> Vec<String> {
+        list.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_minimal_secure_picks_first_fixed_release() {
+        let mut pkg = package("urllib3", VersionConstraint::Pinned("1.4.0".to_string()));
+        pkg.current_version = "1.4.0".to_string();
+        pkg.latest_version = Some("2.1.0".to_string());
+        let releases = versions(&["1.4.0", "1.4.1", "1.4.2", "1.5.0", "2.0.0rc1", "2.1.0"]);
+        let advisories = vec![advisory("CVE-2023-0001", "1.4.2"), advisory("CVE-2023-0002", "1.4.1")];
+
+        let pick = |strategy| select_target_version(strategy, &pkg, &releases, &advisories);
+        assert_eq!(pick(ResolutionStrategy::MinimalSecure).as_deref(), Some("1.4.2"));
+        assert_eq!(pick(ResolutionStrategy::CompatibleOnly).as_deref(), Some("1.5.0"));
+        assert_eq!(pick(ResolutionStrategy::Latest).as_deref(), Some("2.1.0"));
+        assert_eq!(
+            select_target_version(ResolutionStrategy::MinimalSecure, &pkg, &releases, &[]),
+            None
+        );
+
+        let unfixed = vec![advisory("CVE-2023-0003", "3.0.0")];
+        assert_eq!(
+            select_target_version(ResolutionStrategy::MinimalSecure, &pkg, &releases, &unfixed),
+            None
+        );
+    }
+
+    #[test]
+    fn test_min_age_skips_releases_that_are_too_new() {
+        let now = Utc::now();
+        let days_ago = |days| now - chrono::Duration::days(days);
+        let releases = vec![
+            ("2.0.0".to_string(), days_ago(1)),
+            ("1.9.0".to_string(), days_ago(20)),
+            ("1.9.1rc1".to_string(), days_ago(10)),
+            ("1.8.0".to_string(), days_ago(90)),
+        ];
+
+        let cutoff = now - chrono::Duration::days(7);
+        assert_eq!(newest_settled_release(&releases, "2.0.0", cutoff).as_deref(), Some("1.9.0"));
+        assert_eq!(newest_settled_release(&releases, "1.8.0", cutoff).as_deref(), Some("1.8.0"));
+        assert_eq!(newest_settled_release(&releases, "2.0.0", now).as_deref(), Some("2.0.0"));
+    }
+
+    #[tokio::test]
+    async fn test_apply_min_age_holds_targets_back() {
+        let now = Utc::now();
+        let uploaded = |days: i64| (now - chrono::Duration::days(days)).to_rfc3339();
+        let index = |name: &str, latest: &str, releases: &[(&str, i64)]| {
+            let releases: Vec<String> = releases
+                .iter()
+                .map(|(v, days)| format!(r#""{v}": [{{"filename": "{name}-{v}.tar.gz", "upload_time_iso_8601": "{}"}}]"#, uploaded(*days)))
+                .collect();
+            MockResponse::json(format!(
+                r#"{{"info": {{"name": "{name}", "version": "{latest}", "summary": ""}}, "releases": {{{}}}}}"#,
+                releases.join(", ")
+            ))
+        };
+        let server = MockServer::routes(&[
+            ("/pypi/django/json", index("django", "5.0", &[("4.2.0", 300), ("4.2.5", 30), ("5.0", 1)])),
+            ("/pypi/celery/json", index("celery", "5.4.0", &[("5.3.0", 300), ("5.4.0", 2)])),
+        ]);
+        let mut client = PyPIClient::new();
+        client.apply_options(&[GlobalOption::IndexUrl(server.url("/pypi"))]);
+        let mut packages = vec![
+            Package { current_version: "4.2.0".to_string(), ..package("django", VersionConstraint::Unspecified) },
+            Package { current_version: "5.3.0".to_string(), ..package("celery", VersionConstraint::Unspecified) },
+        ];
+        client.update_packages(&mut packages).await;
+
+        apply_min_age(&mut packages, &client, chrono::Duration::days(7), now);
+        assert_eq!(packages[0].target_version.as_deref(), Some("4.2.5"));
+        assert_eq!(packages[0].status, VersionStatus::Patch);
+        assert_eq!(packages[1].target_version.as_deref(), Some("5.3.0"));
+        assert_eq!(packages[1].status, VersionStatus::Minor);
+        assert_eq!(packages[1].notes, vec!["no release after 5.3.0 is 7 day(s) old yet".to_string()]);
+    }
+
+    #[test]
+    fn test_compatible_constraints_are_satisfiable() {
+        let packages = vec![
+            package("flask", VersionConstraint::GreaterEqual("2.0".to_string())),
+            package("flask", VersionConstraint::Compatible("2.3".to_string())),
+            package("flask", VersionConstraint::Pinned("2.3.2".to_string())),
+        ];
+
+        assert!(DependencyResolver::new().validate_constraints(&packages).is_empty());
+    }
+}
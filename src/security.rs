@@ -1,49 +1,188 @@
-use crate::models::{Package, SecurityAdvisory, SecurityStatus, Severity};
-use anyhow::Result;
+use crate::models::{compare_release, Package, SecurityAdvisory, SecurityStatus, Severity};
+use crate::error::{PyElevateError, Result};
+use crate::cache;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 const OSV_API: &str = "https://api.osv.dev/v1/query";
 
+/// One advisory against one requirement, as listed by `audit`.
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+    pub package: String,
+    pub version: String,
+    pub advisory: SecurityAdvisory,
+    /// On the ignore list: reported, but never fails the audit.
+    pub ignored: bool,
+}
+
+/// Whether `audit` should fail: any finding at or above `threshold` that
+/// isn't ignored.
+pub fn exceeds_threshold(findings: &[AuditFinding], threshold: Severity) -> bool {
+    findings.iter().any(|f| !f.ignored && f.advisory.severity.at_least(threshold))
+}
+
+/// Whether `audit` passes: nothing reached `threshold`, and every package
+/// could be checked unless `allow_lookup_failures` is set.
+pub fn audit_passed(
+    findings: &[AuditFinding],
+    failed: &[String],
+    threshold: Severity,
+    allow_lookup_failures: bool,
+) -> bool {
+    !exceeds_threshold(findings, threshold) && (allow_lookup_failures || failed.is_empty())
+}
+
+/// Clones share the HTTP client and the advisory cache.
+#[derive(Clone)]
 pub struct SecurityChecker {
     client: Client,
-    cache: HashMap<String, Vec<SecurityAdvisory>>,
+    cache: cache::SharedLru<String, Vec<SecurityAdvisory>>,
+    api_url: String,
+    max_body_bytes: usize,
+    ignored: Vec<String>,
+    min_severity: Severity,
 }
 
+/// Starts the note listing advisories below the minimum severity, which
+/// are shown but don't make the package vulnerable.
+pub const BELOW_THRESHOLD_NOTE: &str = "Informational:";
+
 impl SecurityChecker {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
-            cache: HashMap::new(),
+            cache: cache::bounded(cache::DEFAULT_CAPACITY),
+            api_url: OSV_API.to_string(),
+            max_body_bytes: crate::http::DEFAULT_MAX_BODY_BYTES,
+            ignored: Vec::new(),
+            min_severity: Severity::Low,
         }
     }
 
+    /// Caps every advisory response body.
+    pub fn with_max_body_bytes(mut self, limit: usize) -> Self {
+        self.max_body_bytes = limit;
+        self
+    }
+
+    pub fn with_api_url(mut self, url: &str) -> Self {
+        self.api_url = url.to_string();
+        self
+    }
+
+    /// Keeps advisories for at most `capacity` packages, evicting the least
+    /// recently checked.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = cache::bounded(capacity);
+        self
+    }
+
+    /// Advisory ids (GHSA, PYSEC or a CVE alias) that have been accepted and
+    /// shouldn't count against a package.
+    pub fn with_ignored(mut self, ids: &[String]) -> Self {
+        self.ignored = ids.to_vec();
+        self
+    }
+
+    /// Advisories less severe than `severity` are kept for display but no
+    /// longer mark the package vulnerable.
+    pub fn with_min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = severity;
+        self
+    }
+
+    pub fn is_ignored(&self, advisory: &SecurityAdvisory) -> bool {
+        self.ignored.iter().any(|id| advisory.matches_id(id))
+    }
+
     pub async fn check_package(&mut self, pkg: &mut Package) -> Result<()> {
-        if !matches!(pkg.source, crate::models::DependencySource::PyPI) {
+        if !matches!(pkg.source, crate::models::DependencySource::PyPI) || pkg.policy.ignore_security {
             return Ok(());
         }
 
-        if let Some(cached) = self.cache.get(&pkg.name) {
-            pkg.security_status = if cached.is_empty() {
-                SecurityStatus::Safe
-            } else {
-                SecurityStatus::Vulnerable { cve_count: cached.len() }
-            };
-            return Ok(());
+        let cached = self.cache.lock().get(&pkg.name).is_some();
+        if !cached {
+            let advisories = self.fetch_advisories(&pkg.name, &pkg.current_version).await?;
+            self.cache.lock().put(pkg.name.clone(), advisories);
         }
 
-        let advisories = self.fetch_advisories(&pkg.name, &pkg.current_version).await?;
-        
-        pkg.security_status = if advisories.is_empty() {
+        let (ignored, advisories): (Vec<_>, Vec<_>) =
+            self.advisories(&pkg.name).into_iter().partition(|a| self.is_ignored(a));
+        let counted = advisories.iter().filter(|a| a.severity.at_least(self.min_severity)).count();
+        pkg.security_status = if counted == 0 {
             SecurityStatus::Safe
         } else {
-            SecurityStatus::Vulnerable { cve_count: advisories.len() }
+            SecurityStatus::Vulnerable { cve_count: counted }
         };
-
-        self.cache.insert(pkg.name.clone(), advisories);
+        pkg.notes.retain(|note| !note.starts_with(BELOW_THRESHOLD_NOTE));
+        if counted < advisories.len() {
+            pkg.notes.push(format!(
+                "{} {} advisory(ies) below {} severity",
+                BELOW_THRESHOLD_NOTE,
+                advisories.len() - counted,
+                self.min_severity.as_str()
+            ));
+        }
+        pkg.advisories = advisories;
+        pkg.ignored_advisories = ignored;
         Ok(())
     }
 
+    /// Drops the cached advisories for `package`, so the next check queries again.
+    pub fn forget(&self, package: &str) {
+        self.cache.lock().pop(package);
+    }
+
+    pub fn advisories(&self, package: &str) -> Vec<SecurityAdvisory> {
+        self.cache.lock().peek(package).cloned().unwrap_or_default()
+    }
+
+    /// Scans every PyPI requirement and lists its advisories. Lookups that
+    /// fail are returned by name so the caller can report them rather than
+    /// treat the package as clean.
+    pub async fn audit(&mut self, packages: &mut [Package]) -> (Vec<AuditFinding>, Vec<String>) {
+        let mut findings = Vec::new();
+        let mut failed = Vec::new();
+        for pkg in packages.iter_mut().filter(|p| !p.policy.ignore_security) {
+            if self.check_package(pkg).await.is_err() {
+                failed.push(pkg.name.clone());
+                continue;
+            }
+            findings.extend(self.advisories(&pkg.name).iter().map(|advisory| AuditFinding {
+                package: pkg.name.clone(),
+                version: pkg.current_version.clone(),
+                advisory: advisory.clone(),
+                ignored: self.is_ignored(advisory),
+            }));
+        }
+        (findings, failed)
+    }
+
+    /// Counts the distinct CVEs whose fix is at or below each package's upgrade
+    /// target, i.e. the CVEs that upgrading would actually close. Ignored and
+    /// below-threshold advisories don't count.
+    pub fn fixable_cves<'a>(&self, packages: impl IntoIterator<Item = &'a Package>) -> usize {
+        let mut cves = HashSet::new();
+        for pkg in packages {
+            let Some(target) = pkg.upgrade_target() else {
+                continue;
+            };
+            cves.extend(
+                self.advisories(&pkg.name)
+                    .iter()
+                    .filter(|a| !self.is_ignored(a) && a.severity.at_least(self.min_severity))
+                    .filter(|a| {
+                        a.fixed_version
+                            .as_deref()
+                            .is_some_and(|fixed| compare_release(target, fixed).is_ge())
+                    })
+                    .map(|a| a.cve_id().to_string()),
+            );
+        }
+        cves.len()
+    }
+
     async fn fetch_advisories(&self, package: &str, version: &str) -> Result<Vec<SecurityAdvisory>> {
         let query = serde_json::json!({
             "package": {
@@ -53,48 +192,284 @@ impl SecurityChecker {
             "version": version
         });
 
-        match self.client.post(OSV_API).json(&query).send().await {
-            Ok(response) => {
-                if let Ok(data) = response.json::<serde_json::Value>().await {
-                    if let Some(vulns) = data.get("vulns").and_then(|v| v.as_array()) {
-                        let advisories = vulns
-                            .iter()
-                            .filter_map(|v| {
-                                let id = v.get("id")?.as_str()?;
-                                let summary = v.get("summary")?.as_str()?;
-                                let severity_str = v
-                                    .get("severity")
-                                    .and_then(|s| s.as_str())
-                                    .unwrap_or("MEDIUM");
-
-                                Some(SecurityAdvisory {
-                                    id: id.to_string(),
-                                    title: summary.to_string(),
-                                    severity: match severity_str {
-                                        "CRITICAL" => Severity::Critical,
-                                        "HIGH" => Severity::High,
-                                        "MEDIUM" => Severity::Medium,
-                                        _ => Severity::Low,
-                                    },
-                                    affected_versions: Vec::new(),
-                                    fixed_version: None,
-                                    url: format!("https://osv.dev/{}", id),
-                                })
-                            })
-                            .collect();
-                        return Ok(advisories);
-                    }
-                }
-            }
-            Err(_) => {}
+        let response = self.client.post(&self.api_url).json(&query).send().await?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(PyElevateError::RateLimited(self.api_url.clone()));
         }
+        let data: serde_json::Value =
+            crate::http::read_json(response.error_for_status()?, self.max_body_bytes).await?;
+
+        let Some(vulns) = data.get("vulns").and_then(|v| v.as_array()) else {
+            return Ok(Vec::new());
+        };
+        let advisories = vulns
+            .iter()
+            .filter(|v| !is_withdrawn(v))
+            .filter_map(|v| {
+                let id = v.get("id")?.as_str()?;
+                let summary = v.get("summary")?.as_str()?;
+                // GHSA-sourced entries carry a textual rating here; OSV's own
+                // `severity` field is a list of CVSS vectors.
+                let severity_str = v
+                    .pointer("/database_specific/severity")
+                    .or_else(|| v.get("severity"))
+                    .and_then(|s| s.as_str())
+                    .unwrap_or("MEDIUM");
 
-        Ok(Vec::new())
+                Some(SecurityAdvisory {
+                    id: id.to_string(),
+                    title: summary.to_string(),
+                    severity: match severity_str {
+                        "CRITICAL" => Severity::Critical,
+                        "HIGH" => Severity::High,
+                        "MEDIUM" | "MODERATE" => Severity::Medium,
+                        _ => Severity::Low,
+                    },
+                    affected_versions: Vec::new(),
+                    fixed_version: fixed_version_for(v, version),
+                    url: format!("https://osv.dev/{}", id),
+                    aliases: v
+                        .get("aliases")
+                        .and_then(|a| a.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|a| Some(a.as_str()?.to_string()))
+                        .collect(),
+                })
+            })
+            .collect();
+        Ok(advisories)
     }
 }
 
+/// OSV keeps withdrawn entries queryable, with a `withdrawn` timestamp;
+/// one that has passed means the advisory no longer stands.
+fn is_withdrawn(vuln: &serde_json::Value) -> bool {
+    vuln.get("withdrawn")
+        .and_then(|w| w.as_str())
+        .and_then(|w| chrono::DateTime::parse_from_rfc3339(w).ok())
+        .is_some_and(|at| at <= chrono::Utc::now())
+}
+
+fn fixed_version_for(vuln: &serde_json::Value, current: &str) -> Option<String> {
+    vuln.get("affected")?
+        .as_array()?
+        .iter()
+        .filter_map(|affected| affected.get("ranges")?.as_array())
+        .flatten()
+        .filter_map(|range| range.get("events")?.as_array())
+        .flatten()
+        .filter_map(|event| event.get("fixed")?.as_str())
+        .filter(|fixed| compare_release(fixed, current).is_gt())
+        .min_by(|a, b| compare_release(a, b))
+        .map(|fixed| fixed.to_string())
+}
+
 impl Default for SecurityChecker {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{VersionConstraint, VersionStatus};
+    use crate::test_support::{MockResponse, MockServer};
+
+    fn package(name: &str, current: &str, latest: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            current_version: current.to_string(),
+            latest_version: Some(latest.to_string()),
+            constraint: VersionConstraint::Pinned(current.to_string()),
+            status: VersionStatus::Minor,
+            ..Package::default()
+        }
+    }
+
+    fn advisory(id: &str, fixed: Option<&str>) -> SecurityAdvisory {
+        SecurityAdvisory {
+            id: id.to_string(),
+            aliases: Vec::new(),
+            title: id.to_string(),
+            severity: Severity::High,
+            affected_versions: Vec::new(),
+            fixed_version: fixed.map(|f| f.to_string()),
+            url: format!("https://osv.dev/{}", id),
+        }
+    }
+
+    #[test]
+    fn test_fixable_cves_sums_across_packages() {
+        let checker = SecurityChecker::new();
+        checker.cache.lock().put(
+            "django".to_string(),
+            vec![advisory("CVE-1", Some("4.2.1")), advisory("CVE-2", Some("4.2.5"))],
+        );
+        checker.cache.lock().put(
+            "requests".to_string(),
+            vec![advisory("CVE-3", Some("2.31.0")), advisory("CVE-4", None)],
+        );
+        checker
+            .cache
+            .lock()
+            .put("flask".to_string(), vec![advisory("CVE-5", Some("3.0.0"))]);
+
+        let packages = vec![
+            package("django", "4.2.0", "4.2.7"),
+            package("requests", "2.28.0", "2.31.0"),
+            package("flask", "2.0.0", "2.3.3"),
+        ];
+        assert_eq!(checker.fixable_cves(&packages), 3);
+
+        // A GHSA and a PYSEC entry for the same CVE count once.
+        let ghsa = SecurityAdvisory {
+            aliases: vec!["CVE-1".to_string()],
+            ..advisory("GHSA-1", Some("4.2.1"))
+        };
+        checker.cache.lock().get_mut("django").unwrap().push(ghsa);
+        assert_eq!(checker.fixable_cves(&packages), 3);
+    }
+
+    #[tokio::test]
+    async fn test_check_package_against_stubbed_osv() {
+        let server = MockServer::start(|request| {
+            assert_eq!(request.method, "POST");
+            if !request.body.contains(r#""name":"jinja2""#) {
+                return MockResponse::json("{}");
+            }
+            MockResponse::json(
+                r#"{"vulns": [{
+                    "id": "GHSA-h5c8-rqwp-cp95",
+                    "summary": "Jinja vulnerable to HTML attribute injection",
+                    "affected": [{"ranges": [{"events": [{"introduced": "0"}, {"fixed": "3.1.3"}]}]}]
+                }]}"#,
+            )
+        });
+        let mut checker = SecurityChecker::new().with_api_url(&server.url("/v1/query"));
+
+        let mut jinja = package("jinja2", "3.1.2", "3.1.4");
+        checker.check_package(&mut jinja).await.unwrap();
+        assert_eq!(jinja.security_status, SecurityStatus::Vulnerable { cve_count: 1 });
+        assert_eq!(checker.advisories("jinja2")[0].fixed_version.as_deref(), Some("3.1.3"));
+
+        let mut flask = package("flask", "3.0.0", "3.0.0");
+        checker.check_package(&mut flask).await.unwrap();
+        assert_eq!(flask.security_status, SecurityStatus::Safe);
+        assert_eq!(server.hits("/v1/query"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_withdrawn_advisory_does_not_count() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"vulns": [
+                    {"id": "GHSA-active", "summary": "Still applies"},
+                    {"id": "GHSA-withdrawn", "summary": "Was a false positive",
+                     "withdrawn": "2024-02-01T00:00:00Z"}
+                ]}"#,
+            )
+        });
+        let mut checker = SecurityChecker::new().with_api_url(&server.url("/v1/query"));
+
+        let mut pkg = package("jinja2", "3.1.2", "3.1.4");
+        checker.check_package(&mut pkg).await.unwrap();
+        assert_eq!(pkg.security_status, SecurityStatus::Vulnerable { cve_count: 1 });
+        let ids: Vec<&str> = pkg.advisories.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["GHSA-active"]);
+    }
+
+    #[tokio::test]
+    async fn test_ignored_advisory_is_reported_but_not_vulnerable() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"vulns": [{
+                    "id": "GHSA-h5c8-rqwp-cp95",
+                    "aliases": ["CVE-2024-22195"],
+                    "summary": "Jinja vulnerable to HTML attribute injection",
+                    "database_specific": {"severity": "HIGH"}
+                }]}"#,
+            )
+        });
+        let mut checker = SecurityChecker::new()
+            .with_api_url(&server.url("/v1/query"))
+            .with_ignored(&["cve-2024-22195".to_string()]);
+
+        let mut packages = vec![package("jinja2", "3.1.2", "3.1.4")];
+        let (findings, failed) = checker.audit(&mut packages).await;
+        assert!(failed.is_empty());
+        assert_eq!(packages[0].security_status, SecurityStatus::Safe);
+        assert!(packages[0].advisories.is_empty());
+        assert_eq!(packages[0].ignored_advisories[0].id, "GHSA-h5c8-rqwp-cp95");
+        assert!(findings[0].ignored);
+        assert!(!exceeds_threshold(&findings, Severity::Low));
+
+        let report = crate::report::audit_report(&findings, &failed, Default::default());
+        assert!(report.starts_with("0 known vulnerabilities"));
+        assert!(report.contains("Ignored (1)\n  jinja2 3.1.2  GHSA-h5c8-rqwp-cp95"));
+    }
+
+    #[tokio::test]
+    async fn test_medium_advisory_under_high_threshold_is_informational() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                r#"{"vulns": [{
+                    "id": "GHSA-xxxx-yyyy-zzzz",
+                    "summary": "Open redirect",
+                    "database_specific": {"severity": "MODERATE"},
+                    "affected": [{"ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "4.2.5"}]}]}]
+                }]}"#,
+            )
+        });
+        let mut checker = SecurityChecker::new()
+            .with_api_url(&server.url("/v1/query"))
+            .with_min_severity(Severity::High);
+
+        let mut pkg = package("django", "4.2.0", "4.2.7");
+        checker.check_package(&mut pkg).await.unwrap();
+        assert_eq!(pkg.advisories[0].severity, Severity::Medium);
+        assert_eq!(pkg.security_status, SecurityStatus::Safe);
+        assert_eq!(pkg.notes, vec!["Informational: 1 advisory(ies) below HIGH severity"]);
+        assert_eq!(pkg.advisories[0].fixed_version.as_deref(), Some("4.2.5"));
+        assert_eq!(checker.fixable_cves([&pkg]), 0);
+
+        checker.check_package(&mut pkg).await.unwrap();
+        assert_eq!(pkg.notes.len(), 1);
+    }
+
+    #[test]
+    fn test_audit_threshold() {
+        let finding = |severity| AuditFinding {
+            package: "django".to_string(),
+            version: "4.2.0".to_string(),
+            advisory: SecurityAdvisory { severity, ..advisory("CVE-1", None) },
+            ignored: false,
+        };
+
+        assert!(exceeds_threshold(&[finding(Severity::Critical)], Severity::High));
+        assert!(!exceeds_threshold(&[finding(Severity::Low)], Severity::Medium));
+        assert!(exceeds_threshold(&[finding(Severity::Low)], Severity::Low));
+        assert!(!exceeds_threshold(&[], Severity::Low));
+    }
+
+    #[tokio::test]
+    async fn test_audit_fails_when_a_lookup_fails() {
+        let server = MockServer::start(|request| {
+            if request.body.contains(r#""name":"flask""#) {
+                MockResponse::status(500)
+            } else {
+                MockResponse::json("{}")
+            }
+        });
+        let mut checker = SecurityChecker::new().with_api_url(&server.url("/v1/query"));
+        let mut packages = vec![package("django", "4.2.0", "4.2.7"), package("flask", "2.0.0", "2.3.3")];
+
+        let (findings, failed) = checker.audit(&mut packages).await;
+        assert!(findings.is_empty());
+        assert_eq!(failed, vec!["flask".to_string()]);
+        assert!(!audit_passed(&findings, &failed, Severity::Low, false));
+        assert!(audit_passed(&findings, &failed, Severity::Low, true));
+        assert!(audit_passed(&findings, &[], Severity::Low, false));
+    }
+}
@@ -1,6 +1,12 @@
-use crate::models::{Package, UpgradeSimulation, RiskLevel, VersionStatus};
+use crate::models::{normalize_package_name, Package, UpgradeSimulation, RiskLevel, VersionStatus};
+use crate::glyphs::Glyphs;
+use crate::report::banner;
 use crate::resolver::DependencyResolver;
 
+/// A major upgrade of a package with at least this many dependents is
+/// treated as high risk on its own.
+pub const HIGH_IMPACT_DEPENDENTS: usize = 3;
+
 pub struct UpgradeSimulator {
     resolver: DependencyResolver,
 }
@@ -29,7 +35,22 @@ impl UpgradeSimulator {
 
         let conflicts = self.resolver.detect_conflicts(packages).len();
 
-        let risk_level = calculate_risk_level(major_changes, conflicts, security_fixes, packages_to_upgrade);
+        let graph = DependencyResolver::from_packages(packages);
+        let mut impacts: Vec<(String, usize)> = selected
+            .iter()
+            .map(|p| (p.name.clone(), graph.impact(&normalize_package_name(&p.name))))
+            .filter(|(_, impact)| *impact > 0)
+            .collect();
+        impacts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let high_impact_major = selected.iter().any(|p| {
+            p.status == VersionStatus::Major
+                && impacts.iter().any(|(name, impact)| *name == p.name && *impact >= HIGH_IMPACT_DEPENDENTS)
+        });
+
+        let mut risk_level = calculate_risk_level(major_changes, conflicts, security_fixes, packages_to_upgrade);
+        if high_impact_major && matches!(risk_level, RiskLevel::Low | RiskLevel::Medium) {
+            risk_level = RiskLevel::High;
+        }
 
         UpgradeSimulation {
             packages_to_upgrade,
@@ -37,51 +58,61 @@ impl UpgradeSimulator {
             conflicts_detected: conflicts,
             security_fixes,
             risk_level,
+            impacts,
         }
     }
 
-    pub fn generate_report(&self, packages: &[Package]) -> String {
+    /// Why the upgrade rates `Critical`: each conflict and each selected
+    /// major upgrade. Empty at lower risk levels.
+    pub fn critical_reasons(&self, packages: &[Package], g: &Glyphs) -> Vec<String> {
+        if self.simulate_upgrade(packages).risk_level != RiskLevel::Critical {
+            return Vec::new();
+        }
+        let conflicts = self
+            .resolver
+            .detect_conflicts(packages)
+            .into_iter()
+            .map(|c| format!("Conflict: {}: {}", c.package, c.reason));
+        let majors = packages
+            .iter()
+            .filter(|p| p.selected && p.status == VersionStatus::Major)
+            .map(|p| {
+                let target = p.upgrade_target().unwrap_or("?");
+                format!("Major: {} {} {} {}", p.name, p.current_version, g.arrow, target)
+            });
+        conflicts.chain(majors).collect()
+    }
+
+    pub fn generate_report(&self, packages: &[Package], g: &Glyphs) -> String {
         let simulation = self.simulate_upgrade(packages);
-        
-        let mut report = String::new();
-        report.push_str("╔════════════════════════════════════════╗\n");
-        report.push_str("║     UPGRADE SIMULATION REPORT          ║\n");
-        report.push_str("╚════════════════════════════════════════╝\n\n");
-
-        report.push_str(&format!(
-            "📦 Packages to upgrade:     {}\n",
-            simulation.packages_to_upgrade
-        ));
-        report.push_str(&format!(
-            "🔴 Major changes:           {}\n",
-            simulation.major_changes
-        ));
-        report.push_str(&format!(
-            "⚠️  Conflicts detected:      {}\n",
-            simulation.conflicts_detected
-        ));
-        report.push_str(&format!(
-            "🔒 Security fixes:          {}\n",
-            simulation.security_fixes
-        ));
-        report.push_str(&format!(
-            "📊 Overall Risk:            {}\n\n",
-            simulation.risk_level.as_str()
-        ));
+
+        let mut report = banner("UPGRADE SIMULATION REPORT", g);
+        report.push('\n');
+
+        let mut stat = |icon: &str, label: &str, value: &dyn std::fmt::Display| {
+            report.push_str(&format!("{} {:<26}{}\n", g.icon(icon), label, value));
+        };
+        stat(g.package, "Packages to upgrade:", &simulation.packages_to_upgrade);
+        stat(g.major, "Major changes:", &simulation.major_changes);
+        stat(g.warning, "Conflicts detected:", &simulation.conflicts_detected);
+        stat(g.lock, "Security fixes:", &simulation.security_fixes);
+        stat(g.chart, "Overall Risk:", &simulation.risk_level.as_str());
+        report.push('\n');
+        for (name, impact) in &simulation.impacts {
+            report.push_str(&format!("{} Impact: {} ({} dependent(s))\n", g.icon(g.impact), name, impact));
+        }
 
         report
     }
 }
 
-fn calculate_risk_level(major: usize, conflicts: usize, security: usize, total: usize) -> RiskLevel {
+fn calculate_risk_level(major: usize, conflicts: usize, _security: usize, total: usize) -> RiskLevel {
     if conflicts > 0 && major > 0 {
         RiskLevel::Critical
     } else if major > (total / 2) {
         RiskLevel::High
     } else if major > 0 || conflicts > 0 {
         RiskLevel::Medium
-    } else if security > 0 {
-        RiskLevel::Low
     } else {
         RiskLevel::Low
     }
@@ -1,4 +1,4 @@
-use crate::models::VersionStatus;
+use crate::models::{Health, Severity, VersionStatus};
 use ratatui::style::{Color, Modifier, Style};
 
 pub struct Theme {
@@ -117,3 +117,21 @@ pub fn status_color(status: VersionStatus) -> Color {
         VersionStatus::Unknown => Color::Gray,
     }
 }
+
+pub fn health_color(health: Health) -> Color {
+    match health {
+        Health::Good => Color::Green,
+        Health::Caution => Color::Yellow,
+        Health::Poor => Color::Red,
+        Health::Unknown => Color::DarkGray,
+    }
+}
+
+pub fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Critical => Color::Magenta,
+        Severity::High => Color::Red,
+        Severity::Medium => Color::Yellow,
+        Severity::Low => Color::Cyan,
+    }
+}
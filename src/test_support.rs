@@ -0,0 +1,132 @@
+//! A small HTTP stub server for tests. Clients are pointed at it through
+//! their configurable base URLs, so no test talks to PyPI or OSV.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self { status: 200, body: body.into() }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self { status, body: String::new() }
+    }
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// Serves every connection on its own thread, so handlers may block to
+/// simulate slow responses. Each response closes its connection.
+pub struct MockServer {
+    addr: SocketAddr,
+    hits: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl MockServer {
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hits = Arc::new(Mutex::new(HashMap::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let server_hits = hits.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let (handler, hits) = (handler.clone(), server_hits.clone());
+                std::thread::spawn(move || {
+                    let Some(request) = read_request(&mut stream) else { return };
+                    *hits.lock().entry(request.path.clone()).or_insert(0) += 1;
+                    let response = handler(&request);
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.status,
+                        reason(response.status),
+                        response.body.len(),
+                        response.body
+                    );
+                });
+            }
+        });
+
+        Self { addr, hits }
+    }
+
+    /// Answers `GET <path>` from a fixed table and 404s everything else.
+    pub fn routes(routes: &[(&str, MockResponse)]) -> Self {
+        let routes: HashMap<String, MockResponse> = routes
+            .iter()
+            .map(|(path, response)| (path.to_string(), response.clone()))
+            .collect();
+        Self::start(move |request| {
+            routes
+                .get(&request.path)
+                .cloned()
+                .unwrap_or_else(|| MockResponse::status(404))
+        })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn hits(&self, path: &str) -> usize {
+        self.hits.lock().get(path).copied().unwrap_or(0)
+    }
+}
+
+fn read_request(stream: &mut std::net::TcpStream) -> Option<MockRequest> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut content_length = 0;
+    let mut header = String::new();
+    while reader.read_line(&mut header).ok()? > 2 {
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+        header.clear();
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).ok()?;
+    Some(MockRequest {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
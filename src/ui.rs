@@ -1,11 +1,12 @@
-use crate::app::{App, AppMode};
+use crate::app::{App, AppMode, SortBy};
+use crate::freshness;
+use crate::glyphs::{self, Glyphs};
 use crate::panels;
 use crate::styles::{Styles, Theme};
-use crate::simulator::UpgradeSimulator;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
 
@@ -18,21 +19,36 @@ pub fn draw(f: &mut Frame, app: &App) {
         AppMode::Display => draw_main_multi_panel(f, app, &styles, &theme),
         AppMode::Search => draw_search_mode(f, app, &styles),
         AppMode::Confirm => draw_confirm(f, app, &styles),
-        AppMode::Upgrading => draw_upgrading(f, &styles),
+        AppMode::ConfirmRemove => {
+            draw_main_multi_panel(f, app, &styles, &theme);
+            draw_confirm_remove(f, app, &styles);
+        }
+        AppMode::Upgrading => draw_upgrading(f, app, &styles),
         AppMode::Done => draw_done(f, app, &styles),
         AppMode::GraphView => draw_graph_view(f, app, &styles),
         AppMode::ChangelogView => draw_changelog_detail(f, app, &styles),
+        AppMode::FreshnessView => draw_freshness_detail(f, app, &styles),
+        AppMode::PackageSearch => draw_package_search(f, app, &styles),
+        AppMode::EditConstraint => {
+            draw_main_multi_panel(f, app, &styles, &theme);
+            draw_edit_constraint(f, app, &styles);
+        }
+        AppMode::SortMenu => {
+            draw_main_multi_panel(f, app, &styles, &theme);
+            draw_sort_menu(f, app, &styles);
+        }
     }
 }
 
 fn draw_loading(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(3), Constraint::Min(10), Constraint::Length(3)])
         .split(size);
 
-    let title = Paragraph::new("🚀 PyElevate v0.2.0 - God Tier Dev Tool")
+    let title = Paragraph::new(format!("{} PyElevate v0.2.0 - God Tier Dev Tool", g.icon(g.app)))
         .style(styles.title)
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
@@ -40,7 +56,7 @@ fn draw_loading(f: &mut Frame, app: &App, styles: &Styles) {
     let loading_text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
-            format!("⟳ {}", app.loading_message),
+            format!("{} {}", g.icon(g.busy), app.loading_message),
             styles.header,
         )]),
         Line::from(""),
@@ -52,9 +68,39 @@ fn draw_loading(f: &mut Frame, app: &App, styles: &Styles) {
     f.render_widget(message, chunks[1]);
 }
 
-fn draw_main_multi_panel(f: &mut Frame, app: &App, styles: &Styles, _theme: &Theme) {
-    let size = f.size();
-    
+/// Below this width or height the side panels would be too cramped to
+/// read, so the list takes the whole screen instead.
+pub const MIN_MULTI_PANEL_WIDTH: u16 = 90;
+pub const MIN_MULTI_PANEL_HEIGHT: u16 = 22;
+
+/// Where each part of the main view goes for a given terminal size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MainLayout {
+    MultiPanel {
+        header: Rect,
+        list: Rect,
+        info: Rect,
+        popularity: Rect,
+        changelog: Rect,
+        conflicts: Rect,
+        help: Rect,
+    },
+    /// Just the list, with a one-line note asking for a bigger terminal.
+    SinglePanel { list: Rect, notice: Rect },
+}
+
+pub fn main_layout(size: Rect) -> MainLayout {
+    if size.width < MIN_MULTI_PANEL_WIDTH || size.height < MIN_MULTI_PANEL_HEIGHT {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(size);
+        return MainLayout::SinglePanel {
+            list: chunks[0],
+            notice: chunks[1],
+        };
+    }
+
     let outer_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -64,8 +110,6 @@ fn draw_main_multi_panel(f: &mut Frame, app: &App, styles: &Styles, _theme: &The
         ])
         .split(size);
 
-    draw_header(f, outer_chunks[0], styles);
-
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
@@ -73,41 +117,110 @@ fn draw_main_multi_panel(f: &mut Frame, app: &App, styles: &Styles, _theme: &The
 
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(40), Constraint::Percentage(30), Constraint::Percentage(30)])
+        .constraints([
+            Constraint::Percentage(35),
+            Constraint::Percentage(20),
+            Constraint::Percentage(25),
+            Constraint::Percentage(20),
+        ])
         .split(main_chunks[1]);
 
-    panels::render_dependency_list(
-        f,
-        main_chunks[0],
-        &app.packages,
-        app.selected_index,
-    );
+    MainLayout::MultiPanel {
+        header: outer_chunks[0],
+        list: main_chunks[0],
+        info: right_chunks[0],
+        popularity: right_chunks[1],
+        changelog: right_chunks[2],
+        conflicts: right_chunks[3],
+        help: outer_chunks[2],
+    }
+}
 
-    let selected = app.get_selected_package_ref();
-    panels::render_info_panel(f, right_chunks[0], selected);
-    panels::render_popularity_panel(f, right_chunks[1], selected.and_then(|p| p.popularity.as_ref()));
-    panels::render_changelog_panel(f, right_chunks[2], selected.and_then(|p| p.changelog.as_ref()));
+fn draw_main_multi_panel(f: &mut Frame, app: &App, styles: &Styles, _theme: &Theme) {
+    let g = app.glyph_set.glyphs();
+    match main_layout(f.size()) {
+        MainLayout::SinglePanel { list, notice } => {
+            draw_package_list(f, list, app);
+            let message = Paragraph::new(format!(
+                "Enlarge the terminal to at least {}{}{} for details",
+                MIN_MULTI_PANEL_WIDTH, g.times, MIN_MULTI_PANEL_HEIGHT
+            ))
+            .style(styles.help);
+            f.render_widget(message, notice);
+        }
+        MainLayout::MultiPanel {
+            header,
+            list,
+            info,
+            popularity,
+            changelog,
+            conflicts,
+            help,
+        } => {
+            draw_header(f, header, styles, g);
+            draw_package_list(f, list, app);
+
+            let selected = app.get_selected_package_ref();
+            let impact = selected.map(|p| app.impact_of(&p.name)).unwrap_or(0);
+            panels::render_info_panel(f, info, selected, impact);
+            panels::render_popularity_panel(f, popularity, selected.and_then(|p| p.popularity.as_ref()));
+            panels::render_changelog_panel(f, changelog, selected.and_then(|p| p.changelog.as_ref()), g);
+            panels::render_conflicts_panel(f, conflicts, &app.conflicts, g);
+
+            draw_help_bar(f, help, app, styles);
+        }
+    }
+}
 
-    draw_help_bar(f, outer_chunks[2], styles);
+fn draw_package_list(f: &mut Frame, area: Rect, app: &App) {
+    if let Some(message) = crate::report::empty_state(&app.requirements_path, &app.packages) {
+        let empty = Paragraph::new(vec![
+            Line::from(""),
+            Line::from(message),
+            Line::from(""),
+            Line::from("Press + to search PyPI and add a package."),
+        ])
+        .wrap(Wrap { trim: true })
+        .block(Block::default().title(" Dependencies ").borders(Borders::ALL));
+        f.render_widget(empty, area);
+    } else {
+        panels::render_dependency_list(
+            f,
+            area,
+            &app.visible_packages(),
+            app.selected_index,
+            panels::ListOptions {
+                group_by_file: app.group_by_file,
+                watchlist: &app.watchlist,
+                two_line: app.two_line_rows,
+                glyphs: app.glyph_set.glyphs(),
+            },
+        );
+    }
 }
 
-fn draw_header(f: &mut Frame, area: Rect, styles: &Styles) {
+fn draw_header(f: &mut Frame, area: Rect, styles: &Styles, g: &Glyphs) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(40), Constraint::Percentage(30), Constraint::Percentage(30)])
         .split(area);
 
-    let title = Paragraph::new("🚀 PyElevate v0.2.0")
+    let title = Paragraph::new(format!("{} PyElevate v0.2.0", g.icon(g.app)))
         .style(styles.title)
         .alignment(Alignment::Left);
     f.render_widget(title, chunks[0]);
 
     let stats = Paragraph::new(format!(
-        "📦 {} | 🔴 {} | 🔶 {} | 🟢 {} | ⚠️  {}",
+        "{} {} | {} {} | {} {} | {} {} | {} {}",
+        g.package,
         "total",
+        g.major,
         "major",
+        g.minor,
         "minor",
+        g.patch,
         "patch",
+        g.icon(g.warning),
         "vulnerable"
     ))
     .style(styles.help)
@@ -120,11 +233,24 @@ fn draw_header(f: &mut Frame, area: Rect, styles: &Styles) {
     f.render_widget(version, chunks[2]);
 }
 
-fn draw_help_bar(f: &mut Frame, area: Rect, styles: &Styles) {
-    let help_text = "↑↓: Navigate | Tab: Switch Panel | Space: Select | U: Upgrade | G: Graph | C: Changelog | F: Filter | Ctrl+C: Quit";
+fn draw_help_bar(f: &mut Frame, area: Rect, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
+    // A bound `G` (vim: bottom) leaves the graph view on `V`.
+    let graph_key = if app.keymap.is_bound("G") { "V" } else { "G" };
+    let help_text = format!("{}: Navigate | Tab: Switch Panel | Space: Select | U: Upgrade | {}: Graph | C: Changelog | B: Freshness | S: Sort | F: Group by file | L: Two-line rows | H: Direct only | r: Refetch | R: Retry failed (was r) | X: Remove | W: Use canonical name | E: Edit constraint | T: Target mode | Ctrl+E: Versions only | y: Copy line | Y: Copy pip command (was line) | O: Copy advisory | Ctrl+C: Quit", g.up_down, graph_key);
+
+    let (text, style) = if let Some(error) = &app.error_message {
+        (format!("{} {}", g.icon(g.error), error), styles.error)
+    } else if let Some(success) = &app.success_message {
+        (format!("{} {}", g.icon(g.up_to_date), success), styles.patch)
+    } else if app.enriching {
+        (format!("{} {}", g.icon(g.busy), app.loading_message), styles.help)
+    } else {
+        (help_text, styles.help)
+    };
 
-    let help = Paragraph::new(help_text)
-        .style(styles.help)
+    let help = Paragraph::new(text)
+        .style(style)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::TOP).style(styles.border));
 
@@ -132,6 +258,7 @@ fn draw_help_bar(f: &mut Frame, area: Rect, styles: &Styles) {
 }
 
 fn draw_search_mode(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -143,9 +270,9 @@ fn draw_search_mode(f: &mut Frame, app: &App, styles: &Styles) {
         ])
         .split(size);
 
-    draw_header(f, chunks[0], styles);
+    draw_header(f, chunks[0], styles, g);
 
-    let search_box = Paragraph::new(format!("🔍 Search: {}_", app.search_query))
+    let search_box = Paragraph::new(format!("{} Search: {}_", g.icon(g.search), app.search_query))
         .style(styles.header)
         .block(Block::default().borders(Borders::ALL).style(styles.border));
     f.render_widget(search_box, chunks[1]);
@@ -163,10 +290,10 @@ fn draw_search_mode(f: &mut Frame, app: &App, styles: &Styles) {
             ratatui::style::Style::default()
         };
 
-        let latest = pkg.latest_version.as_ref().map(|v| v.as_str()).unwrap_or("N/A");
+        let latest = pkg.latest_version.as_deref().unwrap_or("N/A");
         lines.push(Line::from(vec![
-            Span::styled(format!("{:<25} ", &pkg.name[..pkg.name.len().min(25)]), style),
-            Span::raw(format!("{:<8} → {:<8} ", pkg.current_version, latest)),
+            Span::styled(format!("{} ", glyphs::pad(&glyphs::truncate(&pkg.name, 25, ""), 25)), style),
+            Span::raw(format!("{:<8} {} {:<8} ", pkg.current_version, g.arrow, latest)),
             Span::styled(pkg.status.as_str(), style),
         ]));
     }
@@ -175,7 +302,10 @@ fn draw_search_mode(f: &mut Frame, app: &App, styles: &Styles) {
         .block(Block::default().title(" Results ").borders(Borders::ALL));
     f.render_widget(results, chunks[2]);
 
-    let help = Paragraph::new("Type to search | ↑↓: Navigate | Space: Select | Esc: Back | Enter: Upgrade")
+    let help = Paragraph::new(format!(
+        "Type to search | {}: Navigate | Space: Select | Esc: Back | Enter: Upgrade",
+        g.up_down
+    ))
         .style(styles.help)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::TOP).style(styles.border));
@@ -183,49 +313,81 @@ fn draw_search_mode(f: &mut Frame, app: &App, styles: &Styles) {
 }
 
 fn draw_confirm(f: &mut Frame, app: &App, styles: &Styles) {
+    let Some(simulation) = &app.confirm_simulation else {
+        return;
+    };
+    let g = app.glyph_set.glyphs();
     let size = f.size();
-    let simulator = UpgradeSimulator::new();
-    let simulation = simulator.simulate_upgrade(&app.packages);
-
-    let dialog_width = size.width.saturating_sub(4).min(80);
-    let dialog_height = 20usize;
+    let fixable_cves = app
+        .security_checker
+        .fixable_cves(app.packages.iter().filter(|p| p.selected));
 
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length((size.height.saturating_sub(dialog_height as u16)) / 2),
-            Constraint::Length(dialog_height as u16),
-            Constraint::Min(1),
-        ])
-        .split(size);
-
-    let popup_area = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Length((size.width.saturating_sub(dialog_width as u16)) / 2),
-            Constraint::Length(dialog_width as u16),
-            Constraint::Min(1),
-        ])
-        .split(popup_layout[1])[1];
-
-    let content = vec![
+    let mut content = vec![
         Line::from(""),
-        Line::from("📋 UPGRADE SIMULATION REPORT"),
-        Line::from(""),
-        Line::from(format!("📦 Packages to upgrade:  {}", simulation.packages_to_upgrade)),
-        Line::from(format!("🔴 Major changes:       {}", simulation.major_changes)),
-        Line::from(format!("⚠️  Conflicts:          {}", simulation.conflicts_detected)),
-        Line::from(format!("🔒 Security fixes:      {}", simulation.security_fixes)),
-        Line::from(format!("📊 Risk level:          {}", simulation.risk_level.as_str())),
+        Line::from(format!("{} UPGRADE SIMULATION REPORT", g.icon(g.report))),
         Line::from(""),
+        Line::from(format!("{} Upgrading will fix {} CVE(s)", g.icon(g.shield), fixable_cves)),
+        Line::from(format!("{} Packages to upgrade:  {}", g.icon(g.package), simulation.packages_to_upgrade)),
+        Line::from(format!("{} Major changes:       {}", g.icon(g.major), simulation.major_changes)),
+        Line::from(format!("{} Conflicts:           {}", g.icon(g.warning), simulation.conflicts_detected)),
+        Line::from(format!("{} Security fixes:      {}", g.icon(g.lock), simulation.security_fixes)),
+        Line::from(format!("{} Risk level:          {}", g.icon(g.chart), simulation.risk_level.as_str())),
+    ];
+    if !simulation.impacts.is_empty() {
+        let impacts: Vec<String> = simulation
+            .impacts
+            .iter()
+            .map(|(name, impact)| format!("{} {}", name, impact))
+            .collect();
+        content.push(Line::from(format!("{} Impact (dependents): {}", g.icon(g.impact), impacts.join(", "))));
+    }
+    content.push(Line::from(""));
+    content.extend(
+        app.confirm_warnings
+            .iter()
+            .map(|warning| Line::from(Span::styled(format!("{} {}", g.icon(g.warning), warning), styles.error))),
+    );
+    if !app.confirm_warnings.is_empty() {
+        content.push(Line::from(""));
+    }
+
+    let key_hint = if app.confirm_reasons.is_empty() {
         Line::from(vec![
             Span::styled("Enter", styles.header),
             Span::raw(": Confirm  |  "),
             Span::styled("Esc", styles.header),
             Span::raw(": Cancel"),
-        ]),
-        Line::from(""),
-    ];
+        ])
+    } else {
+        Line::from(vec![
+            Span::styled("Type yes", styles.header),
+            Span::raw(format!(" and Enter: Confirm [{}]  |  ", app.confirm_input)),
+            Span::styled("Esc", styles.header),
+            Span::raw(": Cancel"),
+        ])
+    };
+    // On a short terminal the report and warnings give way first: the
+    // critical reasons explain the prompt, and the key hint is the only way
+    // to confirm.
+    let rows = (size.height as usize).saturating_sub(2);
+    let reason_rows = app.confirm_reasons.len().min(rows.saturating_sub(2));
+    content.truncate(rows.saturating_sub(reason_rows + 2));
+    content.extend(
+        app.confirm_reasons[..reason_rows]
+            .iter()
+            .map(|reason| Line::from(Span::styled(format!("{} {}", g.icon(g.critical), reason), styles.error))),
+    );
+    content.push(key_hint);
+    content.push(Line::from(""));
+
+    let dialog_width = size.width.saturating_sub(4).min(80);
+    let dialog_height = (content.len() as u16 + 2).min(size.height);
+    let popup_area = Rect::new(
+        size.width.saturating_sub(dialog_width) / 2,
+        size.height.saturating_sub(dialog_height) / 2,
+        dialog_width,
+        dialog_height,
+    );
 
     let dialog = Paragraph::new(content)
         .block(
@@ -244,7 +406,146 @@ fn draw_confirm(f: &mut Frame, app: &App, styles: &Styles) {
     f.render_widget(dialog, popup_area);
 }
 
-fn draw_upgrading(f: &mut Frame, styles: &Styles) {
+fn draw_confirm_remove(f: &mut Frame, app: &App, styles: &Styles) {
+    let Some(pkg) = app.get_selected_package_ref() else {
+        return;
+    };
+    let g = app.glyph_set.glyphs();
+    let size = f.size();
+    let dependents = app.dependents_of(&pkg.name);
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(format!("{} Remove {} from the requirements?", g.icon(g.remove), pkg.name)),
+        Line::from(""),
+    ];
+    if !dependents.is_empty() {
+        content.push(Line::from(Span::styled(
+            format!("{} Required by: {}", g.icon(g.warning), dependents.join(", ")),
+            styles.error,
+        )));
+        content.push(Line::from(""));
+    }
+    content.push(Line::from(vec![
+        Span::styled("Enter/Y", styles.header),
+        Span::raw(": Remove  |  "),
+        Span::styled("Esc/N", styles.header),
+        Span::raw(": Cancel"),
+    ]));
+
+    let width = size.width.saturating_sub(4).min(60);
+    let height = (content.len() as u16 + 3).min(size.height);
+    let area = Rect::new(
+        size.width.saturating_sub(width) / 2,
+        size.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .title(" Remove Dependency ")
+            .borders(Borders::ALL)
+            .style(styles.header),
+    );
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_sort_menu(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
+    let size = f.size();
+
+    let mut content = vec![Line::from("")];
+    for (i, sort_by) in SortBy::ALL.iter().enumerate() {
+        let marker = list_marker(g, i == app.sort_menu_index);
+        let current = if *sort_by == app.sort_by { " (current)" } else { "" };
+        let style = if i == app.sort_menu_index { styles.selected } else { styles.normal };
+        content.push(Line::from(Span::styled(
+            format!("{}{}{}", marker, sort_by.as_str(), current),
+            style,
+        )));
+    }
+    content.push(Line::from(""));
+    content.push(Line::from(format!(
+        "Direction: {}",
+        if app.sort_reversed { "reversed" } else { "normal" }
+    )));
+    content.push(Line::from(""));
+    content.push(Line::from(vec![
+        Span::styled(g.up_down, styles.header),
+        Span::raw(": Move  |  "),
+        Span::styled("Enter", styles.header),
+        Span::raw(": Sort  |  "),
+        Span::styled("R", styles.header),
+        Span::raw(": Reverse  |  "),
+        Span::styled("Esc", styles.header),
+        Span::raw(": Close"),
+    ]));
+
+    let width = size.width.saturating_sub(4).min(60);
+    let height = (content.len() as u16 + 3).min(size.height);
+    let area = Rect::new(
+        size.width.saturating_sub(width) / 2,
+        size.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .title(" Sort By ")
+            .borders(Borders::ALL)
+            .style(styles.header),
+    );
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_edit_constraint(f: &mut Frame, app: &App, styles: &Styles) {
+    let Some(pkg) = app.get_selected_package_ref() else {
+        return;
+    };
+    let g = app.glyph_set.glyphs();
+    let size = f.size();
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(format!("{} Constraint for {}: {}_", g.icon(g.edit), pkg.name, app.constraint_input)),
+        Line::from(""),
+    ];
+    if let Some(error) = &app.error_message {
+        content.push(Line::from(Span::styled(error.clone(), styles.error)));
+        content.push(Line::from(""));
+    }
+    content.push(Line::from(vec![
+        Span::styled("Enter", styles.header),
+        Span::raw(": Apply  |  "),
+        Span::styled("Esc", styles.header),
+        Span::raw(": Cancel  |  e.g. >=2.0, ~=1.4, ==3.1.2"),
+    ]));
+
+    let width = size.width.saturating_sub(4).min(70);
+    let height = (content.len() as u16 + 3).min(size.height);
+    let area = Rect::new(
+        size.width.saturating_sub(width) / 2,
+        size.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+
+    let dialog = Paragraph::new(content).block(
+        Block::default()
+            .title(" Edit Constraint ")
+            .borders(Borders::ALL)
+            .style(styles.header),
+    );
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(dialog, area);
+}
+
+fn draw_upgrading(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -255,7 +556,7 @@ fn draw_upgrading(f: &mut Frame, styles: &Styles) {
         ])
         .split(size);
 
-    let title = Paragraph::new("🚀 PyElevate v0.2.0")
+    let title = Paragraph::new(format!("{} PyElevate v0.2.0", g.icon(g.app)))
         .style(styles.title)
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
@@ -263,7 +564,7 @@ fn draw_upgrading(f: &mut Frame, styles: &Styles) {
     let message = Paragraph::new(vec![
         Line::from(""),
         Line::from(vec![Span::styled(
-            "⟳ Processing upgrades...",
+            format!("{} Processing upgrades...", g.icon(g.busy)),
             styles.header,
         )]),
         Line::from(""),
@@ -274,6 +575,7 @@ fn draw_upgrading(f: &mut Frame, styles: &Styles) {
 }
 
 fn draw_done(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -284,23 +586,25 @@ fn draw_done(f: &mut Frame, app: &App, styles: &Styles) {
         ])
         .split(size);
 
-    let title = Paragraph::new("🎉 PyElevate v0.2.0")
+    let title = Paragraph::new(format!("{} PyElevate v0.2.0", g.icon(g.done)))
         .style(styles.title)
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
 
-    let message_lines = if let Some(success) = &app.success_message {
+    let message_lines = if app.show_only_changed {
+        changed_lines(app, styles)
+    } else if let Some(success) = &app.success_message {
         vec![
             Line::from(""),
             Line::from(vec![Span::styled(
-                "✅ Upgrade completed successfully!",
+                format!("{} Upgrade completed successfully!", g.icon(g.up_to_date)),
                 styles.patch,
             )]),
             Line::from(""),
             Line::from(success.as_str()),
             Line::from(""),
             if let Some(backup) = &app.backup_path {
-                Line::from(format!("📦 Backup: {}", backup))
+                Line::from(format!("{} Backup: {}", g.icon(g.package), backup))
             } else {
                 Line::from("")
             },
@@ -309,7 +613,7 @@ fn draw_done(f: &mut Frame, app: &App, styles: &Styles) {
         vec![
             Line::from(""),
             Line::from(vec![Span::styled(
-                "✓ No upgrades selected",
+                format!("{} No upgrades selected", g.check),
                 styles.help,
             )]),
             Line::from(""),
@@ -321,13 +625,44 @@ fn draw_done(f: &mut Frame, app: &App, styles: &Styles) {
         .block(Block::default().borders(Borders::ALL).style(styles.border));
     f.render_widget(message, chunks[1]);
 
-    let help = Paragraph::new("Press any key to exit")
+    let help = Paragraph::new(if app.show_only_changed {
+        "V: Back to summary | Any other key: Exit"
+    } else {
+        "V: Show only changed | Any other key: Exit"
+    })
         .style(styles.help)
         .alignment(Alignment::Center);
     f.render_widget(help, chunks[2]);
 }
 
+/// The packages the upgrade rewrote, one `name old → new` line each.
+fn changed_lines<'a>(app: &'a App, styles: &Styles) -> Vec<Line<'a>> {
+    let g = app.glyph_set.glyphs();
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Changed packages ({})", app.upgraded_changes.len()),
+            styles.header,
+        )),
+        Line::from(""),
+    ];
+    if app.upgraded_changes.is_empty() {
+        lines.push(Line::from("No requirement lines were rewritten"));
+    }
+    for change in &app.upgraded_changes {
+        lines.push(Line::from(vec![
+            Span::raw(format!("{} {} {} ", change.package, change.from_version, g.arrow)),
+            Span::styled(
+                change.to_version.as_str(),
+                ratatui::style::Style::default().fg(crate::styles::status_color(change.status)),
+            ),
+        ]));
+    }
+    lines
+}
+
 fn draw_graph_view(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -338,14 +673,17 @@ fn draw_graph_view(f: &mut Frame, app: &App, styles: &Styles) {
         ])
         .split(size);
 
-    let title = Paragraph::new("📊 Dependency Graph")
+    let title = Paragraph::new(format!("{} Dependency Graph", g.icon(g.chart)))
         .style(styles.title)
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
 
     let mut lines = vec![Line::from("")];
     for pkg in app.packages.iter().take(20) {
-        lines.push(Line::from(format!("📦 {}", pkg.name)));
+        lines.push(Line::from(format!("{} {}", g.icon(g.package), pkg.name)));
+        for dep in &pkg.dependencies {
+            lines.push(Line::from(format!("   {} {}", g.branch, dep)));
+        }
     }
 
     let graph = Paragraph::new(lines)
@@ -360,6 +698,7 @@ fn draw_graph_view(f: &mut Frame, app: &App, styles: &Styles) {
 }
 
 fn draw_changelog_detail(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
     let size = f.size();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -370,7 +709,7 @@ fn draw_changelog_detail(f: &mut Frame, app: &App, styles: &Styles) {
         ])
         .split(size);
 
-    let title = Paragraph::new("📝 Changelog")
+    let title = Paragraph::new(format!("{} Changelog", g.icon(g.changelog)))
         .style(styles.title)
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
@@ -384,7 +723,7 @@ fn draw_changelog_detail(f: &mut Frame, app: &App, styles: &Styles) {
             ]));
             lines.push(Line::from(""));
             for change in &changelog.changes {
-                lines.push(Line::from(format!("• {}", change)));
+                lines.push(Line::from(format!("{} {}", g.bullet, change)));
             }
         }
     }
@@ -399,3 +738,186 @@ fn draw_changelog_detail(f: &mut Frame, app: &App, styles: &Styles) {
         .block(Block::default().borders(Borders::TOP).style(styles.border));
     f.render_widget(help, chunks[2]);
 }
+
+fn draw_freshness_detail(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
+    let size = f.size();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(10),
+            Constraint::Length(3),
+        ])
+        .split(size);
+
+    let title = Paragraph::new(format!("{} Freshness", g.icon(g.clock)))
+        .style(styles.title)
+        .alignment(Alignment::Center);
+    f.render_widget(title, chunks[0]);
+
+    let bold = ratatui::style::Style::default().add_modifier(ratatui::style::Modifier::BOLD);
+    let mut lines = vec![Line::from("")];
+    if let Some(pkg) = app.get_selected_package_ref() {
+        let releases = app.pypi_client.release_dates(&pkg.name);
+        let freshness = freshness::measure(pkg, &releases);
+        let or_unknown = |d: Option<chrono::Duration>| d.map(freshness::describe).unwrap_or_else(|| "unknown".to_string());
+        lines.push(Line::from(vec![
+            Span::styled("Package: ", bold),
+            Span::raw(format!(
+                "{} {} {} {}",
+                pkg.name,
+                pkg.current_version,
+                g.arrow,
+                pkg.latest_version.as_deref().unwrap_or("N/A")
+            )),
+        ]));
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Versions behind: ", bold),
+            Span::raw(freshness.versions_behind.to_string()),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Time behind: ", bold),
+            Span::raw(or_unknown(freshness.time_behind)),
+        ]));
+        lines.push(Line::from(vec![
+            Span::styled("Release cadence: ", bold),
+            Span::raw(match freshness.cadence {
+                Some(cadence) => format!(
+                    "one release every {} (over {} releases)",
+                    freshness::describe(cadence),
+                    freshness.releases
+                ),
+                None => "unknown".to_string(),
+            }),
+        ]));
+    }
+
+    let detail = Paragraph::new(lines)
+        .block(Block::default().title(" Compared to its own releases ").borders(Borders::ALL));
+    f.render_widget(detail, chunks[1]);
+
+    let help = Paragraph::new("B: Back to main | Esc: Back")
+        .style(styles.help)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::TOP).style(styles.border));
+    f.render_widget(help, chunks[2]);
+}
+
+fn draw_package_search(f: &mut Frame, app: &App, styles: &Styles) {
+    let g = app.glyph_set.glyphs();
+    let size = f.size();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(10),
+            Constraint::Length(3),
+        ])
+        .split(size);
+
+    draw_header(f, chunks[0], styles, g);
+
+    let search = &app.package_search;
+    let search_box = Paragraph::new(format!("{} Search PyPI: {}_", g.icon(g.package), search.query))
+        .style(styles.header)
+        .block(Block::default().borders(Borders::ALL).style(styles.border));
+    f.render_widget(search_box, chunks[1]);
+
+    let mut lines = Vec::new();
+    if search.results.is_empty() && !search.searched_query.is_empty() {
+        lines.push(Line::from(format!("No packages matching \"{}\"", search.searched_query)));
+    }
+    for (idx, result) in search.results.iter().enumerate() {
+        let style = if idx == search.selected {
+            styles.selected
+        } else {
+            styles.normal
+        };
+        let marker = list_marker(g, idx == search.selected);
+        lines.push(Line::from(Span::styled(format!("{}{}", marker, result.name), style)));
+    }
+
+    let results = Paragraph::new(lines)
+        .block(Block::default().title(" PyPI Results ").borders(Borders::ALL));
+    f.render_widget(results, chunks[2]);
+
+    let help = Paragraph::new(format!(
+        "Type a name | Enter: Search / Add selected (written on U) | {}: Navigate | Esc: Back",
+        g.up_down
+    ))
+        .style(styles.help)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::TOP).style(styles.border));
+    f.render_widget(help, chunks[3]);
+}
+
+/// The arrow in front of the highlighted entry of a menu, or as many
+/// spaces.
+fn list_marker(g: &Glyphs, highlighted: bool) -> String {
+    if highlighted {
+        format!("{} ", g.arrow)
+    } else {
+        " ".repeat(glyphs::width(g.arrow) + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_terminal_falls_back_to_single_panel() {
+        let small = Rect::new(0, 0, 60, 20);
+        match main_layout(small) {
+            MainLayout::SinglePanel { list, notice } => {
+                assert_eq!(list.width, 60);
+                assert_eq!(notice.height, 1);
+                assert_eq!(list.height + notice.height, 20);
+            }
+            other => panic!("expected the single panel, got {:?}", other),
+        }
+        assert!(matches!(main_layout(Rect::new(0, 0, 200, 12)), MainLayout::SinglePanel { .. }));
+        assert!(matches!(main_layout(Rect::new(0, 0, 120, 40)), MainLayout::MultiPanel { .. }));
+    }
+
+    #[test]
+    fn test_confirm_keeps_critical_reasons_and_prompt_on_short_terminal() {
+        use crate::models::{Package, VersionStatus};
+        let mut app = App::new("requirements.txt".to_string());
+        let mut packages = vec![Package {
+            name: "asgiref".to_string(),
+            current_version: "3.0.0".to_string(),
+            latest_version: Some("3.1.0".to_string()),
+            status: VersionStatus::Minor,
+            ..Package::default()
+        }];
+        for i in 0..12 {
+            packages.push(Package {
+                name: format!("major-{:02}", i),
+                current_version: "1.0.0".to_string(),
+                latest_version: Some("2.0.0".to_string()),
+                status: VersionStatus::Major,
+                selected: true,
+                dependencies: vec!["asgiref".to_string()],
+                ..Package::default()
+            });
+        }
+        app.set_packages(packages);
+        app.open_confirm();
+        assert!(app.confirm_reasons.len() > 12);
+
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(100, 24)).unwrap();
+        terminal.draw(|f| draw(f, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer.get(x, y).symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(screen.contains("Type yes and Enter: Confirm"));
+        assert!(screen.contains("Conflict: major-00"));
+        assert!(!screen.contains("UPGRADE SIMULATION REPORT"));
+    }
+}
@@ -1,8 +1,12 @@
-use crate::models::{Package, VersionConstraint};
+use crate::models::{
+    normalize_package_name, ConstraintUpdate, DependencySource, Package, RelaxStrategy, UpgradeStats,
+    VersionConstraint, VersionStatus,
+};
 use anyhow::Result;
 use chrono::Local;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 pub struct UpgradeManager;
 
@@ -15,59 +19,452 @@ pub struct UpgradeResult {
     pub error: Option<String>,
 }
 
+/// What [`UpgradeManager::write_upgraded_files`] did to one file.
+#[derive(Debug, Clone)]
+pub struct FileUpgrade {
+    pub path: String,
+    pub backup_path: Option<String>,
+    pub upgraded: usize,
+}
+
+/// One requirement an [`UpgradePlan`] rewrites.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedChange {
+    pub package: String,
+    pub from_version: String,
+    pub to_version: String,
+    /// The entry as written, with any `--hash` continuations, one per line.
+    pub old_line: String,
+    /// The rewritten entry, laid out the same way.
+    pub new_line: String,
+    pub status: VersionStatus,
+}
+
+/// What an upgrade would do to a requirements file, worked out without
+/// touching the filesystem.
+#[derive(Debug, Clone)]
+pub struct UpgradePlan {
+    pub changes: Vec<PlannedChange>,
+    /// The whole upgraded file.
+    pub content: String,
+    /// Selected packages left as they are because no target version is
+    /// known, e.g. their lookup failed after they were selected.
+    pub skipped: Vec<String>,
+    pub stats: UpgradeStats,
+}
+
+/// How a requirements file has drifted from its lock file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LockDiff {
+    /// Required but not locked.
+    pub added: Vec<String>,
+    /// Locked but no longer required.
+    pub removed: Vec<String>,
+    /// `(name, locked version, requirement)` where the locked version no
+    /// longer satisfies the requirement.
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl LockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 impl UpgradeManager {
     pub fn generate_upgraded_content(
         packages: &[Package],
         original_content: &str,
         only_selected: bool,
+        constraint: ConstraintUpdate,
     ) -> Result<String> {
-        let lines: Vec<&str> = original_content.lines().collect();
+        Ok(Self::plan(packages, original_content, only_selected, constraint)?.content)
+    }
+
+    /// The upgrade of `original` as a list of line changes plus the upgraded
+    /// content. Errors only when a hash-pinned package has no known hashes
+    /// for its target.
+    pub fn plan(
+        packages: &[Package],
+        original: &str,
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<UpgradePlan> {
+        let mut lines = crate::parser::strip_bom(original).lines();
         let mut result = Vec::new();
+        let mut changes = Vec::new();
 
-        for line in lines {
+        while let Some(line) = lines.next() {
             let line_trimmed = line.trim();
 
-            if line_trimmed.is_empty() || line_trimmed.starts_with('#') {
+            if line_trimmed.is_empty()
+                || line_trimmed.starts_with('#')
+                || crate::parser::parse_global_option(line_trimmed).is_some()
+            {
                 result.push(line.to_string());
                 continue;
             }
 
-            let should_upgrade = packages
+            // A requirement and its backslash continuations are rewritten
+            // together, so `--hash` lines never outlive the pin they check.
+            let mut continuations = Vec::new();
+            let mut continued = line.trim_end().ends_with('\\');
+            while continued {
+                let Some(next) = lines.next() else { break };
+                continued = next.trim_end().ends_with('\\');
+                continuations.push(next);
+            }
+
+            let name = crate::parser::requirement_name(line).map(|name| normalize_package_name(&name));
+            let pkg = packages
                 .iter()
-                .find(|p| {
-                    let line_lower = line.to_lowercase();
-                    line_lower.contains(&p.name.to_lowercase())
-                })
-                .map(|p| {
-                    if only_selected {
-                        p.selected && p.latest_version.is_some()
-                    } else {
-                        p.latest_version.is_some()
+                .find(|p| name.as_deref() == Some(normalize_package_name(&p.name).as_str()))
+                .filter(|p| is_upgraded(p, only_selected, constraint));
+
+            match pkg.and_then(|pkg| Some((pkg, pkg.upgrade_target()?))) {
+                Some((pkg, target)) => {
+                    let upgraded = generate_requirement_line(pkg, target, constraint);
+                    let hashed = std::iter::once(line).chain(continuations.iter().copied()).any(has_hash);
+                    if hashed && pkg.target_hashes.is_empty() {
+                        anyhow::bail!(
+                            "{} is hash-pinned but no sha256 hashes are known for {}",
+                            pkg.name,
+                            target
+                        );
                     }
-                })
-                .unwrap_or(false);
-
-            if should_upgrade {
-                if let Some(pkg) = packages.iter().find(|p| {
-                    let line_lower = line.to_lowercase();
-                    line_lower.contains(&p.name.to_lowercase())
-                }) {
-                    if let Some(latest) = &pkg.latest_version {
-                        let upgraded = generate_requirement_line(pkg, latest);
-                        result.push(upgraded);
-                        continue;
+
+                    let indent = continuations
+                        .first()
+                        .map(|c| &c[..c.len() - c.trim_start().len()])
+                        .unwrap_or("    ");
+                    let mut rewritten = vec![upgraded];
+                    rewritten.extend(
+                        continuations
+                            .iter()
+                            .filter(|c| !has_hash(c))
+                            .map(|c| c.trim_end().trim_end_matches('\\').trim_end().to_string()),
+                    );
+                    if hashed {
+                        rewritten.extend(pkg.target_hashes.iter().map(|h| format!("{}--hash={}", indent, h)));
                     }
+
+                    let last = rewritten.len() - 1;
+                    let first = result.len();
+                    for (i, mut rewritten_line) in rewritten.into_iter().enumerate() {
+                        if i < last {
+                            rewritten_line.push_str(" \\");
+                        } else if let Some(directive) = std::iter::once(line)
+                            .chain(continuations.iter().copied())
+                            .find_map(crate::parser::policy_comment)
+                        {
+                            rewritten_line = format!("{}  {}", rewritten_line, directive);
+                        }
+                        result.push(rewritten_line);
+                    }
+                    changes.push(PlannedChange {
+                        package: pkg.name.clone(),
+                        from_version: pkg.current_version.clone(),
+                        to_version: target.to_string(),
+                        old_line: std::iter::once(line).chain(continuations).collect::<Vec<_>>().join("\n"),
+                        new_line: result[first..].join("\n"),
+                        status: pkg.status,
+                    });
+                }
+                None => {
+                    result.push(line.to_string());
+                    result.extend(continuations.iter().map(|c| c.to_string()));
                 }
             }
+        }
+
+        let skipped = packages
+            .iter()
+            .filter(|p| only_selected && p.selected && p.upgrade_target().is_none())
+            .map(|p| p.name.clone())
+            .collect();
+        Ok(UpgradePlan {
+            changes,
+            skipped,
+            content: join_like(original, &result, original.ends_with('\n')),
+            stats: UpgradeStats::new(packages),
+        })
+    }
+
+    /// [`UpgradeManager::generate_upgraded_content`] for the file at `path`,
+    /// which may also be a pyproject.toml.
+    pub fn generate_upgraded_file(
+        path: &Path,
+        packages: &[Package],
+        original_content: &str,
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<String> {
+        if crate::pyproject::is_pyproject(path) {
+            Self::generate_upgraded_pyproject(packages, original_content, only_selected, constraint)
+        } else {
+            Self::generate_upgraded_content(packages, original_content, only_selected, constraint)
+        }
+    }
+
+    /// Rewrites the version of each upgraded declaration of a pyproject.toml
+    /// where it stands, leaving the rest of the document as written. PEP 508
+    /// strings get what a requirements file line would; Poetry versions keep
+    /// their caret or tilde, or take the matching PEP 440 specifier.
+    pub fn generate_upgraded_pyproject(
+        packages: &[Package],
+        original_content: &str,
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<String> {
+        let all = crate::pyproject::GroupSelection { groups: Vec::new(), all: true };
+        let entries = crate::pyproject::entries(original_content, "pyproject.toml", &all)?;
+        let mut lines: Vec<String> = crate::parser::strip_bom(original_content).lines().map(str::to_string).collect();
+
+        for entry in entries {
+            let name = crate::parser::requirement_name(&entry.spec).map(|name| normalize_package_name(&name));
+            let Some(pkg) = packages
+                .iter()
+                .find(|p| name.as_deref() == Some(normalize_package_name(&p.name).as_str()) && p.group == entry.group)
+            else {
+                continue;
+            };
+            let upgraded = Self::generate_upgraded_content(std::slice::from_ref(pkg), &entry.spec, only_selected, constraint)?;
+            // Notes such as `# cap raised from` have no place inside a TOML string.
+            let upgraded = upgraded.split("  #").next().unwrap_or(&upgraded).trim_end();
+            let (Some(target), Some(line)) = (pkg.upgrade_target(), entry.line.checked_sub(1).and_then(|i| lines.get_mut(i)))
+            else {
+                continue;
+            };
+            if upgraded == entry.spec {
+                continue;
+            }
 
-            result.push(line.to_string());
+            let (old, new) = match &entry.poetry_version {
+                Some(version) => (version.clone(), poetry_version(version, upgraded, target, constraint)),
+                None => (entry.spec.clone(), upgraded.to_string()),
+            };
+            if let Some(quoted) = ['"', '\''].iter().map(|q| format!("{q}{old}{q}")).find(|q| line.contains(q.as_str())) {
+                let quote = &quoted[..1];
+                *line = line.replacen(&quoted, &format!("{quote}{new}{quote}"), 1);
+            }
         }
 
-        Ok(result.join("\n"))
+        Ok(join_like(original_content, &lines, original_content.ends_with('\n')))
     }
 
-    pub fn create_backup<P: AsRef<Path>>(path: P) -> Result<String> {
+    /// Whether upgrading `pkg` changes its line at all. A bare name kept as
+    /// written already installs the latest release, so it is left alone.
+    pub fn rewrites_line(pkg: &Package, constraint: ConstraintUpdate) -> bool {
+        constraint != ConstraintUpdate::Keep || !matches!(pkg.constraint, VersionConstraint::Unspecified)
+    }
+
+    /// Inserts a pinned line for each of `added`. When the existing
+    /// requirements are alphabetical each line goes to its sorted position,
+    /// otherwise it follows the last requirement. The pin operator follows the
+    /// one most of the file already uses.
+    pub fn add_requirements(content: &str, added: &[&Package]) -> String {
+        let mut lines: Vec<String> = crate::parser::strip_bom(content)
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+        let operator = dominant_operator(&lines);
+
+        for pkg in added {
+            let version = pkg.upgrade_target().unwrap_or(&pkg.current_version);
+            let extras = if pkg.extras.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]", pkg.extras.join(","))
+            };
+            let new_line = format!("{}{}{}{}", pkg.name, extras, operator, version);
+
+            let requirements: Vec<(usize, String)> = lines
+                .iter()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    crate::parser::requirement_name(line).map(|name| (i, normalize_package_name(&name)))
+                })
+                .collect();
+            let sorted = requirements.windows(2).all(|w| w[0].1 <= w[1].1);
+            let name = normalize_package_name(&pkg.name);
+
+            let position = if sorted {
+                requirements
+                    .iter()
+                    .find(|(_, existing)| *existing > name)
+                    .map(|(i, _)| *i)
+            } else {
+                None
+            }
+            .or_else(|| requirements.last().map(|(i, _)| i + 1))
+            .unwrap_or(lines.len());
+
+            lines.insert(position, new_line);
+        }
+
+        join_like(content, &lines, content.ends_with('\n'))
+    }
+
+    /// Drops the lines declaring any of `removed`, along with their
+    /// backslash continuations (e.g. `--hash` lines). Everything else,
+    /// including neighbouring comments and blank lines, is kept as is.
+    pub fn remove_requirements(content: &str, removed: &[String]) -> String {
+        let removed: Vec<String> = removed.iter().map(|n| normalize_package_name(n)).collect();
+        let mut result = Vec::new();
+        let mut skipping_continuation = false;
+
+        for line in crate::parser::strip_bom(content).lines() {
+            if skipping_continuation {
+                skipping_continuation = line.trim_end().ends_with('\\');
+                continue;
+            }
+
+            let drop = crate::parser::requirement_name(line)
+                .is_some_and(|name| removed.contains(&normalize_package_name(&name)));
+            if drop {
+                skipping_continuation = line.trim_end().ends_with('\\');
+                continue;
+            }
+            result.push(line);
+        }
+
+        join_like(content, &result, content.ends_with('\n'))
+    }
+
+    /// Rewrites the requirement line of each of `edited` with its current
+    /// name, extras and constraint, keeping markers, inline options such as
+    /// `--hash`, comments and continuations. `renamed` pairs the normalized
+    /// name a line still has with the one its package has taken since.
+    pub fn rewrite_constraints(content: &str, edited: &[&Package], renamed: &[(String, String)]) -> String {
+        let mut result = Vec::new();
+        let mut in_continuation = false;
+
+        for line in crate::parser::strip_bom(content).lines() {
+            if in_continuation {
+                in_continuation = line.trim_end().ends_with('\\');
+                result.push(line.to_string());
+                continue;
+            }
+            in_continuation = line.trim_end().ends_with('\\');
+
+            let pkg = crate::parser::requirement_name(line).and_then(|name| {
+                let name = normalize_package_name(&name);
+                let name = renamed
+                    .iter()
+                    .find(|(old, _)| *old == name)
+                    .map_or(name.clone(), |(_, new)| new.clone());
+                edited.iter().find(|p| normalize_package_name(&p.name) == name)
+            });
+            let Some(pkg) = pkg else {
+                result.push(line.to_string());
+                continue;
+            };
+
+            let extras = if pkg.extras.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]", pkg.extras.join(","))
+            };
+            let marker = pkg.marker.as_deref().map(|m| format!("; {}", m)).unwrap_or_default();
+            result.push(format!(
+                "{}{}{}{}{}",
+                pkg.name,
+                extras,
+                pkg.constraint.as_str(),
+                marker,
+                requirement_trailer(line)
+            ));
+        }
+
+        join_like(content, &result, content.ends_with('\n'))
+    }
+
+    /// Turns a pinned file back into a loose `requirements.in`: `==` pins
+    /// become `~=`/`>=` ranges, `--hash` continuations and `# via`
+    /// annotations go, and pins that are only there for another package
+    /// (see [`Package::is_direct`]) are left out. Everything else, such as
+    /// options, comments and URL requirements, is kept.
+    pub fn relax_pins(content: &str, packages: &[Package], strategy: RelaxStrategy) -> String {
+        let mut result = Vec::new();
+        let mut in_continuation = false;
+
+        for line in crate::parser::strip_bom(content).lines() {
+            if in_continuation {
+                in_continuation = line.trim_end().ends_with('\\');
+                continue;
+            }
+            in_continuation = line.trim_end().ends_with('\\');
+            if line.starts_with(char::is_whitespace) && line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let pkg = crate::parser::requirement_name(line).and_then(|name| {
+                let name = normalize_package_name(&name);
+                packages.iter().find(|p| normalize_package_name(&p.name) == name)
+            });
+            let Some(pkg) = pkg else {
+                result.push(without_hashes(line));
+                continue;
+            };
+            if !pkg.is_direct {
+                continue;
+            }
+            if !matches!(pkg.source, DependencySource::PyPI) {
+                result.push(without_hashes(line));
+                continue;
+            }
+
+            let spec = match &pkg.constraint {
+                VersionConstraint::Pinned(version) => match strategy {
+                    RelaxStrategy::Floor => format!(">={}", version),
+                    RelaxStrategy::Compatible => match crate::version::Pep440Version::parse(version) {
+                        Some(parsed) => format!("~={}.{}", parsed.major(), parsed.minor()),
+                        None => format!(">={}", version),
+                    },
+                },
+                other => other.as_str(),
+            };
+            let extras = if pkg.extras.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]", pkg.extras.join(","))
+            };
+            let marker = pkg.marker.as_deref().map(|m| format!("; {}", m)).unwrap_or_default();
+            let mut relaxed = format!("{}{}{}{}", pkg.name, extras, spec, marker);
+            if let Some(comment) = inline_comment(line) {
+                relaxed = format!("{}  {}", relaxed, comment);
+            }
+            result.push(relaxed);
+        }
+
+        join_like(content, &result, true)
+    }
+
+    pub fn preview_upgrade<P: AsRef<Path>>(
+        path: P,
+        packages: &[Package],
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<String> {
         let path = path.as_ref();
+        let original = fs::read_to_string(path)?;
+        let upgraded = Self::generate_upgraded_file(path, packages, &original, only_selected, constraint)?;
+
+        let mut diff = vec![
+            format!("--- {}", path.display()),
+            format!("+++ {} (upgraded)", path.display()),
+        ];
+        let old: Vec<&str> = crate::parser::strip_bom(&original).lines().collect();
+        let new: Vec<&str> = crate::parser::strip_bom(&upgraded).lines().collect();
+        diff.extend(line_diff(&old, &new));
+
+        Ok(diff.join("\n"))
+    }
+
+    /// Backs up the file `path` refers to next to it: a symlinked
+    /// requirements file is backed up beside its target.
+    pub fn create_backup<P: AsRef<Path>>(path: P) -> Result<String> {
+        let path = &real_path(path.as_ref());
         let content = fs::read_to_string(path)?;
 
         let timestamp = Local::now().format("%Y%m%d_%H%M%S");
@@ -82,8 +479,70 @@ impl UpgradeManager {
     }
 
     pub fn write_requirements<P: AsRef<Path>>(path: P, content: &str) -> Result<()> {
-        fs::write(path, content)?;
-        Ok(())
+        write_atomic(path.as_ref(), content)
+    }
+
+    /// Writes the upgraded content of `input` to `output`, leaving the input
+    /// untouched. Without an output, or when it names the input itself, the
+    /// input is backed up and rewritten in place; the backup path is returned.
+    pub fn write_upgraded<P: AsRef<Path>>(
+        input: P,
+        output: Option<&Path>,
+        packages: &[Package],
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<Option<String>> {
+        let input = input.as_ref();
+        let new_content = Self::generate_upgraded_file(
+            input,
+            packages,
+            &fs::read_to_string(input)?,
+            only_selected,
+            constraint,
+        )?;
+
+        match output {
+            Some(output) if !is_same_file(input, output) => {
+                write_atomic(output, &new_content)?;
+                Ok(None)
+            }
+            _ => {
+                let backup_path = Self::create_backup(input)?;
+                write_atomic(input, &new_content)?;
+                Ok(Some(backup_path))
+            }
+        }
+    }
+
+    /// Upgrades every file in place, each with its own backup and atomic
+    /// write. Packages are matched to files by `origin_file`; files with
+    /// nothing to upgrade are left untouched and get no backup.
+    pub fn write_upgraded_files(
+        files: &[String],
+        packages: &[Package],
+        only_selected: bool,
+        constraint: ConstraintUpdate,
+    ) -> Result<Vec<FileUpgrade>> {
+        let mut results = Vec::new();
+        for file in files {
+            let declared: Vec<Package> = packages
+                .iter()
+                .filter(|p| p.origin_file.as_deref() == Some(file.as_str()))
+                .cloned()
+                .collect();
+            let upgraded = declared.iter().filter(|p| is_upgraded(p, only_selected, constraint)).count();
+            let backup_path = if upgraded > 0 {
+                Self::write_upgraded(file, None, &declared, only_selected, constraint)?
+            } else {
+                None
+            };
+            results.push(FileUpgrade {
+                path: file.clone(),
+                backup_path,
+                upgraded,
+            });
+        }
+        Ok(results)
     }
 
     pub fn write_lock_file<P: AsRef<Path>>(
@@ -91,44 +550,99 @@ impl UpgradeManager {
         packages: &[Package],
     ) -> Result<String> {
         let lock_path = format!("{}.lock", base_path.as_ref().display());
-        let mut lines = Vec::new();
-
-        lines.push("# PyElevate Lock File - DO NOT EDIT MANUALLY".to_string());
-        lines.push("# This file is automatically generated".to_string());
-        lines.push(format!(
-            "# Generated at {}",
-            Local::now().format("%Y-%m-%d %H:%M:%S UTC")
-        ));
-        lines.push(String::new());
-
-        for pkg in packages {
-            if let Some(latest) = &pkg.latest_version {
-                lines.push(format!(
-                    "{}{}=={}",
-                    pkg.name,
-                    if pkg.extras.is_empty() {
-                        String::new()
-                    } else {
-                        format!("[{}]", pkg.extras.join(","))
-                    },
-                    latest
-                ));
-            } else {
-                lines.push(format!(
-                    "{}{}=={}",
-                    pkg.name,
-                    if pkg.extras.is_empty() {
-                        String::new()
-                    } else {
-                        format!("[{}]", pkg.extras.join(","))
-                    },
-                    pkg.current_version
-                ));
+        write_atomic(Path::new(&lock_path), &Self::lock_content(packages))?;
+        Ok(lock_path)
+    }
+
+    /// Compares the requirements against the lock file they were locked
+    /// to. A locked version only counts as changed when it no longer meets
+    /// the requirement, so `django>=4.2` locked at 4.2.9 is not drift; URL
+    /// and path requirements are only checked for presence.
+    pub fn diff_lock(requirements: &[Package], lock: &str) -> LockDiff {
+        let locked: Vec<(String, String, &str)> = lock
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| match line.split_once("==") {
+                Some((name, version)) => {
+                    let bare = name.split('[').next().unwrap_or(name).trim();
+                    Some((normalize_package_name(bare), bare.to_string(), version.trim()))
+                }
+                None => {
+                    let name = crate::parser::requirement_name(line)?;
+                    Some((normalize_package_name(&name), name, ""))
+                }
+            })
+            .collect();
+        let mut diff = LockDiff::default();
+
+        for pkg in requirements {
+            let name = normalize_package_name(&pkg.name);
+            let Some((_, _, version)) = locked.iter().find(|(locked, _, _)| *locked == name) else {
+                if !diff.added.contains(&pkg.name) {
+                    diff.added.push(pkg.name.clone());
+                }
+                continue;
+            };
+            let spec = pkg.constraint.as_str();
+            if matches!(pkg.source, DependencySource::PyPI)
+                && !crate::version::specifier_allows(&spec, version)
+            {
+                diff.changed.push((pkg.name.clone(), version.to_string(), spec));
+            }
+        }
+        for (name, original, _) in &locked {
+            if !requirements.iter().any(|p| normalize_package_name(&p.name) == *name) {
+                diff.removed.push(original.clone());
             }
         }
+        diff
+    }
 
-        fs::write(&lock_path, lines.join("\n"))?;
-        Ok(lock_path)
+    /// The lock file body: one `name[extras]==version` line per package,
+    /// sorted by normalized name with extras in sorted order. Git, URL and
+    /// path requirements keep their reference instead of a version. There
+    /// is no timestamp, so an unchanged set of packages locks to the same
+    /// bytes.
+    pub fn lock_content(packages: &[Package]) -> String {
+        let mut entries: Vec<(String, String)> = packages
+            .iter()
+            .filter(|pkg| !matches!(pkg.source, DependencySource::Unknown))
+            .map(|pkg| {
+                if let Some(reference) = pkg.direct_reference() {
+                    return (normalize_package_name(&pkg.name), reference);
+                }
+                // Policy exceptions stay on the version the requirements file keeps.
+                let locked = !pkg.policy.freeze && (!pkg.policy.pin || pkg.selected);
+                let version = pkg
+                    .upgrade_target()
+                    .filter(|_| locked)
+                    .unwrap_or(&pkg.current_version);
+                let mut extras = pkg.extras.clone();
+                extras.sort();
+                let extras = if extras.is_empty() {
+                    String::new()
+                } else {
+                    format!("[{}]", extras.join(","))
+                };
+                (
+                    normalize_package_name(&pkg.name),
+                    format!("{}{}=={}", pkg.name, extras, version),
+                )
+            })
+            .collect();
+        entries.sort();
+        entries.dedup();
+
+        let mut lines = vec![
+            "# PyElevate Lock File - DO NOT EDIT MANUALLY".to_string(),
+            "# This file is automatically generated".to_string(),
+            String::new(),
+        ];
+        lines.extend(entries.into_iter().map(|(_, line)| line));
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
     }
 
     pub fn calculate_upgrade_results(
@@ -139,15 +653,15 @@ impl UpgradeManager {
             .iter()
             .filter(|p| {
                 if only_selected {
-                    p.selected && p.latest_version.is_some()
+                    p.selected && p.upgrade_target().is_some()
                 } else {
-                    p.latest_version.is_some()
+                    p.upgrade_target().is_some()
                 }
             })
             .map(|p| UpgradeResult {
                 package_name: p.name.clone(),
                 old_version: p.current_version.clone(),
-                new_version: p.latest_version.clone().unwrap_or_default(),
+                new_version: p.upgrade_target().unwrap_or_default().to_string(),
                 success: true,
                 error: None,
             })
@@ -155,42 +669,264 @@ impl UpgradeManager {
     }
 }
 
-fn generate_requirement_line(pkg: &Package, new_version: &str) -> String {
+fn dominant_operator(lines: &[String]) -> &'static str {
+    let mut counts = [("==", 0), (">=", 0), ("~=", 0)];
+    for line in lines {
+        if crate::parser::requirement_name(line).is_none() {
+            continue;
+        }
+        for (operator, count) in counts.iter_mut() {
+            if line.contains(*operator) {
+                *count += 1;
+            }
+        }
+    }
+    counts
+        .iter()
+        .fold(counts[0], |best, &c| if c.1 > best.1 { c } else { best })
+        .0
+}
+
+/// `line` without inline `--hash` options or a trailing continuation.
+fn without_hashes(line: &str) -> String {
+    line.split(" --hash")
+        .next()
+        .unwrap_or(line)
+        .trim_end()
+        .trim_end_matches('\\')
+        .trim_end()
+        .to_string()
+}
+
+/// The trailing comment of a requirement line, unless it's a `# via`
+/// annotation. A `#` needs whitespace before it, so URL fragments don't count.
+fn inline_comment(line: &str) -> Option<&str> {
+    let start = line
+        .match_indices('#')
+        .map(|(i, _)| i)
+        .find(|&i| i > 0 && line[..i].ends_with(char::is_whitespace))?;
+    let comment = line[start..].trim_end().trim_end_matches('\\').trim_end();
+    (!comment[1..].trim_start().starts_with("via ")).then_some(comment)
+}
+
+/// Joins rewritten `lines` with the byte order mark and line ending of the
+/// file they came from.
+fn join_like<S: AsRef<str>>(original: &str, lines: &[S], trailing_newline: bool) -> String {
+    let ending = crate::parser::line_ending(original);
+    let mut result = String::new();
+    if original.starts_with('\u{feff}') {
+        result.push('\u{feff}');
+    }
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            result.push_str(ending);
+        }
+        result.push_str(line.as_ref());
+    }
+    if trailing_newline && !lines.is_empty() {
+        result.push_str(ending);
+    }
+    result
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    if a == b {
+        return true;
+    }
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The file `path` names, following a symlink so it isn't replaced by a
+/// plain file. Anything else is kept as given, so messages still show the
+/// path the user typed.
+fn real_path(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it over the
+/// target, so readers only ever observe the complete old or new file.
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let path = &real_path(path);
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Invalid target path: {}", path.display()))?;
+    let tmp_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name,
+        &uuid::Uuid::new_v4().to_string()[0..8]
+    ));
+
+    let result = (|| -> Result<()> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// `old` turned into `new` as unified diff body lines, from their longest
+/// common subsequence, so a rewrite that adds or drops lines stays aligned.
+fn line_diff(old: &[&str], new: &[&str]) -> Vec<String> {
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!(" {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// What follows the requirement itself on `line`: per-requirement options,
+/// a comment and the continuation backslash, with the whitespace before them.
+fn requirement_trailer(line: &str) -> &str {
+    let continuation = line.trim_end().strip_suffix('\\').map(str::len);
+    let end = [line.find('#'), line.find(" --"), continuation]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(line.len());
+    &line[line[..end].trim_end().len()..]
+}
+
+fn has_hash(line: &str) -> bool {
+    line.split_whitespace().any(|token| token.starts_with("--hash"))
+}
+
+/// The Poetry version string for an upgrade to `target`, given the
+/// upgraded PEP 508 `requirement`. Carets and tildes stay while the
+/// constraint is kept as written; exact versions stay bare.
+fn poetry_version(original: &str, requirement: &str, target: &str, constraint: ConstraintUpdate) -> String {
+    let caret_or_tilde = original.starts_with('^') || (original.starts_with('~') && !original.starts_with("~="));
+    if constraint == ConstraintUpdate::Keep && caret_or_tilde {
+        return format!("{}{}", &original[..1], target);
+    }
+    let spec = &requirement[requirement.find(|c| "<>=!~".contains(c)).unwrap_or(requirement.len())..];
+    let spec = spec.split(';').next().unwrap_or(spec).trim();
+    spec.strip_prefix("==").unwrap_or(spec).to_string()
+}
+
+fn is_upgraded(pkg: &Package, only_selected: bool, constraint: ConstraintUpdate) -> bool {
+    let upgraded = if only_selected {
+        pkg.selected && pkg.has_upgrade()
+    } else {
+        pkg.has_upgrade() && !pkg.policy.pin
+    };
+    upgraded && UpgradeManager::rewrites_line(pkg, constraint)
+}
+
+/// The smallest cap above `new_version` at the precision of `cap`: `<2`
+/// becomes `<3` for 2.1, and `<1.2` becomes `<1.6` for 1.5.
+fn raise_cap(cap: &str, new_version: &str) -> String {
+    let mut parts = crate::models::release_parts(new_version);
+    parts.resize(crate::models::release_parts(cap).len().max(1), 0);
+    if let Some(last) = parts.last_mut() {
+        *last += 1;
+    }
+    parts.iter().map(|part| part.to_string()).collect::<Vec<_>>().join(".")
+}
+
+fn generate_requirement_line(pkg: &Package, new_version: &str, constraint: ConstraintUpdate) -> String {
     let extras_str = if pkg.extras.is_empty() {
         String::new()
     } else {
         format!("[{}]", pkg.extras.join(","))
     };
+    let marker = pkg
+        .marker
+        .as_deref()
+        .map(|marker| format!("; {}", marker))
+        .unwrap_or_default();
 
-    match &pkg.constraint {
-        VersionConstraint::Pinned(_) => {
-            format!("{}{}=={}", pkg.name, extras_str, new_version)
-        }
-        VersionConstraint::GreaterEqual(old) => {
-            format!(
-                "{}{}>={}  # upgraded from {}",
-                pkg.name, extras_str, new_version, old
-            )
-        }
-        VersionConstraint::Compatible(_) => {
-            format!("{}{}~={}", pkg.name, extras_str, new_version)
+    let operator = match (constraint, &pkg.constraint) {
+        (ConstraintUpdate::Floor, _) => ">=",
+        (ConstraintUpdate::Pin, _) => "==",
+        (ConstraintUpdate::Compatible, _) => {
+            // `~=` needs at least two release segments.
+            let version = if new_version.contains('.') {
+                new_version.to_string()
+            } else {
+                format!("{}.0", new_version)
+            };
+            return format!("{}{}~={}{}", pkg.name, extras_str, version, marker);
         }
-        VersionConstraint::Range(_, _) => {
-            format!("{}{}=={}", pkg.name, extras_str, new_version)
+        (ConstraintUpdate::Keep, VersionConstraint::GreaterEqual(old)) => {
+            return format!(
+                "{}{}>={}{}  # upgraded from {}",
+                pkg.name, extras_str, new_version, marker, old
+            );
         }
-        VersionConstraint::Less(_) => {
-            format!("{}{}=={}", pkg.name, extras_str, new_version)
+        (ConstraintUpdate::Keep, VersionConstraint::Compatible(_)) => "~=",
+        // A cap stays while the new version is still under it, and is
+        // otherwise raised just past it, with a note saying so.
+        (ConstraintUpdate::Keep, VersionConstraint::Range(_, high) | VersionConstraint::Less(high)) => {
+            if crate::models::compare_release(new_version, high).is_lt() {
+                return format!("{}{}>={},<{}{}", pkg.name, extras_str, new_version, high, marker);
+            }
+            return format!(
+                "{}{}>={},<{}{}  # cap raised from <{}",
+                pkg.name,
+                extras_str,
+                new_version,
+                raise_cap(high, new_version),
+                marker,
+                high
+            );
         }
-        VersionConstraint::Unspecified => {
-            format!("{}{}=={}", pkg.name, extras_str, new_version)
+        // A bare name already takes the latest release; pinning it would be a surprise.
+        (ConstraintUpdate::Keep, VersionConstraint::Unspecified) => {
+            return format!("{}{}{}", pkg.name, extras_str, marker);
         }
-    }
+        (ConstraintUpdate::Keep, VersionConstraint::Pinned(_)) => "==",
+    };
+    format!("{}{}{}{}{}", pkg.name, extras_str, operator, new_version, marker)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{DependencySource, VersionStatus, SecurityStatus};
+    use crate::models::{VersionStatus, SecurityStatus};
 
     #[test]
     fn test_generate_requirement_line() {
@@ -200,20 +936,68 @@ mod tests {
             latest_version: Some("2.28.1".to_string()),
             status: VersionStatus::Patch,
             selected: true,
-            extras: vec![],
             constraint: VersionConstraint::Pinned("2.28.0".to_string()),
-            error: None,
-            source: DependencySource::PyPI,
             security_status: SecurityStatus::Safe,
-            changelog: None,
-            popularity: None,
-            dependencies: vec![],
+            ..Package::default()
         };
 
-        let line = generate_requirement_line(&pkg, "2.28.1");
+        let line = generate_requirement_line(&pkg, "2.28.1", ConstraintUpdate::Keep);
         assert_eq!(line, "requests==2.28.1");
     }
 
+    #[test]
+    fn test_update_constraint_modes() {
+        let pkg = Package {
+            name: "pkg".to_string(),
+            current_version: "1.0".to_string(),
+            latest_version: Some("1.5".to_string()),
+            status: VersionStatus::Minor,
+            selected: true,
+            constraint: VersionConstraint::GreaterEqual("1.0".to_string()),
+            security_status: SecurityStatus::Safe,
+            ..Package::default()
+        };
+        let upgrade = |mode| {
+            UpgradeManager::generate_upgraded_content(std::slice::from_ref(&pkg), "pkg>=1.0\n", false, mode)
+                .unwrap()
+        };
+
+        assert_eq!(upgrade(ConstraintUpdate::Keep), "pkg>=1.5  # upgraded from 1.0\n");
+        assert_eq!(upgrade(ConstraintUpdate::Floor), "pkg>=1.5\n");
+        assert_eq!(upgrade(ConstraintUpdate::Pin), "pkg==1.5\n");
+        assert_eq!(upgrade(ConstraintUpdate::Compatible), "pkg~=1.5\n");
+        assert_eq!(generate_requirement_line(&pkg, "2", ConstraintUpdate::Compatible), "pkg~=2.0");
+    }
+
+    #[test]
+    fn test_keep_preserves_operator_style() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let upgrade = |line: &str| {
+            fs::write(&path, line).unwrap();
+            let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+            packages[0].latest_version = Some("1.5".to_string());
+            UpgradeManager::generate_upgraded_content(&packages, line, false, ConstraintUpdate::Keep).unwrap()
+        };
+
+        assert_eq!(upgrade("pkg>=1.0\n"), "pkg>=1.5  # upgraded from 1.0\n");
+        assert_eq!(upgrade("pkg==1.0\n"), "pkg==1.5\n");
+        assert_eq!(upgrade("pkg~=1.0\n"), "pkg~=1.5\n");
+        assert_eq!(upgrade("pkg>=1.0,<2\n"), "pkg>=1.5,<2\n");
+        assert_eq!(upgrade("pkg<1.2\n"), "pkg>=1.5,<1.6  # cap raised from <1.2\n");
+        assert_eq!(upgrade("pkg>=1.0,<1.5\n"), "pkg>=1.5,<1.6  # cap raised from <1.5\n");
+        assert_eq!(upgrade("pkg\n"), "pkg\n");
+
+        // A bare name is not an upgrade: nothing is counted or backed up.
+        fs::write(&path, "pkg\n").unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        packages[0].latest_version = Some("1.5".to_string());
+        let file = path.to_string_lossy().to_string();
+        let results = UpgradeManager::write_upgraded_files(&[file], &packages, false, ConstraintUpdate::Keep).unwrap();
+        assert_eq!((results[0].upgraded, results[0].backup_path.as_deref()), (0, None));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn test_generate_requirement_line_with_extras() {
         let pkg = Package {
@@ -224,15 +1008,500 @@ mod tests {
             selected: true,
             extras: vec!["security".to_string(), "socks".to_string()],
             constraint: VersionConstraint::Pinned("2.28.0".to_string()),
-            error: None,
-            source: DependencySource::PyPI,
             security_status: SecurityStatus::Safe,
-            changelog: None,
-            popularity: None,
-            dependencies: vec![],
+            ..Package::default()
         };
 
-        let line = generate_requirement_line(&pkg, "2.28.1");
+        let line = generate_requirement_line(&pkg, "2.28.1", ConstraintUpdate::Keep);
         assert!(line.contains("requests[security,socks]==2.28.1"));
+
+        let pkg = Package {
+            marker: Some(r#"sys_platform == "win32""#.to_string()),
+            ..pkg
+        };
+        let line = generate_requirement_line(&pkg, "2.28.1", ConstraintUpdate::Keep);
+        assert_eq!(line, r#"requests[security,socks]==2.28.1; sys_platform == "win32""#);
+    }
+
+    #[test]
+    fn test_policy_directive_survives_upgrade() {
+        let original = "flask==1.0.0  # pyelevate: pin, ignore-security\nrequests==2.28.0\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        fs::write(&path, original).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        assert!(packages[0].policy.pin && packages[0].policy.ignore_security);
+        for pkg in &mut packages {
+            pkg.latest_version = Some("3.0.0".to_string());
+        }
+
+        // A pin is left alone by a full upgrade...
+        let content = UpgradeManager::generate_upgraded_content(&packages, original, false, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(content, "flask==1.0.0  # pyelevate: pin, ignore-security\nrequests==3.0.0\n");
+
+        // ...and keeps its directive when selected explicitly.
+        packages[0].selected = true;
+        let content = UpgradeManager::generate_upgraded_content(&packages, original, true, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(content, "flask==3.0.0  # pyelevate: pin, ignore-security\nrequests==2.28.0\n");
+    }
+
+    #[test]
+    fn test_upgrade_matches_whole_names_not_prefixes() {
+        let content = "django-cors-headers==4.0.0\ndjango==4.2.9\nsix==1.16.0\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        fs::write(&path, content).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        for pkg in &mut packages {
+            pkg.latest_version = Some(format!("{}.1", pkg.current_version));
+            pkg.selected = pkg.name == "django";
+        }
+
+        let upgraded = UpgradeManager::generate_upgraded_content(&packages, content, true, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(upgraded, "django-cors-headers==4.0.0\ndjango==4.2.9.1\nsix==1.16.0\n");
+    }
+
+    #[tokio::test]
+    async fn test_upgrading_hashed_pin_refreshes_hashes() {
+        let server = crate::test_support::MockServer::routes(&[(
+            "/pypi/requests/json",
+            crate::test_support::MockResponse::json(
+                r#"{"info":{"name":"requests","version":"2.28.1","summary":""},"releases":{
+                    "2.28.0":[{"filename":"requests-2.28.0.tar.gz","digests":{"sha256":"aaa"}}],
+                    "2.28.1":[
+                        {"filename":"requests-2.28.1-py3-none-any.whl","digests":{"sha256":"bbb"}},
+                        {"filename":"requests-2.28.1.tar.gz","digests":{"sha256":"ccc"}},
+                        {"filename":"requests-2.28.1.zip","digests":{"sha256":"ddd"},"yanked":true}
+                    ]}}"#,
+            ),
+        )]);
+        let mut client = crate::pypi::PyPIClient::new();
+        client.apply_options(&[crate::models::GlobalOption::IndexUrl(server.url("/pypi"))]);
+
+        let original = "requests==2.28.0 \\\n    --hash=sha256:aaa\nsix==1.16.0 --hash=sha256:eee\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        fs::write(&path, original).unwrap();
+        let req_file = crate::parser::parse_requirements(&path).unwrap();
+        assert!(crate::parser::hash_violations(&req_file.raw_lines).is_empty());
+
+        let mut packages = req_file.packages;
+        packages.retain(|p| p.name == "requests");
+        client.update_packages(&mut packages).await;
+        client.attach_hashes(&mut packages);
+
+        let content = UpgradeManager::generate_upgraded_content(&packages, original, false, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(
+            content,
+            "requests==2.28.1 \\\n    --hash=sha256:bbb \\\n    --hash=sha256:ccc\nsix==1.16.0 --hash=sha256:eee\n"
+        );
+        let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+        assert!(crate::parser::hash_violations(&lines).is_empty());
+
+        // Without known hashes the upgrade is refused rather than unhashed.
+        packages[0].target_hashes.clear();
+        assert!(UpgradeManager::generate_upgraded_content(&packages, original, false, ConstraintUpdate::Keep).is_err());
+    }
+
+    #[test]
+    fn test_preview_upgrade_shows_result_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let original = "# HTTP\nrequests==2.28.0\nflask==2.0.0\n";
+        fs::write(&path, original).unwrap();
+
+        let pkg = Package {
+            name: "requests".to_string(),
+            current_version: "2.28.0".to_string(),
+            latest_version: Some("2.31.0".to_string()),
+            status: VersionStatus::Minor,
+            selected: true,
+            constraint: VersionConstraint::Pinned("2.28.0".to_string()),
+            security_status: SecurityStatus::Safe,
+            ..Package::default()
+        };
+
+        let preview = UpgradeManager::preview_upgrade(&path, &[pkg], false, ConstraintUpdate::Keep).unwrap();
+        assert!(preview.contains("-requests==2.28.0"));
+        assert!(preview.contains("+requests==2.31.0"));
+        assert!(preview.contains(" flask==2.0.0"));
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_line_diff_keeps_lines_after_an_insertion_aligned() {
+        let old = ["requests==2.28.0", "six==1.16.0"];
+        let new = ["requests==2.31.0", "    --hash=sha256:bbb", "six==1.16.0"];
+        assert_eq!(
+            line_diff(&old, &new),
+            vec![
+                "-requests==2.28.0",
+                "+requests==2.31.0",
+                "+    --hash=sha256:bbb",
+                " six==1.16.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preview_stays_aligned_when_hash_lines_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        fs::write(&path, "requests==2.28.0 \\\n    --hash=sha256:aaa\nsix==1.16.0\n").unwrap();
+
+        let pkg = Package {
+            name: "requests".to_string(),
+            current_version: "2.28.0".to_string(),
+            latest_version: Some("2.31.0".to_string()),
+            status: VersionStatus::Minor,
+            constraint: VersionConstraint::Pinned("2.28.0".to_string()),
+            target_hashes: vec!["sha256:bbb".to_string(), "sha256:ccc".to_string()],
+            ..Package::default()
+        };
+
+        let preview = UpgradeManager::preview_upgrade(&path, &[pkg], false, ConstraintUpdate::Keep).unwrap();
+        let body: Vec<&str> = preview.lines().skip(2).collect();
+        assert_eq!(
+            body,
+            vec![
+                "-requests==2.28.0 \\",
+                "-    --hash=sha256:aaa",
+                "+requests==2.31.0 \\",
+                "+    --hash=sha256:bbb \\",
+                "+    --hash=sha256:ccc",
+                " six==1.16.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upgrading_two_files_backs_up_and_writes_each() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<String> = ["requirements.txt", "requirements-dev.txt", "docs.txt"]
+            .iter()
+            .map(|name| dir.path().join(name).to_string_lossy().to_string())
+            .collect();
+        fs::write(&files[0], "requests==2.28.0\nflask==2.0.0\n").unwrap();
+        fs::write(&files[1], "pytest==7.0.0\n").unwrap();
+        fs::write(&files[2], "sphinx==7.0.0\n").unwrap();
+
+        let mut packages = Vec::new();
+        for file in &files {
+            packages.extend(crate::parser::parse_requirements(file).unwrap().packages);
+        }
+        for pkg in packages.iter_mut().filter(|p| p.name != "sphinx") {
+            pkg.latest_version = Some("9.0.0".to_string());
+        }
+
+        let results = UpgradeManager::write_upgraded_files(&files, &packages, false, ConstraintUpdate::Keep).unwrap();
+        let upgraded: Vec<usize> = results.iter().map(|r| r.upgraded).collect();
+        assert_eq!(upgraded, vec![2, 1, 0]);
+        assert_eq!(fs::read_to_string(&files[0]).unwrap(), "requests==9.0.0\nflask==9.0.0\n");
+        assert_eq!(fs::read_to_string(&files[1]).unwrap(), "pytest==9.0.0\n");
+        assert_eq!(fs::read_to_string(&files[2]).unwrap(), "sphinx==7.0.0\n");
+
+        let backups: Vec<&String> = results.iter().filter_map(|r| r.backup_path.as_ref()).collect();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(backups[0]).unwrap(), "requests==2.28.0\nflask==2.0.0\n");
+        assert_eq!(fs::read_to_string(backups[1]).unwrap(), "pytest==7.0.0\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 5);
+    }
+
+    #[test]
+    fn test_upgrading_pyproject_rewrites_versions_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pyproject.toml");
+        fs::write(
+            &path,
+            "[project]\nname = \"demo\"\ndependencies = [\n    \"django>=4.2,<5\",  # web\n    \"requests[socks]==2.31.0\",\n    \"six\",\n]\n\n[tool.poetry.group.lint.dependencies]\nblack = \"^24.1\"\nisort = { version = \"5.13.2\", extras = [\"colors\"] }\n",
+        )
+        .unwrap();
+        let all = crate::pyproject::GroupSelection { groups: Vec::new(), all: true };
+        let mut packages = crate::pyproject::parse_pyproject(&path, &all).unwrap().packages;
+        for (name, latest) in [("django", "5.1"), ("requests", "2.32.3"), ("six", "1.17.0"), ("black", "25.1.0"), ("isort", "6.0.0")] {
+            packages.iter_mut().find(|p| p.name == name).unwrap().latest_version = Some(latest.to_string());
+        }
+
+        UpgradeManager::write_upgraded(&path, None, &packages, false, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[project]\nname = \"demo\"\ndependencies = [\n    \"django>=5.1,<6\",  # web\n    \"requests[socks]==2.32.3\",\n    \"six\",\n]\n\n[tool.poetry.group.lint.dependencies]\nblack = \"^25.1.0\"\nisort = { version = \"6.0.0\", extras = [\"colors\"] }\n"
+        );
+    }
+
+    #[test]
+    fn test_write_upgraded_to_separate_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("requirements.txt");
+        let output = dir.path().join("requirements.upgraded.txt");
+        let original = "requests==2.28.0\nflask==2.0.0\n";
+        fs::write(&input, original).unwrap();
+
+        let pkg = Package {
+            name: "requests".to_string(),
+            current_version: "2.28.0".to_string(),
+            latest_version: Some("2.31.0".to_string()),
+            status: VersionStatus::Minor,
+            selected: true,
+            constraint: VersionConstraint::Pinned("2.28.0".to_string()),
+            security_status: SecurityStatus::Safe,
+            ..Package::default()
+        };
+
+        let packages = [pkg];
+        let backup = UpgradeManager::write_upgraded(&input, Some(&output), &packages, false, ConstraintUpdate::Keep)
+            .unwrap();
+        assert!(backup.is_none());
+        assert_eq!(fs::read_to_string(&input).unwrap(), original);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "requests==2.31.0\nflask==2.0.0\n"
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let backup = UpgradeManager::write_upgraded(&input, Some(&input), &packages, false, ConstraintUpdate::Keep).unwrap();
+        assert!(backup.is_some());
+        assert!(fs::read_to_string(&input).unwrap().contains("requests==2.31.0"));
+    }
+
+    #[test]
+    fn test_add_requirements_inserts_formatted_line() {
+        let mut pkg = Package {
+            name: "httpx".to_string(),
+            current_version: "0.27.0".to_string(),
+            latest_version: Some("0.27.0".to_string()),
+            status: VersionStatus::UpToDate,
+            constraint: VersionConstraint::Pinned("0.27.0".to_string()),
+            ..Package::default()
+        };
+
+        let sorted = "# web\nflask==2.0.0\nrequests==2.28.0  # http\n\n# tools\n";
+        assert_eq!(
+            UpgradeManager::add_requirements(sorted, &[&pkg]),
+            "# web\nflask==2.0.0\nhttpx==0.27.0\nrequests==2.28.0  # http\n\n# tools\n"
+        );
+
+        pkg.extras = vec!["http2".to_string()];
+        let unsorted = "requests>=2.28.0\nflask>=2.0.0\n# end";
+        assert_eq!(
+            UpgradeManager::add_requirements(unsorted, &[&pkg]),
+            "requests>=2.28.0\nflask>=2.0.0\nhttpx[http2]>=0.27.0\n# end"
+        );
+    }
+
+    #[test]
+    fn test_relax_pins_into_requirements_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "--index-url https://pypi.org/simple\n\
+pkg==1.4.2 \\\n    --hash=sha256:abc\n    # via -r requirements.in\n\
+requests[socks]==2.31.0 ; python_version >= \"3.8\"  # needs socks\n    # via -r requirements.in\n\
+click==8.1.7  # via -r requirements.in\n\
+urllib3==2.0.7\n    # via requests\n\
+mylib @ git+https://github.com/user/mylib.git@v1\n    # via -r requirements.in\n";
+        std::fs::write(&path, content).unwrap();
+        let packages = crate::parser::parse_requirements(&path).unwrap().packages;
+
+        assert_eq!(
+            UpgradeManager::relax_pins(content, &packages, RelaxStrategy::Compatible),
+            "--index-url https://pypi.org/simple\n\
+pkg~=1.4\n\
+requests[socks]~=2.31; python_version >= \"3.8\"  # needs socks\n\
+click~=8.1\n\
+mylib @ git+https://github.com/user/mylib.git@v1\n"
+        );
+        let floor = UpgradeManager::relax_pins(content, &packages, RelaxStrategy::Floor);
+        assert!(floor.contains("pkg>=1.4.2\n"));
+    }
+
+    #[test]
+    fn test_plan_for_one_selected_upgrade_has_one_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "# web\ndjango==4.2.9 \\\n    --hash=sha256:old\ndjango-redis==5.4.0\nflask==2.0.0\n";
+        std::fs::write(&path, content).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        for pkg in &mut packages {
+            pkg.latest_version = Some(format!("{}.1", pkg.current_version));
+            pkg.status = VersionStatus::Patch;
+            pkg.selected = pkg.name == "django";
+            pkg.target_hashes = vec!["sha256:new".to_string()];
+        }
+
+        let plan = UpgradeManager::plan(&packages, content, true, ConstraintUpdate::Keep).unwrap();
+        assert_eq!(
+            plan.changes,
+            vec![PlannedChange {
+                package: "django".to_string(),
+                from_version: "4.2.9".to_string(),
+                to_version: "4.2.9.1".to_string(),
+                old_line: "django==4.2.9 \\\n    --hash=sha256:old".to_string(),
+                new_line: "django==4.2.9.1 \\\n    --hash=sha256:new".to_string(),
+                status: VersionStatus::Patch,
+            }]
+        );
+        assert_eq!(
+            plan.content,
+            "# web\ndjango==4.2.9.1 \\\n    --hash=sha256:new\ndjango-redis==5.4.0\nflask==2.0.0\n"
+        );
+        assert_eq!(plan.stats.patch_available, 3);
+    }
+
+    #[test]
+    fn test_selected_package_without_target_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let content = "django==4.2.9\nflask==2.0.0";
+        std::fs::write(&path, content).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        for pkg in &mut packages {
+            pkg.selected = true;
+            pkg.status = VersionStatus::Error;
+        }
+
+        let plan = UpgradeManager::plan(&packages, content, true, ConstraintUpdate::Pin).unwrap();
+        assert!(plan.changes.is_empty());
+        assert_eq!(plan.skipped, vec!["django", "flask"]);
+        assert_eq!(plan.content, content);
+    }
+
+    #[test]
+    fn test_requirement_edited_after_locking_is_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let git = "mylib @ git+https://github.com/user/mylib.git@v1";
+        std::fs::write(&path, format!("django==4.2.9\nrequests>=2.28\nflask==3.0.0\n{}\n", git)).unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+        let releases = ["2.27.0", "2.31.0", "3.0.0rc1"].map(String::from);
+        let requests = packages.iter_mut().find(|p| p.name == "requests").unwrap();
+        assert_eq!(crate::resolver::newest_allowed(requests, &releases).as_deref(), Some("2.31.0"));
+        requests.current_version = "2.31.0".to_string();
+        let lock = UpgradeManager::lock_content(&packages);
+        assert!(lock.contains("requests==2.31.0\n"));
+        assert!(lock.contains(&format!("{}\n", git)));
+        assert!(UpgradeManager::diff_lock(&packages, &lock).is_empty());
+
+        std::fs::write(&path, format!("django==5.0.1\nrequests>=2.28\nhttpx==0.27.0\n{}\n", git)).unwrap();
+        let edited = crate::parser::parse_requirements(&path).unwrap().packages;
+        let diff = UpgradeManager::diff_lock(&edited, &lock);
+        assert_eq!(
+            diff.changed,
+            vec![("django".to_string(), "4.2.9".to_string(), "==5.0.1".to_string())]
+        );
+        assert_eq!(diff.added, vec!["httpx"]);
+        assert_eq!(diff.removed, vec!["flask"]);
+    }
+
+    #[test]
+    fn test_lock_file_is_sorted_and_reproducible() {
+        let locked = |name: &str, current: &str, latest: &str, extras: &[&str]| Package {
+            name: name.to_string(),
+            current_version: current.to_string(),
+            latest_version: Some(latest.to_string()),
+            status: VersionStatus::Minor,
+            selected: true,
+            extras: extras.iter().map(|e| e.to_string()).collect(),
+            constraint: VersionConstraint::Pinned(current.to_string()),
+            ..Package::default()
+        };
+        let packages = vec![
+            locked("requests", "2.28.0", "2.31.0", &["socks", "security"]),
+            locked("Django", "4.2.0", "4.2.7", &[]),
+            locked("charset_normalizer", "3.0.0", "3.3.2", &[]),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("requirements.txt");
+
+        let lock_path = UpgradeManager::write_lock_file(&base, &packages).unwrap();
+        let first = fs::read(&lock_path).unwrap();
+        let reversed: Vec<Package> = packages.iter().rev().cloned().collect();
+        UpgradeManager::write_lock_file(&base, &reversed).unwrap();
+        assert_eq!(fs::read(&lock_path).unwrap(), first);
+
+        let content = String::from_utf8(first).unwrap();
+        let entries: Vec<&str> = content.lines().filter(|l| !l.is_empty() && !l.starts_with('#')).collect();
+        assert_eq!(
+            entries,
+            vec!["charset_normalizer==3.3.2", "Django==4.2.7", "requests[security,socks]==2.31.0"]
+        );
+    }
+
+    #[test]
+    fn test_remove_requirements_drops_only_that_line() {
+        let content = "# web\nflask==2.0.0\nrequests==2.28.0 \\\n    --hash=sha256:abc\n\n# tools\nblack==23.1.0\n";
+        assert_eq!(
+            UpgradeManager::remove_requirements(content, &["Requests".to_string()]),
+            "# web\nflask==2.0.0\n\n# tools\nblack==23.1.0\n"
+        );
+        assert_eq!(UpgradeManager::remove_requirements(content, &[]), content);
+    }
+
+    #[test]
+    fn test_write_requirements_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        let old_content = "requests==2.28.0\n".repeat(20_000);
+        let new_content = "requests==2.28.1\n".repeat(20_000);
+        fs::write(&path, &old_content).unwrap();
+
+        let reader_path = path.clone();
+        let (old, new) = (old_content.clone(), new_content.clone());
+        let reader = std::thread::spawn(move || {
+            for _ in 0..200 {
+                let seen = fs::read_to_string(&reader_path).unwrap();
+                assert!(seen == old || seen == new, "observed a partial write");
+            }
+        });
+
+        for _ in 0..20 {
+            UpgradeManager::write_requirements(&path, &new_content).unwrap();
+            UpgradeManager::write_requirements(&path, &old_content).unwrap();
+        }
+        UpgradeManager::write_requirements(&path, &new_content).unwrap();
+        reader.join().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), new_content);
+        let leftovers = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_requirements_back_up_and_rewrite_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let real_dir = dir.path().join("shared");
+        fs::create_dir(&real_dir).unwrap();
+        let target = real_dir.join("requirements.txt");
+        fs::write(&target, "flask==2.0.0\n").unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir(&project).unwrap();
+        let link = project.join("requirements.txt");
+        std::os::unix::fs::symlink("../shared/requirements.txt", &link).unwrap();
+
+        let backup = UpgradeManager::create_backup(&link).unwrap();
+        assert_eq!(
+            Path::new(&backup).parent().unwrap(),
+            fs::canonicalize(&real_dir).unwrap()
+        );
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "flask==2.0.0\n");
+        assert_eq!(fs::read_dir(&project).unwrap().count(), 1);
+
+        UpgradeManager::write_requirements(&link, "flask==3.0.0\n").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "flask==3.0.0\n");
+    }
+
+    #[test]
+    fn test_failed_write_leaves_target_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("requirements.txt");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("keep"), "old").unwrap();
+
+        assert!(UpgradeManager::write_requirements(&target, "requests==2.28.1").is_err());
+        assert_eq!(fs::read_to_string(target.join("keep")).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
     }
 }
@@ -0,0 +1,267 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::cmp::Ordering;
+
+static VERSION_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?x)^
+        v?
+        (?:(?P<epoch>[0-9]+)!)?
+        (?P<release>[0-9]+(?:\.[0-9]+)*)
+        (?:[-_.]?(?P<pre_l>alpha|a|beta|b|preview|pre|c|rc)[-_.]?(?P<pre_n>[0-9]+)?)?
+        (?:-(?P<post_n1>[0-9]+)|[-_.]?(?P<post_l>post|rev|r)[-_.]?(?P<post_n2>[0-9]+)?)?
+        (?:[-_.]?(?P<dev_l>dev)[-_.]?(?P<dev_n>[0-9]+)?)?
+        (?:\+(?P<local>[a-z0-9]+(?:[-_.][a-z0-9]+)*))?
+        $",
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreRelease {
+    Alpha,
+    Beta,
+    Rc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalSegment {
+    Number(u64),
+    Text(String),
+}
+
+impl Ord for LocalSegment {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LocalSegment::Number(a), LocalSegment::Number(b)) => a.cmp(b),
+            (LocalSegment::Text(a), LocalSegment::Text(b)) => a.cmp(b),
+            (LocalSegment::Number(_), LocalSegment::Text(_)) => Ordering::Greater,
+            (LocalSegment::Text(_), LocalSegment::Number(_)) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for LocalSegment {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A PEP 440 version, ordered the way pip orders them: a local version
+/// (`1.0.0+cu118`) sorts above its public base, and dev releases sort below
+/// pre-releases of the same release.
+#[derive(Debug, Clone)]
+pub struct Pep440Version {
+    pub epoch: u64,
+    pub release: Vec<u64>,
+    pub pre: Option<(PreRelease, u64)>,
+    pub post: Option<u64>,
+    pub dev: Option<u64>,
+    pub local: Vec<LocalSegment>,
+}
+
+impl Pep440Version {
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().to_lowercase();
+        let caps = VERSION_PATTERN.captures(&version)?;
+        let number = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u64>().ok());
+
+        let pre = caps.name("pre_l").map(|label| {
+            let kind = match label.as_str() {
+                "a" | "alpha" => PreRelease::Alpha,
+                "b" | "beta" => PreRelease::Beta,
+                _ => PreRelease::Rc,
+            };
+            (kind, number("pre_n").unwrap_or(0))
+        });
+        let post = number("post_n1").or_else(|| {
+            caps.name("post_l")
+                .map(|_| number("post_n2").unwrap_or(0))
+        });
+        let dev = caps.name("dev_l").map(|_| number("dev_n").unwrap_or(0));
+        let local = caps
+            .name("local")
+            .map(|local| {
+                local
+                    .as_str()
+                    .split(['-', '_', '.'])
+                    .map(|segment| match segment.parse() {
+                        Ok(n) => LocalSegment::Number(n),
+                        Err(_) => LocalSegment::Text(segment.to_string()),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            epoch: number("epoch").unwrap_or(0),
+            release: caps["release"]
+                .split('.')
+                .map(|part| part.parse().unwrap_or(0))
+                .collect(),
+            pre,
+            post,
+            dev,
+            local,
+        })
+    }
+
+    pub fn major(&self) -> u64 {
+        self.release.first().copied().unwrap_or(0)
+    }
+
+    pub fn minor(&self) -> u64 {
+        self.release.get(1).copied().unwrap_or(0)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some() || self.dev.is_some()
+    }
+
+    fn cmp_release(&self, other: &Self) -> Ordering {
+        let len = self.release.len().max(other.release.len());
+        (0..len)
+            .map(|i| {
+                let a = self.release.get(i).unwrap_or(&0);
+                let b = other.release.get(i).unwrap_or(&0);
+                a.cmp(b)
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Pre-release key: a bare dev release sorts before any pre-release, and
+    /// a final release after all of them.
+    fn pre_key(&self) -> (u8, Option<(PreRelease, u64)>) {
+        match (self.pre, self.post, self.dev) {
+            (None, None, Some(_)) => (0, None),
+            (Some(pre), _, _) => (1, Some(pre)),
+            _ => (2, None),
+        }
+    }
+}
+
+impl Ord for Pep440Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch
+            .cmp(&other.epoch)
+            .then_with(|| self.cmp_release(other))
+            .then_with(|| self.pre_key().cmp(&other.pre_key()))
+            .then_with(|| self.post.cmp(&other.post))
+            .then_with(|| match (self.dev, other.dev) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(&b),
+            })
+            .then_with(|| self.local.cmp(&other.local))
+    }
+}
+
+impl PartialEq for Pep440Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Pep440Version {}
+
+impl PartialOrd for Pep440Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Whether `version` satisfies a comma-separated specifier set such as a
+/// `Requires-Python` value (`>=3.8,!=3.9.*`). Clauses that can't be parsed
+/// are treated as satisfied.
+pub fn specifier_allows(specifiers: &str, version: &str) -> bool {
+    let Some(version) = Pep440Version::parse(version) else {
+        return true;
+    };
+    specifiers
+        .split(',')
+        .map(str::trim)
+        .filter(|clause| !clause.is_empty())
+        .all(|clause| clause_allows(clause, &version))
+}
+
+fn clause_allows(clause: &str, version: &Pep440Version) -> bool {
+    let split = clause.find(|c: char| c.is_ascii_alphanumeric()).unwrap_or(clause.len());
+    let (op, value) = (clause[..split].trim(), clause[split..].trim());
+    let has_prefix = |prefix: &[u64]| {
+        prefix.iter().enumerate().all(|(i, part)| version.release.get(i).unwrap_or(&0) == part)
+    };
+
+    if let Some(prefix) = value.strip_suffix(".*") {
+        let Some(prefix) = Pep440Version::parse(prefix) else {
+            return true;
+        };
+        return match op {
+            "==" => has_prefix(&prefix.release),
+            "!=" => !has_prefix(&prefix.release),
+            _ => true,
+        };
+    }
+
+    let Some(bound) = Pep440Version::parse(value) else {
+        return true;
+    };
+    match op {
+        "==" | "===" => *version == bound,
+        "!=" => *version != bound,
+        ">=" => *version >= bound,
+        "<=" => *version <= bound,
+        ">" => *version > bound,
+        "<" => *version < bound,
+        "~=" => *version >= bound && has_prefix(&bound.release[..bound.release.len().saturating_sub(1)]),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{compare_release, compare_versions, VersionStatus};
+
+    fn v(version: &str) -> Pep440Version {
+        Pep440Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_local_versions_sort_above_their_base() {
+        assert!(v("1.0.0+cu118") > v("1.0.0"));
+        assert!(v("1.0.0+cu118") < v("1.0.1"));
+        assert!(v("2.1.0+cu121") > v("2.1.0+cu118"));
+        assert_eq!(v("1.0.0+cu118").local, vec![LocalSegment::Text("cu118".to_string())]);
+        assert!(compare_release("1.0.0+cu118", "1.0.0").is_gt());
+
+        assert_eq!(compare_versions("1.0.0", "1.0.0+cu118"), VersionStatus::Patch);
+        assert_eq!(compare_versions("2.1.0+cu118", "2.1.0"), VersionStatus::UpToDate);
+        assert_eq!(compare_versions("2.0.1+cu118", "2.1.0+cu121"), VersionStatus::Minor);
+    }
+
+    #[test]
+    fn test_pep440_ordering() {
+        let ordered = [
+            "1.0.dev1", "1.0a1", "1.0a2.dev1", "1.0b1", "1.0rc1", "1.0", "1.0.post1", "1.1", "1!0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(Pep440Version::parse("not-a-version").is_none());
+        assert_eq!(compare_versions("1.0.0", "2.0.0rc1"), VersionStatus::Prerelease);
+    }
+
+    #[test]
+    fn test_specifier_allows() {
+        assert!(specifier_allows(">=3.8", "3.9"));
+        assert!(!specifier_allows(">=3.11", "3.9"));
+        assert!(!specifier_allows(">=3.7, !=3.9.*", "3.9"));
+        assert!(specifier_allows(">=3.7, !=3.9.*", "3.10"));
+        assert!(specifier_allows("~=3.8", "3.12"));
+        assert!(!specifier_allows("<3.12,>=3.8", "3.12"));
+        assert!(specifier_allows("", "3.9"));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::models::{compare_versions, normalize_package_name, Distribution, GlobalOption, Package};
+use crate::pypi::PyPIClient;
+use crate::version::{specifier_allows, Pep440Version};
+use std::process::Command;
+
+/// The note for packages whose latest release has no wheel the target
+/// platform can install.
+pub const NO_WHEEL_NOTE: &str = "no wheel for your platform";
+
+/// Starts the note for back-ports of modules the target Python ships.
+pub const REDUNDANT_NOTE: &str = "redundant on Python >=";
+
+/// PyPI back-ports of standard library modules, with the Python version
+/// that added the module.
+const STDLIB_BACKPORTS: &[(&str, (u32, u32))] = &[
+    ("argparse", (3, 2)),
+    ("asyncio", (3, 4)),
+    ("backports.zoneinfo", (3, 9)),
+    ("contextvars", (3, 7)),
+    ("dataclasses", (3, 7)),
+    ("enum34", (3, 4)),
+    ("futures", (3, 2)),
+    ("ipaddress", (3, 3)),
+    ("pathlib", (3, 4)),
+    ("selectors34", (3, 4)),
+    ("statistics", (3, 4)),
+    ("tomli", (3, 11)),
+    ("typing", (3, 5)),
+];
+
+/// The interpreter and platform that wheels have to match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformTags {
+    /// `(major, minor)` of the target interpreter; `None` accepts any CPython 3.
+    pub python: Option<(u32, u32)>,
+    pub os: String,
+    pub arch: String,
+    /// A musl libc Linux such as Alpine, which needs `musllinux` wheels
+    /// instead of `manylinux` ones.
+    pub musl: bool,
+}
+
+impl PlatformTags {
+    /// The platform pyelevate runs on, with the Python version reported by
+    /// `python3` on `PATH`.
+    pub fn detect() -> Self {
+        Self {
+            python: detect_python_version(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            musl: detect_musl(),
+        }
+    }
+
+    /// Targets `python` instead of the detected interpreter, when given.
+    pub fn with_python(mut self, python: Option<(u32, u32)>) -> Self {
+        if python.is_some() {
+            self.python = python;
+        }
+        self
+    }
+
+    /// Whether a `python-abi` tag pair can be loaded by the target
+    /// interpreter: `py3X`/`none` and `cp3X`/`abi3` accept any newer 3.x,
+    /// while a version-specific ABI (`cp311`) needs an exact match.
+    fn interpreter_ok(&self, python: &str, abi: &str) -> bool {
+        let (interpreter, version) = python.split_at(python.len().min(2));
+        if !matches!(interpreter, "py" | "cp") || !version.starts_with('3') {
+            return false;
+        }
+        let minor: Option<u32> = version[1..].parse().ok();
+        let target = self.python.map(|(_, minor)| minor);
+        let at_most_target = match (minor, target) {
+            (Some(minor), Some(target)) => minor <= target,
+            _ => true,
+        };
+
+        match abi {
+            "none" => at_most_target,
+            "abi3" => interpreter == "cp" && at_most_target,
+            abi => {
+                let abi_minor = abi
+                    .trim_end_matches(['m', 'd', 'u'])
+                    .strip_prefix("cp3")
+                    .and_then(|m| m.parse::<u32>().ok());
+                match (abi_minor, target) {
+                    (Some(abi_minor), Some(target)) => abi_minor == target,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                }
+            }
+        }
+    }
+
+    fn platform_tag_ok(&self, tag: &str) -> bool {
+        if tag == "any" {
+            return true;
+        }
+        match self.os.as_str() {
+            "linux" => {
+                let arch = match self.arch.as_str() {
+                    "x86" => "i686",
+                    arch => arch,
+                };
+                let libc = if self.musl { "musllinux" } else { "manylinux" };
+                (tag.starts_with(libc) || tag.starts_with("linux_")) && tag.ends_with(arch)
+            }
+            "macos" => {
+                let arch = match self.arch.as_str() {
+                    "aarch64" => "arm64",
+                    arch => arch,
+                };
+                tag.starts_with("macosx_")
+                    && (tag.ends_with(arch) || tag.ends_with("universal2") || tag.ends_with("universal"))
+            }
+            "windows" => matches!(
+                (self.arch.as_str(), tag),
+                ("x86_64", "win_amd64") | ("aarch64", "win_arm64") | ("x86", "win32")
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether `filename` is a wheel installable on this platform. Tags may
+    /// be compressed (`py2.py3`, `manylinux1_x86_64.manylinux2014_x86_64`).
+    pub fn supports_wheel(&self, filename: &str) -> bool {
+        let Some(stem) = filename.strip_suffix(".whl") else {
+            return false;
+        };
+        let parts: Vec<&str> = stem.split('-').collect();
+        if parts.len() < 5 {
+            return false;
+        }
+        let [py, abi, platform] = [parts[parts.len() - 3], parts[parts.len() - 2], parts[parts.len() - 1]];
+
+        py.split('.')
+            .any(|py| abi.split('.').any(|abi| self.interpreter_ok(py, abi)))
+            && platform.split('.').any(|t| self.platform_tag_ok(t))
+    }
+
+    pub fn has_compatible_wheel(&self, filenames: &[String]) -> bool {
+        filenames.iter().any(|f| self.supports_wheel(f))
+    }
+}
+
+/// Whether the system's dynamic loader is musl's, as on Alpine.
+fn detect_musl() -> bool {
+    std::fs::read_dir("/lib")
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+        })
+        .unwrap_or(false)
+}
+
+/// Parses a `--python-version` value such as `3.9`.
+pub fn parse_python_version(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected MAJOR.MINOR, e.g. 3.9, got {:?}", value);
+    let (major, minor) = value.trim().split_once('.').ok_or_else(invalid)?;
+    Ok((major.parse().map_err(|_| invalid())?, minor.parse().map_err(|_| invalid())?))
+}
+
+pub(crate) fn detect_python_version() -> Option<(u32, u32)> {
+    let output = Command::new("python3")
+        .args(["-c", "import sys; print(sys.version_info[0], sys.version_info[1])"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut parts = text.split_whitespace().map(|p| p.parse().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+/// The newest final release that ships a wheel for `tags`. `releases` is the
+/// per-version file listing, in any order.
+pub fn latest_with_wheel(releases: &[(String, Vec<String>)], tags: &PlatformTags) -> Option<String> {
+    releases
+        .iter()
+        .filter_map(|(version, files)| Some((Pep440Version::parse(version)?, version, files)))
+        .filter(|(parsed, _, files)| !parsed.is_prerelease() && tags.has_compatible_wheel(files))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, version, _)| version.clone())
+}
+
+/// Whether a release's files include wheels, source distributions or both;
+/// `None` when it has neither (eggs, installers).
+pub fn distribution_kind(filenames: &[String]) -> Option<Distribution> {
+    let wheel = filenames.iter().any(|f| f.ends_with(".whl"));
+    let sdist = filenames
+        .iter()
+        .any(|f| [".tar.gz", ".tar.bz2", ".tgz", ".zip"].iter().any(|ext| f.ends_with(ext)));
+    match (wheel, sdist) {
+        (true, true) => Some(Distribution::Both),
+        (true, false) => Some(Distribution::WheelOnly),
+        (false, true) => Some(Distribution::SdistOnly),
+        (false, false) => None,
+    }
+}
+
+/// What a requirements file's binary directives let pip install for one
+/// package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryRule {
+    Any,
+    /// `--only-binary`: wheels only, so a release without one can't install.
+    OnlyBinary,
+    /// `--no-binary`: always built from source, so wheels don't matter.
+    NoBinary,
+}
+
+/// The rule `options` leave for `name`. As with pip, later directives win,
+/// `:all:` covers every package and `:none:` clears what its flag set.
+pub fn binary_rule(options: &[GlobalOption], name: &str) -> BinaryRule {
+    let name = normalize_package_name(name);
+    let mut rule = BinaryRule::Any;
+    for option in options {
+        let (kind, value) = match option {
+            GlobalOption::OnlyBinary(value) => (BinaryRule::OnlyBinary, value),
+            GlobalOption::NoBinary(value) => (BinaryRule::NoBinary, value),
+            _ => continue,
+        };
+        for item in value.split(',').map(str::trim) {
+            match item {
+                ":all:" => rule = kind,
+                ":none:" if rule == kind => rule = BinaryRule::Any,
+                ":none:" => {}
+                item if normalize_package_name(item) == name => rule = kind,
+                _ => {}
+            }
+        }
+    }
+    rule
+}
+
+/// Records what the latest release ships, and notes packages whose latest
+/// release would need a source build here. With `prefer_wheels`, or
+/// `--prefer-binary` in the file, the upgrade target becomes the newest
+/// release that does have a compatible wheel; `--only-binary` packages always
+/// get that target, and `--no-binary` ones are left alone.
+pub fn apply_wheel_compatibility(
+    packages: &mut [Package],
+    pypi_client: &PyPIClient,
+    tags: &PlatformTags,
+    prefer_wheels: bool,
+) {
+    let options = pypi_client.binary_options();
+    let prefer_wheels = prefer_wheels || options.contains(&GlobalOption::PreferBinary);
+    for pkg in packages.iter_mut() {
+        let rule = binary_rule(options, &pkg.name);
+        let Some(latest) = pkg.latest_version.clone() else {
+            continue;
+        };
+        let releases = pypi_client.release_files(&pkg.name);
+        let Some((_, latest_files)) = releases.iter().find(|(v, _)| *v == latest) else {
+            continue;
+        };
+        pkg.distribution = distribution_kind(latest_files);
+
+        let ships_wheels = latest_files.iter().any(|f| f.ends_with(".whl"));
+        let had_compatible = releases.iter().any(|(_, files)| tags.has_compatible_wheel(files));
+        if rule == BinaryRule::NoBinary
+            || tags.has_compatible_wheel(latest_files)
+            || !(ships_wheels || had_compatible || rule == BinaryRule::OnlyBinary)
+        {
+            continue;
+        }
+
+        if !pkg.notes.iter().any(|n| n == NO_WHEEL_NOTE) {
+            pkg.notes.push(NO_WHEEL_NOTE.to_string());
+        }
+
+        if prefer_wheels || rule == BinaryRule::OnlyBinary {
+            if let Some(target) = latest_with_wheel(&releases, tags) {
+                pkg.status = compare_versions(&pkg.current_version, &target);
+                pkg.target_version = Some(target);
+            }
+        }
+    }
+}
+
+/// Notes packages whose latest release, by its `Requires-Python`, can't be
+/// installed on the target interpreter.
+pub fn apply_python_support(packages: &mut [Package], python: Option<(u32, u32)>) {
+    let Some((major, minor)) = python else {
+        return;
+    };
+    let target = format!("{}.{}", major, minor);
+    for pkg in packages.iter_mut() {
+        let Some(spec) = &pkg.requires_python else {
+            continue;
+        };
+        if specifier_allows(spec, &target) || pkg.drops_python.as_deref() == Some(target.as_str()) {
+            continue;
+        }
+        pkg.notes.push(format!("latest drops support for Python {} (requires {})", target, spec));
+        pkg.drops_python = Some(target.clone());
+    }
+}
+
+/// Notes back-ports the target interpreter already has in its standard
+/// library. Requirements behind a marker are taken to be conditional on an
+/// older Python already and are left alone.
+pub fn apply_backport_notes(packages: &mut [Package], python: Option<(u32, u32)>) {
+    let Some(target) = python else {
+        return;
+    };
+    for pkg in packages.iter_mut().filter(|p| p.marker.is_none()) {
+        let name = normalize_package_name(&pkg.name);
+        let Some((_, (major, minor))) = STDLIB_BACKPORTS
+            .iter()
+            .find(|(backport, since)| normalize_package_name(backport) == name && target >= *since)
+        else {
+            continue;
+        };
+        if !pkg.notes.iter().any(|n| n.starts_with(REDUNDANT_NOTE)) {
+            pkg.notes.push(format!("{} {}.{} (in the standard library)", REDUNDANT_NOTE, major, minor));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linux_311() -> PlatformTags {
+        PlatformTags {
+            python: Some((3, 11)),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            musl: false,
+        }
+    }
+
+    fn release(version: &str, files: &[&str]) -> (String, Vec<String>) {
+        (version.to_string(), files.iter().map(|f| f.to_string()).collect())
+    }
+
+    #[test]
+    fn test_picks_newest_release_with_matching_wheel() {
+        let releases = vec![
+            release("1.24.0", &[
+                "numpy-1.24.0-cp311-cp311-manylinux_2_17_x86_64.manylinux2014_x86_64.whl",
+                "numpy-1.24.0.tar.gz",
+            ]),
+            release("1.25.0", &[
+                "numpy-1.25.0-cp311-cp311-win_amd64.whl",
+                "numpy-1.25.0-cp311-cp311-manylinux_2_17_aarch64.whl",
+                "numpy-1.25.0.tar.gz",
+            ]),
+            release("1.26.0", &[
+                "numpy-1.26.0-cp312-cp312-manylinux_2_17_x86_64.whl",
+                "numpy-1.26.0.tar.gz",
+            ]),
+            release("2.0.0rc1", &["numpy-2.0.0rc1-cp311-cp311-manylinux_2_17_x86_64.whl"]),
+        ];
+
+        assert_eq!(latest_with_wheel(&releases, &linux_311()).as_deref(), Some("1.24.0"));
+    }
+
+    #[test]
+    fn test_distribution_kind_from_release_files() {
+        let (_, wheels) = release("1.0", &["black-24.1.0-py3-none-any.whl"]);
+        let (_, sdists) = release("1.0", &["pycparser-2.21.tar.gz", "pycparser-2.21.zip"]);
+        let (_, both) = release("1.0", &["six-1.16.0-py2.py3-none-any.whl", "six-1.16.0.tar.gz"]);
+        let (_, eggs) = release("1.0", &["legacy-1.0-py2.7.egg"]);
+
+        assert_eq!(distribution_kind(&wheels), Some(Distribution::WheelOnly));
+        assert_eq!(distribution_kind(&wheels).unwrap().as_str(), "wheel only");
+        assert_eq!(distribution_kind(&sdists), Some(Distribution::SdistOnly));
+        assert_eq!(distribution_kind(&sdists).unwrap().as_str(), "sdist only");
+        assert_eq!(distribution_kind(&both), Some(Distribution::Both));
+        assert_eq!(distribution_kind(&eggs), None);
+    }
+
+    #[test]
+    fn test_wheel_tag_matching() {
+        let tags = linux_311();
+        assert!(tags.supports_wheel("requests-2.31.0-py3-none-any.whl"));
+        assert!(tags.supports_wheel("six-1.16.0-py2.py3-none-any.whl"));
+        assert!(tags.supports_wheel("cryptography-41.0.0-cp37-abi3-manylinux_2_28_x86_64.whl"));
+        assert!(!tags.supports_wheel("cryptography-41.0.0-cp37-abi3-macosx_10_12_universal2.whl"));
+        assert!(!tags.supports_wheel("pydantic_core-2.0.0-cp310-cp310-manylinux_2_17_x86_64.whl"));
+        assert!(!tags.supports_wheel("legacy-1.0-py2-none-any.whl"));
+        assert!(!tags.supports_wheel("requests-2.31.0.tar.gz"));
+    }
+
+    #[test]
+    fn test_musllinux_wheels_match_only_musl_platforms() {
+        let glibc = linux_311();
+        let musl = PlatformTags { musl: true, ..linux_311() };
+        let musllinux = "numpy-1.26.0-cp311-cp311-musllinux_1_1_x86_64.whl";
+        let manylinux = "numpy-1.26.0-cp311-cp311-manylinux_2_17_x86_64.whl";
+
+        assert!(musl.supports_wheel(musllinux));
+        assert!(!musl.supports_wheel(manylinux));
+        assert!(glibc.supports_wheel(manylinux));
+        assert!(!glibc.supports_wheel(musllinux));
+    }
+
+    #[tokio::test]
+    async fn test_package_dropping_target_python_is_flagged() {
+        use crate::models::GlobalOption;
+        use crate::test_support::{MockResponse, MockServer};
+
+        let info = |name: &str, requires: &str| {
+            MockResponse::json(format!(
+                r#"{{"info":{{"name":"{}","version":"2.0.0","summary":"","requires_python":"{}"}}}}"#,
+                name, requires
+            ))
+        };
+        let server = MockServer::routes(&[
+            ("/pypi/newlib/json", info("newlib", ">=3.11")),
+            ("/pypi/oldlib/json", info("oldlib", ">=3.7")),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requirements.txt");
+        std::fs::write(&path, "newlib==1.0.0\noldlib==1.0.0\n").unwrap();
+        let mut packages = crate::parser::parse_requirements(&path).unwrap().packages;
+
+        let mut client = PyPIClient::new();
+        client.apply_options(&[GlobalOption::IndexUrl(server.url("/pypi"))]);
+        client.update_packages(&mut packages).await;
+        assert_eq!(packages[0].requires_python.as_deref(), Some(">=3.11"));
+
+        apply_python_support(&mut packages, Some((3, 9)));
+        assert_eq!(packages[0].notes, vec!["latest drops support for Python 3.9 (requires >=3.11)"]);
+        assert!(packages[1].notes.is_empty());
+
+        apply_python_support(&mut packages, Some((3, 9)));
+        assert_eq!(packages[0].notes.len(), 1);
+        assert_eq!(packages[0].drops_python.as_deref(), Some("3.9"));
+        assert_eq!(packages[1].drops_python, None);
+
+        assert_eq!(parse_python_version("3.9"), Ok((3, 9)));
+        assert!(parse_python_version("3").is_err());
+        let tags = linux_311().with_python(Some((3, 9)));
+        assert_eq!(tags.python, Some((3, 9)));
+        assert_eq!(tags.with_python(None).python, Some((3, 9)));
+    }
+
+    #[test]
+    fn test_stdlib_backport_flagged_as_redundant() {
+        let line = |spec: &str| crate::parser::parse_requirement_line(spec).unwrap();
+        let mut packages = vec![
+            line("dataclasses==0.8"),
+            line("tomli==2.0.1"),
+            line("enum34==1.1.10 ; python_version < \"3.4\""),
+            line("requests==2.31.0"),
+        ];
+
+        apply_backport_notes(&mut packages, Some((3, 7)));
+        assert_eq!(packages[0].notes, vec!["redundant on Python >= 3.7 (in the standard library)"]);
+        assert!(packages[1].notes.is_empty());
+        assert!(packages[2].notes.is_empty());
+        assert!(packages[3].notes.is_empty());
+
+        apply_backport_notes(&mut packages, Some((3, 6)));
+        apply_backport_notes(&mut packages, Some((3, 12)));
+        assert_eq!(packages[0].notes.len(), 1);
+        assert_eq!(packages[1].notes, vec!["redundant on Python >= 3.11 (in the standard library)"]);
+    }
+}